@@ -31,7 +31,7 @@ use bevy_xpbd_3d::{
 use cameras::{LookTransform, LookTransformCache};
 use controller::controller::{ControllerCache, ControllerInput};
 use entity::entity_types::EntityTypeCache;
-use gridmap::grid::{AddedUpdate, Gridmap, LayerTargetCell};
+use gridmap::grid::{AddedUpdate, Gridmap, LayerTargetCell, MapLimits};
 use networking::{client::PostUpdateSendMessage, stamp::TickRateStamp};
 use physics::{
     cache::{Cache, PhysicsCache},
@@ -309,6 +309,7 @@ pub(crate) fn server_start_correcting(world: &mut World) {
     (|| {
         let mut gridmap = world.resource_mut::<Gridmap>();
         gridmap.updates = start_data.gridmap_updates;
+        gridmap.map_length_limit = start_data.gridmap_limits;
     })();
     for _ in start_data.start.start_tick - 1..start_data.start.last_tick + 1 {
         step_game_schedules(world);
@@ -337,6 +338,7 @@ pub struct StartCorrectingMessage {
     pub entity_type_cache: EntityTypeCache,
     pub correcting: bool,
     pub gridmap_updates: BTreeMap<u32, HashMap<LayerTargetCell, AddedUpdate>>,
+    pub gridmap_limits: MapLimits,
 }
 pub enum CorrectionAppMessage {
     Results(CorrectionResults),
@@ -418,6 +420,7 @@ pub(crate) fn start_correction(
         entity_type_cache: entity_type_cache.clone(),
         correcting: true,
         gridmap_updates: gridmap.updates.clone(),
+        gridmap_limits: gridmap.map_length_limit.clone(),
     };
     enabled.0 = true;
     if synchronous_correction.0 {
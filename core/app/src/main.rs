@@ -63,6 +63,7 @@ use correction::CorrectionResultsSender;
 use correction::CorrectionServerPlugin;
 use correction::StartCorrectingMessage;
 use counter_windows::plugin::CounterWindowsPlugin;
+use debris::plugin::DebrisPlugin;
 use entity::plugin::EntityPlugin;
 use escape_menu::plugin::EscapeMenuPlugin;
 use graphics::plugin::GraphicsPlugin;
@@ -89,6 +90,7 @@ use physics::plugin::PhysicsStepSet;
 use pistol_l1::plugin::PistolL1Plugin;
 use player::plugin::PlayerPlugin;
 use point_light::plugin::PointLightPlugin;
+use power_sources::plugin::PowerSourcesPlugin;
 use resources::core::ClientInformation;
 use resources::core::TickRate;
 use resources::correction::ObtainedSynchronousSyncData;
@@ -312,6 +314,8 @@ fn setup_plugins(mut app: &mut App) {
             .add_plugins(AsanaPlugin)
             .add_plugins(SfxPlugin)
             .add_plugins(ComputersPlugin)
+            .add_plugins(PowerSourcesPlugin)
+            .add_plugins(DebrisPlugin)
             .add_plugins(MapPlugin)
             .add_plugins(ConsoleCommandsPlugin)
             .add_plugins(CombatPlugin)
@@ -1,13 +1,21 @@
-use std::{fs::File, io::Write, path::Path};
+use std::{fs::File, io::Write};
 
+use bevy::log::{info, warn};
 use bevy::prelude::{EventReader, EventWriter, Res, ResMut};
 use console_commands::{
     commands::{AllConsoleCommands, ConsoleCommand, InputConsoleCommand},
     net::{ConsoleCommandsServerMessage, ConsoleLine},
 };
-use gridmap::grid::Gridmap;
+use gridmap::{
+    format::{GridmapEncoding, GRIDMAP_TEXT_EXTENSION},
+    generator::{generate_ship, AirlockPlacement, ShipGeneratorParameters},
+    grid::Gridmap,
+    map_image::{export_map_images, map_images_directory, read_entity_markers},
+    validate::validate_map_file,
+};
 use hud::communication::build::CONSOLE_FONT_COLOR;
-use networking::server::OutgoingReliableServerMessage;
+use networking::server::{ConsoleArgVariant, OutgoingReliableServerMessage};
+use resources::maps::{load_map_manifest, SelectedMap};
 use ui::{
     fonts::{Fonts, SOURCECODE_REGULAR_FONT},
     text::{NetTextSection, COMMUNICATION_FONT_SIZE},
@@ -16,8 +24,8 @@ use ui::{
 pub(crate) fn add_export_map_command(mut commands: ResMut<AllConsoleCommands>) {
     commands.list.push(ConsoleCommand {
         base: "exportMap".to_string(),
-        description: "Exports the map to ron.".to_string(),
-        args: vec![],
+        description: "Exports the map into the directory of the selected map. Pass \"ron\" to export the text format.".to_string(),
+        args: vec![("format".to_string(), ConsoleArgVariant::String)],
     });
 }
 
@@ -26,26 +34,65 @@ pub(crate) fn export_map(
     gridmap: Res<Gridmap>,
     mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
     fonts: Res<Fonts>,
+    selected_map: Option<Res<SelectedMap>>,
 ) {
     for command in queue.read() {
         if command.input.command == "exportMap" {
+            let map;
+            match &selected_map {
+                Some(m) => {
+                    map = m;
+                }
+                None => {
+                    warn!("No map selected to export to.");
+                    continue;
+                }
+            }
+            let encoding;
+            match command.input.args.get(0) {
+                Some(format) => {
+                    if format == GRIDMAP_TEXT_EXTENSION {
+                        encoding = GridmapEncoding::Text;
+                    } else {
+                        encoding = GridmapEncoding::Binary;
+                    }
+                }
+                None => {
+                    encoding = GridmapEncoding::Binary;
+                }
+            }
             let mut i = 0;
 
             loop {
-                let path = Path::new("data")
-                    .join("maps")
-                    .join("bullseye")
-                    .join(format!("main_export{}.bin", i));
+                let path = map
+                    .directory
+                    .join(format!("main_export{}.{}", i, encoding.extension()));
                 if !path.exists() {
-                    let mut file = File::create(path.clone()).unwrap();
-                    file.write_all(&gridmap.export_binary()).unwrap();
+                    let text;
+                    match gridmap.export(encoding) {
+                        Ok(data) => match File::create(path.clone())
+                            .and_then(|mut file| file.write_all(&data))
+                        {
+                            Ok(_) => {
+                                text = format!("Exported gridmap to {:?}", path);
+                            }
+                            Err(rr) => {
+                                text = format!("Couldn't export gridmap to {:?}: {}", path, rr);
+                                warn!("{}", text);
+                            }
+                        },
+                        Err(rr) => {
+                            text = format!("Couldn't export gridmap: {}", rr);
+                            warn!("{}", text);
+                        }
+                    }
                     match command.handle_option {
                         Some(handle) => {
                             net.send(OutgoingReliableServerMessage {
                                 message: ConsoleCommandsServerMessage::ConsoleWriteLine(
                                     ConsoleLine {
                                         sections: vec![NetTextSection {
-                                            text: format!("Exported gridmap to {:?}", path),
+                                            text: text,
                                             font: *fonts
                                                 .inv_map
                                                 .get(SOURCECODE_REGULAR_FONT)
@@ -68,3 +115,348 @@ pub(crate) fn export_map(
         }
     }
 }
+
+pub(crate) fn add_gridmap_memory_command(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "gridmapMemory".to_string(),
+        description: "Reports the memory usage of the gridmap chunks.".to_string(),
+        args: vec![],
+    });
+}
+
+pub(crate) fn gridmap_memory(
+    mut queue: EventReader<InputConsoleCommand>,
+    gridmap: Res<Gridmap>,
+    mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for command in queue.read() {
+        if command.input.command == "gridmapMemory" {
+            let usage = gridmap.memory_usage();
+            let text = format!(
+                "{} chunks, {} cells, {} items. ~{} KiB (~{} KiB with dense chunks).",
+                usage.chunks,
+                usage.cells,
+                usage.items,
+                usage.bytes / 1024,
+                usage.dense_bytes / 1024
+            );
+            info!("{}", text);
+            match command.handle_option {
+                Some(handle) => {
+                    net.send(OutgoingReliableServerMessage {
+                        message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+                            sections: vec![NetTextSection {
+                                text: text,
+                                font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                                font_size: COMMUNICATION_FONT_SIZE,
+                                color: CONSOLE_FONT_COLOR,
+                            }],
+                        }),
+                        handle,
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Issues written to the console of the player, all of them get logged on the server.
+const MAX_CONSOLE_MAP_ISSUES: usize = 50;
+
+pub(crate) fn add_validate_map_command(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "validateMap".to_string(),
+        description: "Validates the cells file of a map against the registered tiles and groups. Validates the selected map without a name.".to_string(),
+        args: vec![("map".to_string(), ConsoleArgVariant::String)],
+    });
+}
+
+pub(crate) fn validate_map(
+    mut queue: EventReader<InputConsoleCommand>,
+    gridmap: Res<Gridmap>,
+    mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+    selected_map: Option<Res<SelectedMap>>,
+) {
+    for command in queue.read() {
+        if command.input.command != "validateMap" {
+            continue;
+        }
+        let map;
+        match command.input.args.get(0) {
+            Some(name) => {
+                if name.contains(['/', '\\', '.']) {
+                    map = Err(format!("Invalid map name \"{}\".", name));
+                } else {
+                    match load_map_manifest(name) {
+                        Ok(m) => {
+                            map = Ok(m);
+                        }
+                        Err(rr) => {
+                            map = Err(rr.to_string());
+                        }
+                    }
+                }
+            }
+            None => match &selected_map {
+                Some(m) => {
+                    map = Ok(m.as_ref().clone());
+                }
+                None => {
+                    map = Err("No map selected.".to_string());
+                }
+            },
+        }
+        let mut lines = vec![];
+        match map {
+            Ok(map) => match validate_map_file(&gridmap, &map) {
+                Ok(issues) => {
+                    for issue in issues.iter() {
+                        warn!("{}", issue);
+                    }
+                    lines.push(format!(
+                        "Map \"{}\" has {} issues.",
+                        map.manifest.name,
+                        issues.len()
+                    ));
+                    info!("{}", lines[0]);
+                    for issue in issues.iter().take(MAX_CONSOLE_MAP_ISSUES) {
+                        lines.push(issue.to_string());
+                    }
+                    if issues.len() > MAX_CONSOLE_MAP_ISSUES {
+                        lines.push(format!(
+                            "{} more issues are in the server log.",
+                            issues.len() - MAX_CONSOLE_MAP_ISSUES
+                        ));
+                    }
+                }
+                Err(rr) => {
+                    lines.push(format!("Couldn't validate map: {}", rr));
+                    warn!("{}", lines[0]);
+                }
+            },
+            Err(rr) => {
+                lines.push(format!("Couldn't validate map: {}", rr));
+                warn!("{}", lines[0]);
+            }
+        }
+        match command.handle_option {
+            Some(handle) => {
+                for text in lines {
+                    net.send(OutgoingReliableServerMessage {
+                        message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+                            sections: vec![NetTextSection {
+                                text,
+                                font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                                font_size: COMMUNICATION_FONT_SIZE,
+                                color: CONSOLE_FONT_COLOR,
+                            }],
+                        }),
+                        handle,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+pub(crate) fn add_generate_map_command(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "generateMap".to_string(),
+        description: "Generates a random ship into the directory of the selected map. Airlocks are \"none\", \"ends\" or an amount.".to_string(),
+        args: vec![
+            ("seed".to_string(), ConsoleArgVariant::Int),
+            ("rooms".to_string(), ConsoleArgVariant::Int),
+            ("corridorWidth".to_string(), ConsoleArgVariant::Int),
+            ("decks".to_string(), ConsoleArgVariant::Int),
+            ("airlocks".to_string(), ConsoleArgVariant::String),
+        ],
+    });
+}
+
+fn parse_generator_parameters(args: &Vec<String>) -> Result<ShipGeneratorParameters, String> {
+    let mut parameters = ShipGeneratorParameters::default();
+    for (i, arg) in args.iter().enumerate() {
+        let invalid = || format!("Invalid generateMap argument \"{}\".", arg);
+        match i {
+            0 => parameters.seed = arg.parse().map_err(|_| invalid())?,
+            1 => parameters.room_count = arg.parse().map_err(|_| invalid())?,
+            2 => parameters.corridor_width = arg.parse().map_err(|_| invalid())?,
+            3 => parameters.deck_count = arg.parse().map_err(|_| invalid())?,
+            4 => {
+                parameters.airlocks = match arg.as_str() {
+                    "none" => AirlockPlacement::None,
+                    "ends" => AirlockPlacement::CorridorEnds,
+                    _ => AirlockPlacement::Random(arg.parse().map_err(|_| invalid())?),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(parameters)
+}
+
+pub(crate) fn generate_map(
+    mut queue: EventReader<InputConsoleCommand>,
+    mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+    selected_map: Option<Res<SelectedMap>>,
+) {
+    for command in queue.read() {
+        if command.input.command != "generateMap" {
+            continue;
+        }
+        let map;
+        match &selected_map {
+            Some(m) => {
+                map = m;
+            }
+            None => {
+                warn!("No map selected to generate into.");
+                continue;
+            }
+        }
+        let text;
+        match parse_generator_parameters(&command.input.args) {
+            Ok(parameters) => {
+                let ship = generate_ship(&parameters);
+                let mut i = 0;
+                let mut path = map.directory.join(format!(
+                    "generated{}.{}",
+                    i,
+                    GridmapEncoding::Binary.extension()
+                ));
+                while path.exists() {
+                    i += 1;
+                    path = map.directory.join(format!(
+                        "generated{}.{}",
+                        i,
+                        GridmapEncoding::Binary.extension()
+                    ));
+                }
+                match ship.export(GridmapEncoding::Binary) {
+                    Ok(data) => {
+                        match File::create(path.clone()).and_then(|mut file| file.write_all(&data))
+                        {
+                            Ok(_) => {
+                                text = format!(
+                                    "Generated {} cells with {:?} to {:?}",
+                                    ship.cells.len(),
+                                    parameters,
+                                    path
+                                );
+                                info!("{}", text);
+                            }
+                            Err(rr) => {
+                                text =
+                                    format!("Couldn't write generated map to {:?}: {}", path, rr);
+                                warn!("{}", text);
+                            }
+                        }
+                    }
+                    Err(rr) => {
+                        text = format!("Couldn't encode generated map: {}", rr);
+                        warn!("{}", text);
+                    }
+                }
+            }
+            Err(rr) => {
+                text = rr;
+            }
+        }
+        match command.handle_option {
+            Some(handle) => {
+                net.send(OutgoingReliableServerMessage {
+                    message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+                        sections: vec![NetTextSection {
+                            text,
+                            font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                            font_size: COMMUNICATION_FONT_SIZE,
+                            color: CONSOLE_FONT_COLOR,
+                        }],
+                    }),
+                    handle,
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+pub(crate) fn add_export_map_images_command(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "exportMapImages".to_string(),
+        description: "Renders a top-down image per y level into the images directory of the selected map. Pass false to leave out entity markers.".to_string(),
+        args: vec![("entities".to_string(), ConsoleArgVariant::Bool)],
+    });
+}
+
+pub(crate) fn export_map_images_command(
+    mut queue: EventReader<InputConsoleCommand>,
+    gridmap: Res<Gridmap>,
+    mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+    selected_map: Option<Res<SelectedMap>>,
+) {
+    for command in queue.read() {
+        if command.input.command != "exportMapImages" {
+            continue;
+        }
+        let map;
+        match &selected_map {
+            Some(m) => {
+                map = m;
+            }
+            None => {
+                warn!("No map selected to export images of.");
+                continue;
+            }
+        }
+        let markers;
+        match command.input.args.get(0) {
+            Some(entities) if entities == "false" => {
+                markers = vec![];
+            }
+            _ => match read_entity_markers(map) {
+                Ok(m) => {
+                    markers = m;
+                }
+                Err(rr) => {
+                    warn!("{}", rr);
+                    markers = vec![];
+                }
+            },
+        }
+        let text;
+        let directory = map_images_directory(map);
+        match export_map_images(&gridmap, &markers, &directory) {
+            Ok(paths) => {
+                text = format!("Exported {} map images to {:?}", paths.len(), directory);
+                info!("{}", text);
+            }
+            Err(rr) => {
+                text = rr.to_string();
+                warn!("{}", text);
+            }
+        }
+        match command.handle_option {
+            Some(handle) => {
+                net.send(OutgoingReliableServerMessage {
+                    message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+                        sections: vec![NetTextSection {
+                            text,
+                            font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                            font_size: COMMUNICATION_FONT_SIZE,
+                            color: CONSOLE_FONT_COLOR,
+                        }],
+                    }),
+                    handle,
+                });
+            }
+            None => {}
+        }
+    }
+}
@@ -5,7 +5,11 @@ use resources::{modes::is_server_mode, ordering::Update};
 
 use crate::{
     commands::{coords, rcon_console_commands, GiveAllRCON},
-    gridmap::{add_export_map_command, export_map},
+    gridmap::{
+        add_export_map_command, add_export_map_images_command, add_generate_map_command,
+        add_gridmap_memory_command, add_validate_map_command, export_map,
+        export_map_images_command, generate_map, gridmap_memory, validate_map,
+    },
     help::{add_help_command, help_command},
 };
 
@@ -19,7 +23,16 @@ impl Plugin for BasicConsoleCommandsPlugin {
         if is_server_mode(app) {
             app.add_systems(
                 Update,
-                (rcon_console_commands, export_map, coords).after(ConsoleCommandsSet::Input),
+                (
+                    rcon_console_commands,
+                    export_map,
+                    gridmap_memory,
+                    validate_map,
+                    generate_map,
+                    export_map_images_command,
+                    coords,
+                )
+                    .after(ConsoleCommandsSet::Input),
             )
             .insert_resource::<GiveAllRCON>(GiveAllRCON {
                 give: self.give_all_rcon,
@@ -33,6 +46,15 @@ impl Plugin for BasicConsoleCommandsPlugin {
                     .before(ConsoleCommandsClientSet::Display),
             );
         }
-        app.add_systems(Startup, add_export_map_command);
+        app.add_systems(
+            Startup,
+            (
+                add_export_map_command,
+                add_gridmap_memory_command,
+                add_validate_map_command,
+                add_generate_map_command,
+                add_export_map_images_command,
+            ),
+        );
     }
 }
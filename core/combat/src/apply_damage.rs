@@ -1,6 +1,10 @@
 use bevy::log::warn;
 use bevy::prelude::{Entity, Event, EventReader, EventWriter, Query, Res, ResMut, Resource};
 use entity::health::{HealthComponent, HealthContainer};
+use gridmap::{
+    damage::{hit_tile, CellDamage, DamageTile},
+    grid::Gridmap,
+};
 use inventory::server::combat::{DamageModel, MeleeCombat, ProjectileCombat};
 use resources::math::Vec3Int;
 
@@ -159,6 +163,8 @@ pub(crate) fn finalize_apply_damage(
     mut health_entities: Query<&mut HealthComponent>,
     mut health_combat_hit_result: EventWriter<HealthCombatHitResult>,
     mut active_applydamage: ResMut<ActiveApplyDamage>,
+    gridmap: Res<Gridmap>,
+    mut damage_tile: EventWriter<DamageTile>,
 ) {
     for damage_appler in active_applydamage.list.iter() {
         let attack_cache;
@@ -189,7 +195,7 @@ pub(crate) fn finalize_apply_damage(
         }
 
         let mut entity_hits = vec![];
-        let cell_hits = vec![];
+        let mut cell_hits = vec![];
 
         let mut brute_multiplier = 1.;
         let mut burn_multiplier = 1.;
@@ -271,55 +277,48 @@ pub(crate) fn finalize_apply_damage(
                     }
                 }
             }
-            /*
-            for cell_id in hit_result.cell_hits.iter() {
-                match gridmap_main.get_cell(cell_id.cell) {
-                    Some(cell_data) => match cell_data.clone().health.health_container {
-                        HealthContainer::Structure(_) => {
-                            let (brute_damage, burn_damage, toxin_damage, hit_result) =
-                                calculate_damage(
-                                    &cell_data.health.health_flags,
-                                    &cell_data.health.damage_flags,
-                                    &apply_damage_model.damage_model.brute,
-                                    &apply_damage_model.damage_model.burn,
-                                    &apply_damage_model.damage_model.toxin,
-                                );
-
-                            let mut new_cell_data = cell_data.clone();
-                            match &mut new_cell_data.health.health_container {
-                                HealthContainer::Structure(cont) => {
-                                    cont.brute += brute_damage;
-                                    cont.burn += burn_damage;
-                                    cont.toxin += toxin_damage;
-                                }
-                                _ => (),
-                            }
-
-                            set_cell.send(SetCell {
-                                id: cell_id.cell,
-                                data: new_cell_data,
-                            });
-
-                            if apply_damage_model.signature == "main" {
-                                cell_hits.push(CellHit {
-                                    cell_id: cell_id.cell,
-                                    hit_result,
-                                });
-                            }
-                        }
-                        HealthContainer::Humanoid(_) => {
-                            info!("struck humanoid instead.");
-                        }
-                        HealthContainer::Entity(_) => {
-                            info!("struck entity instead.");
-                        }
-                    },
+            for cell_hit in hit_result.cell_hits.iter() {
+                let cell;
+                match hit_tile(&gridmap, cell_hit.cell, cell_hit.hit_point) {
+                    Some(c) => {
+                        cell = c;
+                    }
+                    None => {
+                        warn!("Couldnt find hit cell {:?} in gridmap.", cell_hit.cell);
+                        continue;
+                    }
+                }
+                let cell_data;
+                match gridmap.get_cell(cell.clone()) {
+                    Some(c) => {
+                        cell_data = c;
+                    }
                     None => {
-                        warn!("Couldnt find cellid in grid_map main.");
                         continue;
                     }
                 }
-            }*/
+                let (brute_damage, burn_damage, toxin_damage, new_hit_result) = calculate_damage(
+                    &cell_data.health.health_flags,
+                    &apply_damage_model.damage_model.damage_flags,
+                    &(&apply_damage_model.damage_model.brute * brute_multiplier),
+                    &(&apply_damage_model.damage_model.burn * burn_multiplier),
+                    &(&apply_damage_model.damage_model.toxin * toxin_multiplier),
+                );
+                damage_tile.send(DamageTile {
+                    cell,
+                    damage: CellDamage {
+                        brute: brute_damage,
+                        burn: burn_damage,
+                        toxin: toxin_damage,
+                    },
+                });
+                if apply_damage_model.signature == "main" {
+                    cell_hits.push(CellHit {
+                        cell_id: cell_hit.cell,
+                        hit_result: new_hit_result,
+                    });
+                }
+            }
         }
 
         health_combat_hit_result.send(HealthCombatHitResult {
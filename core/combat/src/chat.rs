@@ -3,7 +3,6 @@ use bevy::prelude::{Component, Entity, EventReader, EventWriter, Query, Res, Tra
 use entity::examine::Examinable;
 use entity::health::HealthComponent;
 use entity::health::HealthContainer;
-use entity::senser::to_doryen_coordinates;
 use entity::senser::Senser;
 use gridmap::grid::Gridmap;
 use inventory::item::InventoryItem;
@@ -132,8 +131,6 @@ pub fn attacked_by_chat<T: Component>(
 
         let attacker_cell_id = world_to_cell_id(attacker_transform.translation);
 
-        let attacker_cell_id_doryen = to_doryen_coordinates(attacker_cell_id.x, attacker_cell_id.z);
-
         for attacked_entity_hit in query_hit_result.entities_hits.iter() {
             let health_component;
             let examinable_component;
@@ -152,9 +149,6 @@ pub fn attacked_by_chat<T: Component>(
             }
 
             let attacked_gridmap_coords = world_to_cell_id(target_transform.translation);
-            let attacked_cell_id_doryen =
-                to_doryen_coordinates(attacked_gridmap_coords.x, attacked_gridmap_coords.z);
-
             match &health_component.health.health_container {
                 HealthContainer::Humanoid(_) => {
                     for (entity, senser) in sensers.iter() {
@@ -164,10 +158,7 @@ pub fn attacked_by_chat<T: Component>(
 
                         let attacker_is_visible;
 
-                        if senser.fov.is_in_fov(
-                            attacker_cell_id_doryen.0 as usize,
-                            attacker_cell_id_doryen.1 as usize,
-                        ) {
+                        if senser.is_in_fov(attacker_cell_id) {
                             attacker_is_visible = true;
                         } else {
                             attacker_is_visible = false;
@@ -175,10 +166,7 @@ pub fn attacked_by_chat<T: Component>(
 
                         let attacked_is_visible;
 
-                        if senser.fov.is_in_fov(
-                            attacked_cell_id_doryen.0 as usize,
-                            attacked_cell_id_doryen.1 as usize,
-                        ) {
+                        if senser.is_in_fov(attacked_gridmap_coords) {
                             attacked_is_visible = true;
                         } else {
                             attacked_is_visible = false;
@@ -336,10 +324,7 @@ pub fn attacked_by_chat<T: Component>(
 
                         let attacker_is_visible;
 
-                        if senser.fov.is_in_fov(
-                            attacker_cell_id_doryen.0 as usize,
-                            attacker_cell_id_doryen.1 as usize,
-                        ) {
+                        if senser.is_in_fov(attacker_cell_id) {
                             attacker_is_visible = true;
                         } else {
                             attacker_is_visible = false;
@@ -347,10 +332,7 @@ pub fn attacked_by_chat<T: Component>(
 
                         let attacked_is_visible;
 
-                        if senser.fov.is_in_fov(
-                            attacked_cell_id_doryen.0 as usize,
-                            attacked_cell_id_doryen.1 as usize,
-                        ) {
+                        if senser.is_in_fov(attacked_gridmap_coords) {
                             attacked_is_visible = true;
                         } else {
                             attacked_is_visible = false;
@@ -514,10 +496,7 @@ pub fn hit_query_chat_cells(
             }
         }
 
-        let attacker_cell_id = world_to_cell_id(attacker_transform.translation);
-
-        let _attacker_cell_id_doryen =
-            to_doryen_coordinates(attacker_cell_id.x, attacker_cell_id.z);
+        let _attacker_cell_id = world_to_cell_id(attacker_transform.translation);
 
         /*for attacked_cell_id in query_hit_result.cell_hits.iter() {
             let attacked_cell_id_doryen =
@@ -0,0 +1,203 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+/// Cell coordinates of a voxel map.
+pub type Voxel = (i16, i16, i16);
+
+/// Sparse 3D map of the faces between voxels that block sight. Empty space is transparent.
+#[derive(Default, Clone)]
+pub struct VoxelMapData {
+    /// Per voxel, bit `axis` is set when the face towards the next voxel on that axis blocks sight.
+    pub blocked: HashMap<Voxel, u8>,
+}
+
+impl VoxelMapData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Whether the face between the voxel and the next voxel along the axis (0 = x, 1 = y, 2 = z) blocks sight.
+    pub fn is_face_blocked(&self, voxel: Voxel, axis: usize) -> bool {
+        match self.blocked.get(&voxel) {
+            Some(faces) => faces & (1 << axis) != 0,
+            None => false,
+        }
+    }
+    pub fn set_face_blocked(&mut self, voxel: Voxel, axis: usize, is_blocked: bool) {
+        let faces = self.blocked.entry(voxel).or_default();
+        if is_blocked {
+            *faces |= 1 << axis;
+        } else {
+            *faces &= !(1 << axis);
+        }
+        if *faces == 0 {
+            self.blocked.remove(&voxel);
+        }
+    }
+    /// Whether sight can pass from the voxel to its neighbour in the direction of `sign` along the axis.
+    fn can_cross(&self, voxel: [i32; 3], axis: usize, sign: i32) -> bool {
+        let mut face_voxel = voxel;
+        if sign < 0 {
+            face_voxel[axis] -= 1;
+        }
+        match to_voxel(face_voxel) {
+            Some(v) => !self.is_face_blocked(v, axis),
+            None => true,
+        }
+    }
+}
+
+fn to_voxel(v: [i32; 3]) -> Option<Voxel> {
+    Some((
+        i16::try_from(v[0]).ok()?,
+        i16::try_from(v[1]).ok()?,
+        i16::try_from(v[2]).ok()?,
+    ))
+}
+
+/// 3D field of view by casting a ray from the center of the observer voxel to the center of every voxel in range.
+/// Rays that pass exactly through an edge or corner get through when any way around it is open.
+#[derive(Default, Clone)]
+pub struct FovVoxelRayCasting {
+    /// Visible voxels.
+    pub fov: HashSet<Voxel>,
+}
+
+impl FovVoxelRayCasting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn clear_fov(&mut self) {
+        self.fov.clear();
+    }
+    pub fn is_in_fov(&self, x: i16, y: i16, z: i16) -> bool {
+        self.fov.contains(&(x, y, z))
+    }
+    /// `max_radius` limits the distance on the xz plane, `max_vertical` the distance on the y axis.
+    pub fn compute_fov(
+        &mut self,
+        map: &VoxelMapData,
+        origin: Voxel,
+        max_radius: usize,
+        max_vertical: usize,
+    ) {
+        let o = [origin.0 as i32, origin.1 as i32, origin.2 as i32];
+        let radius = max_radius as i32;
+        let vertical = max_vertical as i32;
+        let r2 = radius * radius;
+        for dy in -vertical..=vertical {
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    if dx * dx + dz * dz > r2 {
+                        continue;
+                    }
+                    let target = [o[0] + dx, o[1] + dy, o[2] + dz];
+                    match to_voxel(target) {
+                        Some(voxel) => {
+                            if ray_reaches(map, o, [dx, dy, dz]) {
+                                self.fov.insert(voxel);
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+        self.fov.insert(origin);
+    }
+}
+
+/// Walk the voxels along the ray with integer arithmetic. Crossing the face on an axis happens at
+/// `t = (2k + 1) / (2|d|)`, compared exactly through cross multiplication.
+fn ray_reaches(map: &VoxelMapData, origin: [i32; 3], delta: [i32; 3]) -> bool {
+    let mut current = origin;
+    let mut crossed = [0i32; 3];
+    let length = [delta[0].abs(), delta[1].abs(), delta[2].abs()];
+    let sign = [delta[0].signum(), delta[1].signum(), delta[2].signum()];
+    loop {
+        // Next crossing of every axis that still has faces to cross, as the fraction (2k + 1) / (2|d|).
+        // Axes crossed at the same time are kept as a bit mask.
+        let mut next: Option<(i64, i64)> = None;
+        let mut axes: u8 = 0;
+        for axis in 0..3 {
+            if crossed[axis] >= length[axis] {
+                continue;
+            }
+            let t = ((2 * crossed[axis] + 1) as i64, (2 * length[axis]) as i64);
+            match next {
+                Some(n) => match (t.0 * n.1).cmp(&(n.0 * t.1)) {
+                    Ordering::Less => {
+                        next = Some(t);
+                        axes = 1 << axis;
+                    }
+                    Ordering::Equal => {
+                        axes |= 1 << axis;
+                    }
+                    Ordering::Greater => {}
+                },
+                None => {
+                    next = Some(t);
+                    axes = 1 << axis;
+                }
+            }
+        }
+        if axes == 0 {
+            return true;
+        }
+        if !corner_clear(map, current, axes, &sign) {
+            return false;
+        }
+        for axis in 0..3 {
+            if axes & (1 << axis) != 0 {
+                current[axis] += sign[axis];
+                crossed[axis] += 1;
+            }
+        }
+    }
+}
+
+/// Whether any order of crossing the faces on the axes of the `axes` bit mask is open.
+fn corner_clear(map: &VoxelMapData, voxel: [i32; 3], axes: u8, sign: &[i32; 3]) -> bool {
+    if axes == 0 {
+        return true;
+    }
+    for axis in 0..3 {
+        if axes & (1 << axis) == 0 || !map.can_cross(voxel, axis, sign[axis]) {
+            continue;
+        }
+        let mut next = voxel;
+        next[axis] += sign[axis];
+        if corner_clear(map, next, axes & !(1 << axis), sign) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voxel_fov_blocked_by_faces() {
+        let mut map = VoxelMapData::new();
+        // A wall on the x axis between x = 2 and x = 3 spanning z -1..=1, and a floor above the observer.
+        for z in -1..=1 {
+            map.set_face_blocked((2, 0, z), 0, true);
+        }
+        for x in -5..=5 {
+            for z in -5..=5 {
+                map.set_face_blocked((x, 0, z), 1, true);
+            }
+        }
+        let mut fov = FovVoxelRayCasting::new();
+        fov.compute_fov(&map, (0, 0, 0), 8, 2);
+        assert!(fov.is_in_fov(2, 0, 0));
+        assert!(!fov.is_in_fov(3, 0, 0));
+        assert!(!fov.is_in_fov(5, 0, 0));
+        assert!(fov.is_in_fov(3, 0, 4));
+        assert!(!fov.is_in_fov(0, 1, 0));
+        assert!(fov.is_in_fov(0, -1, 0));
+    }
+}
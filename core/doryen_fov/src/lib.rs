@@ -4,8 +4,11 @@ mod fov_recursive_shadowcasting;
 
 mod fov_restrictive;
 
+mod fov_voxel;
+
 pub use fov_recursive_shadowcasting::*;
 pub use fov_restrictive::*;
+pub use fov_voxel::*;
 
 /// Some basic structure to store map cells' transparency and fov computation result
 
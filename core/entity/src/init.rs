@@ -13,29 +13,54 @@ pub struct RawEntityRon {
     pub scale: Vec3,
     pub data: String,
 }
-use std::path::Path;
-
-use bevy::prelude::EventWriter;
-use std::fs;
+use bevy::log::error;
+use bevy::prelude::{EventWriter, Res};
+use resources::maps::{read_map_file, MapError, SelectedMap};
 
 use crate::entity_data::RawSpawnEvent;
 
 /// Build the entities from ron.
-pub(crate) fn load_ron_entities(mut raw_spawner: EventWriter<RawSpawnEvent>) {
-    let entities_ron = Path::new("data")
-        .join("maps")
-        .join("bullseye")
-        .join("entities.ron");
-    let current_map_entities_raw_ron: String =
-        fs::read_to_string(entities_ron).expect("Error reading map entities.ron file from drive.");
+pub(crate) fn load_ron_entities(
+    mut raw_spawner: EventWriter<RawSpawnEvent>,
+    selected_map: Option<Res<SelectedMap>>,
+) {
+    let map;
+    match selected_map {
+        Some(m) => {
+            map = m;
+        }
+        None => {
+            error!("No map selected, no entities to spawn.");
+            return;
+        }
+    }
+    let entities_path = map.entities_path();
+    let current_map_entities_raw_ron;
+    match read_map_file(&entities_path) {
+        Ok(raw) => {
+            current_map_entities_raw_ron = String::from_utf8_lossy(&raw).to_string();
+        }
+        Err(rr) => {
+            error!("{}", rr);
+            return;
+        }
+    }
 
     if current_map_entities_raw_ron.len() == 0 {
-        warn!("Empty entities.ron map file.");
+        warn!("Empty entities file {:?}.", entities_path);
         return;
     }
 
-    let current_map_entities_data: Vec<RawEntityRon> = ron::from_str(&current_map_entities_raw_ron)
-        .expect("Error parsing map entities.ron String.");
+    let current_map_entities_data: Vec<RawEntityRon>;
+    match ron::from_str(&current_map_entities_raw_ron) {
+        Ok(data) => {
+            current_map_entities_data = data;
+        }
+        Err(rr) => {
+            error!("{}", MapError::Parse(entities_path, rr.to_string()));
+            return;
+        }
+    }
 
     for raw_entity in current_map_entities_data.iter() {
         raw_spawner.send(RawSpawnEvent {
@@ -1,6 +1,6 @@
 use bevy::prelude::{Component, Entity};
-use doryen_fov::FovRecursiveShadowCasting;
-use resources::math::Vec2Int;
+use doryen_fov::FovVoxelRayCasting;
+use resources::math::Vec3Int;
 
 /// Used to check if entities are authorized to examine and obtain certain additional data.
 #[derive(PartialEq)]
@@ -14,8 +14,9 @@ pub enum SensingAbility {
 #[derive(Component)]
 
 pub struct Senser {
-    pub cell_id: Vec2Int,
-    pub fov: FovRecursiveShadowCasting,
+    /// Cell the FOV was last computed from.
+    pub cell_id: Option<Vec3Int>,
+    pub fov: FovVoxelRayCasting,
     pub sensing: Vec<Entity>,
     pub sfx: Vec<Entity>,
     pub sensing_abilities: Vec<SensingAbility>,
@@ -24,8 +25,8 @@ pub struct Senser {
 impl Default for Senser {
     fn default() -> Self {
         Self {
-            cell_id: Vec2Int { x: 0, y: 0 },
-            fov: FovRecursiveShadowCasting::new(WORLD_WIDTH_CELLS, WORLD_WIDTH_CELLS),
+            cell_id: None,
+            fov: FovVoxelRayCasting::new(),
             sensing: vec![],
             sfx: vec![],
             sensing_abilities: vec![],
@@ -33,26 +34,14 @@ impl Default for Senser {
     }
 }
 
-/// Turning up these values drastically increases fov calculation time.
-/// Dividible by 2.
-
-pub const WORLD_WIDTH_CELLS: usize = 1024;
-
-/// Use this to use the Doryen FOV algorithm.
-
-pub fn to_doryen_coordinates(x: i16, y: i16) -> (usize, usize) {
-    let mut n_x = x + WORLD_WIDTH_CELLS as i16 / 2;
-    let mut n_y = y + WORLD_WIDTH_CELLS as i16 / 2;
-
-    if doryen_coordinates_out_of_range(n_x as usize, n_y as usize) {
-        n_x = 0;
-        n_y = 0;
+impl Senser {
+    /// Whether the cell is in the field of view of this senser.
+    pub fn is_in_fov(&self, id: Vec3Int) -> bool {
+        self.fov.is_in_fov(id.x, id.y, id.z)
     }
-
-    (n_x as usize, n_y as usize)
 }
-/// Check if supplied doryen coordinates are out of range as a function.
 
-pub fn doryen_coordinates_out_of_range(x: usize, y: usize) -> bool {
-    x > WORLD_WIDTH_CELLS || y > WORLD_WIDTH_CELLS
-}
+/// Width of the world in cells on the x and z axes.
+/// Dividible by 2.
+
+pub const WORLD_WIDTH_CELLS: usize = 1024;
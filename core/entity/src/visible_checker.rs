@@ -2,12 +2,40 @@ use bevy::prelude::{Entity, EventWriter, Query, Transform};
 
 use crate::{sensable::Sensable, senser::Senser};
 use networking::server::ConnectedPlayer;
+use resources::math::world_to_cell_id;
 
 use crate::spawning_events::SpawnClientEntity;
 use networking::server::OutgoingReliableServerMessage;
 
 use crate::net::EntityServerMessage;
-/// Perform FOV checks to see what is and what isn't visible.
+
+/// Distance in which audible entities are sensed, also through walls.
+pub const AUDIBLE_DISTANCE: f32 = 16.;
+
+/// Whether a senser senses an entity: it is always sensed, it is the senser itself, it is in FOV or it is audible and near.
+pub fn is_sensed(
+    senser_entity: Entity,
+    senser: &Senser,
+    senser_transform: &Transform,
+    sensable_entity: Entity,
+    sensable: &Sensable,
+    sensable_transform: &Transform,
+) -> bool {
+    if sensable.always_sensed || senser_entity == sensable_entity {
+        return true;
+    }
+    if sensable.is_audible
+        && senser_transform
+            .translation
+            .distance(sensable_transform.translation)
+            <= AUDIBLE_DISTANCE
+    {
+        return true;
+    }
+    senser.is_in_fov(world_to_cell_id(sensable_transform.translation))
+}
+
+/// Perform FOV checks to see what is and what isn't visible. Loads entities on clients as they get sensed and unloads them once they aren't.
 
 pub(crate) fn visible_checker(
     mut query_visible_entities: Query<(Entity, &mut Sensable, &Transform)>,
@@ -23,14 +51,21 @@ pub(crate) fn visible_checker(
     for (
         visible_checker_entity_id,
         mut senser_component,
-        _visible_checker_rigid_body_position_component,
+        visible_checker_transform_component,
         visible_checker_component_option,
     ) in query_visible_checker_entities_rigid.iter_mut()
     {
-        for (visible_entity_id, mut sensable_component, _visible_transform_component) in
+        for (visible_entity_id, mut sensable_component, visible_transform_component) in
             query_visible_entities.iter_mut()
         {
-            let is_sensed = true;
+            let is_sensed = is_sensed(
+                visible_checker_entity_id,
+                &senser_component,
+                visible_checker_transform_component,
+                visible_entity_id,
+                &sensable_component,
+                visible_transform_component,
+            );
 
             let sensed_by_contains = sensable_component
                 .sensed_by
@@ -60,13 +95,23 @@ pub(crate) fn visible_checker(
                     match senser_component
                         .sensing
                         .iter()
-                        .position(|x| x == &visible_checker_entity_id)
+                        .position(|x| x == &visible_entity_id)
                     {
                         Some(index) => {
                             senser_component.sensing.remove(index);
                         }
                         None => {}
                     }
+                    match senser_component
+                        .sfx
+                        .iter()
+                        .position(|x| x == &visible_entity_id)
+                    {
+                        Some(index) => {
+                            senser_component.sfx.remove(index);
+                        }
+                        None => {}
+                    }
                 }
             } else {
                 if !senser_component.sensing.contains(&visible_entity_id) {
@@ -141,3 +186,83 @@ pub(crate) fn visible_checker(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> Transform {
+        Transform::from_xyz(x, 0., 0.)
+    }
+
+    #[test]
+    fn test_always_sensed() {
+        let sensable = Sensable {
+            always_sensed: true,
+            ..Default::default()
+        };
+        assert!(is_sensed(
+            Entity::from_raw(1),
+            &Senser::default(),
+            &at(0.),
+            Entity::from_raw(2),
+            &sensable,
+            &at(1000.),
+        ));
+    }
+
+    #[test]
+    fn test_senser_senses_itself() {
+        let entity = Entity::from_raw(1);
+        assert!(is_sensed(
+            entity,
+            &Senser::default(),
+            &at(0.),
+            entity,
+            &Sensable::default(),
+            &at(0.),
+        ));
+    }
+
+    #[test]
+    fn test_audible_within_distance() {
+        let senser = Senser::default();
+        let audible = Sensable {
+            is_audible: true,
+            ..Default::default()
+        };
+        let sensed = |sensable: &Sensable, x: f32| {
+            is_sensed(
+                Entity::from_raw(1),
+                &senser,
+                &at(0.),
+                Entity::from_raw(2),
+                sensable,
+                &at(x),
+            )
+        };
+        assert!(sensed(&audible, 1.));
+        assert!(sensed(&audible, AUDIBLE_DISTANCE));
+        assert!(!sensed(&audible, AUDIBLE_DISTANCE + 0.01));
+        // Silent entities nearby but out of view aren't sensed.
+        assert!(!sensed(&Sensable::default(), 1.));
+    }
+
+    #[test]
+    fn test_sensed_in_fov() {
+        let mut senser = Senser::default();
+        senser.fov.fov.insert((40, 0, 0));
+        let sensed = |x: f32| {
+            is_sensed(
+                Entity::from_raw(1),
+                &senser,
+                &at(0.),
+                Entity::from_raw(2),
+                &Sensable::default(),
+                &at(x),
+            )
+        };
+        assert!(sensed(40.5));
+        assert!(!sensed(41.5));
+    }
+}
@@ -0,0 +1,56 @@
+//! Compares chunk colliders with a cuboid per tile against greedy-merged colliders.
+//!
+//! Run with `cargo bench -p gridmap`.
+
+use bevy::prelude::{Quat, Vec3};
+use bevy_xpbd_3d::prelude::Collider;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gridmap::colliders::merge_chunk_colliders;
+
+/// A 32 by 32 floor plane enclosed by walls two cells high, the typical content of a ship chunk.
+fn room_colliders() -> Vec<(Vec3, Quat, Collider)> {
+    let mut data = vec![];
+    for x in 0..32 {
+        for z in 0..32 {
+            data.push((
+                Vec3::new(x as f32, 0., z as f32),
+                Quat::IDENTITY,
+                Collider::cuboid(1., 0.2, 1.),
+            ));
+        }
+    }
+    let quarter = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+    for y in 1..3 {
+        for i in 0..32 {
+            for (position, rotation) in [
+                (Vec3::new(i as f32, y as f32, -0.5), Quat::IDENTITY),
+                (Vec3::new(i as f32, y as f32, 31.5), Quat::IDENTITY),
+                (Vec3::new(-0.5, y as f32, i as f32), quarter),
+                (Vec3::new(31.5, y as f32, i as f32), quarter),
+            ] {
+                data.push((position, rotation, Collider::cuboid(1., 1., 0.2)));
+            }
+        }
+    }
+    data
+}
+
+fn build(c: &mut Criterion) {
+    let data = room_colliders();
+    println!(
+        "Room chunk: {} cuboids per tile, {} merged.",
+        data.len(),
+        merge_chunk_colliders(data.clone()).len()
+    );
+    let mut group = c.benchmark_group("chunk_collider_build");
+    group.bench_function("per_tile", |b| {
+        b.iter(|| black_box(Collider::compound(data.clone())))
+    });
+    group.bench_function("merged", |b| {
+        b.iter(|| black_box(Collider::compound(merge_chunk_colliders(data.clone()))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, build);
+criterion_main!(benches);
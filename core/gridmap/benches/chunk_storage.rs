@@ -0,0 +1,119 @@
+//! Compares the sparse gridmap chunk storage against fully allocated chunks.
+//!
+//! Run with `cargo bench -p gridmap`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gridmap::grid::{CellItem, GridCell, GridmapChunk, GRID_CHUNK_TILES_AMOUNT};
+
+/// The chunk layout before sparse storage, every cell slot allocated up front.
+struct DenseChunk {
+    cells: Vec<Option<GridCell>>,
+}
+
+impl DenseChunk {
+    fn new() -> Self {
+        Self {
+            cells: vec![None; GRID_CHUNK_TILES_AMOUNT],
+        }
+    }
+}
+
+fn floor_cell() -> GridCell {
+    GridCell {
+        floor: Some(CellItem::default()),
+        ..Default::default()
+    }
+}
+
+/// A single 32 by 32 floor plane, the typical content of a ship chunk.
+fn floor_plane_indexes() -> Vec<usize> {
+    (0..32 * 32).collect()
+}
+
+fn insert(c: &mut Criterion) {
+    let indexes = floor_plane_indexes();
+    let mut group = c.benchmark_group("chunk_insert_floor_plane");
+    group.bench_function("dense", |b| {
+        b.iter(|| {
+            let mut chunk = DenseChunk::new();
+            for i in indexes.iter() {
+                chunk.cells[*i] = Some(floor_cell());
+            }
+            black_box(chunk);
+        })
+    });
+    group.bench_function("sparse", |b| {
+        b.iter(|| {
+            let mut chunk = GridmapChunk::default();
+            for i in indexes.iter() {
+                *chunk.get_or_insert(*i) = floor_cell();
+            }
+            black_box(chunk);
+        })
+    });
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let indexes = floor_plane_indexes();
+    let mut dense = DenseChunk::new();
+    let mut sparse = GridmapChunk::default();
+    for i in indexes.iter() {
+        dense.cells[*i] = Some(floor_cell());
+        *sparse.get_or_insert(*i) = floor_cell();
+    }
+    let mut group = c.benchmark_group("chunk_lookup");
+    group.bench_function("dense", |b| {
+        b.iter(|| {
+            for i in (0..GRID_CHUNK_TILES_AMOUNT).step_by(7) {
+                black_box(dense.cells[i].as_ref());
+            }
+        })
+    });
+    group.bench_function("sparse", |b| {
+        b.iter(|| {
+            for i in (0..GRID_CHUNK_TILES_AMOUNT).step_by(7) {
+                black_box(sparse.get(i));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let indexes = floor_plane_indexes();
+    let mut dense = DenseChunk::new();
+    let mut sparse = GridmapChunk::default();
+    for i in indexes.iter() {
+        dense.cells[*i] = Some(floor_cell());
+        *sparse.get_or_insert(*i) = floor_cell();
+    }
+    let mut group = c.benchmark_group("chunk_iterate_occupied");
+    group.bench_function("dense", |b| {
+        b.iter(|| {
+            let mut items = 0;
+            for cell in dense.cells.iter() {
+                match cell {
+                    Some(cell) => {
+                        items += cell.get_items().len();
+                    }
+                    None => {}
+                }
+            }
+            black_box(items);
+        })
+    });
+    group.bench_function("sparse", |b| {
+        b.iter(|| {
+            let mut items = 0;
+            for (_, cell) in sparse.cells.iter() {
+                items += cell.get_items().len();
+            }
+            black_box(items);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, insert, lookup, iterate);
+criterion_main!(benches);
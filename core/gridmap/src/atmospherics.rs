@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::{Entity, EventReader, Res, ResMut, Resource};
+use resources::{
+    grid::{CellFace, GridId, TargetCell},
+    math::{Vec3Int, CELL_SIZE},
+};
+
+use crate::grid::{AddTile, Gridmap, LayerTargetCell, RemoveTile};
+use crate::layers::LayerId;
+
+/// Molar gas constant in J/(mol K).
+const GAS_CONSTANT: f32 = 8.314;
+/// Volume of a cell in m³.
+pub const CELL_VOLUME: f32 = CELL_SIZE * CELL_SIZE * CELL_SIZE;
+/// Temperature of fresh air in kelvin.
+pub const STANDARD_TEMPERATURE: f32 = 293.15;
+/// Pressure of fresh air in kPa.
+pub const STANDARD_PRESSURE: f32 = 101.325;
+/// Temperature of space in kelvin.
+pub const SPACE_TEMPERATURE: f32 = 2.7;
+/// Most cells between a floor below and a floor above for the cells between them to hold gas.
+pub const MAX_ROOM_HEIGHT: i16 = 4;
+/// Seconds between simulation ticks.
+pub const ATMOSPHERICS_TICK: f32 = 1. / 4.;
+/// Share of the difference in gas between two open neighbours that flows per tick. Six neighbours times this must stay below 1.
+const DIFFUSION_RATE: f32 = 0.125;
+/// Share of the gas of a cell lost to space per tick for every side open to space.
+const VENT_RATE: f32 = 0.15;
+/// Moles below which a cell counts as vacuum.
+const MIN_MOLES: f32 = 0.001;
+
+/// Neighbour offsets with the axis of the face towards them.
+const NEIGHBOURS: [(Vec3Int, usize); 6] = [
+    (Vec3Int { x: 1, y: 0, z: 0 }, 0),
+    (Vec3Int { x: -1, y: 0, z: 0 }, 0),
+    (Vec3Int { x: 0, y: 1, z: 0 }, 1),
+    (Vec3Int { x: 0, y: -1, z: 0 }, 1),
+    (Vec3Int { x: 0, y: 0, z: 1 }, 2),
+    (Vec3Int { x: 0, y: 0, z: -1 }, 2),
+];
+
+/// Gas in a cell, in moles per gas.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GasMixture {
+    pub oxygen: f32,
+    pub nitrogen: f32,
+    pub carbon_dioxide: f32,
+    /// Kelvin.
+    pub temperature: f32,
+}
+
+impl GasMixture {
+    /// Breathable air at standard pressure and temperature.
+    pub fn standard_air() -> Self {
+        let moles = STANDARD_PRESSURE * 1000. * CELL_VOLUME / (GAS_CONSTANT * STANDARD_TEMPERATURE);
+        Self {
+            oxygen: moles * 0.21,
+            nitrogen: moles * 0.79,
+            carbon_dioxide: 0.,
+            temperature: STANDARD_TEMPERATURE,
+        }
+    }
+    pub fn vacuum() -> Self {
+        Self {
+            temperature: SPACE_TEMPERATURE,
+            ..Default::default()
+        }
+    }
+    pub fn total_moles(&self) -> f32 {
+        self.oxygen + self.nitrogen + self.carbon_dioxide
+    }
+    /// Pressure of an amount of gas in a cell in kPa.
+    pub fn partial_pressure(&self, moles: f32) -> f32 {
+        moles * GAS_CONSTANT * self.temperature / CELL_VOLUME / 1000.
+    }
+    /// Pressure in kPa.
+    pub fn pressure(&self) -> f32 {
+        self.partial_pressure(self.total_moles())
+    }
+    fn gases(&self) -> [f32; 3] {
+        [self.oxygen, self.nitrogen, self.carbon_dioxide]
+    }
+}
+
+/// Gas in and out of a cell during a tick. Heat is tracked as moles times temperature.
+#[derive(Default)]
+struct GasDelta {
+    gases: [f32; 3],
+    heat: f32,
+}
+
+/// Per cell gas mixtures of the main gridmap layer on the server.
+/// Cells hold gas when they are enclosed by a floor below and a floor above, every other cell is space.
+#[derive(Resource, Default)]
+pub struct Atmospherics {
+    pub cells: HashMap<Vec3Int, GasMixture>,
+    /// Faces that block gas, as bits of the axis of the face after the cell like [Gridmap::get_face_between_cells].
+    blocked: HashMap<Vec3Int, u8>,
+    /// Cells that let no gas through, such as those of closed airlocks and counter windows.
+    pub sealed: HashSet<Vec3Int>,
+    /// The cell sealed by each entity, to unseal it once the entity is gone.
+    sealed_by: HashMap<Entity, Vec3Int>,
+}
+
+impl Atmospherics {
+    pub fn get(&self, id: &Vec3Int) -> Option<&GasMixture> {
+        self.cells.get(id)
+    }
+    /// Seal or unseal the cell of an entity, such as a closing or opening airlock.
+    pub fn set_entity_sealed(&mut self, entity: Entity, id: Vec3Int, sealed: bool) {
+        if sealed {
+            self.sealed.insert(id);
+            self.sealed_by.insert(entity, id);
+        } else {
+            self.sealed.remove(&id);
+            self.sealed_by.remove(&entity);
+        }
+    }
+    /// Unseal the cell of an entity that got removed.
+    pub fn remove_entity_seal(&mut self, entity: Entity) {
+        match self.sealed_by.remove(&entity) {
+            Some(id) => {
+                self.sealed.remove(&id);
+            }
+            None => {}
+        }
+    }
+    fn set_face_blocked(&mut self, id: Vec3Int, axis: usize, is_blocked: bool) {
+        let faces = self.blocked.entry(id).or_default();
+        if is_blocked {
+            *faces |= 1 << axis;
+        } else {
+            *faces &= !(1 << axis);
+        }
+        if *faces == 0 {
+            self.blocked.remove(&id);
+        }
+    }
+    /// Whether gas can flow from the cell to its neighbour at the offset.
+    fn is_open(&self, id: Vec3Int, offset: Vec3Int, axis: usize) -> bool {
+        let neighbour = id + offset;
+        if self.sealed.contains(&id) || self.sealed.contains(&neighbour) {
+            return false;
+        }
+        let face_cell = if offset.x + offset.y + offset.z > 0 {
+            id
+        } else {
+            neighbour
+        };
+        match self.blocked.get(&face_cell) {
+            Some(faces) => faces & (1 << axis) == 0,
+            None => true,
+        }
+    }
+
+    /// Diffuse gas between open neighbours and vent it to space through breaches.
+    pub fn tick(&mut self) {
+        let mut deltas: HashMap<Vec3Int, GasDelta> = HashMap::new();
+        for (id, mixture) in self.cells.iter() {
+            if self.sealed.contains(id) {
+                continue;
+            }
+            let gases = mixture.gases();
+            for (offset, axis) in NEIGHBOURS {
+                if !self.is_open(*id, offset, axis) {
+                    continue;
+                }
+                let neighbour = *id + offset;
+                match self.cells.get(&neighbour) {
+                    Some(other) => {
+                        // Every pair once, from the cell with the lower offset.
+                        if offset.x + offset.y + offset.z < 0 {
+                            continue;
+                        }
+                        let other_gases = other.gases();
+                        let mut flow = [0.; 3];
+                        let mut heat = 0.;
+                        for i in 0..3 {
+                            flow[i] = (gases[i] - other_gases[i]) * DIFFUSION_RATE;
+                            if flow[i] > 0. {
+                                heat += flow[i] * mixture.temperature;
+                            } else {
+                                heat += flow[i] * other.temperature;
+                            }
+                        }
+                        let from = deltas.entry(*id).or_default();
+                        for i in 0..3 {
+                            from.gases[i] -= flow[i];
+                        }
+                        from.heat -= heat;
+                        let to = deltas.entry(neighbour).or_default();
+                        for i in 0..3 {
+                            to.gases[i] += flow[i];
+                        }
+                        to.heat += heat;
+                    }
+                    None => {
+                        let delta = deltas.entry(*id).or_default();
+                        for i in 0..3 {
+                            delta.gases[i] -= gases[i] * VENT_RATE;
+                        }
+                        delta.heat -= mixture.total_moles() * VENT_RATE * mixture.temperature;
+                    }
+                }
+            }
+        }
+        for (id, delta) in deltas {
+            match self.cells.get_mut(&id) {
+                Some(mixture) => {
+                    let heat = mixture.total_moles() * mixture.temperature + delta.heat;
+                    mixture.oxygen = (mixture.oxygen + delta.gases[0]).max(0.);
+                    mixture.nitrogen = (mixture.nitrogen + delta.gases[1]).max(0.);
+                    mixture.carbon_dioxide = (mixture.carbon_dioxide + delta.gases[2]).max(0.);
+                    let moles = mixture.total_moles();
+                    if moles > MIN_MOLES {
+                        mixture.temperature = (heat / moles).max(SPACE_TEMPERATURE);
+                    } else {
+                        *mixture = GasMixture::vacuum();
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+fn has_floor(gridmap: &Gridmap, id: Vec3Int) -> bool {
+    gridmap
+        .get_cell(LayerTargetCell {
+            target: TargetCell {
+                id,
+                face: CellFace::Floor,
+                grid: GridId::MAIN,
+            },
+            layer: LayerId::STRUCTURE,
+        })
+        .is_some()
+}
+
+/// Whether a cell has a floor at most [MAX_ROOM_HEIGHT] below and above it.
+fn is_enclosed(gridmap: &Gridmap, id: Vec3Int) -> bool {
+    let offset = |y: i16| Vec3Int {
+        x: id.x,
+        y: id.y + y,
+        z: id.z,
+    };
+    (0..MAX_ROOM_HEIGHT).any(|y| has_floor(gridmap, offset(-y)))
+        && (1..=MAX_ROOM_HEIGHT).any(|y| has_floor(gridmap, offset(y)))
+}
+
+/// Keep the blocking faces and the cells holding gas up to date with tile changes.
+/// Cells enclosed by tiles of the map file start with fresh air, cells enclosed by construction start as vacuum.
+pub(crate) fn update_atmospherics_map(
+    mut add_events: EventReader<AddTile>,
+    mut remove_events: EventReader<RemoveTile>,
+    gridmap: Res<Gridmap>,
+    mut atmospherics: ResMut<Atmospherics>,
+) {
+    // Changed cells with whether they come from the map file.
+    let mut changed: HashMap<Vec3Int, bool> = HashMap::new();
+    for event in remove_events.read() {
+        if !gridmap.layer_collides(event.cell.layer) || event.cell.target.grid != GridId::MAIN {
+            continue;
+        }
+        match gridmap.get_face_between_cells(event.cell.target.clone()) {
+            Some((id, axis)) => {
+                atmospherics.set_face_blocked(id, axis, false);
+            }
+            None => {}
+        }
+        changed.entry(event.cell.target.id).or_insert(false);
+    }
+    for event in add_events.read() {
+        if !gridmap.layer_collides(event.layer) || event.grid != GridId::MAIN {
+            continue;
+        }
+        let blocks_gas = match gridmap.tile_properties.get(&event.tile_type) {
+            Some(properties) => properties.atmospherics_blocker,
+            None => true,
+        };
+        match gridmap.get_face_between_cells(event.target()) {
+            Some((id, axis)) => {
+                atmospherics.set_face_blocked(id, axis, blocks_gas);
+            }
+            None => {}
+        }
+        let from_map = changed.entry(event.id).or_insert(event.default_map_spawn);
+        *from_map = *from_map && event.default_map_spawn;
+    }
+
+    for (id, from_map) in changed {
+        for y in -MAX_ROOM_HEIGHT..=MAX_ROOM_HEIGHT {
+            let cell = Vec3Int {
+                x: id.x,
+                y: id.y + y,
+                z: id.z,
+            };
+            if is_enclosed(&gridmap, cell) {
+                if !atmospherics.cells.contains_key(&cell) {
+                    let mixture = if from_map {
+                        GasMixture::standard_air()
+                    } else {
+                        GasMixture::vacuum()
+                    };
+                    atmospherics.cells.insert(cell, mixture);
+                }
+            } else {
+                atmospherics.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+pub(crate) fn simulate_atmospherics(mut atmospherics: ResMut<Atmospherics>) {
+    atmospherics.tick();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atmospherics_vents_through_breach() {
+        let mut atmospherics = Atmospherics::default();
+        // A sealed room of two cells next to a breached cell.
+        let a = Vec3Int { x: 0, y: 0, z: 0 };
+        let b = Vec3Int { x: 1, y: 0, z: 0 };
+        atmospherics.cells.insert(a, GasMixture::standard_air());
+        atmospherics.cells.insert(b, GasMixture::vacuum());
+        for id in [a, b] {
+            atmospherics.set_face_blocked(id, 1, true);
+            atmospherics.set_face_blocked(id - Vec3Int { x: 0, y: 1, z: 0 }, 1, true);
+            atmospherics.set_face_blocked(id, 2, true);
+            atmospherics.set_face_blocked(id - Vec3Int { x: 0, y: 0, z: 1 }, 2, true);
+        }
+        atmospherics.set_face_blocked(a - Vec3Int { x: 1, y: 0, z: 0 }, 0, true);
+        atmospherics.set_face_blocked(b, 0, true);
+
+        let total = atmospherics.cells[&a].total_moles();
+        for _ in 0..100 {
+            atmospherics.tick();
+        }
+        let pressure_a = atmospherics.cells[&a].pressure();
+        let pressure_b = atmospherics.cells[&b].pressure();
+        assert!((pressure_a - pressure_b).abs() < 0.1);
+        let moles = atmospherics.cells[&a].total_moles() + atmospherics.cells[&b].total_moles();
+        assert!((moles - total).abs() < 0.01);
+
+        // Breach the outer wall of the second cell.
+        atmospherics.set_face_blocked(b, 0, false);
+        for _ in 0..200 {
+            atmospherics.tick();
+        }
+        assert!(atmospherics.cells[&a].pressure() < 1.);
+    }
+
+    #[test]
+    fn test_removed_entity_unseals() {
+        let mut atmospherics = Atmospherics::default();
+        let airlock = Entity::from_raw(1);
+        let id = Vec3Int { x: 2, y: 0, z: 0 };
+        atmospherics.set_entity_sealed(airlock, id, true);
+        assert!(atmospherics.sealed.contains(&id));
+        atmospherics.remove_entity_seal(airlock);
+        assert!(!atmospherics.sealed.contains(&id));
+    }
+}
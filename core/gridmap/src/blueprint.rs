@@ -0,0 +1,711 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::log::warn;
+use bevy::prelude::{Quat, Resource, Vec3};
+use resources::{
+    grid::{CellFace, GridId, TargetCell},
+    math::Vec3Int,
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    format::legacy_layer,
+    grid::{
+        CellIndexes, CellTypeId, CellTypeName, Gridmap, GroupInstance, GroupTypeId, GroupTypeName,
+        LayerTargetCell, Orthogonal, OrthogonalBases, TileMetadata,
+    },
+    net::NewCell,
+};
+
+/// File extension of blueprints.
+pub const BLUEPRINT_EXTENSION: &str = "ron";
+/// Largest amount of cells a blueprint region may span.
+pub const MAX_BLUEPRINT_VOLUME: i64 = 65536;
+/// Prefix of blueprints in the construction options list.
+pub const BLUEPRINT_OPTION_PREFIX: &str = "blueprint:";
+
+/// Directory blueprints are saved in. Blueprints refer to tiles by name so they can be copied between servers.
+pub fn blueprint_directory() -> PathBuf {
+    Path::new("data").join("blueprints")
+}
+
+/// A cell of a blueprint, relative to the lowest corner of the copied region.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlueprintCell {
+    pub offset: Vec3Int,
+    pub face: CellFace,
+    pub orientation: u8,
+    pub tile: CellTypeName,
+    /// Name of the layer of the tile.
+    pub layer: String,
+    #[serde(default)]
+    pub metadata: TileMetadata,
+}
+
+/// A tile group instance of a blueprint. Gets pasted whole as a new group instance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlueprintGroup {
+    pub group: GroupTypeName,
+    /// Origin of the group relative to the lowest corner of the copied region.
+    pub offset: Vec3Int,
+    /// Index of [OrthogonalBases] the group was placed with.
+    pub orientation: u8,
+}
+
+/// A named copy of a region of the gridmap that can be pasted elsewhere.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Blueprint {
+    pub name: String,
+    /// Tiles that aren't part of a group.
+    pub cells: Vec<BlueprintCell>,
+    pub groups: Vec<BlueprintGroup>,
+}
+
+/// Blueprint cells as stored in files, including those saved before named layers.
+#[derive(Deserialize)]
+struct BlueprintCellFile {
+    offset: Vec3Int,
+    face: CellFace,
+    orientation: u8,
+    tile: CellTypeName,
+    #[serde(default)]
+    layer: String,
+    /// Blueprints from before named layers only tell whether the tile is a detail.
+    #[serde(default)]
+    is_detail: bool,
+    #[serde(default)]
+    metadata: TileMetadata,
+}
+#[derive(Deserialize)]
+struct BlueprintFile {
+    name: String,
+    cells: Vec<BlueprintCellFile>,
+    #[serde(default)]
+    groups: Vec<BlueprintGroup>,
+}
+
+impl From<BlueprintFile> for Blueprint {
+    fn from(file: BlueprintFile) -> Self {
+        Self {
+            name: file.name,
+            cells: file
+                .cells
+                .into_iter()
+                .map(|cell| BlueprintCell {
+                    offset: cell.offset,
+                    face: cell.face,
+                    orientation: cell.orientation,
+                    tile: cell.tile,
+                    layer: match cell.layer.is_empty() {
+                        true => legacy_layer(cell.is_detail).to_string(),
+                        false => cell.layer,
+                    },
+                    metadata: cell.metadata,
+                })
+                .collect(),
+            groups: file.groups,
+        }
+    }
+}
+
+/// A group a pasted blueprint places.
+pub struct PlacedGroup {
+    pub group: GroupTypeId,
+    pub orientation: u8,
+    pub cells: Vec<NewCell>,
+}
+
+/// The cells a pasted blueprint places.
+#[derive(Default)]
+pub struct BlueprintPlacement {
+    pub cells: Vec<NewCell>,
+    pub groups: Vec<PlacedGroup>,
+}
+
+impl BlueprintPlacement {
+    /// Loose and group cells together.
+    pub fn all_cells(&self) -> Vec<NewCell> {
+        let mut cells = self.cells.clone();
+        for group in self.groups.iter() {
+            cells.extend(group.cells.iter().cloned());
+        }
+        cells
+    }
+}
+
+#[derive(Debug)]
+pub enum BlueprintError {
+    InvalidName(String),
+    TooLarge(i64),
+    Read(PathBuf, String),
+    Parse(PathBuf, String),
+    Write(PathBuf, String),
+    Exists(String),
+}
+
+impl fmt::Display for BlueprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlueprintError::InvalidName(name) => write!(
+                f,
+                "Invalid blueprint name \"{}\", use letters, digits, - and _.",
+                name
+            ),
+            BlueprintError::TooLarge(volume) => write!(
+                f,
+                "Blueprint region of {} cells exceeds the maximum of {}.",
+                volume, MAX_BLUEPRINT_VOLUME
+            ),
+            BlueprintError::Read(path, rr) => {
+                write!(f, "Couldn't read blueprint {:?}: {}", path, rr)
+            }
+            BlueprintError::Parse(path, rr) => {
+                write!(f, "Couldn't parse blueprint {:?}: {}", path, rr)
+            }
+            BlueprintError::Write(path, rr) => {
+                write!(f, "Couldn't write blueprint {:?}: {}", path, rr)
+            }
+            BlueprintError::Exists(name) => write!(
+                f,
+                "Blueprint {} exists already, pass true as second argument to overwrite it.",
+                name
+            ),
+        }
+    }
+}
+
+fn blueprint_path(name: &str) -> Result<PathBuf, BlueprintError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(BlueprintError::InvalidName(name.to_string()));
+    }
+    Ok(blueprint_directory().join(format!("{}.{}", name, BLUEPRINT_EXTENSION)))
+}
+
+/// Direction a face points to, [None] for the center.
+fn face_direction(face: &CellFace) -> Option<Vec3> {
+    match face {
+        CellFace::FrontWall => Some(Vec3::Z),
+        CellFace::BackWall => Some(Vec3::NEG_Z),
+        CellFace::RightWall => Some(Vec3::X),
+        CellFace::LeftWall => Some(Vec3::NEG_X),
+        CellFace::Ceiling => Some(Vec3::Y),
+        CellFace::Floor => Some(Vec3::NEG_Y),
+        CellFace::Center => None,
+    }
+}
+
+/// The face a face ends up as after rotating its cell.
+pub fn rotate_face(face: &CellFace, rotation: Quat) -> CellFace {
+    let direction;
+    match face_direction(face) {
+        Some(d) => {
+            direction = rotation.mul_vec3(d).round();
+        }
+        None => {
+            return CellFace::Center;
+        }
+    }
+    if direction == Vec3::Z {
+        CellFace::FrontWall
+    } else if direction == Vec3::NEG_Z {
+        CellFace::BackWall
+    } else if direction == Vec3::X {
+        CellFace::RightWall
+    } else if direction == Vec3::NEG_X {
+        CellFace::LeftWall
+    } else if direction == Vec3::Y {
+        CellFace::Ceiling
+    } else {
+        CellFace::Floor
+    }
+}
+
+/// Offset of a cell relative to a rotation origin after rotating it.
+pub fn rotate_offset(offset: Vec3Int, rotation: Quat) -> Vec3Int {
+    let rotated = rotation
+        .mul_vec3(Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32))
+        .round();
+    Vec3Int {
+        x: rotated.x as i16,
+        y: rotated.y as i16,
+        z: rotated.z as i16,
+    }
+}
+
+/// Face and orientation of a tile after rotating its cell.
+/// Walls get their rotation from their face, other tiles rotate along.
+pub fn rotate_placement(face: &CellFace, orientation: u8, rotation: Quat) -> (CellFace, u8) {
+    let bases = OrthogonalBases::default().bases;
+    let rotated_orientation =
+        (rotation * bases[orientation as usize % bases.len()]).get_orthogonal_index();
+    let new_orientation;
+    match face_direction(face) {
+        Some(direction) => {
+            if direction.y == 0. {
+                new_orientation = orientation;
+            } else {
+                new_orientation = rotated_orientation;
+            }
+        }
+        None => {
+            new_orientation = rotated_orientation;
+        }
+    }
+    (rotate_face(face, rotation), new_orientation)
+}
+
+/// Origin of a placed group instance, found by matching its tiles against the cells the group places.
+fn group_origin(
+    gridmap: &Gridmap,
+    grid: GridId,
+    instance: &GroupInstance,
+    tiles: &Vec<(TargetCell, CellTypeId)>,
+) -> Option<Vec3Int> {
+    let strict = |target: &TargetCell| {
+        let strict = gridmap.get_strict_cell(target.clone());
+        (strict.id, strict.face)
+    };
+    let (first, first_type) = tiles.first()?;
+    let (first_id, first_face) = strict(first);
+    let at_zero = gridmap.group_cells(
+        instance.group,
+        grid,
+        Vec3Int { x: 0, y: 0, z: 0 },
+        instance.orientation,
+    );
+    for candidate in at_zero.iter() {
+        if candidate.tile_type != *first_type {
+            continue;
+        }
+        let (candidate_id, candidate_face) = strict(&candidate.cell.target);
+        if candidate_face != first_face {
+            continue;
+        }
+        let origin = first_id - candidate_id;
+        let placed: Vec<_> = gridmap
+            .group_cells(instance.group, grid, origin, instance.orientation)
+            .iter()
+            .map(|new| (strict(&new.cell.target), new.tile_type))
+            .collect();
+        if tiles
+            .iter()
+            .all(|(target, tile_type)| placed.contains(&(strict(target), *tile_type)))
+        {
+            return Some(origin);
+        }
+    }
+    None
+}
+
+impl Blueprint {
+    /// Copy all cells of all layers of a grid in the box spanned by two corners.
+    /// Group instances with tiles in the box get copied whole.
+    pub fn capture(
+        name: &str,
+        gridmap: &Gridmap,
+        grid: GridId,
+        corner_a: Vec3Int,
+        corner_b: Vec3Int,
+    ) -> Result<Self, BlueprintError> {
+        blueprint_path(name)?;
+        let min = Vec3Int {
+            x: corner_a.x.min(corner_b.x),
+            y: corner_a.y.min(corner_b.y),
+            z: corner_a.z.min(corner_b.z),
+        };
+        let max = Vec3Int {
+            x: corner_a.x.max(corner_b.x),
+            y: corner_a.y.max(corner_b.y),
+            z: corner_a.z.max(corner_b.z),
+        };
+        let volume = (max.x as i64 - min.x as i64 + 1)
+            * (max.y as i64 - min.y as i64 + 1)
+            * (max.z as i64 - min.z as i64 + 1);
+        if volume > MAX_BLUEPRINT_VOLUME {
+            return Err(BlueprintError::TooLarge(volume));
+        }
+
+        let mut cells = vec![];
+        let mut instances: HashMap<u32, Vec<(TargetCell, CellTypeId, BlueprintCell)>> =
+            HashMap::new();
+        for (layer, properties) in gridmap.layer_properties.iter() {
+            let layer_name = properties.name.clone();
+            let chunks;
+            match gridmap.layer(grid, *layer) {
+                Some(c) => {
+                    chunks = c;
+                }
+                None => {
+                    continue;
+                }
+            }
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        let id = Vec3Int { x, y, z };
+                        let indexes: CellIndexes;
+                        match gridmap.get_indexes(id) {
+                            Ok(i) => {
+                                indexes = i;
+                            }
+                            Err(_) => {
+                                continue;
+                            }
+                        }
+                        let grid_cell;
+                        match chunks.get(&indexes.chunk) {
+                            Some(chunk) => match chunk.get(indexes.cell) {
+                                Some(c) => {
+                                    grid_cell = c;
+                                }
+                                None => {
+                                    continue;
+                                }
+                            },
+                            None => {
+                                continue;
+                            }
+                        }
+                        for (item, face) in grid_cell.get_items() {
+                            match gridmap.id_name_map.get(&item.tile_type) {
+                                Some(tile) => {
+                                    let cell = BlueprintCell {
+                                        offset: id - min,
+                                        face: face.clone(),
+                                        orientation: item.orientation,
+                                        tile: tile.clone(),
+                                        layer: layer_name.clone(),
+                                        metadata: item.metadata.clone(),
+                                    };
+                                    match item.group_id_option {
+                                        Some(group_id) => {
+                                            instances.entry(group_id).or_default().push((
+                                                TargetCell { id, face, grid },
+                                                item.tile_type,
+                                                cell,
+                                            ));
+                                        }
+                                        None => {
+                                            cells.push(cell);
+                                        }
+                                    }
+                                }
+                                None => {
+                                    warn!("Couldn't find name of tile {:?}.", item.tile_type);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut groups = vec![];
+        let mut instance_ids: Vec<&u32> = instances.keys().collect();
+        instance_ids.sort();
+        for instance_id in instance_ids {
+            let tiles = instances.get(instance_id).unwrap();
+            let group = gridmap
+                .group_instances
+                .get(instance_id)
+                .and_then(|instance| {
+                    let name = gridmap.id_group_map.get(&instance.group)?.clone();
+                    let targets = tiles
+                        .iter()
+                        .map(|(target, tile_type, _)| (target.clone(), *tile_type))
+                        .collect();
+                    let origin = group_origin(gridmap, grid, instance, &targets)?;
+                    Some(BlueprintGroup {
+                        group: name,
+                        offset: origin - min,
+                        orientation: instance.orientation,
+                    })
+                });
+            match group {
+                Some(g) => {
+                    groups.push(g);
+                }
+                None => {
+                    warn!(
+                        "Couldn't find the group of instance {}, copying its tiles loose.",
+                        instance_id
+                    );
+                    cells.extend(tiles.iter().map(|(_, _, cell)| cell.clone()));
+                }
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            cells,
+            groups,
+        })
+    }
+
+    /// Write the blueprint to the drive. Existing blueprints only get replaced with `overwrite`.
+    pub fn save(&self, overwrite: bool) -> Result<PathBuf, BlueprintError> {
+        let path = blueprint_path(&self.name)?;
+        if !overwrite && path.exists() {
+            return Err(BlueprintError::Exists(self.name.clone()));
+        }
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|rr| BlueprintError::Write(path.clone(), rr.to_string()))?;
+        fs::create_dir_all(blueprint_directory())
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|rr| BlueprintError::Write(path.clone(), rr.to_string()))?;
+        Ok(path)
+    }
+
+    pub fn load(name: &str) -> Result<Self, BlueprintError> {
+        let path = blueprint_path(name)?;
+        let text = fs::read_to_string(&path)
+            .map_err(|rr| BlueprintError::Read(path.clone(), rr.to_string()))?;
+        let file: BlueprintFile = ron::from_str(&text)
+            .map_err(|rr| BlueprintError::Parse(path.clone(), rr.to_string()))?;
+        Ok(file.into())
+    }
+
+    /// The cells this blueprint places at `origin` of `grid`, rotated by one of the [OrthogonalBases].
+    pub fn placed_cells(
+        &self,
+        gridmap: &Gridmap,
+        grid: GridId,
+        origin: Vec3Int,
+        orientation: u8,
+    ) -> BlueprintPlacement {
+        let bases = OrthogonalBases::default().bases;
+        let rotation = bases[orientation as usize % bases.len()];
+        let mut placed = BlueprintPlacement::default();
+        for group in self.groups.iter() {
+            let group_type;
+            match gridmap.group_id_map.get(&group.group) {
+                Some(id) => {
+                    group_type = *id;
+                }
+                None => {
+                    warn!(
+                        "Blueprint {} contains unknown group {:?}.",
+                        self.name, group.group
+                    );
+                    continue;
+                }
+            }
+            let group_orientation =
+                (rotation * bases[group.orientation as usize % bases.len()]).get_orthogonal_index();
+            placed.groups.push(PlacedGroup {
+                group: group_type,
+                orientation: group_orientation,
+                cells: gridmap.group_cells(
+                    group_type,
+                    grid,
+                    origin + rotate_offset(group.offset, rotation),
+                    group_orientation,
+                ),
+            });
+        }
+        for cell in self.cells.iter() {
+            let tile_type;
+            match gridmap.name_id_map.get(&cell.tile) {
+                Some(id) => {
+                    tile_type = *id;
+                }
+                None => {
+                    warn!(
+                        "Blueprint {} contains unknown tile {:?}.",
+                        self.name, cell.tile
+                    );
+                    continue;
+                }
+            }
+            let layer;
+            match gridmap.layer_id(&cell.layer) {
+                Some(id) => {
+                    layer = id;
+                }
+                None => {
+                    warn!(
+                        "Blueprint {} contains unknown layer {}.",
+                        self.name, cell.layer
+                    );
+                    continue;
+                }
+            }
+            let (face, cell_orientation) = rotate_placement(&cell.face, cell.orientation, rotation);
+            let offset = rotate_offset(cell.offset, rotation);
+            placed.cells.push(NewCell {
+                cell: LayerTargetCell {
+                    target: TargetCell {
+                        id: origin + offset,
+                        face,
+                        grid,
+                    },
+                    layer,
+                },
+                orientation: cell_orientation,
+                tile_type,
+                metadata: cell.metadata.clone(),
+            });
+        }
+        placed
+    }
+}
+
+/// Names of all blueprints on the drive.
+pub fn list_blueprints() -> Vec<String> {
+    let mut names = vec![];
+    match fs::read_dir(blueprint_directory()) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                match (path.extension(), path.file_stem()) {
+                    (Some(extension), Some(stem)) => {
+                        if extension == BLUEPRINT_EXTENSION {
+                            names.push(stem.to_string_lossy().to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(_) => {}
+    }
+    names.sort();
+    names
+}
+
+/// Blueprints loaded from the drive.
+#[derive(Resource, Default)]
+pub struct Blueprints {
+    pub map: HashMap<String, Blueprint>,
+}
+
+impl Blueprints {
+    pub fn get_or_load(&mut self, name: &str) -> Result<&Blueprint, BlueprintError> {
+        if !self.map.contains_key(name) {
+            let blueprint = Blueprint::load(name)?;
+            self.map.insert(name.to_string(), blueprint);
+        }
+        Ok(self.map.get(name).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellType, FullCell, TileProperties};
+    use crate::layers::{DETAILS_LAYER, STRUCTURE_LAYER};
+
+    #[test]
+    fn test_capture_and_paste_group() {
+        let mut gridmap = Gridmap::default();
+        let tile_type = CellTypeId(1);
+        gridmap.tile_properties.insert(
+            tile_type,
+            TileProperties {
+                cell_type: CellType::Center,
+                ..Default::default()
+            },
+        );
+        gridmap
+            .id_name_map
+            .insert(tile_type, CellTypeName("window".to_string()));
+        let group = GroupTypeId(0);
+        gridmap
+            .group_id_map
+            .insert(GroupTypeName("window_group".to_string()), group);
+        gridmap
+            .id_group_map
+            .insert(group, GroupTypeName("window_group".to_string()));
+        let mut members = HashMap::default();
+        for x in 0..2 {
+            members.insert(
+                Vec3Int { x, y: 0, z: 0 },
+                FullCell {
+                    face: CellFace::Center,
+                    orientation: 0,
+                    tile_type,
+                    entity_option: None,
+                },
+            );
+        }
+        gridmap.groups.insert(group, members);
+
+        let orientation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2).get_orthogonal_index();
+        let origin = Vec3Int { x: 5, y: 0, z: 5 };
+        let instance = gridmap.new_group_instance(group, orientation);
+        let placed = gridmap.group_cells(group, GridId::MAIN, origin, orientation);
+        for new in placed.iter() {
+            let item = gridmap.set_test_tile(
+                GridId::MAIN,
+                new.cell.target.id,
+                CellFace::Center,
+                tile_type,
+            );
+            item.group_id_option = Some(instance);
+            item.orientation = new.orientation;
+        }
+
+        // Only one of the two tiles is inside the region, the group gets copied whole.
+        let corner = placed
+            .iter()
+            .map(|new| new.cell.target.id)
+            .find(|id| *id != origin)
+            .unwrap();
+        let blueprint = Blueprint::capture("test", &gridmap, GridId::MAIN, corner, corner).unwrap();
+        assert!(blueprint.cells.is_empty());
+        assert_eq!(blueprint.groups.len(), 1);
+        assert_eq!(blueprint.groups[0].group.0, "window_group");
+        assert_eq!(blueprint.groups[0].offset, origin - corner);
+        assert_eq!(blueprint.groups[0].orientation, orientation);
+
+        let paste = Vec3Int { x: 10, y: 0, z: 10 };
+        let placement = blueprint.placed_cells(&gridmap, GridId::MAIN, paste, 0);
+        assert!(placement.cells.is_empty());
+        assert_eq!(placement.groups.len(), 1);
+        assert_eq!(placement.groups[0].group, group);
+        assert_eq!(placement.groups[0].orientation, orientation);
+        let ids: Vec<Vec3Int> = placement.groups[0]
+            .cells
+            .iter()
+            .map(|new| new.cell.target.id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&paste));
+        assert!(ids.contains(&(paste + origin - corner)));
+    }
+
+    #[test]
+    fn test_load_legacy_detail_cells() {
+        let text = r#"(
+            name: "old",
+            cells: [
+                (
+                    offset: (x: 0, y: 0, z: 0),
+                    face: Floor,
+                    orientation: 0,
+                    tile: ("generic_floor"),
+                ),
+                (
+                    offset: (x: 0, y: 0, z: 0),
+                    face: FrontWall,
+                    orientation: 0,
+                    tile: ("poster"),
+                    is_detail: true,
+                ),
+            ],
+        )"#;
+        let file: BlueprintFile = ron::from_str(text).unwrap();
+        let blueprint: Blueprint = file.into();
+        assert_eq!(blueprint.cells[0].layer, STRUCTURE_LAYER);
+        assert_eq!(blueprint.cells[1].layer, DETAILS_LAYER);
+        assert!(blueprint.groups.is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::log::warn;
+use bevy::prelude::{Commands, Mat3, Quat, Query, Res, ResMut, Resource, TransformBundle, Vec3};
+use bevy_xpbd_3d::components::{CoefficientCombine, CollisionLayers, Friction, RigidBody};
+use bevy_xpbd_3d::prelude::Collider;
+use networking::stamp::TickRateStamp;
+use physics::physics::{get_bit_masks, ColliderGroup};
+use physics::rigid_body::STANDARD_BODY_FRICTION;
+use resources::grid::{GridId, GridmapCollider};
+
+use crate::grid::Gridmap;
+
+/// Ticks a chunk has to go without tile changes before its collider gets rebuilt.
+/// Bursts of edits, like loading a map or pasting a blueprint, rebuild each chunk once.
+pub const CHUNK_COLLIDER_REBUILD_DELAY: u32 = 2;
+/// Most ticks a chunk waits for its rebuild, so chunks that keep changing still get new colliders.
+pub const CHUNK_COLLIDER_MAX_WAIT: u32 = 30;
+
+/// Merged boxes are compared on a grid of this many steps per unit, so float noise doesn't prevent merges.
+const MERGE_PRECISION: f32 = 1000.;
+
+/// Ticks of the first and last tile change of a chunk waiting for its collider rebuild.
+#[derive(Clone, Copy, Debug)]
+pub struct PendingRebuild {
+    pub first_change: u32,
+    pub last_change: u32,
+}
+
+/// Chunks waiting for their collider to get rebuilt.
+#[derive(Resource, Default)]
+pub struct ChunkColliderRebuilds {
+    pub pending: HashMap<(GridId, usize), PendingRebuild>,
+}
+
+impl ChunkColliderRebuilds {
+    /// Rebuild the collider of a chunk once it stops changing.
+    pub fn queue(&mut self, grid: GridId, chunk_id: usize, tick: u32) {
+        self.pending
+            .entry((grid, chunk_id))
+            .and_modify(|pending| pending.last_change = tick)
+            .or_insert(PendingRebuild {
+                first_change: tick,
+                last_change: tick,
+            });
+    }
+    /// Take the chunks that stopped changing or waited [CHUNK_COLLIDER_MAX_WAIT] ticks.
+    pub fn take_ready(&mut self, tick: u32) -> Vec<(GridId, usize)> {
+        let mut ready = vec![];
+        self.pending.retain(|chunk, pending| {
+            if tick.saturating_sub(pending.last_change) >= CHUNK_COLLIDER_REBUILD_DELAY
+                || tick.saturating_sub(pending.first_change) >= CHUNK_COLLIDER_MAX_WAIT
+            {
+                ready.push(*chunk);
+                false
+            } else {
+                true
+            }
+        });
+        ready
+    }
+}
+
+/// An axis-aligned box in [MERGE_PRECISION] steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MergeBox {
+    min: [i32; 3],
+    max: [i32; 3],
+}
+
+impl MergeBox {
+    /// [None] for colliders that aren't cuboids or aren't rotated onto the axes.
+    fn from_collider(position: Vec3, rotation: Quat, collider: &Collider) -> Option<Self> {
+        let half_extents;
+        match collider.shape_scaled().as_cuboid() {
+            Some(cuboid) => {
+                half_extents = Vec3::new(
+                    cuboid.half_extents.x,
+                    cuboid.half_extents.y,
+                    cuboid.half_extents.z,
+                );
+            }
+            None => {
+                return None;
+            }
+        }
+        let basis = Mat3::from_quat(rotation);
+        for axis in [basis.x_axis, basis.y_axis, basis.z_axis] {
+            if axis.abs().max_element() < 1. - 1e-4 {
+                return None;
+            }
+        }
+        let extents = basis.x_axis.abs() * half_extents.x
+            + basis.y_axis.abs() * half_extents.y
+            + basis.z_axis.abs() * half_extents.z;
+        let min = ((position - extents) * MERGE_PRECISION).round();
+        let max = ((position + extents) * MERGE_PRECISION).round();
+        Some(Self {
+            min: [min.x as i32, min.y as i32, min.z as i32],
+            max: [max.x as i32, max.y as i32, max.z as i32],
+        })
+    }
+    fn collider_data(&self) -> (Vec3, Quat, Collider) {
+        let min =
+            Vec3::new(self.min[0] as f32, self.min[1] as f32, self.min[2] as f32) / MERGE_PRECISION;
+        let max =
+            Vec3::new(self.max[0] as f32, self.max[1] as f32, self.max[2] as f32) / MERGE_PRECISION;
+        let size = max - min;
+        (
+            (min + max) / 2.,
+            Quat::IDENTITY,
+            Collider::cuboid(size.x, size.y, size.z),
+        )
+    }
+}
+
+/// Merge boxes along one axis that share their cross-section and touch or overlap.
+fn merge_along(boxes: Vec<MergeBox>, axis: usize) -> Vec<MergeBox> {
+    let others = [(axis + 1) % 3, (axis + 2) % 3];
+    let key = |b: &MergeBox| {
+        (
+            b.min[others[0]],
+            b.max[others[0]],
+            b.min[others[1]],
+            b.max[others[1]],
+        )
+    };
+    let mut sorted = boxes;
+    sorted.sort_by_key(|b| (key(b), b.min[axis]));
+    let mut merged: Vec<MergeBox> = vec![];
+    for next in sorted {
+        match merged.last_mut() {
+            Some(current) => {
+                if key(current) == key(&next) && next.min[axis] <= current.max[axis] {
+                    current.max[axis] = current.max[axis].max(next.max[axis]);
+                    continue;
+                }
+            }
+            None => {}
+        }
+        merged.push(next);
+    }
+    merged
+}
+
+/// Greedily merge the axis-aligned cuboid colliders of a chunk into as few boxes as possible.
+/// Rows get merged along x first, then into planes along z and finally into blocks along y.
+/// Other colliders, like diagonal floors, are kept as they are.
+pub fn merge_chunk_colliders(data: Vec<(Vec3, Quat, Collider)>) -> Vec<(Vec3, Quat, Collider)> {
+    let mut boxes = vec![];
+    let mut colliders = vec![];
+    for (position, rotation, collider) in data {
+        match MergeBox::from_collider(position, rotation, &collider) {
+            Some(b) => {
+                boxes.push(b);
+            }
+            None => {
+                colliders.push((position, rotation, collider));
+            }
+        }
+    }
+    for axis in [0, 2, 1] {
+        boxes = merge_along(boxes, axis);
+    }
+    colliders.extend(boxes.iter().map(|b| b.collider_data()));
+    colliders
+}
+
+/// Rebuild the colliders of chunks that stopped changing.
+pub(crate) fn rebuild_chunk_colliders(
+    mut rebuilds: ResMut<ChunkColliderRebuilds>,
+    mut commands: Commands,
+    mut gridmap: ResMut<Gridmap>,
+    mut gridmap_collider_query: Query<(&GridmapCollider, &mut Collider)>,
+    stamp: Res<TickRateStamp>,
+) {
+    for (grid, chunk_id) in rebuilds.take_ready(stamp.tick) {
+        if !gridmap.has_grid(grid) {
+            continue;
+        }
+        let chunk_colliders =
+            merge_chunk_colliders(gridmap.get_chunk_collider_data(grid, chunk_id));
+        let grid_entity_option = gridmap.grids.get(&grid).map(|sub| sub.entity);
+        let colliders = gridmap.chunk_colliders_mut(grid).unwrap();
+
+        match colliders.get(&chunk_id).copied() {
+            Some(chunk_collider_entity) => {
+                // The last colliding tile of the chunk is gone.
+                if chunk_colliders.is_empty() {
+                    commands.entity(chunk_collider_entity).despawn_recursive();
+                    colliders.remove(&chunk_id);
+                    continue;
+                }
+                match gridmap_collider_query.get_mut(chunk_collider_entity) {
+                    Ok((gridmap_collider_component, mut collider)) => {
+                        if gridmap_collider_component.chunk_id == chunk_id {
+                            *collider = Collider::compound(chunk_colliders);
+                        }
+                    }
+                    Err(_) => {
+                        warn!("No collider chunk found.");
+                    }
+                }
+            }
+            None => {
+                if chunk_colliders.is_empty() {
+                    continue;
+                }
+                let mut friction_component = Friction::new(STANDARD_BODY_FRICTION);
+                friction_component.combine_rule = CoefficientCombine::Min;
+                let masks = get_bit_masks(ColliderGroup::Standard);
+                let mut builder = commands.spawn((
+                    Collider::compound(chunk_colliders),
+                    GridmapCollider {
+                        chunk_id: chunk_id,
+                        grid,
+                    },
+                    friction_component,
+                    CollisionLayers::from_bits(masks.0, masks.1),
+                    TransformBundle::default(),
+                ));
+                match grid_entity_option {
+                    // Chunks of other grids are part of the rigid body of their grid.
+                    Some(grid_entity) => {
+                        builder.set_parent(grid_entity);
+                    }
+                    None => {
+                        builder.insert(RigidBody::Static);
+                    }
+                }
+                colliders.insert(chunk_id, builder.id());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::World;
+
+    fn floor(x: f32, z: f32) -> (Vec3, Quat, Collider) {
+        (
+            Vec3::new(x, 0., z),
+            Quat::IDENTITY,
+            Collider::cuboid(1., 0.2, 1.),
+        )
+    }
+
+    #[test]
+    fn test_merge_floor_plane() {
+        let mut data = vec![];
+        for x in 0..8 {
+            for z in 0..4 {
+                data.push(floor(x as f32, z as f32));
+            }
+        }
+        let merged = merge_chunk_colliders(data);
+        assert_eq!(merged.len(), 1);
+        let (position, _, collider) = &merged[0];
+        assert!(position.abs_diff_eq(Vec3::new(3.5, 0., 1.5), 1e-4));
+        let half_extents = collider.shape_scaled().as_cuboid().unwrap().half_extents;
+        assert!((half_extents.x - 4.).abs() < 1e-4);
+        assert!((half_extents.z - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_merge_keeps_other_colliders() {
+        let diagonal = Quat::from_rotation_x(std::f32::consts::FRAC_PI_4);
+        let data = vec![
+            floor(0., 0.),
+            floor(2., 0.),
+            (
+                Vec3::new(1., 0., 0.),
+                diagonal,
+                Collider::cuboid(1., 0.2, 1.),
+            ),
+            (Vec3::new(4., 0., 0.), Quat::IDENTITY, Collider::sphere(0.5)),
+        ];
+        // The gap between the floors keeps them apart.
+        assert_eq!(merge_chunk_colliders(data).len(), 4);
+    }
+
+    #[test]
+    fn test_merge_rotated_wall() {
+        let quarter = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let data = vec![
+            (
+                Vec3::new(0., 0., 0.),
+                quarter,
+                Collider::cuboid(1., 1., 0.2),
+            ),
+            (
+                Vec3::new(0., 0., 1.),
+                quarter,
+                Collider::cuboid(1., 1., 0.2),
+            ),
+        ];
+        assert_eq!(merge_chunk_colliders(data).len(), 1);
+    }
+
+    #[test]
+    fn test_rebuild_debounce() {
+        let mut rebuilds = ChunkColliderRebuilds::default();
+        rebuilds.queue(GridId::MAIN, 0, 10);
+        rebuilds.queue(GridId::MAIN, 1, 10);
+        assert!(rebuilds.take_ready(11).is_empty());
+        // Chunk 1 keeps changing every tick.
+        let mut tick = 11;
+        while tick < 10 + CHUNK_COLLIDER_MAX_WAIT {
+            rebuilds.queue(GridId::MAIN, 1, tick);
+            let ready = rebuilds.take_ready(tick);
+            if tick == 10 + CHUNK_COLLIDER_REBUILD_DELAY {
+                assert_eq!(ready, vec![(GridId::MAIN, 0)]);
+            } else {
+                assert!(ready.is_empty());
+            }
+            tick += 1;
+        }
+        rebuilds.queue(GridId::MAIN, 1, tick);
+        assert_eq!(rebuilds.take_ready(tick), vec![(GridId::MAIN, 1)]);
+        assert!(rebuilds.pending.is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_emptied_chunk() {
+        let mut world = World::new();
+        let chunk_collider = world
+            .spawn((
+                Collider::cuboid(1., 1., 1.),
+                GridmapCollider {
+                    chunk_id: 0,
+                    grid: GridId::MAIN,
+                },
+            ))
+            .id();
+        // The chunk has no tiles left.
+        let mut gridmap = Gridmap::default();
+        gridmap.colliders.insert(0, chunk_collider);
+        world.insert_resource(gridmap);
+        let mut rebuilds = ChunkColliderRebuilds::default();
+        rebuilds.queue(GridId::MAIN, 0, 0);
+        world.insert_resource(rebuilds);
+        world.insert_resource(TickRateStamp::new(CHUNK_COLLIDER_REBUILD_DELAY));
+
+        world.run_system_once(rebuild_chunk_colliders);
+        assert!(world.get_entity(chunk_collider).is_none());
+        assert!(world.resource::<Gridmap>().colliders.is_empty());
+    }
+}
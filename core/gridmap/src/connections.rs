@@ -1,7 +1,12 @@
-use bevy::prelude::{EventReader, Res};
+use bevy::prelude::{EventReader, Res, ResMut};
 use player::connections::SendServerConfiguration;
 
-use crate::{grid::Gridmap, net::GridmapServerMessage};
+use crate::{
+    download::{MapDownloads, ServedMap},
+    grid::Gridmap,
+    net::{GridSnapshot, GridmapServerMessage},
+    power::PowerNetwork,
+};
 use networking::server::OutgoingReliableServerMessage;
 
 use bevy::prelude::EventWriter;
@@ -10,6 +15,9 @@ pub(crate) fn configure(
     mut config_events: EventReader<SendServerConfiguration>,
     mut server: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
     gridmap_data: Res<Gridmap>,
+    mut served_map: ResMut<ServedMap>,
+    mut downloads: ResMut<MapDownloads>,
+    power: Res<PowerNetwork>,
 ) {
     for event in config_events.read() {
         server.send(OutgoingReliableServerMessage {
@@ -18,5 +26,40 @@ pub(crate) fn configure(
                 gridmap_data.ordered_names.clone(),
             ),
         });
+        server.send(OutgoingReliableServerMessage {
+            handle: event.handle,
+            message: GridmapServerMessage::ConfigMapLimits(gridmap_data.map_length_limit.clone()),
+        });
+        // Downloads serve the map as it was hashed here, even if it gets re-encoded in between.
+        let hash = served_map.get_hash(&gridmap_data);
+        downloads
+            .configured
+            .insert(event.handle, served_map.data.clone());
+        server.send(OutgoingReliableServerMessage {
+            handle: event.handle,
+            message: GridmapServerMessage::ConfigMapHash(hash),
+        });
+        if !power.unpowered_tiles.is_empty() {
+            server.send(OutgoingReliableServerMessage {
+                handle: event.handle,
+                message: GridmapServerMessage::TilesPowered(
+                    power
+                        .unpowered_tiles
+                        .iter()
+                        .map(|cell| (cell.clone(), false))
+                        .collect(),
+                ),
+            });
+        }
+        for (grid, sub) in gridmap_data.grids.iter() {
+            server.send(OutgoingReliableServerMessage {
+                handle: event.handle,
+                message: GridmapServerMessage::SpawnGrid(GridSnapshot {
+                    grid: *grid,
+                    motion: sub.motion(),
+                    cells: gridmap_data.get_grid_snapshot(*grid),
+                }),
+            });
+        }
     }
 }
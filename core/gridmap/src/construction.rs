@@ -1,9 +1,10 @@
 use std::{collections::HashMap, f32::consts::PI};
 
 use bevy::ecs::system::Local;
-use bevy::log::warn;
+use bevy::hierarchy::BuildChildren;
+use bevy::log::{info, warn};
 use bevy::math::Dir3;
-use bevy::prelude::TransformBundle;
+use bevy::prelude::{Added, SpatialBundle, TransformBundle};
 use bevy::transform::components::GlobalTransform;
 use bevy::{
     gltf::GltfMesh,
@@ -24,22 +25,27 @@ use networking::client::{IncomingReliableServerMessage, OutgoingReliableClientMe
 use physics::physics::{get_bit_masks, ColliderGroup};
 use resources::pawn::ClientPawn;
 use resources::{
-    grid::{CellFace, TargetCell},
+    grid::{CellFace, GridId, TargetCell},
     hud::HudState,
     input::{InputBuffer, KeyBind, KeyBinds, KeyCodeEnum},
 };
 use resources::{
-    math::{cell_id_to_world, world_to_cell_id, Vec2Int, Vec3Int},
+    math::{cell_id_to_world, Vec2Int, Vec3Int},
     ui::TextInput,
 };
 
+use crate::blueprint::Blueprint;
 use crate::grid::LayerTargetCell;
+use crate::layers::LayerId;
 use crate::{
     grid::{
         CellIds, CellTypeId, Gridmap, GroupTypeId, Orthogonal, OrthogonalBases,
         TargetCellWithOrientationWType,
     },
-    net::{ConstructCell, DeconstructCell, GridmapClientMessage, GridmapServerMessage},
+    net::{
+        ConstructCell, DeconstructCell, GridmapClientMessage, GridmapServerMessage, PasteBlueprint,
+        SelectBlueprintCorner,
+    },
 };
 
 #[derive(Component)]
@@ -82,6 +88,10 @@ pub fn insert_plane_resource(
                 .insert(NotShadowCaster)
                 .insert(NotShadowReceiver)
                 .id();
+            let grid_anchor = commands
+                .spawn(SpatialBundle::default())
+                .add_child(plane_entity)
+                .id();
             commands.insert_resource(GridmapConstructionState {
                 selected: None,
                 y_level: 0,
@@ -93,6 +103,15 @@ pub fn insert_plane_resource(
                 ghost_material: m,
                 rotated_ghost_ids: HashMap::default(),
                 first_show: false,
+                blueprint: None,
+                blueprint_orientation: 0,
+                blueprint_ghosts: vec![],
+                group_orientation: 0,
+                placement_mode: PlacementMode::default(),
+                drag_start: None,
+                placement_ghosts: vec![],
+                grid: GridId::MAIN,
+                grid_anchor,
             });
             *local = true;
         }
@@ -120,8 +139,134 @@ pub struct GridmapConstructionState {
     pub ghost_items: HashMap<Vec3Int, GhostTile>,
     pub rotated_ghost_ids: HashMap<Vec3Int, Vec3Int>,
     pub group_id: Option<GroupTypeId>,
+    /// Index of [OrthogonalBases] the selected group gets placed with.
+    pub group_orientation: u8,
     pub ghost_material: Handle<StandardMaterial>,
     pub first_show: bool,
+    /// Blueprint selected for pasting, replaces the ghost items.
+    pub blueprint: Option<Blueprint>,
+    /// Index of [OrthogonalBases] the blueprint gets pasted with.
+    pub blueprint_orientation: u8,
+    pub blueprint_ghosts: Vec<Entity>,
+    pub placement_mode: PlacementMode,
+    /// Cell the construct button got pressed on in a drag placement mode.
+    pub drag_start: Option<Vec3Int>,
+    pub placement_ghosts: Vec<Entity>,
+    /// Grid being built on, selected cells are relative to it.
+    pub grid: GridId,
+    /// Follows the transform of [Self::grid], parent of the y plane and the ghosts.
+    pub grid_anchor: Entity,
+}
+
+/// How dragging the construct button places a single tile type on the y plane.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlacementMode {
+    /// One cell per click.
+    #[default]
+    Single,
+    /// A straight line along the longest axis of the drag.
+    Line,
+    /// The outline of the dragged rectangle, ie the walls or floor edges of a room.
+    Box,
+    /// The full dragged rectangle.
+    Fill,
+}
+
+impl PlacementMode {
+    pub fn next(&self) -> Self {
+        match self {
+            PlacementMode::Single => PlacementMode::Line,
+            PlacementMode::Line => PlacementMode::Box,
+            PlacementMode::Box => PlacementMode::Fill,
+            PlacementMode::Fill => PlacementMode::Single,
+        }
+    }
+}
+
+/// Most cells a single drag placement may construct.
+pub const MAX_PLACEMENT_CELLS: usize = 4096;
+
+/// Cells covered by dragging from `start` to `end`, on the y level of `start`, with the face placed on each.
+/// Walls dragged in [PlacementMode::Box] face outwards on every side of the rectangle, other tiles keep `face`.
+pub fn placement_cells(
+    mode: PlacementMode,
+    start: Vec3Int,
+    end: Vec3Int,
+    face: &CellFace,
+) -> Vec<(Vec3Int, CellFace)> {
+    let y = start.y;
+    let mut cells = vec![];
+    match mode {
+        PlacementMode::Single => {
+            cells.push((
+                Vec3Int {
+                    x: end.x,
+                    y,
+                    z: end.z,
+                },
+                face.clone(),
+            ));
+        }
+        PlacementMode::Line => {
+            if (end.x - start.x).abs() >= (end.z - start.z).abs() {
+                for x in start.x.min(end.x)..=start.x.max(end.x) {
+                    cells.push((Vec3Int { x, y, z: start.z }, face.clone()));
+                }
+            } else {
+                for z in start.z.min(end.z)..=start.z.max(end.z) {
+                    cells.push((Vec3Int { x: start.x, y, z }, face.clone()));
+                }
+            }
+        }
+        PlacementMode::Box => {
+            let (min_x, max_x) = (start.x.min(end.x), start.x.max(end.x));
+            let (min_z, max_z) = (start.z.min(end.z), start.z.max(end.z));
+            let is_wall;
+            match face {
+                CellFace::FrontWall
+                | CellFace::RightWall
+                | CellFace::BackWall
+                | CellFace::LeftWall => {
+                    is_wall = true;
+                }
+                CellFace::Floor | CellFace::Ceiling | CellFace::Center => {
+                    is_wall = false;
+                }
+            }
+            for x in min_x..=max_x {
+                for z in min_z..=max_z {
+                    let id = Vec3Int { x, y, z };
+                    if !is_wall {
+                        if x == min_x || x == max_x || z == min_z || z == max_z {
+                            cells.push((id, face.clone()));
+                        }
+                        continue;
+                    }
+                    // Corner cells get a wall for both of their sides.
+                    if z == min_z {
+                        cells.push((id, CellFace::BackWall));
+                    }
+                    if z == max_z {
+                        cells.push((id, CellFace::FrontWall));
+                    }
+                    if x == min_x {
+                        cells.push((id, CellFace::LeftWall));
+                    }
+                    if x == max_x {
+                        cells.push((id, CellFace::RightWall));
+                    }
+                }
+            }
+        }
+        PlacementMode::Fill => {
+            for x in start.x.min(end.x)..=start.x.max(end.x) {
+                for z in start.z.min(end.z)..=start.z.max(end.z) {
+                    cells.push((Vec3Int { x, y, z }, face.clone()));
+                }
+            }
+        }
+    }
+    cells
 }
 #[derive(Clone, Debug)]
 pub struct GhostTile {
@@ -130,7 +275,7 @@ pub struct GhostTile {
     pub ghost_entity_option: Option<Entity>,
     pub ghost_rotation: u8,
     pub ghost_face: CellFace,
-    pub is_detail: bool,
+    pub layer: LayerId,
 }
 #[derive(Event)]
 pub struct ShowYLevelPlane {
@@ -170,6 +315,7 @@ pub(crate) fn move_ylevel_plane(
     mut state: ResMut<GridmapConstructionState>,
     ylevel_query: Query<Entity, With<SelectCellCameraYPlane>>,
     mut transforms: Query<&mut Transform>,
+    gridmap: Res<Gridmap>,
 ) {
     if !state.is_constructing {
         return;
@@ -177,7 +323,7 @@ pub(crate) fn move_ylevel_plane(
     match camera_query.get_single() {
         Ok(ent) => {
             let transform = transforms.get(ent).unwrap();
-            let camera_cell_id = world_to_cell_id(transform.translation);
+            let camera_cell_id = gridmap.world_to_grid_cell_id(state.grid, transform.translation);
             if state.y_plane_position.x != camera_cell_id.x
                 || state.y_plane_position.y != camera_cell_id.z
                 || state.first_show
@@ -236,6 +382,11 @@ pub const ROTATE_CONSTRUCTION_LEFT_BIND: &str = "rotateMapConstructionLeft";
 pub const ROTATE_CONSTRUCTION_RIGHT_BIND: &str = "rotateMapConstructionRight";
 pub const ROTATE_CONSTRUCTION_UP_BIND: &str = "rotateMapConstructionUp";
 pub const ROTATE_CONSTRUCTION_DOWN_BIND: &str = "rotateMapConstructionDown";
+pub const UNDO_CONSTRUCTION_BIND: &str = "undoMapConstruction";
+pub const REDO_CONSTRUCTION_BIND: &str = "redoMapConstruction";
+pub const BLUEPRINT_CORNER_BIND: &str = "selectBlueprintCorner";
+pub const PLACEMENT_MODE_BIND: &str = "cycleMapConstructionPlacement";
+pub const CONSTRUCTION_GRID_BIND: &str = "cycleMapConstructionGrid";
 
 pub(crate) fn register_input(mut binds: ResMut<KeyBinds>) {
     binds.list.insert(
@@ -292,6 +443,51 @@ pub(crate) fn register_input(mut binds: ResMut<KeyBinds>) {
             customizable: true,
         },
     );
+    binds.list.insert(
+        UNDO_CONSTRUCTION_BIND.to_string(),
+        KeyBind {
+            key_code: KeyCodeEnum::Keyboard(KeyCode::KeyZ),
+            description: "Undoes the last map construction.".to_string(),
+            name: "Map Construction Undo".to_string(),
+            customizable: true,
+        },
+    );
+    binds.list.insert(
+        REDO_CONSTRUCTION_BIND.to_string(),
+        KeyBind {
+            key_code: KeyCodeEnum::Keyboard(KeyCode::KeyY),
+            description: "Redoes the last undone map construction.".to_string(),
+            name: "Map Construction Redo".to_string(),
+            customizable: true,
+        },
+    );
+    binds.list.insert(
+        BLUEPRINT_CORNER_BIND.to_string(),
+        KeyBind {
+            key_code: KeyCodeEnum::Keyboard(KeyCode::KeyB),
+            description: "Marks a corner of the region to save as blueprint.".to_string(),
+            name: "Map Construction Blueprint Corner".to_string(),
+            customizable: true,
+        },
+    );
+    binds.list.insert(
+        PLACEMENT_MODE_BIND.to_string(),
+        KeyBind {
+            key_code: KeyCodeEnum::Keyboard(KeyCode::KeyX),
+            description: "Cycles between single, line, box and fill placement.".to_string(),
+            name: "Map Construction Placement Mode".to_string(),
+            customizable: true,
+        },
+    );
+    binds.list.insert(
+        CONSTRUCTION_GRID_BIND.to_string(),
+        KeyBind {
+            key_code: KeyCodeEnum::Keyboard(KeyCode::KeyG),
+            description: "Cycles the grid map construction builds on.".to_string(),
+            name: "Map Construction Grid".to_string(),
+            customizable: true,
+        },
+    );
     binds.list.insert(
         CONSTRUCT_CELL.to_string(),
         KeyBind {
@@ -339,6 +535,123 @@ pub(crate) fn input_yplane_position(
         }
     }
 }
+
+pub(crate) fn input_construction_history(
+    keys: Res<InputBuffer>,
+    state: Res<GridmapConstructionState>,
+    mut net: EventWriter<OutgoingReliableClientMessage<GridmapClientMessage>>,
+    focus: Res<TextInput>,
+) {
+    if state.is_constructing && focus.focused_input.is_none() {
+        if keys.just_pressed(UNDO_CONSTRUCTION_BIND) {
+            net.send(OutgoingReliableClientMessage {
+                message: GridmapClientMessage::UndoConstruction,
+            });
+        }
+        if keys.just_pressed(REDO_CONSTRUCTION_BIND) {
+            net.send(OutgoingReliableClientMessage {
+                message: GridmapClientMessage::RedoConstruction,
+            });
+        }
+    }
+}
+pub(crate) fn input_blueprint_corner(
+    keys: Res<InputBuffer>,
+    state: Res<GridmapConstructionState>,
+    mut net: EventWriter<OutgoingReliableClientMessage<GridmapClientMessage>>,
+    focus: Res<TextInput>,
+) {
+    if state.is_constructing && focus.focused_input.is_none() {
+        if keys.just_pressed(BLUEPRINT_CORNER_BIND) {
+            match state.selected {
+                Some(id) => {
+                    net.send(OutgoingReliableClientMessage {
+                        message: GridmapClientMessage::SelectBlueprintCorner(
+                            SelectBlueprintCorner {
+                                grid: state.grid,
+                                id,
+                            },
+                        ),
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+}
+pub(crate) fn input_placement_mode(
+    keys: Res<InputBuffer>,
+    mut state: ResMut<GridmapConstructionState>,
+    mut events: EventWriter<ConstructionCellSelectionChanged>,
+    focus: Res<TextInput>,
+) {
+    if state.is_constructing && focus.focused_input.is_none() {
+        if keys.just_pressed(PLACEMENT_MODE_BIND) {
+            state.placement_mode = state.placement_mode.next();
+            state.drag_start = None;
+            info!("Placement mode {:?}.", state.placement_mode);
+            events.send(ConstructionCellSelectionChanged {
+                only_selection_changed: true,
+            });
+        }
+    }
+}
+
+pub(crate) fn input_construction_grid(
+    keys: Res<InputBuffer>,
+    mut state: ResMut<GridmapConstructionState>,
+    gridmap: Res<Gridmap>,
+    mut events: EventWriter<ConstructionCellSelectionChanged>,
+    focus: Res<TextInput>,
+) {
+    if state.is_constructing && focus.focused_input.is_none() {
+        if keys.just_pressed(CONSTRUCTION_GRID_BIND) {
+            let mut grids = vec![GridId::MAIN];
+            grids.extend(gridmap.grids.keys());
+            let next = match grids.iter().position(|grid| *grid == state.grid) {
+                Some(index) => (index + 1) % grids.len(),
+                None => 0,
+            };
+            state.grid = grids[next];
+            state.drag_start = None;
+            state.first_show = true;
+            info!("Constructing on grid {}.", state.grid.0);
+            events.send(ConstructionCellSelectionChanged {
+                only_selection_changed: true,
+            });
+        }
+    }
+}
+
+/// Moves the y plane and the ghosts along with the grid that is being built on.
+pub(crate) fn follow_construction_grid(
+    mut state: ResMut<GridmapConstructionState>,
+    gridmap: Res<Gridmap>,
+    ghosts: Query<Entity, Added<GhostTileComponent>>,
+    mut transforms: Query<&mut Transform>,
+    mut commands: Commands,
+) {
+    if !gridmap.has_grid(state.grid) {
+        state.grid = GridId::MAIN;
+        state.drag_start = None;
+        state.first_show = true;
+    }
+    match transforms.get_mut(state.grid_anchor) {
+        Ok(mut transform) => {
+            let grid_transform = gridmap.grid_transform(state.grid);
+            if *transform != grid_transform {
+                *transform = grid_transform;
+            }
+        }
+        Err(_) => {
+            warn!("Couldnt query construction grid anchor.");
+        }
+    }
+    for ghost in ghosts.iter() {
+        commands.entity(state.grid_anchor).add_child(ghost);
+    }
+}
+
 #[derive(Resource, Default)]
 pub(crate) struct NewGhostBuffer {
     pub buffer: Vec<ConstructionCellSelectionChanged>,
@@ -370,6 +683,16 @@ pub(crate) fn update_ghost_cell(
                 None => {}
             }
         }
+        for ghost_entity in state.blueprint_ghosts.drain(..) {
+            despawn.send(DespawnEntity {
+                entity: ghost_entity,
+            });
+        }
+        for ghost_entity in state.placement_ghosts.drain(..) {
+            despawn.send(DespawnEntity {
+                entity: ghost_entity,
+            });
+        }
 
         let full_id;
         match state.selected {
@@ -381,91 +704,97 @@ pub(crate) fn update_ghost_cell(
                 continue;
             }
         }
+        let grid = state.grid;
+        match state.blueprint.clone() {
+            Some(blueprint) => {
+                let mut ghosts = vec![];
+                for new in blueprint
+                    .placed_cells(&gridmap, grid, full_id, state.blueprint_orientation)
+                    .all_cells()
+                {
+                    match gridmap.tile_properties.get(&new.tile_type) {
+                        Some(properties) => {
+                            let mut t =
+                                gridmap.get_cell_transform(new.cell.target, new.orientation);
+                            t.scale = Vec3::from([1.05; 3]);
+                            match assets_gltfmesh.get(&properties.mesh_option.clone().unwrap()) {
+                                Some(mesh) => {
+                                    ghosts.push(
+                                        commands
+                                            .spawn(GhostTileComponent)
+                                            .insert(PbrBundle {
+                                                mesh: mesh.primitives[0].mesh.clone(),
+                                                material: state.ghost_material.clone(),
+                                                transform: t,
+                                                ..Default::default()
+                                            })
+                                            .insert(NotShadowCaster)
+                                            .insert(NotShadowReceiver)
+                                            .id(),
+                                    );
+                                }
+                                None => {
+                                    warn!("gltf mesh not found.");
+                                }
+                            }
+                        }
+                        None => {
+                            warn!("Couldnt find tiletype.");
+                        }
+                    }
+                }
+                state.blueprint_ghosts = ghosts;
+                buffer.buffer.push(changed.clone());
+                continue;
+            }
+            None => {}
+        }
         match state.group_id {
             Some(groupid) => {
-                // Commands spawn group cells at correct positions with the right rotations.
-
-                if !changed.only_selection_changed {
-                    state.ghost_items.clear();
-                    state.rotated_ghost_ids.clear();
-                }
-                match gridmap.groups.get(&groupid) {
-                    Some(group) => {
-                        for (local_id, tile) in group.iter() {
-                            match gridmap.tile_properties.get(&tile.tile_type) {
-                                Some(properties) => {
-                                    let prev_item;
-
-                                    let f;
-                                    if !changed.only_selection_changed {
-                                        f = properties.cell_type.default_face();
-                                    } else {
-                                        f = tile.face.clone();
-                                    }
-
-                                    match state.ghost_items.get(local_id) {
-                                        Some(i) => {
-                                            prev_item = i.clone();
-                                        }
-                                        None => {
-                                            prev_item = GhostTile {
-                                                tile_type: tile.tile_type,
-                                                ghost_entity_option: None,
-                                                ghost_rotation: tile.orientation,
-                                                ghost_face: f.clone(),
-                                                is_detail: properties.is_detail,
-                                            };
-                                        }
-                                    }
-                                    let mut t = gridmap.get_cell_transform(
-                                        TargetCell {
-                                            id: full_id + *local_id,
-                                            face: f,
+                // Commands spawn group cells at their rotated positions.
+                state.ghost_items.clear();
+                state.rotated_ghost_ids.clear();
+                for new in gridmap.group_cells(groupid, grid, full_id, state.group_orientation) {
+                    let local_id = new.cell.target.id - full_id;
+                    match gridmap.tile_properties.get(&new.tile_type) {
+                        Some(properties) => {
+                            let mut t = gridmap
+                                .get_cell_transform(new.cell.target.clone(), new.orientation);
+                            t.scale = Vec3::from([1.05; 3]);
+                            match assets_gltfmesh.get(&properties.mesh_option.clone().unwrap()) {
+                                Some(gltf) => {
+                                    let ghost_entity = commands
+                                        .spawn(GhostTileComponent)
+                                        .insert(PbrBundle {
+                                            mesh: gltf.primitives[0].mesh.clone(),
+                                            material: state.ghost_material.clone(),
+                                            transform: t,
+                                            ..Default::default()
+                                        })
+                                        .insert(NotShadowCaster)
+                                        .insert(NotShadowReceiver)
+                                        .id();
+                                    state.ghost_items.insert(
+                                        local_id,
+                                        GhostTile {
+                                            tile_type: new.tile_type,
+                                            ghost_entity_option: Some(ghost_entity),
+                                            ghost_rotation: new.orientation,
+                                            ghost_face: new.cell.target.face.clone(),
+                                            layer: properties.layer,
                                         },
-                                        tile.orientation,
                                     );
-
-                                    t.scale = Vec3::from([1.05; 3]);
-
-                                    match assets_gltfmesh
-                                        .get(&properties.mesh_option.clone().unwrap())
-                                    {
-                                        Some(gltf) => {
-                                            let ghost_entity = commands
-                                                .spawn(GhostTileComponent)
-                                                .insert(PbrBundle {
-                                                    mesh: gltf.primitives[0].mesh.clone(),
-                                                    material: state.ghost_material.clone(),
-                                                    transform: t,
-                                                    ..Default::default()
-                                                })
-                                                .insert(NotShadowCaster)
-                                                .insert(NotShadowReceiver)
-                                                .id();
-                                            let new_tile = GhostTile {
-                                                tile_type: tile.tile_type,
-                                                ghost_entity_option: Some(ghost_entity),
-                                                ghost_rotation: prev_item.ghost_rotation,
-                                                ghost_face: prev_item.ghost_face.clone(),
-                                                is_detail: properties.is_detail,
-                                            };
-
-                                            state.ghost_items.insert(*local_id, new_tile);
-                                        }
-                                        None => {
-                                            warn!("Couldnt find ghost material asset gltf.");
-                                        }
-                                    }
+                                    // Offsets are already rotated.
+                                    state.rotated_ghost_ids.insert(local_id, local_id);
                                 }
                                 None => {
-                                    warn!("Couldnt find tiletype.");
-                                    continue;
+                                    warn!("Couldnt find ghost material asset gltf.");
                                 }
                             }
                         }
-                    }
-                    None => {
-                        warn!("Couldnt find group. (1): {:?}", groupid);
+                        None => {
+                            warn!("Couldnt find tiletype.");
+                        }
                     }
                 }
             }
@@ -483,6 +812,9 @@ pub(crate) fn update_ghost_cell(
                     state.ghost_items.remove(&c);
                 }
                 let m = state.ghost_material.clone();
+                let drag_start = state.drag_start;
+                let placement_mode = state.placement_mode;
+                let mut placement_ghosts = vec![];
                 match state.ghost_items.get_mut(&Vec3Int { x: 0, y: 0, z: 0 }) {
                     Some(g) => match gridmap.tile_properties.get(&g.tile_type) {
                         Some(properties) => {
@@ -493,6 +825,7 @@ pub(crate) fn update_ghost_cell(
                                 TargetCell {
                                     id: full_id,
                                     face: g.ghost_face.clone(),
+                                    grid,
                                 },
                                 g.ghost_rotation,
                             );
@@ -505,7 +838,7 @@ pub(crate) fn update_ghost_cell(
                                         .spawn(GhostTileComponent)
                                         .insert(PbrBundle {
                                             mesh: mesh.primitives[0].mesh.clone(),
-                                            material: m,
+                                            material: m.clone(),
                                             transform: t,
                                             ..Default::default()
                                         })
@@ -513,6 +846,40 @@ pub(crate) fn update_ghost_cell(
                                         .insert(NotShadowReceiver)
                                         .id();
                                     g.ghost_entity_option = Some(ghost_entity);
+                                    // Ghosts for the rest of the dragged cells.
+                                    match drag_start {
+                                        Some(start) => {
+                                            for (id, face) in placement_cells(
+                                                placement_mode,
+                                                start,
+                                                full_id,
+                                                &g.ghost_face,
+                                            ) {
+                                                if id == full_id && face == g.ghost_face {
+                                                    continue;
+                                                }
+                                                let mut t = gridmap.get_cell_transform(
+                                                    TargetCell { id, face, grid },
+                                                    g.ghost_rotation,
+                                                );
+                                                t.scale = Vec3::from([1.05; 3]);
+                                                placement_ghosts.push(
+                                                    commands
+                                                        .spawn(GhostTileComponent)
+                                                        .insert(PbrBundle {
+                                                            mesh: mesh.primitives[0].mesh.clone(),
+                                                            material: m.clone(),
+                                                            transform: t,
+                                                            ..Default::default()
+                                                        })
+                                                        .insert(NotShadowCaster)
+                                                        .insert(NotShadowReceiver)
+                                                        .id(),
+                                                );
+                                            }
+                                        }
+                                        None => {}
+                                    }
                                 }
                                 None => {
                                     warn!("gltf mesh not found.");
@@ -528,6 +895,7 @@ pub(crate) fn update_ghost_cell(
                         //warn!("No local tiletype.");
                     }
                 }
+                state.placement_ghosts = placement_ghosts;
             }
         }
 
@@ -541,11 +909,40 @@ pub(crate) fn apply_ghost_rotation(
     gridmap: Res<Gridmap>,
     mut ghost_query: Query<&mut Transform, With<GhostTileComponent>>,
     mut events: ResMut<NewGhostBuffer>,
+    mut selection_changed: EventWriter<ConstructionCellSelectionChanged>,
 ) {
     if !state.is_constructing {
         return;
     }
 
+    if state.blueprint.is_some() || state.group_id.is_some() {
+        events.buffer.clear();
+        let rotation;
+        if keys.just_pressed(ROTATE_CONSTRUCTION_LEFT_BIND) {
+            rotation = Quat::from_axis_angle(Vec3::Y, PI / 2.);
+        } else if keys.just_pressed(ROTATE_CONSTRUCTION_RIGHT_BIND) {
+            rotation = Quat::from_axis_angle(Vec3::Y, -PI / 2.);
+        } else if keys.just_pressed(ROTATE_CONSTRUCTION_UP_BIND) {
+            rotation = Quat::from_axis_angle(Vec3::X, PI / 2.);
+        } else if keys.just_pressed(ROTATE_CONSTRUCTION_DOWN_BIND) {
+            rotation = Quat::from_axis_angle(Vec3::X, -PI / 2.);
+        } else {
+            return;
+        }
+        let bases = OrthogonalBases::default().bases;
+        if state.blueprint.is_some() {
+            state.blueprint_orientation =
+                (rotation * bases[state.blueprint_orientation as usize]).get_orthogonal_index();
+        } else {
+            state.group_orientation =
+                (rotation * bases[state.group_orientation as usize]).get_orthogonal_index();
+        }
+        selection_changed.send(ConstructionCellSelectionChanged {
+            only_selection_changed: true,
+        });
+        return;
+    }
+
     let mut changed = false;
     for _ in events.buffer.iter() {
         changed = true;
@@ -575,6 +972,7 @@ pub(crate) fn apply_ghost_rotation(
     }
 
     let state_selected = state.selected.clone();
+    let grid = state.grid;
 
     let mut new_ids: HashMap<Vec3Int, Vec3Int> = HashMap::default();
 
@@ -958,6 +1356,7 @@ pub(crate) fn apply_ghost_rotation(
                                     TargetCell {
                                         id: full_id,
                                         face: tile.ghost_face.clone(),
+                                        grid,
                                     },
                                     tile.ghost_rotation,
                                 );
@@ -998,6 +1397,7 @@ pub(crate) fn select_cell_in_front_camera(
     spatial_query: SpatialQuery,
     mut state: ResMut<GridmapConstructionState>,
     mut events: EventWriter<ConstructionCellSelectionChanged>,
+    gridmap: Res<Gridmap>,
 ) {
     if !state.is_constructing {
         return;
@@ -1056,12 +1456,12 @@ pub(crate) fn select_cell_in_front_camera(
     } else {
         return;
     }
-    let world_id = world_to_cell_id(intersection_position);
+    let grid_id = gridmap.world_to_grid_cell_id(state.grid, intersection_position);
 
     let n = Vec3Int {
-        x: world_id.x,
+        x: grid_id.x,
         y: state.y_level,
-        z: world_id.z,
+        z: grid_id.z,
     };
 
     match state.selected {
@@ -1096,10 +1496,10 @@ pub(crate) fn change_ghost_tile_request(
                                 tile.tile_type = *id;
                             }
                             None => {
-                                let is_detail;
+                                let layer;
                                 match gridmap.tile_properties.get(id) {
                                     Some(properties) => {
-                                        is_detail = properties.is_detail;
+                                        layer = properties.layer;
                                     }
                                     None => {
                                         warn!("Couldnt find tile properties.");
@@ -1113,7 +1513,7 @@ pub(crate) fn change_ghost_tile_request(
                                         ghost_entity_option: None,
                                         ghost_rotation: 0,
                                         ghost_face: CellFace::default(),
-                                        is_detail: is_detail,
+                                        layer,
                                     },
                                 );
                             }
@@ -1122,13 +1522,26 @@ pub(crate) fn change_ghost_tile_request(
                     }
                     CellIds::GroupType(id) => {
                         select_state.group_id = Some(*id);
+                        select_state.group_orientation = 0;
                     }
+                    CellIds::Blueprint(_) => {}
                 }
+                select_state.blueprint = None;
 
                 events.send(ConstructionCellSelectionChanged {
                     only_selection_changed: false,
                 });
             }
+            GridmapServerMessage::GhostBlueprint(blueprint) => {
+                select_state.ghost_items.clear();
+                select_state.rotated_ghost_ids.clear();
+                select_state.group_id = None;
+                select_state.blueprint = Some(blueprint.clone());
+                select_state.blueprint_orientation = 0;
+                events.send(ConstructionCellSelectionChanged {
+                    only_selection_changed: false,
+                });
+            }
             _ => (),
         }
     }
@@ -1139,15 +1552,72 @@ pub const DECONSTRUCT_CELL: &str = "DeconstructCell";
 
 pub(crate) fn client_mouse_click_input(
     buttons: Res<InputBuffer>,
-    state: Res<GridmapConstructionState>,
+    mut state: ResMut<GridmapConstructionState>,
     mut net: EventWriter<OutgoingReliableClientMessage<GridmapClientMessage>>,
     hud_state: Res<HudState>,
+    mut selection_changed: EventWriter<ConstructionCellSelectionChanged>,
 ) {
     if !state.is_constructing || hud_state.expanded {
         return;
     }
 
+    if buttons.just_released(CONSTRUCT_CELL) {
+        match (state.drag_start.take(), state.selected) {
+            (Some(start), Some(end)) => {
+                match state.ghost_items.get(&Vec3Int { x: 0, y: 0, z: 0 }) {
+                    Some(tile) => {
+                        let cells: Vec<TargetCellWithOrientationWType> =
+                            placement_cells(state.placement_mode, start, end, &tile.ghost_face)
+                                .into_iter()
+                                .map(|(id, face)| TargetCellWithOrientationWType {
+                                    id,
+                                    face,
+                                    orientation: tile.ghost_rotation,
+                                    tile_type: tile.tile_type,
+                                    grid: state.grid,
+                                })
+                                .collect();
+                        if cells.len() > MAX_PLACEMENT_CELLS {
+                            warn!(
+                                "Placement of {} cells exceeds the maximum of {}.",
+                                cells.len(),
+                                MAX_PLACEMENT_CELLS
+                            );
+                        } else {
+                            net.send(OutgoingReliableClientMessage {
+                                message: GridmapClientMessage::ConstructCells(ConstructCell {
+                                    cells,
+                                    group_option: None,
+                                    group_orientation: 0,
+                                }),
+                            });
+                        }
+                    }
+                    None => {}
+                }
+                selection_changed.send(ConstructionCellSelectionChanged {
+                    only_selection_changed: true,
+                });
+            }
+            _ => {}
+        }
+    }
+
     if buttons.just_pressed(CONSTRUCT_CELL) {
+        match (&state.blueprint, state.selected) {
+            (Some(blueprint), Some(origin)) => {
+                net.send(OutgoingReliableClientMessage {
+                    message: GridmapClientMessage::PasteBlueprint(PasteBlueprint {
+                        name: blueprint.name.clone(),
+                        origin,
+                        orientation: state.blueprint_orientation,
+                        grid: state.grid,
+                    }),
+                });
+                return;
+            }
+            _ => {}
+        }
         if state.ghost_items.len() == 0 {
             return;
         }
@@ -1163,6 +1633,14 @@ pub(crate) fn client_mouse_click_input(
             }
         }
 
+        if state.placement_mode != PlacementMode::Single && state.group_id.is_none() {
+            state.drag_start = Some(cell_id);
+            selection_changed.send(ConstructionCellSelectionChanged {
+                only_selection_changed: true,
+            });
+            return;
+        }
+
         let mut construct_cells = vec![];
 
         //let mut block_construction = false;
@@ -1180,12 +1658,14 @@ pub(crate) fn client_mouse_click_input(
                 face: tile.ghost_face.clone(),
                 orientation: tile.ghost_rotation,
                 tile_type: tile.tile_type,
+                grid: state.grid,
             };
 
             /*match gridmap
                 .get_cell(TargetCell {
                     id: target.id,
                     face: target.face.clone(),
+                    grid: target.grid,
                 })
                 .is_some()
             {
@@ -1198,7 +1678,8 @@ pub(crate) fn client_mouse_click_input(
         net.send(OutgoingReliableClientMessage {
             message: GridmapClientMessage::ConstructCells(ConstructCell {
                 cells: construct_cells,
-                group_option: Some(cell_id),
+                group_option: state.group_id.map(|_| cell_id),
+                group_orientation: state.group_orientation,
             }),
         });
         //}
@@ -1236,8 +1717,9 @@ pub(crate) fn client_mouse_click_input(
                 target: TargetCell {
                     id: cell_id + rotated_id,
                     face: tile.ghost_face.clone(),
+                    grid: state.grid,
                 },
-                is_detail: tile.is_detail,
+                layer: tile.layer,
             });
         }
 
@@ -1248,3 +1730,58 @@ pub(crate) fn client_mouse_click_input(
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement_cells() {
+        let start = Vec3Int { x: 0, y: 2, z: 0 };
+        let end = Vec3Int { x: 3, y: 5, z: -1 };
+        let floor = CellFace::Floor;
+        let line = placement_cells(PlacementMode::Line, start, end, &floor);
+        assert_eq!(line.len(), 4);
+        assert!(line
+            .iter()
+            .all(|(c, face)| c.z == 0 && c.y == 2 && *face == floor));
+        assert_eq!(
+            placement_cells(PlacementMode::Fill, start, end, &floor).len(),
+            8
+        );
+        let end = Vec3Int { x: 4, y: 2, z: 4 };
+        let outline = placement_cells(PlacementMode::Box, start, end, &floor);
+        assert_eq!(outline.len(), 16);
+        assert!(outline.iter().all(|(_, face)| *face == floor));
+        assert_eq!(
+            placement_cells(PlacementMode::Fill, start, end, &floor).len(),
+            25
+        );
+    }
+
+    #[test]
+    fn test_placement_cells_box_walls() {
+        let start = Vec3Int { x: 0, y: 0, z: 0 };
+        let end = Vec3Int { x: 4, y: 0, z: 4 };
+        let walls = placement_cells(PlacementMode::Box, start, end, &CellFace::FrontWall);
+        // Five walls per side, the corners hold two.
+        assert_eq!(walls.len(), 20);
+        for (id, face) in walls.iter() {
+            match face {
+                CellFace::BackWall => assert_eq!(id.z, 0),
+                CellFace::FrontWall => assert_eq!(id.z, 4),
+                CellFace::LeftWall => assert_eq!(id.x, 0),
+                CellFace::RightWall => assert_eq!(id.x, 4),
+                _ => panic!("Unexpected face {:?}.", face),
+            }
+        }
+        for face in [
+            CellFace::BackWall,
+            CellFace::FrontWall,
+            CellFace::LeftWall,
+            CellFace::RightWall,
+        ] {
+            assert_eq!(walls.iter().filter(|(_, f)| *f == face).count(), 5);
+        }
+    }
+}
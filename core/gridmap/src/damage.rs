@@ -0,0 +1,591 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    math::Vec3,
+    prelude::{
+        App, Assets, Commands, Event, EventReader, EventWriter, Handle, Query, Res, ResMut,
+        Resource, StandardMaterial, Transform, Update, Without,
+    },
+};
+use entity::{
+    entity_types::EntityType,
+    health::{Health, HealthContainer, StructureHealth},
+    spawn::{spawn_entity, SpawnEntity},
+};
+use networking::{
+    client::IncomingReliableServerMessage,
+    server::{ConnectedPlayer, OutgoingReliableServerMessage},
+    stamp::TickRateStamp,
+};
+use resources::{
+    core::SF_CONTENT_PREFIX,
+    grid::{CellFace, GridId, TargetCell},
+    math::Vec3Int,
+    modes::is_server_mode,
+    player::SoftPlayer,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::MaterialVariants,
+    grid::{CellIndexes, CellTypeId, Gridmap, LayerTargetCell, RemoveTile, StrictCellFace},
+    items::generic_assets::GenericMaterials,
+    layers::LayerId,
+    net::GridmapServerMessage,
+    streaming::StreamedChunks,
+};
+
+/// Entity type identifier of the debris destroyed tiles leave behind by default.
+pub fn debris_entity_name() -> String {
+    SF_CONTENT_PREFIX.to_owned() + "debris"
+}
+
+/// Damage thresholds of a tile type. Damage is the sum of brute and burn damage.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TileDurability {
+    /// Damage from which the tile shows as damaged.
+    pub damaged: f32,
+    /// Damage from which the tile shows as heavily damaged.
+    pub heavily_damaged: f32,
+    /// Damage at which the tile gets removed.
+    pub destroyed: f32,
+    /// Entity type spawned in place of the tile when it gets destroyed.
+    pub debris_option: Option<String>,
+}
+
+impl Default for TileDurability {
+    fn default() -> Self {
+        Self {
+            damaged: 50.,
+            heavily_damaged: 100.,
+            destroyed: 150.,
+            debris_option: Some(debris_entity_name()),
+        }
+    }
+}
+
+impl TileDurability {
+    pub fn state(&self, damage: f32) -> TileDamageState {
+        if damage >= self.heavily_damaged {
+            TileDamageState::HeavilyDamaged
+        } else if damage >= self.damaged {
+            TileDamageState::Damaged
+        } else {
+            TileDamageState::Intact
+        }
+    }
+}
+
+/// Damaged state of a tile as shown by clients.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TileDamageState {
+    #[default]
+    Intact,
+    Damaged,
+    HeavilyDamaged,
+}
+
+/// Damage of a tile, as applied to it and as stored in map files.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CellDamage {
+    pub brute: f32,
+    pub burn: f32,
+    pub toxin: f32,
+}
+
+impl CellDamage {
+    /// The damage a tile has taken, none when it is undamaged.
+    pub fn from_health(health: &Health) -> Option<Self> {
+        match &health.health_container {
+            HealthContainer::Structure(structure) => {
+                if structure.brute > 0. || structure.burn > 0. || structure.toxin > 0. {
+                    Some(Self {
+                        brute: structure.brute,
+                        burn: structure.burn,
+                        toxin: structure.toxin,
+                    })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+    pub fn total(&self) -> f32 {
+        self.brute + self.burn
+    }
+}
+
+/// Event to damage a tile.
+#[derive(Event, Clone)]
+pub struct DamageTile {
+    pub cell: LayerTargetCell,
+    pub damage: CellDamage,
+}
+
+/// Event of a tile that got removed because its damage reached [TileDurability::destroyed].
+#[derive(Event, Clone)]
+pub struct TileDestroyed {
+    pub cell: LayerTargetCell,
+    pub transform: Transform,
+    pub debris_option: Option<String>,
+}
+
+/// The same cell with the face a tile is stored under, so differently addressed faces compare equal.
+pub fn stored_cell(gridmap: &Gridmap, cell: &LayerTargetCell) -> LayerTargetCell {
+    let strict = gridmap.get_strict_cell(cell.target.clone());
+    let face = match strict.face {
+        StrictCellFace::FrontWall => CellFace::FrontWall,
+        StrictCellFace::RightWall => CellFace::RightWall,
+        StrictCellFace::Floor => CellFace::Floor,
+        StrictCellFace::Center => CellFace::Center,
+    };
+    LayerTargetCell {
+        target: TargetCell {
+            id: strict.id,
+            face,
+            grid: cell.target.grid,
+        },
+        layer: cell.layer,
+    }
+}
+
+/// The main layer tile of a cell nearest to where the cell got hit.
+pub fn hit_tile(gridmap: &Gridmap, id: Vec3Int, hit_point: Vec3) -> Option<LayerTargetCell> {
+    let mut nearest: Option<(LayerTargetCell, f32)> = None;
+    for face in [
+        CellFace::FrontWall,
+        CellFace::RightWall,
+        CellFace::BackWall,
+        CellFace::LeftWall,
+        CellFace::Floor,
+        CellFace::Ceiling,
+        CellFace::Center,
+    ] {
+        let cell = LayerTargetCell {
+            target: TargetCell {
+                id,
+                face,
+                grid: GridId::MAIN,
+            },
+            layer: LayerId::STRUCTURE,
+        };
+        if gridmap.get_cell(cell.clone()).is_none() {
+            continue;
+        }
+        let distance = gridmap
+            .get_cell_transform(cell.target.clone(), 0)
+            .translation
+            .distance(hit_point);
+        match &nearest {
+            Some((_, d)) => {
+                if distance >= *d {
+                    continue;
+                }
+            }
+            None => {}
+        }
+        nearest = Some((cell, distance));
+    }
+    nearest.map(|(cell, _)| cell)
+}
+
+/// The damaged state of a tile given its health.
+pub fn tile_damage_state(
+    gridmap: &Gridmap,
+    tile_type: &CellTypeId,
+    health: &Health,
+) -> TileDamageState {
+    match (
+        gridmap
+            .tile_properties
+            .get(tile_type)
+            .and_then(|properties| properties.durability.as_ref()),
+        CellDamage::from_health(health),
+    ) {
+        (Some(durability), Some(damage)) => durability.state(damage.total()),
+        _ => TileDamageState::Intact,
+    }
+}
+
+/// Damaged tiles of a chunk with their state, to send along with the chunk.
+pub fn chunk_damage_states(
+    gridmap: &Gridmap,
+    chunk: usize,
+) -> Vec<(LayerTargetCell, TileDamageState)> {
+    let mut states = vec![];
+    for (layer, grid) in gridmap.layers.iter() {
+        match grid.get(&chunk) {
+            Some(grid_chunk) => {
+                for (cell_i, cell) in grid_chunk.cells.iter() {
+                    let id;
+                    match gridmap.get_id(CellIndexes {
+                        chunk,
+                        cell: *cell_i,
+                    }) {
+                        Some(i) => {
+                            id = i;
+                        }
+                        None => {
+                            continue;
+                        }
+                    }
+                    for (item, face) in cell.get_items() {
+                        let state = tile_damage_state(gridmap, &item.tile_type, &item.health);
+                        if state != TileDamageState::Intact {
+                            states.push((
+                                LayerTargetCell {
+                                    target: TargetCell {
+                                        id,
+                                        face,
+                                        grid: GridId::MAIN,
+                                    },
+                                    layer: *layer,
+                                },
+                                state,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+    states
+}
+
+/// Apply damage to tiles, tell clients about tiles that changed damaged state and remove destroyed tiles.
+pub(crate) fn damage_tiles(
+    mut events: EventReader<DamageTile>,
+    mut gridmap: ResMut<Gridmap>,
+    mut remove_tile: EventWriter<RemoveTile>,
+    mut destroyed: EventWriter<TileDestroyed>,
+    stamp: Res<TickRateStamp>,
+    connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
+    streamed: Res<StreamedChunks>,
+    mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    // Destroyed tiles stay in the gridmap until their removal gets applied.
+    let mut destroyed_cells: HashSet<LayerTargetCell> = HashSet::new();
+    for event in events.read() {
+        let cell = stored_cell(&gridmap, &event.cell);
+        if destroyed_cells.contains(&cell) {
+            continue;
+        }
+        let tile_type;
+        let old_state;
+        let damage;
+        match gridmap.get_cell(cell.clone()) {
+            Some(item) => {
+                tile_type = item.tile_type;
+                old_state = tile_damage_state(&gridmap, &tile_type, &item.health);
+            }
+            None => {
+                continue;
+            }
+        }
+        match gridmap.get_cell_mut(cell.clone()) {
+            Some(item) => {
+                match &mut item.health.health_container {
+                    HealthContainer::Structure(structure) => {
+                        structure.brute += event.damage.brute;
+                        structure.burn += event.damage.burn;
+                        structure.toxin += event.damage.toxin;
+                    }
+                    container => {
+                        *container = HealthContainer::Structure(StructureHealth {
+                            brute: event.damage.brute,
+                            burn: event.damage.burn,
+                            toxin: event.damage.toxin,
+                        });
+                    }
+                }
+                damage = CellDamage::from_health(&item.health).unwrap_or_default();
+            }
+            None => {
+                continue;
+            }
+        }
+
+        let durability;
+        match gridmap
+            .tile_properties
+            .get(&tile_type)
+            .and_then(|properties| properties.durability.clone())
+        {
+            Some(d) => {
+                durability = d;
+            }
+            None => {
+                // Indestructible.
+                continue;
+            }
+        }
+
+        if damage.total() >= durability.destroyed {
+            let item = gridmap.get_cell(cell.clone()).unwrap();
+            destroyed.send(TileDestroyed {
+                cell: cell.clone(),
+                transform: gridmap.get_cell_world_transform(cell.target.clone(), item.orientation),
+                debris_option: durability.debris_option.clone(),
+            });
+            remove_tile.send(RemoveTile {
+                cell: cell.clone(),
+                stamp: stamp.tick,
+            });
+            destroyed_cells.insert(cell);
+            continue;
+        }
+
+        let new_state = durability.state(damage.total());
+        if new_state == old_state {
+            continue;
+        }
+        let chunk;
+        match gridmap.get_cell_chunk(&cell.target) {
+            Some(c) => {
+                chunk = c;
+            }
+            None => {
+                continue;
+            }
+        }
+        for connected_player in connected_players.iter() {
+            if !connected_player.connected
+                || (cell.target.grid == GridId::MAIN
+                    && !streamed.has_chunk(&connected_player.handle, chunk))
+            {
+                continue;
+            }
+            net.send(OutgoingReliableServerMessage {
+                handle: connected_player.handle,
+                message: GridmapServerMessage::TileDamageStates(vec![(cell.clone(), new_state)]),
+            });
+        }
+    }
+}
+
+/// Spawn the debris of destroyed tiles whose debris is of entity type `T`.
+pub fn spawn_tile_debris<T: EntityType + Default + 'static>(
+    mut events: EventReader<TileDestroyed>,
+    mut commands: Commands,
+    mut spawner: EventWriter<SpawnEntity<T>>,
+) {
+    for event in events.read() {
+        match &event.debris_option {
+            Some(debris) => {
+                if !T::default().is_type(debris.clone()) {
+                    continue;
+                }
+                spawn_entity(
+                    T::default(),
+                    event.transform,
+                    &mut commands,
+                    None,
+                    None,
+                    None,
+                    &mut spawner,
+                );
+            }
+            None => {}
+        }
+    }
+}
+
+/// Let destroyed tiles leave entities of type `T` behind as debris.
+pub fn register_tile_debris<T: EntityType + Clone + Default + 'static>(app: &mut App) {
+    if is_server_mode(app) {
+        app.add_systems(Update, spawn_tile_debris::<T>);
+    }
+}
+
+/// Damaged states of tiles on the client, by the cell they are stored under.
+#[derive(Resource, Default)]
+pub struct TileDamageStates {
+    pub cells: HashMap<LayerTargetCell, TileDamageState>,
+}
+
+/// Show damaged states of tiles sent by the server.
+pub(crate) fn receive_tile_damage_states(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut states: ResMut<TileDamageStates>,
+    gridmap: Res<Gridmap>,
+    mut tile_materials: Query<&mut Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut variants: ResMut<MaterialVariants>,
+    generic_materials: Res<GenericMaterials>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::RemoveCell(cell) => {
+                states.cells.remove(&stored_cell(&gridmap, cell));
+            }
+            GridmapServerMessage::LoadChunk(snapshot) => {
+                // The states of the chunk get resent right after it.
+                states
+                    .cells
+                    .retain(|cell, _| gridmap.get_cell_chunk(&cell.target) != Some(snapshot.chunk));
+            }
+            GridmapServerMessage::TileDamageStates(changes) => {
+                for (cell, state) in changes {
+                    let stored = stored_cell(&gridmap, cell);
+                    match state {
+                        TileDamageState::Intact => {
+                            states.cells.remove(&stored);
+                        }
+                        _ => {
+                            states.cells.insert(stored.clone(), *state);
+                        }
+                    }
+                    let item;
+                    match gridmap.get_cell(stored) {
+                        Some(i) => {
+                            item = i;
+                        }
+                        None => {
+                            continue;
+                        }
+                    }
+                    let base;
+                    match gridmap
+                        .tile_properties
+                        .get(&item.tile_type)
+                        .and_then(|properties| properties.material_option.clone())
+                    {
+                        Some(m) => {
+                            base = m;
+                        }
+                        None => {
+                            base = generic_materials.gray_metallic.clone_weak();
+                        }
+                    }
+                    match item.entity {
+                        Some(entity) => match tile_materials.get_mut(entity) {
+                            Ok(mut material) => {
+                                *material = variants.tile_material(
+                                    &base,
+                                    item.metadata.tint,
+                                    *state,
+                                    &mut materials,
+                                );
+                            }
+                            Err(_) => {}
+                        },
+                        None => {}
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::TileProperties;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{Events, World};
+
+    fn floor(x: i16) -> LayerTargetCell {
+        LayerTargetCell {
+            target: TargetCell {
+                id: Vec3Int { x, y: 0, z: 0 },
+                face: CellFace::Floor,
+                grid: GridId::MAIN,
+            },
+            layer: LayerId::STRUCTURE,
+        }
+    }
+
+    fn brute(amount: f32) -> CellDamage {
+        CellDamage {
+            brute: amount,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_durability_states() {
+        let durability = TileDurability::default();
+        assert_eq!(durability.state(0.), TileDamageState::Intact);
+        assert_eq!(durability.state(49.9), TileDamageState::Intact);
+        assert_eq!(durability.state(50.), TileDamageState::Damaged);
+        assert_eq!(durability.state(99.9), TileDamageState::Damaged);
+        assert_eq!(durability.state(100.), TileDamageState::HeavilyDamaged);
+        assert_eq!(durability.state(1000.), TileDamageState::HeavilyDamaged);
+    }
+
+    #[test]
+    fn test_damage_tiles() {
+        let mut gridmap = Gridmap::default();
+        let destructible = CellTypeId(1);
+        let indestructible = CellTypeId(2);
+        gridmap.tile_properties.insert(
+            destructible,
+            TileProperties {
+                durability: Some(TileDurability::default()),
+                ..Default::default()
+            },
+        );
+        // Tiles without durability opt out of destruction.
+        gridmap.tile_properties.insert(
+            indestructible,
+            TileProperties {
+                durability: None,
+                ..Default::default()
+            },
+        );
+        gridmap.set_test_tile(
+            GridId::MAIN,
+            floor(0).target.id,
+            CellFace::Floor,
+            destructible,
+        );
+        gridmap.set_test_tile(
+            GridId::MAIN,
+            floor(1).target.id,
+            CellFace::Floor,
+            indestructible,
+        );
+
+        let mut world = World::new();
+        world.insert_resource(gridmap);
+        world.init_resource::<TickRateStamp>();
+        world.init_resource::<StreamedChunks>();
+        world.init_resource::<Events<DamageTile>>();
+        world.init_resource::<Events<RemoveTile>>();
+        world.init_resource::<Events<TileDestroyed>>();
+        world.init_resource::<Events<OutgoingReliableServerMessage<GridmapServerMessage>>>();
+
+        // Only the first hit past the threshold destroys the tile.
+        for _ in 0..3 {
+            world.send_event(DamageTile {
+                cell: floor(0),
+                damage: brute(100.),
+            });
+        }
+        world.send_event(DamageTile {
+            cell: floor(1),
+            damage: brute(1000.),
+        });
+        world.run_system_once(damage_tiles);
+
+        let destroyed: Vec<TileDestroyed> = world
+            .resource_mut::<Events<TileDestroyed>>()
+            .drain()
+            .collect();
+        assert_eq!(destroyed.len(), 1);
+        assert_eq!(destroyed[0].cell, floor(0));
+        assert_eq!(destroyed[0].debris_option, Some(debris_entity_name()));
+        assert_eq!(
+            world.resource_mut::<Events<RemoveTile>>().drain().count(),
+            1
+        );
+
+        let gridmap = world.resource::<Gridmap>();
+        let item = gridmap.get_cell(floor(1)).unwrap();
+        assert_eq!(CellDamage::from_health(&item.health), Some(brute(1000.)));
+    }
+}
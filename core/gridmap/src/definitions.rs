@@ -0,0 +1,478 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::log::{info, warn};
+use bevy::prelude::{
+    AlphaMode, AssetServer, Assets, Color, Quat, Res, ResMut, StandardMaterial, Transform, Vec3,
+};
+use bevy_xpbd_3d::prelude::Collider;
+use entity::examine::RichName;
+use resources::{grid::CellFace, light::default_point_light, math::Vec3Int};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    damage::TileDurability,
+    grid::{
+        CellType, CellTypeName, FullCell, Gridmap, GroupTypeName, TileGroup, TileLight,
+        TileProperties,
+    },
+    init::{InitTileGroups, InitTileProperties},
+    layers::{LayerId, STRUCTURE_LAYER},
+    pathfinding::Ramp,
+};
+
+/// File extension of tile and group definitions.
+pub const DEFINITION_EXTENSION: &str = "ron";
+
+/// Directory with a RON file per tile type.
+pub fn tile_definition_directory() -> PathBuf {
+    Path::new("data").join("gridmap").join("tiles")
+}
+
+/// Directory with a RON file per tile group.
+pub fn group_definition_directory() -> PathBuf {
+    Path::new("data").join("gridmap").join("groups")
+}
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    Read(PathBuf, String),
+    Parse(PathBuf, String),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::Read(path, rr) => {
+                write!(f, "Couldn't read tile definition {:?}: {}", path, rr)
+            }
+            DefinitionError::Parse(path, rr) => {
+                write!(f, "Couldn't parse tile definition {:?}: {}", path, rr)
+            }
+        }
+    }
+}
+
+/// Shape of the collider of a tile.
+#[derive(Deserialize, Clone, Debug)]
+pub enum TileColliderShape {
+    Cuboid(f32, f32, f32),
+    Cylinder { height: f32, radius: f32 },
+}
+
+impl TileColliderShape {
+    pub fn collider(&self) -> Collider {
+        match self {
+            TileColliderShape::Cuboid(x, y, z) => Collider::cuboid(*x, *y, *z),
+            TileColliderShape::Cylinder { height, radius } => Collider::cylinder(*height, *radius),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileName {
+    pub name: String,
+    #[serde(default)]
+    pub n: bool,
+    #[serde(default)]
+    pub the: bool,
+}
+
+/// Material of a tile, texture paths are relative to the assets folder.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TileMaterialDefinition {
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<String>,
+    pub metallic_roughness_texture: Option<String>,
+    pub emissive_texture: Option<String>,
+    pub emissive: [f32; 3],
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+    pub alpha_blend: bool,
+    pub thickness: f32,
+    pub ior: f32,
+    pub diffuse_transmission: f32,
+}
+
+impl Default for TileMaterialDefinition {
+    fn default() -> Self {
+        Self {
+            base_color: [1., 1., 1., 1.],
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            emissive_texture: None,
+            emissive: [0., 0., 0.],
+            perceptual_roughness: 0.9,
+            metallic: 0.97,
+            alpha_blend: false,
+            thickness: 0.,
+            ior: 1.5,
+            diffuse_transmission: 0.,
+        }
+    }
+}
+
+impl TileMaterialDefinition {
+    pub fn material(&self, asset_server: &AssetServer) -> StandardMaterial {
+        let [r, g, b, a] = self.base_color;
+        let [er, eg, eb] = self.emissive;
+        StandardMaterial {
+            base_color: Color::srgba(r, g, b, a),
+            base_color_texture: self
+                .base_color_texture
+                .as_ref()
+                .map(|path| asset_server.load(path.clone())),
+            metallic_roughness_texture: self
+                .metallic_roughness_texture
+                .as_ref()
+                .map(|path| asset_server.load(path.clone())),
+            emissive_texture: self
+                .emissive_texture
+                .as_ref()
+                .map(|path| asset_server.load(path.clone())),
+            emissive: Color::srgb(er, eg, eb).into(),
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            alpha_mode: match self.alpha_blend {
+                true => AlphaMode::Blend,
+                false => AlphaMode::Opaque,
+            },
+            thickness: self.thickness,
+            ior: self.ior,
+            diffuse_transmission: self.diffuse_transmission,
+            ..Default::default()
+        }
+    }
+}
+
+/// Point light of a tile, offset from the cell center.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileLightDefinition {
+    pub local_offset: [f32; 3],
+}
+
+fn default_layer() -> String {
+    STRUCTURE_LAYER.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A tile type as written in its RON file. Omitted fields get the [TileProperties] defaults.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileDefinition {
+    pub name_id: String,
+    pub name: TileName,
+    pub description: String,
+    pub cell_type: CellType,
+    pub collider: TileColliderShape,
+    #[serde(default)]
+    pub collider_offset: [f32; 3],
+    /// Rotation of the collider in degrees around the x, y and z axis.
+    #[serde(default)]
+    pub collider_rotation: [f32; 3],
+    #[serde(default)]
+    pub constructable: bool,
+    #[serde(default)]
+    pub non_fov_blocker: bool,
+    #[serde(default = "default_true")]
+    pub combat_obstacle: bool,
+    #[serde(default)]
+    pub placeable_item_surface: bool,
+    #[serde(default = "default_true")]
+    pub laser_combat_obstacle: bool,
+    #[serde(default)]
+    pub vertical_rotation: bool,
+    #[serde(default = "default_true")]
+    pub atmospherics_blocker: bool,
+    #[serde(default)]
+    pub atmospherics_pushes_up: bool,
+    #[serde(default)]
+    pub x_rotations: Vec<u8>,
+    #[serde(default)]
+    pub y_rotations: Vec<u8>,
+    /// Name of the [crate::layers::LayerProperties] the tile gets placed on.
+    #[serde(default = "default_layer")]
+    pub layer: String,
+    /// Path of the gltf mesh relative to the assets folder.
+    #[serde(default)]
+    pub mesh: Option<String>,
+    #[serde(default)]
+    pub material: Option<TileMaterialDefinition>,
+    #[serde(default)]
+    pub light: Option<TileLightDefinition>,
+    #[serde(default)]
+    pub power_cable: bool,
+    #[serde(default)]
+    pub power_usage: f32,
+    /// Damage thresholds, tiles without are indestructible.
+    #[serde(default)]
+    pub durability: Option<TileDurability>,
+    /// Diagonal floors set how far they rise so they can be walked.
+    #[serde(default)]
+    pub ramp: Option<Ramp>,
+    #[serde(default)]
+    pub door: bool,
+    #[serde(default)]
+    pub window: bool,
+}
+
+impl TileDefinition {
+    /// Tile properties without graphics, the server doesn't load those.
+    pub fn properties(&self, layer: LayerId) -> TileProperties {
+        let [x, y, z] = self.collider_offset;
+        let [rx, ry, rz] = self.collider_rotation;
+        TileProperties {
+            name_id: CellTypeName(self.name_id.clone()),
+            name: RichName {
+                name: self.name.name.clone(),
+                n: self.name.n,
+                the: self.name.the,
+            },
+            description: self.description.clone(),
+            non_fov_blocker: self.non_fov_blocker,
+            combat_obstacle: self.combat_obstacle,
+            placeable_item_surface: self.placeable_item_surface,
+            laser_combat_obstacle: self.laser_combat_obstacle,
+            collider: self.collider.collider(),
+            collider_position: Transform {
+                translation: Vec3::new(x, y, z),
+                rotation: Quat::from_euler(
+                    bevy::math::EulerRot::XYZ,
+                    rx.to_radians(),
+                    ry.to_radians(),
+                    rz.to_radians(),
+                ),
+                ..Default::default()
+            },
+            constructable: self.constructable,
+            vertical_rotation: self.vertical_rotation,
+            atmospherics_blocker: self.atmospherics_blocker,
+            atmospherics_pushes_up: self.atmospherics_pushes_up,
+            mesh_option: None,
+            material_option: None,
+            cell_type: self.cell_type.clone(),
+            x_rotations: self.x_rotations.clone(),
+            y_rotations: self.y_rotations.clone(),
+            layer,
+            is_light: self.light.as_ref().map(|light| TileLight {
+                light: default_point_light(),
+                local_offset: Vec3::from(light.local_offset),
+            }),
+            power_cable: self.power_cable,
+            power_usage: self.power_usage,
+            durability: self.durability.clone(),
+            ramp: self.ramp,
+            door: self.door,
+            window: self.window,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct GroupTileDefinition {
+    pub offset: Vec3Int,
+    pub tile: String,
+    #[serde(default)]
+    pub face: CellFace,
+    #[serde(default)]
+    pub orientation: u8,
+}
+
+/// A tile group as written in its RON file, tiles are referred to by name.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileGroupDefinition {
+    pub name_id: String,
+    pub tiles: Vec<GroupTileDefinition>,
+}
+
+impl TileGroupDefinition {
+    pub fn group(&self, gridmap: &Gridmap) -> Result<TileGroup, String> {
+        let mut map = HashMap::new();
+        for tile in self.tiles.iter() {
+            match gridmap.name_id_map.get(&CellTypeName(tile.tile.clone())) {
+                Some(tile_type) => {
+                    map.insert(
+                        tile.offset,
+                        FullCell {
+                            face: tile.face.clone(),
+                            orientation: tile.orientation,
+                            tile_type: *tile_type,
+                            entity_option: None,
+                        },
+                    );
+                }
+                None => {
+                    return Err(format!(
+                        "Group {} refers to unknown tile {}.",
+                        self.name_id, tile.tile
+                    ));
+                }
+            }
+        }
+        Ok(TileGroup {
+            name_id: GroupTypeName(self.name_id.clone()),
+            map,
+        })
+    }
+}
+
+/// Parse every definition file of a directory, sorted by path.
+pub fn read_definitions<T: DeserializeOwned>(directory: &Path) -> Vec<Result<T, DefinitionError>> {
+    let mut paths = vec![];
+    match fs::read_dir(directory) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                match path.extension() {
+                    Some(extension) => {
+                        if extension == DEFINITION_EXTENSION {
+                            paths.push(path);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        Err(rr) => {
+            warn!(
+                "Couldn't read definitions directory {:?}: {}",
+                directory, rr
+            );
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| match fs::read_to_string(&path) {
+            Ok(text) => ron::from_str::<T>(&text)
+                .map_err(|rr| DefinitionError::Parse(path.clone(), rr.to_string())),
+            Err(rr) => Err(DefinitionError::Read(path.clone(), rr.to_string())),
+        })
+        .collect()
+}
+
+/// Add the tile types defined in RON files to [InitTileProperties].
+/// Runs after the tile types defined in Rust, definitions with a name that is taken already get skipped.
+pub(crate) fn load_tile_definitions(
+    mut init: ResMut<InitTileProperties>,
+    gridmap: Res<Gridmap>,
+    asset_server: Option<Res<AssetServer>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    let mut names: HashSet<CellTypeName> = init
+        .properties
+        .iter()
+        .map(|properties| properties.name_id.clone())
+        .collect();
+    let mut loaded = 0;
+    for result in read_definitions::<TileDefinition>(&tile_definition_directory()) {
+        let definition;
+        match result {
+            Ok(d) => {
+                definition = d;
+            }
+            Err(rr) => {
+                warn!("{}", rr);
+                continue;
+            }
+        }
+        let layer;
+        match gridmap.layer_id(&definition.layer) {
+            Some(l) => {
+                layer = l;
+            }
+            None => {
+                warn!(
+                    "Tile {} is on unknown layer {}.",
+                    definition.name_id, definition.layer
+                );
+                continue;
+            }
+        }
+        if !names.insert(CellTypeName(definition.name_id.clone())) {
+            warn!(
+                "Tile {} is defined already, skipping its definition.",
+                definition.name_id
+            );
+            continue;
+        }
+        let mut properties = definition.properties(layer);
+        // Apps without rendering, like the server and tools, load tiles without graphics.
+        match (&asset_server, &mut materials) {
+            (Some(asset_server), Some(materials)) => {
+                properties.mesh_option = definition
+                    .mesh
+                    .as_ref()
+                    .map(|path| asset_server.load(path.clone()));
+                properties.material_option = definition
+                    .material
+                    .as_ref()
+                    .map(|material| materials.add(material.material(asset_server)));
+            }
+            _ => {}
+        }
+        init.properties.push(properties);
+        loaded += 1;
+    }
+    info!("Loaded {} tile definitions.", loaded);
+}
+
+/// Add the tile groups defined in RON files to [InitTileGroups]. Needs the tile types registered.
+pub(crate) fn load_group_definitions(gridmap: Res<Gridmap>, mut init: ResMut<InitTileGroups>) {
+    let mut loaded = 0;
+    for result in read_definitions::<TileGroupDefinition>(&group_definition_directory()) {
+        match result {
+            Ok(definition) => match definition.group(&gridmap) {
+                Ok(group) => {
+                    init.groups.push(group);
+                    loaded += 1;
+                }
+                Err(rr) => {
+                    warn!("{}", rr);
+                }
+            },
+            Err(rr) => {
+                warn!("{}", rr);
+            }
+        }
+    }
+    info!("Loaded {} tile group definitions.", loaded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tile_definition() {
+        let definition: TileDefinition = ron::from_str(
+            r#"(
+                name_id: "test_wall",
+                name: (name: "test wall", n: true),
+                description: "A wall.",
+                cell_type: Wall,
+                collider: Cuboid(1., 1., 0.2),
+                non_fov_blocker: true,
+                x_rotations: [0, 16, 10, 22],
+                light: Some((local_offset: (0., 0., 0.5))),
+                durability: Some((destroyed: 200.)),
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(definition.layer, STRUCTURE_LAYER);
+        let properties = definition.properties(LayerId::STRUCTURE);
+        assert_eq!(properties.name_id, CellTypeName("test_wall".to_string()));
+        assert!(properties.non_fov_blocker);
+        assert!(properties.combat_obstacle);
+        assert!(properties.atmospherics_blocker);
+        assert_eq!(properties.durability.unwrap().destroyed, 200.);
+        assert_eq!(properties.x_rotations, vec![0, 16, 10, 22]);
+        assert!(properties.is_light.is_some());
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::Component;
+use bevy::{
+    log::{info, warn},
+    prelude::{
+        Color, Commands, DespawnRecursiveExt, Entity, EventReader, EventWriter, Query, Res, ResMut,
+        Resource, TextBundle, With,
+    },
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val},
+};
+use bevy_renet::renet::ClientId;
+use networking::{
+    client::{IncomingReliableServerMessage, OutgoingReliableClientMessage},
+    server::{ConnectedPlayer, IncomingReliableClientMessage, OutgoingReliableServerMessage},
+};
+use player::connections::{SFServerEvent, ServerEventBuffer};
+use resources::grid::{GridId, TargetCell};
+use sha2::{Digest, Sha256};
+use ui::fonts::{Fonts, ARIZONE_FONT};
+
+use crate::{
+    format::{decode_gridmap, GridmapEncoding},
+    grid::{AddTile, Gridmap, LayerTargetCell, RemoveTile},
+    init::ItemExport,
+    net::{GridmapClientMessage, GridmapServerMessage, MapDownloadPart, NewCell},
+    streaming::LoadedChunks,
+};
+
+/// Size in bytes of a single map download message.
+pub const MAP_DOWNLOAD_PART_SIZE: usize = 8 * 1024;
+/// Amount of map download messages sent to a client per tick.
+pub const MAP_DOWNLOAD_PARTS_PER_TICK: usize = 2;
+/// Least time between two encodes of the served map. Changes since then are streamed to clients.
+pub const SERVED_MAP_REFRESH: Duration = Duration::from_secs(30);
+
+/// Directory maps downloaded from servers get cached in, by hash.
+pub fn map_cache_directory() -> PathBuf {
+    Path::new("data").join("cache").join("maps")
+}
+
+/// Content hash of an encoded map.
+pub fn map_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// The map of the server encoded for downloads. Shared by all downloads and re-encoded at most every [SERVED_MAP_REFRESH] after the gridmap changed.
+#[derive(Resource, Default)]
+pub struct ServedMap {
+    pub hash: Option<String>,
+    pub data: Arc<Vec<u8>>,
+    /// The gridmap changed since the map was encoded.
+    pub stale: bool,
+    pub encoded_at: Option<Instant>,
+}
+
+impl ServedMap {
+    /// Hash of the served map, encodes the map if it is missing or stale for long enough.
+    pub fn get_hash(&mut self, gridmap: &Gridmap) -> String {
+        let refresh = match (&self.hash, self.encoded_at) {
+            (Some(_), Some(encoded_at)) => self.stale && encoded_at.elapsed() >= SERVED_MAP_REFRESH,
+            _ => true,
+        };
+        if refresh {
+            match gridmap.export(GridmapEncoding::Binary) {
+                Ok(data) => {
+                    self.data = Arc::new(data);
+                }
+                Err(rr) => {
+                    warn!("Couldn't encode map for download: {}", rr);
+                    self.data = Arc::new(vec![]);
+                }
+            }
+            self.hash = Some(map_hash(&self.data));
+            self.stale = false;
+            self.encoded_at = Some(Instant::now());
+        }
+        self.hash.clone().unwrap_or_default()
+    }
+}
+
+pub(crate) fn invalidate_served_map(
+    mut add_tile: EventReader<AddTile>,
+    mut remove_tile: EventReader<RemoveTile>,
+    mut served: ResMut<ServedMap>,
+) {
+    if add_tile.read().count() > 0 || remove_tile.read().count() > 0 {
+        served.stale = true;
+    }
+}
+
+pub struct ActiveMapDownload {
+    pub data: Arc<Vec<u8>>,
+    pub next_part: usize,
+}
+
+impl ActiveMapDownload {
+    pub fn total_parts(&self) -> usize {
+        self.data.len().div_ceil(MAP_DOWNLOAD_PART_SIZE).max(1)
+    }
+}
+
+/// Map downloads in progress per client.
+#[derive(Resource, Default)]
+pub struct MapDownloads {
+    pub clients: HashMap<ClientId, ActiveMapDownload>,
+    /// The encoded map each client got the hash of while being configured.
+    pub configured: HashMap<ClientId, Arc<Vec<u8>>>,
+}
+
+pub(crate) fn start_map_downloads(
+    mut net: EventReader<IncomingReliableClientMessage<GridmapClientMessage>>,
+    mut downloads: ResMut<MapDownloads>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapClientMessage::RequestMapDownload => {
+                if downloads.clients.contains_key(&message.handle) {
+                    warn!(
+                        "Client {} requested the map while downloading it already.",
+                        message.handle
+                    );
+                    continue;
+                }
+                let data;
+                match downloads.configured.remove(&message.handle) {
+                    Some(d) => {
+                        data = d;
+                    }
+                    None => {
+                        warn!(
+                            "Client {} requested the map without having been sent its hash.",
+                            message.handle
+                        );
+                        continue;
+                    }
+                }
+                info!(
+                    "Sending map ({} bytes) to client {}.",
+                    data.len(),
+                    message.handle
+                );
+                downloads
+                    .clients
+                    .insert(message.handle, ActiveMapDownload { data, next_part: 0 });
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Forget the map snapshots of clients that disconnected without downloading it.
+pub(crate) fn forget_disconnected_downloads(
+    buffer: Res<ServerEventBuffer>,
+    mut downloads: ResMut<MapDownloads>,
+) {
+    for event in buffer.buffer.iter() {
+        match event {
+            SFServerEvent::ClientDisconnected { client_id, .. } => {
+                downloads.configured.remove(client_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Send the next parts of active map downloads, a few at a time to not congest the reliable channel.
+pub(crate) fn send_map_download_parts(
+    mut downloads: ResMut<MapDownloads>,
+    connected_players: Query<&ConnectedPlayer>,
+    mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    let mut active = vec![];
+    for connected_player in connected_players.iter() {
+        if connected_player.connected {
+            active.push(connected_player.handle);
+        }
+    }
+    downloads
+        .clients
+        .retain(|handle, _| active.contains(handle));
+
+    let mut finished = vec![];
+    for (handle, download) in downloads.clients.iter_mut() {
+        let total = download.total_parts();
+        for _ in 0..MAP_DOWNLOAD_PARTS_PER_TICK {
+            if download.next_part >= total {
+                break;
+            }
+            let start = download.next_part * MAP_DOWNLOAD_PART_SIZE;
+            let end = (start + MAP_DOWNLOAD_PART_SIZE).min(download.data.len());
+            net.send(OutgoingReliableServerMessage {
+                handle: *handle,
+                message: GridmapServerMessage::MapDownloadPart(MapDownloadPart {
+                    index: download.next_part as u32,
+                    total: total as u32,
+                    data: download.data[start..end].to_vec(),
+                }),
+            });
+            download.next_part += 1;
+        }
+        if download.next_part >= total {
+            finished.push(*handle);
+        }
+    }
+    for handle in finished {
+        downloads.clients.remove(&handle);
+    }
+}
+
+/// Client-side state of the map download.
+#[derive(Resource, Default)]
+pub struct MapDownloadProgress {
+    /// Hash of the map the server is running.
+    pub hash: Option<String>,
+    pub received: u32,
+    pub total: u32,
+    pub data: Vec<u8>,
+    pub downloading: bool,
+}
+
+impl MapDownloadProgress {
+    pub fn percentage(&self) -> f32 {
+        if self.total == 0 {
+            return 0.;
+        }
+        self.received as f32 / self.total as f32 * 100.
+    }
+}
+
+/// The map as downloaded at connect, per chunk. Shown for chunks that aren't streamed.
+#[derive(Resource, Default)]
+pub struct MapBaseline {
+    pub chunks: HashMap<usize, Vec<NewCell>>,
+}
+
+/// Decode a downloaded map into the baseline and spawn it for all chunks that aren't streamed already.
+fn apply_downloaded_map(
+    data: &[u8],
+    gridmap: &Gridmap,
+    baseline: &mut MapBaseline,
+    loaded: &LoadedChunks,
+    add_tile: &mut EventWriter<AddTile>,
+    commands: &mut Commands,
+    stamp: u32,
+) {
+    let decoded;
+    match decode_gridmap(data, GridmapEncoding::Binary) {
+        Ok(d) => {
+            decoded = d;
+        }
+        Err(rr) => {
+            warn!("Couldn't decode downloaded map: {}", rr);
+            return;
+        }
+    }
+    baseline.chunks.clear();
+    for cell_data in decoded.cells.iter() {
+        let name = match &cell_data.item {
+            ItemExport::Cell(name) => name,
+            ItemExport::Group(item) => &item.cell,
+        };
+        let tile_type;
+        match gridmap.name_id_map.get(name) {
+            Some(id) => {
+                tile_type = *id;
+            }
+            None => {
+                warn!("Downloaded map contains unknown tile {:?}.", name);
+                continue;
+            }
+        }
+        let target = TargetCell {
+            id: cell_data.id,
+            face: cell_data.face.clone(),
+            grid: GridId::MAIN,
+        };
+        let layer;
+        match gridmap.layer_id(&cell_data.layer) {
+            Some(l) => {
+                layer = l;
+            }
+            None => {
+                warn!("Downloaded map contains unknown layer {}.", cell_data.layer);
+                continue;
+            }
+        }
+        let chunk;
+        match gridmap.get_cell_chunk(&target) {
+            Some(c) => {
+                chunk = c;
+            }
+            None => {
+                warn!("Downloaded map contains cell outside map limits.");
+                continue;
+            }
+        }
+        baseline.chunks.entry(chunk).or_default().push(NewCell {
+            cell: LayerTargetCell { target, layer },
+            orientation: cell_data.orientation,
+            tile_type,
+            metadata: cell_data.metadata.clone().unwrap_or_default(),
+        });
+    }
+    for (chunk, cells) in baseline.chunks.iter() {
+        if loaded.chunks.contains(chunk) {
+            continue;
+        }
+        spawn_baseline_cells(cells, add_tile, commands, stamp);
+    }
+    info!(
+        "Loaded map with {} cells in {} chunks.",
+        decoded.cells.len(),
+        baseline.chunks.len()
+    );
+}
+
+pub(crate) fn spawn_baseline_cells(
+    cells: &Vec<NewCell>,
+    add_tile: &mut EventWriter<AddTile>,
+    commands: &mut Commands,
+    stamp: u32,
+) {
+    for new in cells.iter() {
+        add_tile.send(AddTile {
+            id: new.cell.target.id,
+            tile_type: new.tile_type,
+            orientation: new.orientation,
+            face: new.cell.target.face.clone(),
+            group_instance_id_option: None,
+            metadata: new.metadata.clone(),
+            entity: commands.spawn(()).id(),
+            default_map_spawn: true,
+            layer: new.cell.layer,
+            grid: new.cell.target.grid,
+            stamp,
+        });
+    }
+}
+
+/// Load the map from the cache or request it from the server.
+pub(crate) fn receive_map_hash(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut progress: ResMut<MapDownloadProgress>,
+    mut outgoing: EventWriter<OutgoingReliableClientMessage<GridmapClientMessage>>,
+    gridmap: Res<Gridmap>,
+    mut baseline: ResMut<MapBaseline>,
+    loaded: Res<LoadedChunks>,
+    mut add_tile: EventWriter<AddTile>,
+    mut commands: Commands,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::ConfigMapHash(hash) => {
+                *progress = MapDownloadProgress {
+                    hash: Some(hash.clone()),
+                    ..Default::default()
+                };
+                let cache_path = map_cache_directory().join(format!("{}.bin", hash));
+                match fs::read(&cache_path) {
+                    Ok(data) => {
+                        if map_hash(&data) == *hash {
+                            info!("Loading cached map {:?}.", cache_path);
+                            apply_downloaded_map(
+                                &data,
+                                &gridmap,
+                                &mut baseline,
+                                &loaded,
+                                &mut add_tile,
+                                &mut commands,
+                                message.stamp,
+                            );
+                            continue;
+                        }
+                        warn!(
+                            "Cached map {:?} is corrupt, downloading it again.",
+                            cache_path
+                        );
+                    }
+                    Err(_) => {}
+                }
+                progress.downloading = true;
+                outgoing.send(OutgoingReliableClientMessage {
+                    message: GridmapClientMessage::RequestMapDownload,
+                });
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Assemble map download parts, verify the hash and cache the map.
+pub(crate) fn receive_map_download(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut progress: ResMut<MapDownloadProgress>,
+    gridmap: Res<Gridmap>,
+    mut baseline: ResMut<MapBaseline>,
+    loaded: Res<LoadedChunks>,
+    mut add_tile: EventWriter<AddTile>,
+    mut commands: Commands,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::MapDownloadPart(part) => {
+                if !progress.downloading {
+                    continue;
+                }
+                if part.index != progress.received {
+                    warn!(
+                        "Received map part {} while expecting part {}.",
+                        part.index, progress.received
+                    );
+                    continue;
+                }
+                progress.total = part.total;
+                progress.received += 1;
+                progress.data.extend_from_slice(&part.data);
+
+                if progress.received < progress.total {
+                    continue;
+                }
+                progress.downloading = false;
+                let data = std::mem::take(&mut progress.data);
+                let hash;
+                match &progress.hash {
+                    Some(h) => {
+                        hash = h.clone();
+                    }
+                    None => {
+                        warn!("Received map download without map hash.");
+                        continue;
+                    }
+                }
+                if map_hash(&data) != hash {
+                    warn!("Downloaded map doesn't match hash {}.", hash);
+                    continue;
+                }
+                let cache_directory = map_cache_directory();
+                match fs::create_dir_all(&cache_directory) {
+                    Ok(_) => {
+                        let cache_path = cache_directory.join(format!("{}.bin", hash));
+                        match fs::write(&cache_path, &data) {
+                            Ok(_) => {
+                                info!("Cached downloaded map as {:?}.", cache_path);
+                            }
+                            Err(rr) => {
+                                warn!("Couldn't cache map {:?}: {}", cache_path, rr);
+                            }
+                        }
+                    }
+                    Err(rr) => {
+                        warn!("Couldn't create map cache {:?}: {}", cache_directory, rr);
+                    }
+                }
+                apply_downloaded_map(
+                    &data,
+                    &gridmap,
+                    &mut baseline,
+                    &loaded,
+                    &mut add_tile,
+                    &mut commands,
+                    message.stamp,
+                );
+            }
+            _ => (),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MapDownloadText;
+
+/// Show the download progress while the map is being downloaded.
+pub(crate) fn map_download_ui(
+    progress: Res<MapDownloadProgress>,
+    mut query: Query<(Entity, &mut Text), With<MapDownloadText>>,
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+    let label = format!("Downloading map.. {:.0}%", progress.percentage());
+    match query.get_single_mut() {
+        Ok((entity, mut text)) => {
+            if progress.downloading {
+                match text.sections.get_mut(0) {
+                    Some(section) => {
+                        section.value = label;
+                    }
+                    None => {}
+                }
+            } else {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        Err(_) => {
+            if !progress.downloading {
+                return;
+            }
+            let font;
+            match fonts.handles.get(ARIZONE_FONT) {
+                Some(f) => {
+                    font = f.clone();
+                }
+                None => {
+                    warn!("Couldn't find font for map download progress.");
+                    return;
+                }
+            }
+            commands.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font,
+                        font_size: 12.0,
+                        color: Color::WHITE.into(),
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent(5.),
+                    left: Val::Percent(5.),
+                    ..Default::default()
+                }),
+                MapDownloadText,
+            ));
+        }
+    }
+}
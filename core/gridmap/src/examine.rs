@@ -5,9 +5,9 @@ use bevy_renet::renet::ClientId;
 use entity::{
     examine::Examinable,
     health::HealthContainer,
-    senser::{to_doryen_coordinates, Senser, SensingAbility},
+    senser::{Senser, SensingAbility},
 };
-use resources::grid::{CellFace, TargetCell};
+use resources::grid::{CellFace, GridId, TargetCell};
 use resources::math::Vec3Int;
 use text_api::core::{
     get_empty_cell_message, get_space_message, ASTRIX, ENGINEERING_TEXT_COLOR, EXAMINATION_EMPTY,
@@ -15,6 +15,8 @@ use text_api::core::{
 };
 
 use crate::grid::{CellItem, CellTypeId, Gridmap, LayerTargetCell};
+use crate::layers::LayerId;
+use crate::power::{examine_power_text, PowerNetwork};
 
 /// Manage examining the gridmap.
 
@@ -22,6 +24,7 @@ pub(crate) fn examine_map(
     mut examine_map_events: ResMut<GridmapExamineMessages>,
     gridmap: Res<Gridmap>,
     senser_entities: Query<&Senser>,
+    power: Res<PowerNetwork>,
 ) {
     for examine_event in examine_map_events.messages.iter_mut() {
         let examiner_senser_component;
@@ -37,20 +40,18 @@ pub(crate) fn examine_map(
         }
 
         let mut examine_text;
+        let target = TargetCell {
+            id: examine_event.gridmap_cell_id,
+            face: examine_event.face.clone(),
+            grid: examine_event.grid,
+        };
 
-        let coords = to_doryen_coordinates(
-            examine_event.gridmap_cell_id.x,
-            examine_event.gridmap_cell_id.z,
-        );
-        if !examiner_senser_component.fov.is_in_fov(coords.0, coords.1) {
+        if !examiner_senser_component.is_in_fov(gridmap.get_world_cell_id(&target)) {
             examine_text = get_empty_cell_message();
         } else {
             match gridmap.get_cell(LayerTargetCell {
-                target: TargetCell {
-                    id: examine_event.gridmap_cell_id,
-                    face: examine_event.face.clone(),
-                },
-                is_detail: false,
+                target,
+                layer: LayerId::STRUCTURE,
             }) {
                 Some(ship_cell) => {
                     examine_text = examine_ship_cell(&ship_cell, &gridmap);
@@ -103,7 +104,7 @@ pub(crate) fn set_action_header_name(
 
                 match gridmap.get_cell(LayerTargetCell {
                     target: t,
-                    is_detail: false,
+                    layer: LayerId::STRUCTURE,
                 }) {
                     Some(data) => {
                         item_id = data.tile_type;
@@ -134,6 +135,7 @@ pub(crate) fn examine_map_health(
     mut examine_map_events: ResMut<GridmapExamineMessages>,
     gridmap_main: Res<Gridmap>,
     senser_entities: Query<&Senser>,
+    power: Res<PowerNetwork>,
 ) {
     for examine_event in examine_map_events.messages.iter_mut() {
         let examiner_senser_component;
@@ -149,13 +151,16 @@ pub(crate) fn examine_map_health(
         }
 
         let gridmap_result;
+        let target = TargetCell {
+            id: examine_event.gridmap_cell_id,
+            face: examine_event.face.clone(),
+            grid: examine_event.grid,
+        };
+        let world_cell_id = gridmap_main.get_world_cell_id(&target);
 
         gridmap_result = gridmap_main.get_cell(LayerTargetCell {
-            target: TargetCell {
-                id: examine_event.gridmap_cell_id,
-                face: examine_event.face.clone(),
-            },
-            is_detail: false,
+            target,
+            layer: LayerId::STRUCTURE,
         });
 
         let ship_cell_option;
@@ -167,11 +172,7 @@ pub(crate) fn examine_map_health(
             }
         }
 
-        let coords = to_doryen_coordinates(
-            examine_event.gridmap_cell_id.x,
-            examine_event.gridmap_cell_id.z,
-        );
-        if examiner_senser_component.fov.is_in_fov(coords.0, coords.1) {
+        if examiner_senser_component.is_in_fov(world_cell_id) {
             match ship_cell_option {
                 Some(ship_cell) => {
                     let mut message = "".to_string();
@@ -257,6 +258,7 @@ pub(crate) fn examine_map_health(
 pub(crate) fn examine_map_abilities(
     mut examine_map_events: ResMut<GridmapExamineMessages>,
     senser_entities: Query<&Senser>,
+    power: Res<PowerNetwork>,
 ) {
     for examine_event in examine_map_events.messages.iter_mut() {
         let examiner_senser_component;
@@ -276,6 +278,16 @@ pub(crate) fn examine_map_abilities(
         for sensing_ability in examiner_senser_component.sensing_abilities.iter() {
             match sensing_ability {
                 SensingAbility::ShipEngineerKnowledge => {
+                    let grid_text;
+                    let power_text;
+                    if examine_event.grid == GridId::MAIN {
+                        grid_text = "".to_string();
+                        power_text = examine_power_text(&power, &examine_event.gridmap_cell_id);
+                    } else {
+                        grid_text = " on grid ".to_string() + &examine_event.grid.0.to_string();
+                        // Only the main grid is wired up to the power network.
+                        power_text = "".to_string();
+                    }
                     examine_text = examine_text
                         + "[font="
                         + FURTHER_ITALIC_FONT
@@ -289,8 +301,11 @@ pub(crate) fn examine_map_abilities(
                         + &examine_event.gridmap_cell_id.x.to_string()
                         + " , "
                         + &examine_event.gridmap_cell_id.z.to_string()
-                        + ")."
-                        + "[/font]\n";
+                        + ")"
+                        + &grid_text
+                        + "."
+                        + "[/font]\n"
+                        + &power_text;
                 }
                 _ => (),
             }
@@ -314,6 +329,7 @@ pub struct InputExamineMap {
     pub entity: Entity,
     pub gridmap_cell_id: Vec3Int,
     pub face: CellFace,
+    pub grid: GridId,
     /// Map examine message being built and sent back to the player.
     pub message: String,
 }
@@ -382,6 +398,7 @@ pub(crate) fn examine_grid(
                                 entity: building.action_taker,
                                 gridmap_cell_id: c.id,
                                 face: CellFace::default(),
+                                grid: c.grid,
                                 message: ASTRIX.to_string(),
                             });
                         }
@@ -410,18 +427,15 @@ pub(crate) fn incoming_messages(
         let client_message = message.message.clone();
 
         match client_message {
-            GridmapClientMessage::ExamineMap(cell_id_x, cell_id_y, cell_id_z) => {
+            GridmapClientMessage::ExamineMap(cell) => {
                 match handle_to_entity.map.get(&message.handle) {
                     Some(player_entity) => {
                         input_examine_map.send(InputExamineMap {
                             handle: message.handle,
                             entity: *player_entity,
-                            gridmap_cell_id: Vec3Int {
-                                x: cell_id_x,
-                                y: cell_id_y,
-                                z: cell_id_z,
-                            },
-                            face: CellFace::default(),
+                            gridmap_cell_id: cell.id,
+                            face: cell.face,
+                            grid: cell.grid,
                             message: ASTRIX.to_string(),
                         });
                     }
@@ -465,5 +479,17 @@ pub fn examine_ship_cell(ship_cell: &CellItem, gridmap_data: &Res<Gridmap>) -> S
 
     message = message + "[font=" + FURTHER_ITALIC_FONT + "]" + examine_text + ".[/font]";
 
+    match &ship_cell.metadata.label {
+        Some(label) => {
+            message = message
+                + "\n[font="
+                + FURTHER_ITALIC_FONT
+                + "]It is labeled \""
+                + label
+                + "\".[/font]";
+        }
+        None => {}
+    }
+
     message
 }
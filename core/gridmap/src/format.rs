@@ -0,0 +1,903 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    io::{Read, Write},
+    path::Path,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use resources::{grid::CellFace, math::Vec3Int};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    damage::CellDamage,
+    grid::{CellTypeName, GroupTypeName, MapLimits, TileMetadata},
+    init::{CellDataExport, GroupItem, ItemExport},
+    layers::{DETAILS_LAYER, STRUCTURE_LAYER},
+};
+
+/// Current version of the gridmap file format.
+pub const GRIDMAP_FORMAT_VERSION: u16 = 5;
+/// Magic bytes at the start of binary gridmap files. Files without them are legacy (version 0) exports.
+pub const GRIDMAP_BINARY_MAGIC: [u8; 4] = *b"SFGM";
+/// File extension of the text variant.
+pub const GRIDMAP_TEXT_EXTENSION: &str = "ron";
+
+/// Encoding of a gridmap file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridmapEncoding {
+    /// Compressed bincode, for shipping maps.
+    Binary,
+    /// Pretty printed RON, for diffing maps in version control.
+    Text,
+}
+
+impl GridmapEncoding {
+    /// Text for `.ron` files, binary for everything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) => {
+                if extension == GRIDMAP_TEXT_EXTENSION {
+                    GridmapEncoding::Text
+                } else {
+                    GridmapEncoding::Binary
+                }
+            }
+            None => GridmapEncoding::Binary,
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GridmapEncoding::Binary => "bin",
+            GridmapEncoding::Text => GRIDMAP_TEXT_EXTENSION,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GridmapFormatError {
+    UnsupportedVersion(u16),
+    Corrupt(String),
+    /// A cell refers to a palette entry that doesn't exist.
+    Palette(String),
+    /// A palette has more entries than its indexes can refer to.
+    PaletteOverflow(&'static str),
+}
+
+impl fmt::Display for GridmapFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridmapFormatError::UnsupportedVersion(v) => write!(
+                f,
+                "Gridmap format version {} is newer than supported version {}.",
+                v, GRIDMAP_FORMAT_VERSION
+            ),
+            GridmapFormatError::Corrupt(rr) => write!(f, "Corrupt gridmap file: {}", rr),
+            GridmapFormatError::Palette(rr) => write!(f, "Invalid gridmap palette: {}", rr),
+            GridmapFormatError::PaletteOverflow(palette) => write!(
+                f,
+                "Gridmap {} palette has more than {} entries.",
+                palette,
+                u16::MAX as usize + 1
+            ),
+        }
+    }
+}
+
+/// Header of a versioned gridmap file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GridmapFileHeader {
+    pub version: u16,
+    pub map_limits: MapLimits,
+    /// Tile names referred to by index from [PaletteItem].
+    pub cell_palette: Vec<CellTypeName>,
+    /// Group names referred to by index from [PaletteItem].
+    pub group_palette: Vec<GroupTypeName>,
+    /// Layer names referred to by index from [PaletteCell]. Added in version 5.
+    pub layer_palette: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PaletteGroupItem {
+    pub group: u16,
+    /// Instance id of the group.
+    pub group_id: u32,
+    pub cell: u16,
+    /// Orientation of the group instance. Added in version 3.
+    #[serde(default)]
+    pub orientation: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum PaletteItem {
+    Cell(u16),
+    Group(PaletteGroupItem),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PaletteCell {
+    pub id: Vec3Int,
+    pub item: PaletteItem,
+    pub orientation: u8,
+    pub face: CellFace,
+    /// Index into the layer palette. Replaced `is_detail` in version 5.
+    pub layer: u16,
+    /// Added in version 2.
+    #[serde(default)]
+    pub damage: Option<CellDamage>,
+    /// Added in version 4.
+    #[serde(default)]
+    pub metadata: Option<TileMetadata>,
+}
+
+/// A versioned gridmap file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GridmapFile {
+    pub header: GridmapFileHeader,
+    pub cells: Vec<PaletteCell>,
+}
+
+/// Version 1 to 4 headers, from before layers were named.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct GridmapFileHeaderV4 {
+    version: u16,
+    map_limits: MapLimits,
+    cell_palette: Vec<CellTypeName>,
+    group_palette: Vec<GroupTypeName>,
+}
+
+/// Version 4 cells, with tiles either on the structure or the details layer.
+#[derive(Deserialize)]
+struct PaletteCellV4 {
+    id: Vec3Int,
+    item: PaletteItem,
+    orientation: u8,
+    face: CellFace,
+    is_detail: bool,
+    #[serde(default)]
+    damage: Option<CellDamage>,
+    #[serde(default)]
+    metadata: Option<TileMetadata>,
+}
+#[derive(Deserialize)]
+struct GridmapFileV4 {
+    header: GridmapFileHeaderV4,
+    cells: Vec<PaletteCellV4>,
+}
+
+/// Name of the layer version 0 to 4 files refer to with their `is_detail` flag.
+pub(crate) fn legacy_layer(is_detail: bool) -> &'static str {
+    match is_detail {
+        true => DETAILS_LAYER,
+        false => STRUCTURE_LAYER,
+    }
+}
+
+impl From<GridmapFileV4> for GridmapFile {
+    fn from(file: GridmapFileV4) -> Self {
+        Self {
+            header: GridmapFileHeader {
+                version: file.header.version,
+                map_limits: file.header.map_limits,
+                cell_palette: file.header.cell_palette,
+                group_palette: file.header.group_palette,
+                layer_palette: vec![
+                    legacy_layer(false).to_string(),
+                    legacy_layer(true).to_string(),
+                ],
+            },
+            cells: file
+                .cells
+                .into_iter()
+                .map(|cell| PaletteCell {
+                    id: cell.id,
+                    item: cell.item,
+                    orientation: cell.orientation,
+                    face: cell.face,
+                    layer: cell.is_detail as u16,
+                    damage: cell.damage,
+                    metadata: cell.metadata,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Binary version 3 cells, from before tile metadata was stored.
+#[derive(Deserialize)]
+struct PaletteCellV3 {
+    id: Vec3Int,
+    item: PaletteItem,
+    orientation: u8,
+    face: CellFace,
+    is_detail: bool,
+    damage: Option<CellDamage>,
+}
+#[derive(Deserialize)]
+struct GridmapFileV3 {
+    header: GridmapFileHeaderV4,
+    cells: Vec<PaletteCellV3>,
+}
+
+impl From<GridmapFileV3> for GridmapFileV4 {
+    fn from(file: GridmapFileV3) -> Self {
+        Self {
+            header: file.header,
+            cells: file
+                .cells
+                .into_iter()
+                .map(|cell| PaletteCellV4 {
+                    id: cell.id,
+                    item: cell.item,
+                    orientation: cell.orientation,
+                    face: cell.face,
+                    is_detail: cell.is_detail,
+                    damage: cell.damage,
+                    metadata: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Binary version 1 and 2 group items, from before group orientations were stored.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct PaletteGroupItemV2 {
+    group: u16,
+    group_id: u32,
+    cell: u16,
+}
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+enum PaletteItemV2 {
+    Cell(u16),
+    Group(PaletteGroupItemV2),
+}
+
+impl From<PaletteItemV2> for PaletteItem {
+    fn from(item: PaletteItemV2) -> Self {
+        match item {
+            PaletteItemV2::Cell(cell) => PaletteItem::Cell(cell),
+            PaletteItemV2::Group(group_item) => PaletteItem::Group(PaletteGroupItem {
+                group: group_item.group,
+                group_id: group_item.group_id,
+                cell: group_item.cell,
+                orientation: 0,
+            }),
+        }
+    }
+}
+
+/// Binary version 2 cells.
+#[derive(Deserialize)]
+struct PaletteCellV2 {
+    id: Vec3Int,
+    item: PaletteItemV2,
+    orientation: u8,
+    face: CellFace,
+    is_detail: bool,
+    damage: Option<CellDamage>,
+}
+#[derive(Deserialize)]
+struct GridmapFileV2 {
+    header: GridmapFileHeaderV4,
+    cells: Vec<PaletteCellV2>,
+}
+
+impl From<GridmapFileV2> for GridmapFileV4 {
+    fn from(file: GridmapFileV2) -> Self {
+        Self {
+            header: file.header,
+            cells: file
+                .cells
+                .into_iter()
+                .map(|cell| PaletteCellV4 {
+                    id: cell.id,
+                    item: cell.item.into(),
+                    orientation: cell.orientation,
+                    face: cell.face,
+                    is_detail: cell.is_detail,
+                    damage: cell.damage,
+                    metadata: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Binary version 1 cells, from before cell damage was stored.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct PaletteCellV1 {
+    id: Vec3Int,
+    item: PaletteItemV2,
+    orientation: u8,
+    face: CellFace,
+    is_detail: bool,
+}
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct GridmapFileV1 {
+    header: GridmapFileHeaderV4,
+    cells: Vec<PaletteCellV1>,
+}
+
+impl From<GridmapFileV1> for GridmapFileV4 {
+    fn from(file: GridmapFileV1) -> Self {
+        Self {
+            header: file.header,
+            cells: file
+                .cells
+                .into_iter()
+                .map(|cell| PaletteCellV4 {
+                    id: cell.id,
+                    item: cell.item.into(),
+                    orientation: cell.orientation,
+                    face: cell.face,
+                    is_detail: cell.is_detail,
+                    damage: None,
+                    metadata: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Version 0 group items.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct LegacyGroupItem {
+    name: GroupTypeName,
+    group_id: u32,
+    cell: CellTypeName,
+}
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+enum LegacyItemExport {
+    Cell(CellTypeName),
+    Group(LegacyGroupItem),
+}
+
+impl From<LegacyItemExport> for ItemExport {
+    fn from(item: LegacyItemExport) -> Self {
+        match item {
+            LegacyItemExport::Cell(name) => ItemExport::Cell(name),
+            LegacyItemExport::Group(group_item) => ItemExport::Group(GroupItem {
+                name: group_item.name,
+                group_id: group_item.group_id,
+                cell: group_item.cell,
+                orientation: 0,
+            }),
+        }
+    }
+}
+
+/// Version 0 cells.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct LegacyCellDataExport {
+    id: Vec3Int,
+    item: LegacyItemExport,
+    orientation: u8,
+    face: CellFace,
+    is_detail: bool,
+}
+
+/// Only the version of a text file, to pick the right migration before parsing the rest.
+#[derive(Deserialize)]
+struct TextVersionProbe {
+    header: TextHeaderProbe,
+}
+#[derive(Deserialize)]
+struct TextHeaderProbe {
+    version: u16,
+}
+
+/// Result of decoding a gridmap file of any supported version.
+pub struct DecodedGridmap {
+    /// The version the file was stored in.
+    pub source_version: u16,
+    pub map_limits: MapLimits,
+    pub cells: Vec<CellDataExport>,
+}
+
+/// Index of an entry of a palette, added to the palette if it is new.
+fn palette_index<T: Clone + Eq + Hash>(
+    palette: &mut Vec<T>,
+    indexes: &mut HashMap<T, u16>,
+    entry: &T,
+    palette_name: &'static str,
+) -> Result<u16, GridmapFormatError> {
+    match indexes.get(entry) {
+        Some(i) => {
+            return Ok(*i);
+        }
+        None => {}
+    }
+    let i = u16::try_from(palette.len())
+        .map_err(|_| GridmapFormatError::PaletteOverflow(palette_name))?;
+    palette.push(entry.clone());
+    indexes.insert(entry.clone(), i);
+    Ok(i)
+}
+
+impl GridmapFile {
+    pub fn from_cells(
+        cells: &Vec<CellDataExport>,
+        map_limits: MapLimits,
+    ) -> Result<Self, GridmapFormatError> {
+        let mut cell_palette: Vec<CellTypeName> = vec![];
+        let mut cell_indexes: HashMap<CellTypeName, u16> = HashMap::new();
+        let mut group_palette: Vec<GroupTypeName> = vec![];
+        let mut group_indexes: HashMap<GroupTypeName, u16> = HashMap::new();
+        let mut layer_palette: Vec<String> = vec![];
+        let mut layer_indexes: HashMap<String, u16> = HashMap::new();
+
+        let mut palette_cells = vec![];
+        for cell in cells.iter() {
+            let item = match &cell.item {
+                ItemExport::Cell(name) => PaletteItem::Cell(palette_index(
+                    &mut cell_palette,
+                    &mut cell_indexes,
+                    name,
+                    "cell",
+                )?),
+                ItemExport::Group(group_item) => PaletteItem::Group(PaletteGroupItem {
+                    group: palette_index(
+                        &mut group_palette,
+                        &mut group_indexes,
+                        &group_item.name,
+                        "group",
+                    )?,
+                    group_id: group_item.group_id,
+                    cell: palette_index(
+                        &mut cell_palette,
+                        &mut cell_indexes,
+                        &group_item.cell,
+                        "cell",
+                    )?,
+                    orientation: group_item.orientation,
+                }),
+            };
+            let layer =
+                palette_index(&mut layer_palette, &mut layer_indexes, &cell.layer, "layer")?;
+            palette_cells.push(PaletteCell {
+                id: cell.id,
+                item,
+                orientation: cell.orientation,
+                face: cell.face.clone(),
+                layer,
+                damage: cell.damage.clone(),
+                metadata: cell.metadata.clone(),
+            });
+        }
+
+        Ok(Self {
+            header: GridmapFileHeader {
+                version: GRIDMAP_FORMAT_VERSION,
+                map_limits,
+                cell_palette,
+                group_palette,
+                layer_palette,
+            },
+            cells: palette_cells,
+        })
+    }
+
+    /// Resolve palette indexes back into named cells.
+    pub fn to_cells(&self) -> Result<Vec<CellDataExport>, GridmapFormatError> {
+        let cell_name = |i: u16| -> Result<CellTypeName, GridmapFormatError> {
+            match self.header.cell_palette.get(i as usize) {
+                Some(name) => Ok(name.clone()),
+                None => Err(GridmapFormatError::Palette(format!(
+                    "cell palette index {} out of range",
+                    i
+                ))),
+            }
+        };
+        let mut cells = vec![];
+        for cell in self.cells.iter() {
+            let item = match &cell.item {
+                PaletteItem::Cell(i) => ItemExport::Cell(cell_name(*i)?),
+                PaletteItem::Group(group_item) => {
+                    let name = match self.header.group_palette.get(group_item.group as usize) {
+                        Some(name) => name.clone(),
+                        None => {
+                            return Err(GridmapFormatError::Palette(format!(
+                                "group palette index {} out of range",
+                                group_item.group
+                            )));
+                        }
+                    };
+                    ItemExport::Group(GroupItem {
+                        name,
+                        group_id: group_item.group_id,
+                        cell: cell_name(group_item.cell)?,
+                        orientation: group_item.orientation,
+                    })
+                }
+            };
+            let layer = match self.header.layer_palette.get(cell.layer as usize) {
+                Some(name) => name.clone(),
+                None => {
+                    return Err(GridmapFormatError::Palette(format!(
+                        "layer palette index {} out of range",
+                        cell.layer
+                    )));
+                }
+            };
+            cells.push(CellDataExport {
+                id: cell.id,
+                item,
+                orientation: cell.orientation,
+                face: cell.face.clone(),
+                layer,
+                damage: cell.damage.clone(),
+                metadata: cell.metadata.clone(),
+            });
+        }
+        Ok(cells)
+    }
+}
+
+/// Encode cells into the current gridmap file format.
+pub fn encode_gridmap(
+    cells: &Vec<CellDataExport>,
+    map_limits: MapLimits,
+    encoding: GridmapEncoding,
+) -> Result<Vec<u8>, GridmapFormatError> {
+    let file = GridmapFile::from_cells(cells, map_limits)?;
+    match encoding {
+        GridmapEncoding::Binary => {
+            let body = bincode::serialize(&file)
+                .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            let mut data = GRIDMAP_BINARY_MAGIC.to_vec();
+            data.extend_from_slice(&GRIDMAP_FORMAT_VERSION.to_le_bytes());
+            let mut encoder = ZlibEncoder::new(data, Compression::default());
+            encoder
+                .write_all(&body)
+                .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            encoder
+                .finish()
+                .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))
+        }
+        GridmapEncoding::Text => ron::ser::to_string_pretty(&file, PrettyConfig::default())
+            .map(|text| text.into_bytes())
+            .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string())),
+    }
+}
+
+/// Decode a gridmap file of any supported version and migrate it to the current one.
+pub fn decode_gridmap(
+    raw: &[u8],
+    encoding: GridmapEncoding,
+) -> Result<DecodedGridmap, GridmapFormatError> {
+    match encoding {
+        GridmapEncoding::Binary => {
+            if !raw.starts_with(&GRIDMAP_BINARY_MAGIC) {
+                return migrate_legacy(raw);
+            }
+            let version_start = GRIDMAP_BINARY_MAGIC.len();
+            if raw.len() < version_start + 2 {
+                return Err(GridmapFormatError::Corrupt("missing version".to_string()));
+            }
+            let version = u16::from_le_bytes([raw[version_start], raw[version_start + 1]]);
+            if version > GRIDMAP_FORMAT_VERSION {
+                return Err(GridmapFormatError::UnsupportedVersion(version));
+            }
+            let mut body = vec![];
+            ZlibDecoder::new(&raw[version_start + 2..])
+                .read_to_end(&mut body)
+                .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            // Bincode isn't self-describing, older versions are decoded with their own layout.
+            let file: GridmapFile;
+            if version < 2 {
+                let v1: GridmapFileV1 = bincode::deserialize(&body)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+                file = GridmapFileV4::from(v1).into();
+            } else if version < 3 {
+                let v2: GridmapFileV2 = bincode::deserialize(&body)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+                file = GridmapFileV4::from(v2).into();
+            } else if version < 4 {
+                let v3: GridmapFileV3 = bincode::deserialize(&body)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+                file = GridmapFileV4::from(v3).into();
+            } else if version < 5 {
+                let v4: GridmapFileV4 = bincode::deserialize(&body)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+                file = v4.into();
+            } else {
+                file = bincode::deserialize(&body)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            }
+            decoded_from_file(version, file)
+        }
+        GridmapEncoding::Text => {
+            let text = String::from_utf8_lossy(raw);
+            let probe: TextVersionProbe =
+                ron::from_str(&text).map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            let version = probe.header.version;
+            if version > GRIDMAP_FORMAT_VERSION {
+                return Err(GridmapFormatError::UnsupportedVersion(version));
+            }
+            let file: GridmapFile;
+            if version < 5 {
+                let v4: GridmapFileV4 = ron::from_str(&text)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+                file = v4.into();
+            } else {
+                file = ron::from_str(&text)
+                    .map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+            }
+            decoded_from_file(version, file)
+        }
+    }
+}
+
+fn decoded_from_file(
+    source_version: u16,
+    file: GridmapFile,
+) -> Result<DecodedGridmap, GridmapFormatError> {
+    Ok(DecodedGridmap {
+        source_version,
+        cells: file.to_cells()?,
+        map_limits: file.header.map_limits,
+    })
+}
+
+/// Version 0: a bare bincode `Vec<CellDataExport>` without header.
+fn migrate_legacy(raw: &[u8]) -> Result<DecodedGridmap, GridmapFormatError> {
+    let legacy: Vec<LegacyCellDataExport> =
+        bincode::deserialize(raw).map_err(|rr| GridmapFormatError::Corrupt(rr.to_string()))?;
+    let cells: Vec<CellDataExport> = legacy
+        .into_iter()
+        .map(|cell| CellDataExport {
+            id: cell.id,
+            item: cell.item.into(),
+            orientation: cell.orientation,
+            face: cell.face,
+            layer: legacy_layer(cell.is_detail).to_string(),
+            damage: None,
+            metadata: None,
+        })
+        .collect();
+    let file = GridmapFile::from_cells(&cells, MapLimits::default())?;
+    decoded_from_file(0, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_cells() -> Vec<CellDataExport> {
+        vec![
+            CellDataExport {
+                id: Vec3Int { x: 1, y: 0, z: -2 },
+                item: ItemExport::Cell(CellTypeName("generic_floor".to_string())),
+                orientation: 0,
+                face: CellFace::Floor,
+                layer: STRUCTURE_LAYER.to_string(),
+                damage: Some(CellDamage {
+                    brute: 12.,
+                    burn: 0.,
+                    toxin: 3.,
+                }),
+                metadata: None,
+            },
+            CellDataExport {
+                id: Vec3Int { x: 1, y: 0, z: -2 },
+                item: ItemExport::Group(GroupItem {
+                    name: GroupTypeName("bridge_wall_group".to_string()),
+                    group_id: 7,
+                    cell: CellTypeName("bridge_wall".to_string()),
+                    orientation: 3,
+                }),
+                orientation: 3,
+                face: CellFace::FrontWall,
+                layer: STRUCTURE_LAYER.to_string(),
+                damage: None,
+                metadata: None,
+            },
+            CellDataExport {
+                id: Vec3Int { x: 4, y: 1, z: 0 },
+                item: ItemExport::Cell(CellTypeName("power_cable".to_string())),
+                orientation: 2,
+                face: CellFace::Floor,
+                layer: DETAILS_LAYER.to_string(),
+                damage: None,
+                metadata: Some(TileMetadata {
+                    tint: Some([255, 0, 0]),
+                    label: Some("bridge".to_string()),
+                    variant: 1,
+                }),
+            },
+        ]
+    }
+
+    /// Cells don't implement [PartialEq], compare their text form instead.
+    fn assert_cells_eq(a: &[CellDataExport], b: &[CellDataExport]) {
+        assert_eq!(ron::to_string(a).unwrap(), ron::to_string(b).unwrap());
+    }
+
+    /// A binary file of an older version with its own body layout.
+    fn encode_binary_version<T: Serialize>(version: u16, file: &T) -> Vec<u8> {
+        let mut data = GRIDMAP_BINARY_MAGIC.to_vec();
+        data.extend_from_slice(&version.to_le_bytes());
+        let mut encoder = ZlibEncoder::new(data, Compression::default());
+        encoder
+            .write_all(&bincode::serialize(file).unwrap())
+            .unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cells = sample_cells();
+        for encoding in [GridmapEncoding::Binary, GridmapEncoding::Text] {
+            let raw = encode_gridmap(&cells, MapLimits { length: 16 }, encoding).unwrap();
+            let decoded = decode_gridmap(&raw, encoding).unwrap();
+            assert_eq!(decoded.source_version, GRIDMAP_FORMAT_VERSION);
+            assert_eq!(decoded.map_limits.length, 16);
+            assert_cells_eq(&decoded.cells, &cells);
+        }
+    }
+
+    #[test]
+    fn test_migrate_version_0() {
+        let legacy = vec![
+            LegacyCellDataExport {
+                id: Vec3Int { x: 1, y: 0, z: -2 },
+                item: LegacyItemExport::Cell(CellTypeName("generic_floor".to_string())),
+                orientation: 0,
+                face: CellFace::Floor,
+                is_detail: false,
+            },
+            LegacyCellDataExport {
+                id: Vec3Int { x: 4, y: 1, z: 0 },
+                item: LegacyItemExport::Group(LegacyGroupItem {
+                    name: GroupTypeName("bridge_wall_group".to_string()),
+                    group_id: 7,
+                    cell: CellTypeName("bridge_wall".to_string()),
+                }),
+                orientation: 2,
+                face: CellFace::RightWall,
+                is_detail: true,
+            },
+        ];
+        let raw = bincode::serialize(&legacy).unwrap();
+        let decoded = decode_gridmap(&raw, GridmapEncoding::Binary).unwrap();
+        assert_eq!(decoded.source_version, 0);
+        assert_eq!(decoded.map_limits.length, MapLimits::default().length);
+        assert_cells_eq(
+            &decoded.cells,
+            &[
+                CellDataExport {
+                    id: Vec3Int { x: 1, y: 0, z: -2 },
+                    item: ItemExport::Cell(CellTypeName("generic_floor".to_string())),
+                    orientation: 0,
+                    face: CellFace::Floor,
+                    layer: STRUCTURE_LAYER.to_string(),
+                    damage: None,
+                    metadata: None,
+                },
+                CellDataExport {
+                    id: Vec3Int { x: 4, y: 1, z: 0 },
+                    item: ItemExport::Group(GroupItem {
+                        name: GroupTypeName("bridge_wall_group".to_string()),
+                        group_id: 7,
+                        cell: CellTypeName("bridge_wall".to_string()),
+                        orientation: 0,
+                    }),
+                    orientation: 2,
+                    face: CellFace::RightWall,
+                    layer: DETAILS_LAYER.to_string(),
+                    damage: None,
+                    metadata: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_migrate_version_1() {
+        let v1 = GridmapFileV1 {
+            header: GridmapFileHeaderV4 {
+                version: 1,
+                map_limits: MapLimits { length: 8 },
+                cell_palette: vec![
+                    CellTypeName("generic_floor".to_string()),
+                    CellTypeName("bridge_wall".to_string()),
+                ],
+                group_palette: vec![GroupTypeName("bridge_wall_group".to_string())],
+            },
+            cells: vec![
+                PaletteCellV1 {
+                    id: Vec3Int { x: 1, y: 0, z: -2 },
+                    item: PaletteItemV2::Cell(0),
+                    orientation: 0,
+                    face: CellFace::Floor,
+                    is_detail: false,
+                },
+                PaletteCellV1 {
+                    id: Vec3Int { x: 4, y: 1, z: 0 },
+                    item: PaletteItemV2::Group(PaletteGroupItemV2 {
+                        group: 0,
+                        group_id: 7,
+                        cell: 1,
+                    }),
+                    orientation: 2,
+                    face: CellFace::RightWall,
+                    is_detail: true,
+                },
+            ],
+        };
+        let raw = encode_binary_version(1, &v1);
+        let decoded = decode_gridmap(&raw, GridmapEncoding::Binary).unwrap();
+        assert_eq!(decoded.source_version, 1);
+        assert_eq!(decoded.map_limits.length, 8);
+        assert_cells_eq(
+            &decoded.cells,
+            &[
+                CellDataExport {
+                    id: Vec3Int { x: 1, y: 0, z: -2 },
+                    item: ItemExport::Cell(CellTypeName("generic_floor".to_string())),
+                    orientation: 0,
+                    face: CellFace::Floor,
+                    layer: STRUCTURE_LAYER.to_string(),
+                    damage: None,
+                    metadata: None,
+                },
+                CellDataExport {
+                    id: Vec3Int { x: 4, y: 1, z: 0 },
+                    item: ItemExport::Group(GroupItem {
+                        name: GroupTypeName("bridge_wall_group".to_string()),
+                        group_id: 7,
+                        cell: CellTypeName("bridge_wall".to_string()),
+                        orientation: 0,
+                    }),
+                    orientation: 2,
+                    face: CellFace::RightWall,
+                    layer: DETAILS_LAYER.to_string(),
+                    damage: None,
+                    metadata: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_reject_newer_version() {
+        let raw = encode_binary_version(GRIDMAP_FORMAT_VERSION + 1, &());
+        assert!(matches!(
+            decode_gridmap(&raw, GridmapEncoding::Binary),
+            Err(GridmapFormatError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_palette_overflow() {
+        let mut cells: Vec<CellDataExport> = (0..=u16::MAX as usize + 1)
+            .map(|i| CellDataExport {
+                id: Vec3Int { x: 0, y: 0, z: 0 },
+                item: ItemExport::Cell(CellTypeName("generic_floor".to_string())),
+                orientation: 0,
+                face: CellFace::Floor,
+                layer: i.to_string(),
+                damage: None,
+                metadata: None,
+            })
+            .collect();
+        assert!(matches!(
+            encode_gridmap(&cells, MapLimits { length: 16 }, GridmapEncoding::Binary),
+            Err(GridmapFormatError::PaletteOverflow("layer"))
+        ));
+        // As many entries as there are indexes still fit.
+        cells.pop();
+        assert!(GridmapFile::from_cells(&cells, MapLimits::default()).is_ok());
+    }
+}
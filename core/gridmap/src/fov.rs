@@ -1,219 +1,206 @@
-use bevy::prelude::{Event, Query, ResMut, Resource, Transform};
-use doryen_fov::{FovAlgorithm, MapData};
-use entity::senser::{to_doryen_coordinates, Senser, WORLD_WIDTH_CELLS};
-use resources::math::{world_to_cell_id, Vec2Int};
-
-use crate::net::ProjectileData;
-
+use bevy::prelude::{Event, EventReader, EventWriter, Query, Res, ResMut, Resource, Transform};
+use doryen_fov::VoxelMapData;
+use entity::senser::Senser;
+use networking::server::{ConnectedPlayer, OutgoingReliableServerMessage};
+use resources::{
+    grid::GridId,
+    math::{world_to_cell_id, Vec3Int, CELL_SIZE},
+};
+
+use crate::{
+    grid::{AddTile, Gridmap, RemoveTile},
+    net::{GridmapServerMessage, ProjectileData},
+};
+
+/// Distance sensers see on the xz plane in cells.
 pub const FOV_DISTANCE: usize = 23;
+/// Distance sensers see up and down in cells, enough for the decks above and below through openings.
+pub const FOV_VERTICAL_DISTANCE: usize = 6;
+/// Distance between the points of a projectile path checked against FOV.
+const PROJECTILE_FOV_STEP: f32 = CELL_SIZE * 0.5;
 
 /// An event for a projectile that exists for a frame so the FOV for its projectile path can be calculated and the projectile will be displayed on the appropiate client's screens.
 #[derive(Event)]
 pub struct ProjectileFOV {
     pub laser_projectile: ProjectileData,
 }
-/// Manage projectiles existing in this frame, calculate the FOV of their trajectories and visually spawn in projectiles on all clients that see them.
-/*
+
+/// Send projectiles of this frame to the clients that see part of their trajectory, trimmed to the part they see.
 pub(crate) fn projectile_fov(
     mut projectile_fov_events: EventReader<ProjectileFOV>,
     sensers: Query<(&Senser, &ConnectedPlayer)>,
     mut server: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
-    gridmap_main: Res<Gridmap>,
 ) {
-    let mut cell_ids_with_projectiles: HashMap<Vec3Int, Vec<(usize, Vec3, f32, Vec3)>> =
-        HashMap::new();
-    let mut projectiles = vec![];
-    let mut projectiles_i: usize = 0;
-
-    for event in projectile_fov_events.iter() {
+    for event in projectile_fov_events.read() {
         match event.laser_projectile {
             ProjectileData::Laser(laser_color, laser_height, laser_radius, start_pos, end_pos) => {
-                let direction = (end_pos - start_pos).normalize();
                 let distance = start_pos.distance(end_pos);
+                if distance == 0. {
+                    continue;
+                }
+                let direction = (end_pos - start_pos) / distance;
 
+                let mut points = vec![];
                 let mut iterated_distance: f32 = 0.;
-
-                projectiles.insert(
-                    projectiles_i,
-                    (laser_color, laser_height, laser_radius, direction),
-                );
-
                 while iterated_distance < distance {
-                    let point = start_pos + (direction * iterated_distance);
-
-                    let cell_id = world_to_cell_id(point);
+                    let point = start_pos + direction * iterated_distance;
+                    points.push((point, world_to_cell_id(point)));
+                    iterated_distance += PROJECTILE_FOV_STEP;
+                }
+                points.push((end_pos, world_to_cell_id(end_pos)));
 
-                    match cell_ids_with_projectiles.get_mut(&cell_id) {
-                        Some(list) => {
-                            list.push((projectiles_i, point, distance, start_pos));
+                for (senser_component, connected_player_component) in sensers.iter() {
+                    if !connected_player_component.connected {
+                        continue;
+                    }
+                    let mut visible = points
+                        .iter()
+                        .filter(|(_, cell_id)| senser_component.is_in_fov(*cell_id));
+                    let adjusted_start_pos;
+                    match visible.next() {
+                        Some((point, _)) => {
+                            adjusted_start_pos = *point;
                         }
                         None => {
-                            cell_ids_with_projectiles
-                                .insert(cell_id, vec![(projectiles_i, point, distance, start_pos)]);
+                            continue;
                         }
                     }
-
-                    iterated_distance += 2.;
+                    let adjusted_end_pos = visible
+                        .last()
+                        .map(|(point, _)| *point)
+                        .unwrap_or(adjusted_start_pos + direction * PROJECTILE_FOV_STEP);
+
+                    server.send(OutgoingReliableServerMessage {
+                        handle: connected_player_component.handle,
+                        message: GridmapServerMessage::FireProjectile(ProjectileData::Laser(
+                            laser_color,
+                            laser_height,
+                            laser_radius,
+                            adjusted_start_pos,
+                            adjusted_end_pos,
+                        )),
+                    });
                 }
-
-                projectiles_i += 1;
             }
             ProjectileData::Ballistic => {}
         }
     }
+}
 
-    if cell_ids_with_projectiles.len() == 0 {
-        return;
-    }
-
-    for (senser_component, connected_player_component) in sensers.iter() {
-        let mut used_projectiles_i = vec![];
-
-        for (cell_id, projectiles_i_list) in cell_ids_with_projectiles.iter() {
-            let coords = to_doryen_coordinates(cell_id.x, cell_id.z);
-
-            match senser_component.fov.is_in_fov(coords.0, coords.1) {
-                true => {
-                    for (projectile_i, point, distance, start_pos) in projectiles_i_list.iter() {
-                        if used_projectiles_i.contains(projectile_i) {
-                            continue;
-                        }
-
-                        used_projectiles_i.push(*projectile_i);
-
-                        let (laser_color, laser_height, laser_radius, direction) =
-                            projectiles.get(*projectile_i).unwrap();
-
-                        let mut iterated_distance: f32 = 0.;
-
-                        let mut negative_distance = false;
-
-                        let mut cells_left_to_try = true;
-
-                        let mut adjusted_start_pos = *point;
-                        let mut adjusted_end_pos = *point;
-
-                        while cells_left_to_try {
-                            if negative_distance {
-                                iterated_distance -= 2.;
-                            } else {
-                                iterated_distance += 2.;
-                            }
-
-                            let too_far = !(iterated_distance.abs() <= *distance);
-
-                            let mut cell_is_blocked = true;
-                            let new_point = *start_pos + (iterated_distance * *direction);
-                            let cell_id = world_to_cell_id(new_point);
-                            let coords = to_doryen_coordinates(cell_id.x, cell_id.z);
-
-                            if !too_far {
-                                match gridmap_main.get_cell(cell_id) {
-                                    Some(cell_data) => {
-                                        if gridmap_main
-                                            .non_fov_blocking_cells_list
-                                            .contains(&cell_data.item_0)
-                                        {
-                                            cell_is_blocked = false;
-                                        } else {
-                                            cell_is_blocked = true;
-                                        }
-                                    }
-                                    None => {
-                                        cell_is_blocked = false;
-                                    }
-                                }
-                            }
-
-                            match (!too_far)
-                                && (!cell_is_blocked
-                                    && senser_component.fov.is_in_fov(coords.0, coords.1))
-                            {
-                                true => {
-                                    if negative_distance {
-                                        adjusted_end_pos = new_point;
-                                    } else {
-                                        adjusted_start_pos = new_point;
-                                    }
-                                }
-                                false => {
-                                    if negative_distance {
-                                        negative_distance = true;
-                                    } else {
-                                        cells_left_to_try = false;
-                                    }
-                                }
-                            }
-                        }
-
-                        if adjusted_start_pos == adjusted_end_pos {
-                            adjusted_start_pos += *direction;
-                            adjusted_end_pos -= *direction;
-                        }
-
-                        if !connected_player_component.connected {
-                            continue;
-                        }
+/// The resource with FOV data. Tracks which faces of the main gridmap layer block sight.
+/// Center tiles only fill part of their cell and don't block sight.
+#[derive(Resource, Default)]
+pub struct FovMap {
+    pub map: VoxelMapData,
+    /// Voxels whose faces changed since the sensers last updated.
+    pub changed: Vec<Vec3Int>,
+}
 
-                        server.send(OutgoingReliableServerMessage {
-                            handle: connected_player_component.handle,
-                            message: GridmapServerMessage::FireProjectile(ProjectileData::Laser(
-                                *laser_color,
-                                *laser_height,
-                                *laser_radius,
-                                adjusted_start_pos,
-                                adjusted_end_pos,
-                            )),
-                        });
-                    }
-                }
-                false => {}
-            }
+impl FovMap {
+    fn set_face_blocked(&mut self, id: Vec3Int, axis: usize, is_blocked: bool) {
+        if self.map.is_face_blocked((id.x, id.y, id.z), axis) == is_blocked {
+            return;
         }
+        self.map
+            .set_face_blocked((id.x, id.y, id.z), axis, is_blocked);
+        self.changed.push(id);
+    }
+    /// Whether a face changed within sight of a senser in this cell.
+    fn changed_near(&self, senser: Vec3Int) -> bool {
+        // Faces lie between two voxels, so one cell of margin covers both sides.
+        let radius = FOV_DISTANCE as i32 + 1;
+        let vertical = FOV_VERTICAL_DISTANCE as i32 + 1;
+        self.changed.iter().any(|id| {
+            let dx = (id.x - senser.x) as i32;
+            let dy = (id.y - senser.y) as i32;
+            let dz = (id.z - senser.z) as i32;
+            dx * dx + dz * dz <= radius * radius && dy.abs() <= vertical
+        })
     }
-}
-*/
-/// The resource with FOV data.
-
-#[derive(Resource)]
-pub struct DoryenMap {
-    pub map: MapData,
 }
 
-impl Default for DoryenMap {
-    fn default() -> Self {
-        DoryenMap {
-            map: MapData::new(WORLD_WIDTH_CELLS, WORLD_WIDTH_CELLS),
+/// Keep the FOV map up to date with tiles that got added or removed, driven by [crate::grid::TileProperties::non_fov_blocker].
+pub(crate) fn update_fov_map(
+    mut add_events: EventReader<AddTile>,
+    mut remove_events: EventReader<RemoveTile>,
+    gridmap: Res<Gridmap>,
+    mut fov_map: ResMut<FovMap>,
+) {
+    for event in remove_events.read() {
+        if !gridmap.layer_blocks_fov(event.cell.layer) || event.cell.target.grid != GridId::MAIN {
+            continue;
+        }
+        match gridmap.get_face_between_cells(event.cell.target.clone()) {
+            Some((id, axis)) => {
+                fov_map.set_face_blocked(id, axis, false);
+            }
+            None => {}
+        }
+    }
+    for event in add_events.read() {
+        if !gridmap.layer_blocks_fov(event.layer) || event.grid != GridId::MAIN {
+            continue;
+        }
+        let blocks_sight = match gridmap.tile_properties.get(&event.tile_type) {
+            Some(properties) => !properties.non_fov_blocker,
+            None => true,
+        };
+        match gridmap.get_face_between_cells(event.target()) {
+            Some((id, axis)) => {
+                fov_map.set_face_blocked(id, axis, blocks_sight);
+            }
+            None => {}
         }
     }
 }
 
-/// Update FOV of a senser.
+/// Update FOV of a senser when it moves to another cell or faces within its sight change.
 
 pub(crate) fn senser_update_fov(
     mut senser_entities: Query<(&mut Senser, &Transform)>,
-    mut map: ResMut<DoryenMap>,
+    mut map: ResMut<FovMap>,
 ) {
     for (mut senser_component, rigid_body_position_component) in senser_entities.iter_mut() {
-        let senser_cell_id_3 = world_to_cell_id(rigid_body_position_component.translation.into());
-        let senser_cell_id = Vec2Int {
-            x: senser_cell_id_3.x,
-            y: senser_cell_id_3.z,
-        };
+        let senser_cell_id: Vec3Int = world_to_cell_id(rigid_body_position_component.translation);
 
-        if senser_component.cell_id != senser_cell_id
-            && senser_cell_id.x < WORLD_WIDTH_CELLS as i16 / 2
-            && senser_cell_id.x > -(WORLD_WIDTH_CELLS as i16) / 2
-            && senser_cell_id.y < WORLD_WIDTH_CELLS as i16 / 2
-            && senser_cell_id.y > -(WORLD_WIDTH_CELLS as i16) / 2
-        {
-            senser_component.cell_id = senser_cell_id;
-
-            senser_component.fov.clear_fov();
-            let coords = to_doryen_coordinates(senser_cell_id.x, senser_cell_id.y);
-            senser_component
-                .fov
-                .compute_fov(&mut map.map, coords.0, coords.1, FOV_DISTANCE, true);
+        if senser_component.cell_id == Some(senser_cell_id) && !map.changed_near(senser_cell_id) {
+            continue;
         }
+        senser_component.cell_id = Some(senser_cell_id);
+
+        senser_component.fov.clear_fov();
+        senser_component.fov.compute_fov(
+            &map.map,
+            (senser_cell_id.x, senser_cell_id.y, senser_cell_id.z),
+            FOV_DISTANCE,
+            FOV_VERTICAL_DISTANCE,
+        );
+    }
+    if !map.changed.is_empty() {
+        map.changed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_near() {
+        let mut fov_map = FovMap::default();
+        let senser = Vec3Int { x: 0, y: 0, z: 0 };
+        let far = Vec3Int {
+            x: FOV_DISTANCE as i16 + 5,
+            y: 0,
+            z: 0,
+        };
+        fov_map.set_face_blocked(far, 0, true);
+        assert!(!fov_map.changed_near(senser));
+        // Setting a face to the state it already has changes nothing.
+        fov_map.changed.clear();
+        fov_map.set_face_blocked(far, 0, true);
+        assert!(fov_map.changed.is_empty());
+        fov_map.set_face_blocked(Vec3Int { x: 3, y: 1, z: -2 }, 2, true);
+        assert!(fov_map.changed_near(senser));
     }
 }
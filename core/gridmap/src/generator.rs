@@ -0,0 +1,373 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f32::consts::{FRAC_PI_2, PI},
+};
+
+use bevy::prelude::Quat;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use resources::{grid::CellFace, math::Vec3Int};
+
+use crate::{
+    format::{encode_gridmap, GridmapEncoding, GridmapFormatError},
+    grid::{CellTypeName, GroupTypeName, MapLimits, Orthogonal, CHUNK_CUBIC_LENGTH},
+    init::{CellDataExport, GroupItem, ItemExport},
+    layers::STRUCTURE_LAYER,
+};
+
+/// Cells between the floors of two decks. Walls are two cells high.
+pub const DECK_HEIGHT: i16 = 2;
+/// Most rooms a generated ship may have.
+pub const MAX_GENERATED_ROOMS: u32 = 1024;
+/// Most decks a generated ship may have.
+pub const MAX_GENERATED_DECKS: u16 = 64;
+
+const FLOOR_TILE: &str = "generic_floor";
+const INTERIOR_WALL_TILE: &str = "wall_clean";
+const EXTERIOR_WALL_TILE: &str = "wall_flat";
+const EXTERIOR_WALL_GROUP: &str = "wall_flat_group";
+const AIRLOCK_TILE: &str = "airlock_evac";
+
+/// Neighbour offsets on the xz plane with the face of the wall towards them.
+const DIRECTIONS: [(i16, i16, CellFace); 4] = [
+    (0, 1, CellFace::FrontWall),
+    (0, -1, CellFace::BackWall),
+    (1, 0, CellFace::RightWall),
+    (-1, 0, CellFace::LeftWall),
+];
+
+/// Where generated ships get their airlocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AirlockPlacement {
+    None,
+    /// Both ends of the corridor of every deck.
+    CorridorEnds,
+    /// This many airlocks on random exterior walls of the ship.
+    Random(u32),
+}
+
+/// Parameters of [generate_ship]. The same parameters always generate the same ship.
+#[derive(Clone, Debug)]
+pub struct ShipGeneratorParameters {
+    pub seed: u64,
+    pub room_count: u32,
+    pub corridor_width: i16,
+    pub deck_count: u16,
+    pub airlocks: AirlockPlacement,
+}
+
+impl Default for ShipGeneratorParameters {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            room_count: 8,
+            corridor_width: 2,
+            deck_count: 1,
+            airlocks: AirlockPlacement::CorridorEnds,
+        }
+    }
+}
+
+/// A generated ship as gridmap export data.
+pub struct GeneratedShip {
+    pub cells: Vec<CellDataExport>,
+    /// Smallest limits fitting the ship, at least the default limits.
+    pub map_limits: MapLimits,
+}
+
+impl GeneratedShip {
+    pub fn export(&self, encoding: GridmapEncoding) -> Result<Vec<u8>, GridmapFormatError> {
+        encode_gridmap(&self.cells, self.map_limits.clone(), encoding)
+    }
+}
+
+/// Floor plan of a single deck. Cells map to the room they belong to, the corridor is room 0.
+struct DeckLayout {
+    cells: BTreeMap<(i16, i16), u32>,
+    /// Pairs of cells of different rooms that have no wall between them.
+    doors: BTreeSet<((i16, i16), (i16, i16))>,
+    /// Exterior walls at both ends of the corridor.
+    corridor_ends: [((i16, i16), usize); 2],
+}
+
+impl DeckLayout {
+    fn is_door(&self, a: (i16, i16), b: (i16, i16)) -> bool {
+        self.doors.contains(&(a, b)) || self.doors.contains(&(b, a))
+    }
+}
+
+fn generate_deck(rng: &mut StdRng, room_count: u32, corridor_width: i16) -> DeckLayout {
+    let mut cells = BTreeMap::new();
+    let mut doors = BTreeSet::new();
+    // Rooms alternate between both sides of the corridor, each side fills up along x.
+    let mut cursors = [0i16, 0i16];
+    for room in 0..room_count {
+        let side = (room % 2) as usize;
+        let width: i16 = rng.gen_range(3..=7);
+        let depth: i16 = rng.gen_range(3..=6);
+        let x0 = cursors[side];
+        cursors[side] += width;
+        let (z0, door_z, corridor_z) = match side {
+            0 => (corridor_width, corridor_width, corridor_width - 1),
+            _ => (-depth, -1, 0),
+        };
+        for x in x0..x0 + width {
+            for z in z0..z0 + depth {
+                cells.insert((x, z), room + 1);
+            }
+        }
+        let door_x = x0 + rng.gen_range(0..width);
+        doors.insert(((door_x, door_z), (door_x, corridor_z)));
+    }
+    let length = cursors[0].max(cursors[1]).max(4);
+    for x in 0..length {
+        for z in 0..corridor_width {
+            cells.insert((x, z), 0);
+        }
+    }
+    let middle = corridor_width / 2;
+    DeckLayout {
+        cells,
+        doors,
+        corridor_ends: [((0, middle), 3), ((length - 1, middle), 2)],
+    }
+}
+
+fn floor_cell(id: Vec3Int) -> CellDataExport {
+    CellDataExport {
+        id,
+        item: ItemExport::Cell(CellTypeName(FLOOR_TILE.to_string())),
+        orientation: 0,
+        face: CellFace::Floor,
+        layer: STRUCTURE_LAYER.to_string(),
+        damage: None,
+        metadata: None,
+    }
+}
+
+fn wall_cell(id: Vec3Int, face: CellFace, tile: &str) -> CellDataExport {
+    CellDataExport {
+        id,
+        item: ItemExport::Cell(CellTypeName(tile.to_string())),
+        orientation: 0,
+        face,
+        layer: STRUCTURE_LAYER.to_string(),
+        damage: None,
+        metadata: None,
+    }
+}
+
+/// Orientation of an exterior wall group facing `face`, the walls of the group itself are on the front face.
+fn exterior_wall_orientation(face: &CellFace) -> u8 {
+    let angle = match face {
+        CellFace::RightWall => FRAC_PI_2,
+        CellFace::BackWall => PI,
+        CellFace::LeftWall => -FRAC_PI_2,
+        _ => 0.,
+    };
+    Quat::from_rotation_y(angle).get_orthogonal_index()
+}
+
+/// Generate a ship of rooms along a corridor per deck, centered around the origin.
+pub fn generate_ship(parameters: &ShipGeneratorParameters) -> GeneratedShip {
+    let mut rng = StdRng::seed_from_u64(parameters.seed);
+    let corridor_width = parameters.corridor_width.clamp(1, 16);
+    let deck_count = parameters.deck_count.clamp(1, MAX_GENERATED_DECKS);
+    let room_count = parameters.room_count.min(MAX_GENERATED_ROOMS);
+
+    let decks: Vec<DeckLayout> = (0..deck_count)
+        .map(|deck| {
+            let mut rooms = room_count / deck_count as u32;
+            if (deck as u32) < room_count % deck_count as u32 {
+                rooms += 1;
+            }
+            generate_deck(&mut rng, rooms, corridor_width)
+        })
+        .collect();
+    let length = decks
+        .iter()
+        .map(|d| d.corridor_ends[1].0 .0 + 1)
+        .max()
+        .unwrap_or(0);
+    let offset = (-length / 2, -corridor_width / 2);
+    let id = |(x, z): (i16, i16), y: i16| Vec3Int {
+        x: x + offset.0,
+        y,
+        z: z + offset.1,
+    };
+
+    // Exterior walls that get an airlock, per deck.
+    let mut airlocks: Vec<BTreeSet<((i16, i16), usize)>> = vec![BTreeSet::new(); decks.len()];
+    match parameters.airlocks {
+        AirlockPlacement::None => {}
+        AirlockPlacement::CorridorEnds => {
+            for (deck, layout) in decks.iter().enumerate() {
+                for end in layout.corridor_ends {
+                    airlocks[deck].insert(end);
+                }
+            }
+        }
+        AirlockPlacement::Random(amount) => {
+            let mut candidates = vec![];
+            for (deck, layout) in decks.iter().enumerate() {
+                for cell in layout.cells.keys() {
+                    for (direction, (dx, dz, _)) in DIRECTIONS.iter().enumerate() {
+                        if !layout.cells.contains_key(&(cell.0 + dx, cell.1 + dz)) {
+                            candidates.push((deck, (*cell, direction)));
+                        }
+                    }
+                }
+            }
+            for _ in 0..amount {
+                if candidates.is_empty() {
+                    break;
+                }
+                let (deck, wall) = candidates.swap_remove(rng.gen_range(0..candidates.len()));
+                airlocks[deck].insert(wall);
+            }
+        }
+    }
+
+    let mut cells = vec![];
+    let mut group_id = 0;
+    let mut previous_footprint: BTreeSet<(i16, i16)> = BTreeSet::new();
+    for (deck, layout) in decks.iter().enumerate() {
+        let y = deck as i16 * DECK_HEIGHT;
+        // The floor of a deck is also the ceiling of the deck below.
+        let floors: BTreeSet<(i16, i16)> = layout
+            .cells
+            .keys()
+            .cloned()
+            .chain(previous_footprint.iter().cloned())
+            .collect();
+        for cell in floors.iter() {
+            cells.push(floor_cell(id(*cell, y)));
+        }
+
+        for (cell, room) in layout.cells.iter() {
+            for (direction, (dx, dz, face)) in DIRECTIONS.iter().enumerate() {
+                let neighbour = (cell.0 + dx, cell.1 + dz);
+                match layout.cells.get(&neighbour) {
+                    Some(neighbour_room) => {
+                        // Interior walls get placed once, from the lower side.
+                        if neighbour_room == room
+                            || layout.is_door(*cell, neighbour)
+                            || *dx < 0
+                            || *dz < 0
+                        {
+                            continue;
+                        }
+                        for height in 0..DECK_HEIGHT {
+                            cells.push(wall_cell(
+                                id(*cell, y + height),
+                                face.clone(),
+                                INTERIOR_WALL_TILE,
+                            ));
+                        }
+                    }
+                    None => {
+                        if airlocks[deck].contains(&(*cell, direction)) {
+                            cells.push(wall_cell(id(*cell, y), face.clone(), AIRLOCK_TILE));
+                            for height in 1..DECK_HEIGHT {
+                                cells.push(wall_cell(
+                                    id(*cell, y + height),
+                                    face.clone(),
+                                    INTERIOR_WALL_TILE,
+                                ));
+                            }
+                            continue;
+                        }
+                        for height in 0..DECK_HEIGHT {
+                            cells.push(CellDataExport {
+                                id: id(*cell, y + height),
+                                item: ItemExport::Group(GroupItem {
+                                    name: GroupTypeName(EXTERIOR_WALL_GROUP.to_string()),
+                                    group_id,
+                                    cell: CellTypeName(EXTERIOR_WALL_TILE.to_string()),
+                                    orientation: exterior_wall_orientation(face),
+                                }),
+                                orientation: 0,
+                                face: face.clone(),
+                                layer: STRUCTURE_LAYER.to_string(),
+                                damage: None,
+                                metadata: None,
+                            });
+                        }
+                        group_id += 1;
+                    }
+                }
+            }
+        }
+        previous_footprint = layout.cells.keys().cloned().collect();
+    }
+    let roof_y = decks.len() as i16 * DECK_HEIGHT;
+    for cell in previous_footprint.iter() {
+        cells.push(floor_cell(id(*cell, roof_y)));
+    }
+
+    let mut furthest: i64 = 0;
+    for cell in cells.iter() {
+        for axis in [cell.id.x, cell.id.y, cell.id.z] {
+            furthest = furthest.max((axis as i64).abs() + 1);
+        }
+    }
+    let chunk_length = CHUNK_CUBIC_LENGTH as i64;
+    let length = ((furthest * 2 + chunk_length - 1) / chunk_length) as i16;
+    GeneratedShip {
+        cells,
+        map_limits: MapLimits::new(length.max(MapLimits::default().length)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueprint::rotate_face;
+    use crate::grid::OrthogonalBases;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_generate_ship_is_deterministic() {
+        let parameters = ShipGeneratorParameters {
+            seed: 7,
+            room_count: 12,
+            deck_count: 2,
+            ..Default::default()
+        };
+        let a = generate_ship(&parameters);
+        let b = generate_ship(&parameters);
+        assert_eq!(a.cells.len(), b.cells.len());
+        assert!(a
+            .cells
+            .iter()
+            .zip(b.cells.iter())
+            .all(|(a, b)| a.id == b.id && a.face == b.face));
+        let airlocks = a
+            .cells
+            .iter()
+            .filter(|c| match &c.item {
+                ItemExport::Cell(name) => name.0 == AIRLOCK_TILE,
+                _ => false,
+            })
+            .count();
+        assert_eq!(airlocks, 4);
+        assert!(a.cells.iter().all(|c| a.map_limits.contains(c.id)));
+    }
+
+    #[test]
+    fn test_exterior_wall_groups_face_outwards() {
+        let bases = OrthogonalBases::default().bases;
+        let ship = generate_ship(&ShipGeneratorParameters::default());
+        let mut faces = HashSet::new();
+        for cell in ship.cells.iter() {
+            match &cell.item {
+                ItemExport::Group(item) => {
+                    let rotation = bases[item.orientation as usize];
+                    assert_eq!(rotate_face(&CellFace::FrontWall, rotation), cell.face);
+                    faces.insert(cell.face.clone());
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(faces.len(), 4);
+    }
+}
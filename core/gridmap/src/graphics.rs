@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use bevy::asset::AssetId;
+use bevy::color::Color;
+use bevy::color::LinearRgba;
 use bevy::gltf::GltfMesh;
 use bevy::hierarchy::BuildChildren;
 use bevy::pbr::PointLightBundle;
@@ -7,20 +10,26 @@ use bevy::prelude::Assets;
 use bevy::prelude::Commands;
 use bevy::prelude::Component;
 use bevy::prelude::EventReader;
+use bevy::prelude::Handle;
 
 use bevy::log::warn;
 use bevy::prelude::PbrBundle;
 use bevy::prelude::Res;
 use bevy::prelude::ResMut;
 use bevy::prelude::Resource;
+use bevy::prelude::StandardMaterial;
 use bevy::transform::components::Transform;
-use resources::grid::TargetCell;
 use resources::math::Vec3Int;
 
+use crate::damage::stored_cell;
+use crate::damage::TileDamageState;
+use crate::damage::TileDamageStates;
 use crate::grid::AddTile;
 use crate::grid::Gridmap;
 use crate::grid::LayerTargetCell;
 use crate::items::generic_assets::GenericMaterials;
+use crate::power::light_visibility;
+use crate::power::UnpoweredTiles;
 
 #[derive(Component)]
 pub struct GraphicsGridLink {
@@ -32,6 +41,111 @@ pub(crate) struct CellGraphicsBuffer {
     pub buffer: HashMap<LayerTargetCell, AddTile>,
 }
 
+/// How a variant of a tile material differs from its base material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialVariant {
+    /// Base color multiplied by an srgb tint.
+    Tint([u8; 3]),
+    /// Darkened for tiles in a damaged state.
+    Damaged(TileDamageState),
+}
+
+impl MaterialVariant {
+    fn apply(&self, material: &mut StandardMaterial) {
+        let base: LinearRgba = material.base_color.into();
+        match self {
+            MaterialVariant::Tint(color) => {
+                let tint: LinearRgba = Color::srgb_u8(color[0], color[1], color[2]).into();
+                material.base_color = Color::LinearRgba(LinearRgba::new(
+                    base.red * tint.red,
+                    base.green * tint.green,
+                    base.blue * tint.blue,
+                    base.alpha,
+                ));
+            }
+            MaterialVariant::Damaged(state) => {
+                let darken;
+                match state {
+                    TileDamageState::Intact => {
+                        return;
+                    }
+                    TileDamageState::Damaged => {
+                        darken = 0.7;
+                    }
+                    TileDamageState::HeavilyDamaged => {
+                        darken = 0.4;
+                    }
+                }
+                material.base_color = Color::LinearRgba(LinearRgba::new(
+                    base.red * darken,
+                    base.green * darken,
+                    base.blue * darken,
+                    base.alpha,
+                ));
+                material.perceptual_roughness =
+                    (material.perceptual_roughness + (1. - darken)).min(1.);
+            }
+        }
+    }
+}
+
+/// Variants of tile materials, created once per base material and variant.
+#[derive(Resource, Default)]
+pub struct MaterialVariants {
+    pub variants: HashMap<(AssetId<StandardMaterial>, MaterialVariant), Handle<StandardMaterial>>,
+}
+
+impl MaterialVariants {
+    /// A variant of a material.
+    pub fn get(
+        &mut self,
+        material: &Handle<StandardMaterial>,
+        variant: MaterialVariant,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        match self.variants.get(&(material.id(), variant)) {
+            Some(handle) => {
+                return handle.clone_weak();
+            }
+            None => {}
+        }
+        let mut new;
+        match materials.get(material) {
+            Some(m) => {
+                new = m.clone();
+            }
+            None => {
+                return material.clone_weak();
+            }
+        }
+        variant.apply(&mut new);
+        let handle = materials.add(new);
+        self.variants
+            .insert((material.id(), variant), handle.clone());
+        handle.clone_weak()
+    }
+    /// The material of a tile with its tint and damaged state.
+    pub fn tile_material(
+        &mut self,
+        material: &Handle<StandardMaterial>,
+        tint: Option<[u8; 3]>,
+        state: TileDamageState,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        let mut tile_material = material.clone_weak();
+        match tint {
+            Some(t) => {
+                tile_material = self.get(&tile_material, MaterialVariant::Tint(t), materials);
+            }
+            None => {}
+        }
+        if state != TileDamageState::Intact {
+            tile_material = self.get(&tile_material, MaterialVariant::Damaged(state), materials);
+        }
+        tile_material
+    }
+}
+
 pub(crate) fn set_cell_graphics(
     mut events: EventReader<AddTile>,
     gridmap_main: Res<Gridmap>,
@@ -39,15 +153,16 @@ pub(crate) fn set_cell_graphics(
     materials: Res<GenericMaterials>,
     assets_gltfmesh: Res<Assets<GltfMesh>>,
     mut buffer: ResMut<CellGraphicsBuffer>,
+    unpowered: Res<UnpoweredTiles>,
+    damage_states: Res<TileDamageStates>,
+    mut variants: ResMut<MaterialVariants>,
+    mut standard_materials: ResMut<Assets<StandardMaterial>>,
 ) {
     for set_cell in events.read() {
         buffer.buffer.insert(
             LayerTargetCell {
-                target: TargetCell {
-                    id: set_cell.id,
-                    face: set_cell.face.clone(),
-                },
-                is_detail: set_cell.is_detail,
+                target: set_cell.target(),
+                layer: set_cell.layer,
             },
             set_cell.clone(),
         );
@@ -56,30 +171,37 @@ pub(crate) fn set_cell_graphics(
     for (id, add_tile) in buffer.buffer.iter() {
         match gridmap_main.tile_properties.get(&add_tile.tile_type) {
             Some(properties) => {
-                let transform = gridmap_main.get_cell_transform(
-                    TargetCell {
-                        id: add_tile.id,
-                        face: add_tile.face.clone(),
-                    },
-                    add_tile.orientation,
-                );
+                // Tiles of other grids are children of their grid, so this is relative to the grid.
+                let transform =
+                    gridmap_main.get_cell_transform(add_tile.target(), add_tile.orientation);
 
-                let mat;
+                let mut mat;
                 match &properties.material_option {
                     Some(m) => {
-                        mat = m;
+                        mat = m.clone_weak();
                     }
                     None => {
-                        mat = &materials.gray_metallic;
+                        mat = materials.gray_metallic.clone_weak();
                     }
                 }
+                let state = damage_states
+                    .cells
+                    .get(&stored_cell(&gridmap_main, id))
+                    .copied()
+                    .unwrap_or_default();
+                mat = variants.tile_material(
+                    &mat,
+                    add_tile.metadata.tint,
+                    state,
+                    &mut standard_materials,
+                );
                 match assets_gltfmesh.get(&properties.mesh_option.clone().unwrap()) {
                     Some(mesh) => {
                         let mut builder = commands.entity(add_tile.entity);
                         builder.insert((
                             PbrBundle {
                                 mesh: mesh.primitives[0].mesh.clone_weak(),
-                                material: mat.clone_weak(),
+                                material: mat,
                                 transform: transform.into(),
                                 ..Default::default()
                             },
@@ -95,6 +217,7 @@ pub(crate) fn set_cell_graphics(
                                         transform: Transform::from_translation(
                                             tile_light.local_offset,
                                         ),
+                                        visibility: light_visibility(!unpowered.cells.contains(id)),
                                         ..Default::default()
                                     });
                                 });
@@ -1,8 +1,14 @@
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
+use std::mem::{replace, size_of};
 use std::path::Path;
-use std::{collections::HashMap, f32::consts::PI, ops::Deref};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+    fmt,
+    ops::Deref,
+};
 
 use bevy::log::{info, warn};
 use bevy::pbr::PointLight;
@@ -14,27 +20,24 @@ use bevy::{
     },
 };
 
-use bevy_renet::renet::ClientId;
-use bevy_xpbd_3d::components::{CoefficientCombine, CollisionLayers, Friction, RigidBody};
+use bevy_xpbd_3d::components::RigidBody;
 use bevy_xpbd_3d::prelude::Collider;
 use entity::{despawn::DespawnEntity, examine::RichName, health::Health};
 use networking::{
     client::IncomingReliableServerMessage,
     server::{ConnectedPlayer, OutgoingReliableServerMessage},
+    stamp::TickRateStamp,
 };
-use physics::physics::{get_bit_masks, ColliderGroup};
-use physics::rigid_body::STANDARD_BODY_FRICTION;
-use resources::grid::GridmapCollider;
-use resources::grid::{CellFace, Tile};
+use resources::grid::{CellFace, GridId, Tile};
 use resources::player::SoftPlayer;
 use resources::{
     grid::TargetCell,
-    math::{cell_id_to_world, Vec3Int},
+    math::{cell_id_to_world, world_to_cell_id, Vec3Int},
 };
 use serde::{Deserialize, Serialize};
 
 /// Gridmap maximum limits as cube dimensions in chunks.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MapLimits {
     /// Full length of the cube as chunks.
     pub length: i16,
@@ -45,7 +48,75 @@ impl Default for MapLimits {
         Self { length: 32 }
     }
 }
-#[derive(Clone, Debug)]
+
+/// Largest map length in chunks. Covers every cell id a [Vec3Int] can represent.
+pub const MAX_MAP_LENGTH: i16 = 2048;
+
+impl MapLimits {
+    /// Limits clamped between a single chunk and [MAX_MAP_LENGTH].
+    pub fn new(length: i16) -> Self {
+        Self {
+            length: length.clamp(1, MAX_MAP_LENGTH),
+        }
+    }
+    /// Limits covering every possible cell id.
+    pub fn unbounded() -> Self {
+        Self {
+            length: MAX_MAP_LENGTH,
+        }
+    }
+    /// Full length of the cube as cells.
+    pub fn cell_length(&self) -> i64 {
+        self.length as i64 * CHUNK_CUBIC_LENGTH as i64
+    }
+    pub fn contains(&self, id: Vec3Int) -> bool {
+        let half_length = self.cell_length() / 2;
+        for axis in [id.x, id.y, id.z] {
+            let offset = axis as i64 + half_length;
+            if offset < 0 || offset >= self.cell_length() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A cell id outside of the [MapLimits] of the gridmap.
+#[derive(Clone, Copy, Debug)]
+pub struct OutOfMapLimits {
+    pub id: Vec3Int,
+    pub length: i16,
+}
+
+impl fmt::Display for OutOfMapLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cell {:?} is outside of the map limits of {} chunks.",
+            self.id, self.length
+        )
+    }
+}
+
+/// Why a tile couldn't be stored in the gridmap.
+#[derive(Clone, Copy, Debug)]
+pub enum InsertTileError {
+    OutOfMapLimits(OutOfMapLimits),
+    UnknownLayer(GridId, LayerId),
+}
+
+impl fmt::Display for InsertTileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertTileError::OutOfMapLimits(rr) => rr.fmt(f),
+            InsertTileError::UnknownLayer(grid, layer) => {
+                write!(f, "Unknown grid {:?} or layer {:?}.", grid, layer)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
 pub enum CellType {
     Wall,
     WallDetail,
@@ -100,8 +171,21 @@ pub struct TileProperties {
     pub cell_type: CellType,
     pub x_rotations: Vec<u8>,
     pub y_rotations: Vec<u8>,
-    pub is_detail: bool,
+    /// Layer the tile gets placed on.
+    pub layer: LayerId,
     pub is_light: Option<TileLight>,
+    /// Connects the power network through its cell.
+    pub power_cable: bool,
+    /// Power in kW the tile draws from the power network of its cell.
+    pub power_usage: f32,
+    /// Damage thresholds of the tile. Indestructible when none.
+    pub durability: Option<TileDurability>,
+    /// Diagonal floors that can be walked up and down.
+    pub ramp: Option<Ramp>,
+    /// Passages that open and close, like airlocks.
+    pub door: bool,
+    /// See-through walls and floors.
+    pub window: bool,
 }
 
 impl Default for TileProperties {
@@ -125,8 +209,14 @@ impl Default for TileProperties {
             x_rotations: vec![],
             y_rotations: vec![],
             vertical_rotation: false,
-            is_detail: false,
+            layer: LayerId::STRUCTURE,
             is_light: None,
+            power_cable: false,
+            power_usage: 0.,
+            durability: None,
+            ramp: None,
+            door: false,
+            window: false,
         }
     }
 }
@@ -156,6 +246,14 @@ impl GridCell {
             StrictCellFace::Center => self.center.clone(),
         }
     }
+    pub fn get_item_from_face_mut(&mut self, strict_face: StrictCellFace) -> Option<&mut CellItem> {
+        match strict_face {
+            StrictCellFace::FrontWall => self.front_wall.as_mut(),
+            StrictCellFace::RightWall => self.right_wall.as_mut(),
+            StrictCellFace::Floor => self.floor.as_mut(),
+            StrictCellFace::Center => self.center.as_mut(),
+        }
+    }
     pub fn get_items(&self) -> Vec<(CellItem, CellFace)> {
         let mut items = vec![];
         match &self.floor {
@@ -205,50 +303,77 @@ pub struct CellItem {
     pub health: Health,
     /// Rotation. Range of 0 - 24. See [OrthogonalBases].
     pub orientation: u8,
+    /// Per instance data like paint and labels.
+    pub metadata: TileMetadata,
+}
+
+/// Small per instance data of a tile, so variants don't need their own tile type.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct TileMetadata {
+    /// Srgb color multiplied onto the material of the tile.
+    pub tint: Option<[u8; 3]>,
+    /// Decal or label text, for example a room name.
+    pub label: Option<String>,
+    /// Variant index, its meaning depends on the tile type.
+    pub variant: u8,
+}
+
+impl TileMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
-/// Maximum amount of available map chunks. 32 by 32 by 32 (cubic length of 1024 meters).
-pub const GRID_CHUNK_AMOUNT: usize = 32768;
 /// The amount of tiles a chunk stores. 32 by 32 by 32.
 pub const GRID_CHUNK_TILES_AMOUNT: usize = 32768;
 /// The length of the cubic chunk in tiles.
 pub const CHUNK_CUBIC_LENGTH: i16 = 32;
 
-/// A chunk of the gridmap.
-#[derive(Clone)]
+/// A chunk of the gridmap. Only occupied cells are stored, ordered by cell index.
+#[derive(Clone, Default)]
 pub struct GridmapChunk {
-    pub cells: Vec<Option<GridCell>>,
+    pub cells: BTreeMap<usize, GridCell>,
 }
 
-const DEFAULT_CELL_DATA: Option<GridCell> = None;
-
-impl Default for GridmapChunk {
-    fn default() -> Self {
-        Self {
-            cells: vec![DEFAULT_CELL_DATA; GRID_CHUNK_TILES_AMOUNT],
-        }
+impl GridmapChunk {
+    pub fn get(&self, cell: usize) -> Option<&GridCell> {
+        self.cells.get(&cell)
+    }
+    pub fn get_mut(&mut self, cell: usize) -> Option<&mut GridCell> {
+        self.cells.get_mut(&cell)
+    }
+    /// Get the cell at the index, inserting an empty cell if there is none.
+    pub fn get_or_insert(&mut self, cell: usize) -> &mut GridCell {
+        self.cells.entry(cell).or_default()
+    }
+    pub fn remove(&mut self, cell: usize) -> Option<GridCell> {
+        self.cells.remove(&cell)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    /// Amount of occupied cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
     }
 }
 
-impl GridmapChunk {
-    fn is_empty(&self) -> bool {
-        let mut empty = true;
-
-        for cell in self.cells.iter() {
-            if cell.is_some() {
-                empty = false;
-                break;
-            }
-        }
-
-        empty
-    }
+/// Approximate memory usage of the gridmap layers.
+#[derive(Clone, Debug, Default)]
+pub struct GridmapMemoryUsage {
+    pub chunks: usize,
+    pub cells: usize,
+    pub items: usize,
+    /// Estimated bytes used by the sparse chunk storage.
+    pub bytes: usize,
+    /// Estimated bytes the same chunks would use with fully allocated chunks and chunk slots.
+    pub dense_bytes: usize,
 }
 
 #[derive(Clone)]
 pub struct AddedUpdate {
     pub cell: GridmapUpdate,
-    pub players_received: Vec<ClientId>,
 }
 #[derive(Clone)]
 pub enum GridmapUpdate {
@@ -259,6 +384,8 @@ pub enum GridmapUpdate {
 pub enum CellIds {
     CellType(CellTypeId),
     GroupType(GroupTypeId),
+    /// Name of a blueprint saved on the drive of the server.
+    Blueprint(String),
 }
 
 impl CellIds {
@@ -266,6 +393,7 @@ impl CellIds {
         match self {
             CellIds::CellType(id) => id.to_string(),
             CellIds::GroupType(id) => id.to_string(),
+            CellIds::Blueprint(name) => name.clone(),
         }
     }
 }
@@ -308,14 +436,16 @@ impl Deref for GroupTypeId {
 #[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
 pub struct LayerTargetCell {
     pub target: TargetCell,
-    pub is_detail: bool,
+    pub layer: LayerId,
 }
 
 /// Stores the main gridmap layer data, huge map data resource. In favor of having each ordinary tile having its own entity with its own sets of components.
 #[derive(Resource, Clone)]
 pub struct Gridmap {
-    pub main_grid: Vec<Option<GridmapChunk>>,
-    pub details_grid: Vec<Option<GridmapChunk>>,
+    /// Occupied chunks of the main grid by layer and chunk index.
+    pub layers: BTreeMap<LayerId, BTreeMap<usize, GridmapChunk>>,
+    /// Registered layers, see [crate::layers::InitGridmapLayers].
+    pub layer_properties: BTreeMap<LayerId, LayerProperties>,
     pub updates: BTreeMap<u32, HashMap<LayerTargetCell, AddedUpdate>>,
     pub ordered_names: Vec<CellTypeName>,
     pub group_id_map: HashMap<GroupTypeName, GroupTypeId>,
@@ -323,6 +453,10 @@ pub struct Gridmap {
     pub tile_properties: HashMap<CellTypeId, TileProperties>,
     pub map_length_limit: MapLimits,
     pub groups: HashMap<GroupTypeId, HashMap<Vec3Int, FullCell>>,
+    /// Placed groups by the instance id their tiles share.
+    pub group_instances: HashMap<u32, GroupInstance>,
+    /// Instances that lost their last tile this update. Tiles moving to another grid get added back before they are forgotten.
+    pub emptied_group_instances: HashSet<u32>,
     pub group_instance_incremental: u32,
     pub tile_type_incremental: u16,
     pub group_type_incremental: u16,
@@ -330,14 +464,18 @@ pub struct Gridmap {
     pub id_name_map: HashMap<CellTypeId, CellTypeName>,
     // Colliders for chunks.
     pub colliders: HashMap<usize, Entity>,
+    /// Grids other than [GridId::MAIN], like shuttles.
+    pub grids: BTreeMap<GridId, SubGrid>,
+    pub grid_incremental: u32,
 }
-const EMPTY_CHUNK: Option<GridmapChunk> = None;
-
 impl Default for Gridmap {
     fn default() -> Self {
         Self {
-            main_grid: vec![EMPTY_CHUNK; GRID_CHUNK_AMOUNT],
-            details_grid: vec![EMPTY_CHUNK; GRID_CHUNK_AMOUNT],
+            layers: BTreeMap::default(),
+            layer_properties: BTreeMap::from([
+                (LayerId::STRUCTURE, LayerProperties::structure()),
+                (LayerId::DETAILS, LayerProperties::details()),
+            ]),
             updates: BTreeMap::default(),
             ordered_names: vec![],
             tile_properties: HashMap::default(),
@@ -345,15 +483,29 @@ impl Default for Gridmap {
             groups: HashMap::default(),
             group_id_map: HashMap::default(),
             id_group_map: HashMap::default(),
+            group_instances: HashMap::default(),
+            emptied_group_instances: HashSet::default(),
             group_instance_incremental: 0,
             tile_type_incremental: 0,
             group_type_incremental: 0,
             name_id_map: HashMap::default(),
             id_name_map: HashMap::default(),
             colliders: HashMap::default(),
+            grids: BTreeMap::default(),
+            grid_incremental: 0,
         }
     }
 }
+/// A placed group.
+#[derive(Clone, Debug)]
+pub struct GroupInstance {
+    pub group: GroupTypeId,
+    /// Index of [OrthogonalBases] the group got placed with.
+    pub orientation: u8,
+    /// Tiles in the gridmap with the instance id. The instance is forgotten with its last tile.
+    pub tiles: u32,
+}
+
 /// Result for [get_indexes].
 #[derive(Clone, Copy, Debug)]
 pub struct CellIndexes {
@@ -361,7 +513,7 @@ pub struct CellIndexes {
     pub cell: usize,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
 pub enum StrictCellFace {
     #[default]
     FrontWall,
@@ -399,7 +551,9 @@ pub(crate) fn export_debug_map() {
             orientation: 0,
             face: CellFace::Floor,
             item: ItemExport::Cell(CellTypeName("generic_floor".to_string())),
-            is_detail: false,
+            layer: STRUCTURE_LAYER.to_string(),
+            damage: None,
+            metadata: None,
         });
         x += 1;
         if x > floor_length / 2 {
@@ -408,7 +562,7 @@ pub(crate) fn export_debug_map() {
         }
     }
 
-    let dats = bincode::serialize(&data).unwrap();
+    let dats = encode_gridmap(&data, MapLimits::default(), GridmapEncoding::Binary).unwrap();
     let path = Path::new("data")
         .join("maps")
         .join("bullseye")
@@ -419,218 +573,170 @@ pub(crate) fn export_debug_map() {
 }
 
 impl Gridmap {
-    pub fn export_binary(&self) -> Vec<u8> {
+    /// Export the map in the versioned gridmap file format.
+    pub fn export(&self, encoding: GridmapEncoding) -> Result<Vec<u8>, GridmapFormatError> {
+        encode_gridmap(
+            &self.export_cells(),
+            self.map_length_limit.clone(),
+            encoding,
+        )
+    }
+    /// All cells of both layers as named export data.
+    pub fn export_cells(&self) -> Vec<CellDataExport> {
         let mut data = vec![];
-        let mut chunk_i = 0;
-        for chunk_option in &self.main_grid {
-            let details_chunk_option = &self.details_grid[chunk_i];
-
-            match details_chunk_option {
-                Some(chunk) => {
-                    let mut cell_i = 0;
-
-                    for cell_option in chunk.cells.iter() {
-                        match cell_option {
-                            Some(cell) => {
-                                for (item, face) in cell.get_items() {
-                                    let cell_item_id;
-
-                                    match self.id_name_map.get(&item.tile_type) {
-                                        Some(x) => {
-                                            cell_item_id = x.clone();
-                                        }
-                                        None => {
-                                            warn!("Couldnt find item {:?}", item.tile_type);
-                                            continue;
-                                        }
-                                    };
-                                    let cell_item;
-
-                                    match item.group_id_option {
-                                        Some(group_id) => {
-                                            let name;
-                                            match self.id_name_map.get(&item.tile_type) {
-                                                Some(n) => {
-                                                    name = n;
-                                                }
-                                                None => {
-                                                    warn!("couldnt find name");
-                                                    continue;
-                                                }
-                                            }
-                                            cell_item = ItemExport::Group(GroupItem {
-                                                name: GroupTypeName(cell_item_id.to_string()),
-                                                group_id: group_id,
-                                                cell: name.clone(),
-                                            });
-                                        }
-                                        None => {
-                                            cell_item = ItemExport::Cell(CellTypeName(
-                                                cell_item_id.to_string(),
-                                            ));
-                                        }
-                                    }
-                                    data.push(CellDataExport {
-                                        id: self
-                                            .get_id(CellIndexes {
-                                                chunk: chunk_i,
-                                                cell: cell_i,
-                                            })
-                                            .unwrap(),
-                                        item: cell_item,
-                                        orientation: item.orientation,
-                                        face: face,
-                                        is_detail: true,
-                                    });
-                                }
-                            }
-                            None => {}
-                        }
-                        cell_i += 1;
-                    }
+        for (layer, grid) in self.layers.iter() {
+            let layer_name;
+            match self.layer_properties.get(layer) {
+                Some(properties) => {
+                    layer_name = properties.name.clone();
+                }
+                None => {
+                    warn!("Couldnt find layer {:?}", layer);
+                    continue;
                 }
-                None => {}
             }
-
-            match chunk_option {
-                Some(chunk) => {
-                    let mut cell_i = 0;
-
-                    for cell_option in chunk.cells.iter() {
-                        match cell_option {
-                            Some(cell) => {
-                                for (item, face) in cell.get_items() {
-                                    let cell_item_id;
-
-                                    match self.id_name_map.get(&item.tile_type) {
-                                        Some(x) => {
-                                            cell_item_id = x.clone();
-                                        }
-                                        None => {
-                                            warn!("Couldnt find item {:?}", item.tile_type);
-                                            continue;
-                                        }
-                                    };
-                                    let cell_item;
-
-                                    match item.group_id_option {
-                                        Some(group_id) => {
-                                            let name;
-                                            match self.id_name_map.get(&item.tile_type) {
-                                                Some(n) => {
-                                                    name = n;
-                                                }
-                                                None => {
-                                                    warn!("couldnt find name");
-                                                    continue;
-                                                }
-                                            }
-                                            cell_item = ItemExport::Group(GroupItem {
-                                                name: GroupTypeName(cell_item_id.to_string()),
-                                                group_id: group_id,
-                                                cell: name.clone(),
-                                            });
-                                        }
-                                        None => {
-                                            cell_item = ItemExport::Cell(CellTypeName(
-                                                cell_item_id.to_string(),
-                                            ));
-                                        }
+            for (chunk_i, chunk) in grid.iter() {
+                for (cell_i, cell) in chunk.cells.iter() {
+                    for (item, face) in cell.get_items() {
+                        let cell_item_id;
+
+                        match self.id_name_map.get(&item.tile_type) {
+                            Some(x) => {
+                                cell_item_id = x.clone();
+                            }
+                            None => {
+                                warn!("Couldnt find item {:?}", item.tile_type);
+                                continue;
+                            }
+                        };
+                        let cell_item;
+
+                        match item.group_id_option {
+                            Some(group_id) => {
+                                let name;
+                                let orientation;
+                                match self.group_instances.get(&group_id).and_then(|instance| {
+                                    self.id_group_map
+                                        .get(&instance.group)
+                                        .map(|name| (name, instance.orientation))
+                                }) {
+                                    Some((n, o)) => {
+                                        name = n.clone();
+                                        orientation = o;
+                                    }
+                                    None => {
+                                        name = GroupTypeName(cell_item_id.to_string());
+                                        orientation = 0;
                                     }
-
-                                    data.push(CellDataExport {
-                                        id: self
-                                            .get_id(CellIndexes {
-                                                chunk: chunk_i,
-                                                cell: cell_i,
-                                            })
-                                            .unwrap(),
-                                        item: cell_item,
-                                        orientation: item.orientation,
-                                        face: face,
-                                        is_detail: false,
-                                    });
                                 }
+                                cell_item = ItemExport::Group(GroupItem {
+                                    name,
+                                    group_id: group_id,
+                                    cell: cell_item_id.clone(),
+                                    orientation,
+                                });
+                            }
+                            None => {
+                                cell_item =
+                                    ItemExport::Cell(CellTypeName(cell_item_id.to_string()));
                             }
-                            None => {}
                         }
-                        cell_i += 1;
+                        data.push(CellDataExport {
+                            id: self
+                                .get_id(CellIndexes {
+                                    chunk: *chunk_i,
+                                    cell: *cell_i,
+                                })
+                                .unwrap(),
+                            item: cell_item,
+                            orientation: item.orientation,
+                            face: face,
+                            layer: layer_name.clone(),
+                            damage: CellDamage::from_health(&item.health),
+                            metadata: Some(item.metadata.clone())
+                                .filter(|metadata| !metadata.is_empty()),
+                        });
                     }
                 }
-                None => {}
             }
-            chunk_i += 1;
         }
-        bincode::serialize(&data).unwrap()
+        data
     }
-    pub fn get_indexes(&self, id: Vec3Int) -> CellIndexes {
-        let map_half_length = ((self.map_length_limit.length as f32 * CHUNK_CUBIC_LENGTH as f32)
-            * 0.5)
-            .floor() as i16;
-
-        let x_id = id.x + map_half_length;
-        let x_chunk_index = (x_id as f32 / CHUNK_CUBIC_LENGTH as f32).floor() as i16;
-
-        let y_id = id.y + map_half_length;
-        let y_chunk_index = (y_id as f32 / CHUNK_CUBIC_LENGTH as f32).floor() as i16;
-
-        let z_id = id.z + map_half_length;
-        let z_chunk_index = (z_id as f32 / CHUNK_CUBIC_LENGTH as f32).floor() as i16;
-
-        let chunk_x_offset = x_chunk_index;
-        let chunk_z_offset = z_chunk_index * self.map_length_limit.length;
-        let chunk_y_offset =
-            y_chunk_index * (self.map_length_limit.length * self.map_length_limit.length);
+    /// Approximate memory usage of all layers compared to fully allocated chunks.
+    pub fn memory_usage(&self) -> GridmapMemoryUsage {
+        let mut usage = GridmapMemoryUsage::default();
+        for grid in self.layers.values() {
+            for (_, chunk) in grid.iter() {
+                usage.chunks += 1;
+                usage.cells += chunk.len();
+                for (_, cell) in chunk.cells.iter() {
+                    usage.items += cell.get_items().len();
+                }
+            }
+            usage.dense_bytes += (self.map_length_limit.length as usize).pow(3)
+                * size_of::<Option<Vec<Option<GridCell>>>>();
+        }
+        usage.bytes = usage.chunks * (size_of::<usize>() + size_of::<GridmapChunk>())
+            + usage.cells * (size_of::<usize>() + size_of::<GridCell>());
+        usage.dense_bytes += usage.chunks * GRID_CHUNK_TILES_AMOUNT * size_of::<Option<GridCell>>();
+        usage
+    }
+    /// Get the chunk and cell indexes of a cell id. Cell ids outside of the map limits are rejected.
+    pub fn get_indexes(&self, id: Vec3Int) -> Result<CellIndexes, OutOfMapLimits> {
+        if !self.map_length_limit.contains(id) {
+            return Err(OutOfMapLimits {
+                id,
+                length: self.map_length_limit.length,
+            });
+        }
+        let length = self.map_length_limit.length as i64;
+        let chunk_length = CHUNK_CUBIC_LENGTH as i64;
+        let map_half_length = self.map_length_limit.cell_length() / 2;
 
-        let chunk_index = chunk_x_offset + chunk_z_offset + chunk_y_offset;
+        let x_id = id.x as i64 + map_half_length;
+        let y_id = id.y as i64 + map_half_length;
+        let z_id = id.z as i64 + map_half_length;
 
-        let x_cell_id = x_id - (x_chunk_index * CHUNK_CUBIC_LENGTH);
-        let y_cell_id = y_id - (y_chunk_index * CHUNK_CUBIC_LENGTH);
-        let z_cell_id = z_id - (z_chunk_index * CHUNK_CUBIC_LENGTH);
+        let chunk_index = (x_id / chunk_length)
+            + (z_id / chunk_length) * length
+            + (y_id / chunk_length) * (length * length);
 
-        let x_offset = x_cell_id;
-        let z_offset = z_cell_id * CHUNK_CUBIC_LENGTH;
-        let y_offset = y_cell_id * (CHUNK_CUBIC_LENGTH * CHUNK_CUBIC_LENGTH);
+        let cell_index = (x_id % chunk_length)
+            + (z_id % chunk_length) * chunk_length
+            + (y_id % chunk_length) * (chunk_length * chunk_length);
 
-        let cell_index = x_offset + z_offset + y_offset;
-        CellIndexes {
+        Ok(CellIndexes {
             chunk: chunk_index as usize,
             cell: cell_index as usize,
-        }
+        })
     }
     pub fn get_id(&self, indexes: CellIndexes) -> Option<Vec3Int> {
-        let chunk_y_id = (indexes.chunk as f32
-            / (self.map_length_limit.length * self.map_length_limit.length) as f32)
-            .floor() as i16;
-
-        let remainder_xz = indexes.chunk as i16
-            - (chunk_y_id * (self.map_length_limit.length * self.map_length_limit.length));
-
-        let chunk_z_id = (remainder_xz as f32 / self.map_length_limit.length as f32).floor() as i16;
-
-        let chunk_x_id = remainder_xz - (chunk_z_id * self.map_length_limit.length);
-
-        let cell_y_id =
-            (indexes.cell as f32 / (CHUNK_CUBIC_LENGTH * CHUNK_CUBIC_LENGTH) as f32).floor() as i16;
-
-        let remainder_xz =
-            indexes.cell as i16 - (cell_y_id * (CHUNK_CUBIC_LENGTH * CHUNK_CUBIC_LENGTH));
+        let length = self.map_length_limit.length as i64;
+        let chunk_length = CHUNK_CUBIC_LENGTH as i64;
+        let chunk = indexes.chunk as i64;
+        let cell = indexes.cell as i64;
+        if chunk >= length * length * length || cell >= GRID_CHUNK_TILES_AMOUNT as i64 {
+            return None;
+        }
 
-        let cell_z_id = (remainder_xz as f32 / CHUNK_CUBIC_LENGTH as f32).floor() as i16;
+        let chunk_y_id = chunk / (length * length);
+        let chunk_z_id = (chunk % (length * length)) / length;
+        let chunk_x_id = chunk % length;
 
-        let cell_x_id = remainder_xz - (cell_z_id * CHUNK_CUBIC_LENGTH);
+        let cell_y_id = cell / (chunk_length * chunk_length);
+        let cell_z_id = (cell % (chunk_length * chunk_length)) / chunk_length;
+        let cell_x_id = cell % chunk_length;
 
-        let map_half_length = ((self.map_length_limit.length as f32 * CHUNK_CUBIC_LENGTH as f32)
-            * 0.5)
-            .floor() as i16;
+        let map_half_length = self.map_length_limit.cell_length() / 2;
 
-        let id = Vec3Int {
-            x: (chunk_x_id * CHUNK_CUBIC_LENGTH + cell_x_id) - map_half_length,
-            y: (chunk_y_id * CHUNK_CUBIC_LENGTH + cell_y_id) - map_half_length,
-            z: (chunk_z_id * CHUNK_CUBIC_LENGTH + cell_z_id) - map_half_length,
-        };
+        let x = chunk_x_id * chunk_length + cell_x_id - map_half_length;
+        let y = chunk_y_id * chunk_length + cell_y_id - map_half_length;
+        let z = chunk_z_id * chunk_length + cell_z_id - map_half_length;
 
-        Some(id)
+        match (i16::try_from(x), i16::try_from(y), i16::try_from(z)) {
+            (Ok(x), Ok(y), Ok(z)) => Some(Vec3Int { x, y, z }),
+            _ => None,
+        }
     }
     pub fn get_strict_cell(&self, cell: TargetCell) -> StrictCell {
         let mut adjusted_id = cell.id.clone();
@@ -667,32 +773,509 @@ impl Gridmap {
             id: adjusted_id,
         }
     }
+    /// The face a tile occupies as the cell it lies after on an axis (0 = x, 1 = y, 2 = z). Center tiles aren't between cells.
+    pub fn get_face_between_cells(&self, cell: TargetCell) -> Option<(Vec3Int, usize)> {
+        let strict = self.get_strict_cell(cell);
+        let id = strict.id;
+        match strict.face {
+            StrictCellFace::RightWall => Some((id, 0)),
+            StrictCellFace::FrontWall => Some((id, 2)),
+            // Floors are the face between the cell and the cell below.
+            StrictCellFace::Floor => Some((
+                Vec3Int {
+                    x: id.x,
+                    y: id.y - 1,
+                    z: id.z,
+                },
+                1,
+            )),
+            StrictCellFace::Center => None,
+        }
+    }
     pub fn get_cell(&self, cell: LayerTargetCell) -> Option<CellItem> {
         let strict = self.get_strict_cell(cell.target);
-        let indexes = self.get_indexes(strict.id);
+        let indexes;
+        match self.get_indexes(strict.id) {
+            Ok(i) => {
+                indexes = i;
+            }
+            Err(_) => {
+                return None;
+            }
+        }
 
         let grid;
-        if cell.is_detail {
-            grid = &self.details_grid;
-        } else {
-            grid = &self.main_grid;
+        match self.layer(cell.target.grid, cell.layer) {
+            Some(g) => {
+                grid = g;
+            }
+            None => {
+                return None;
+            }
         }
 
-        match grid.get(indexes.chunk) {
-            Some(chunk_option) => match chunk_option {
-                Some(chunk) => match chunk.cells.get(indexes.cell) {
-                    Some(cell_data_option) => match cell_data_option {
-                        Some(items) => items.get_item_from_face(strict.face),
-                        None => None,
-                    },
-                    None => None,
-                },
+        match grid.get(&indexes.chunk) {
+            Some(chunk) => match chunk.get(indexes.cell) {
+                Some(items) => items.get_item_from_face(strict.face),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn get_cell_mut(&mut self, cell: LayerTargetCell) -> Option<&mut CellItem> {
+        let strict = self.get_strict_cell(cell.target);
+        let indexes;
+        match self.get_indexes(strict.id) {
+            Ok(i) => {
+                indexes = i;
+            }
+            Err(_) => {
+                return None;
+            }
+        }
+
+        let grid;
+        match self.layer_mut(cell.target.grid, cell.layer) {
+            Some(g) => {
+                grid = g;
+            }
+            None => {
+                return None;
+            }
+        }
+
+        match grid.get_mut(&indexes.chunk) {
+            Some(chunk) => match chunk.get_mut(indexes.cell) {
+                Some(items) => items.get_item_from_face_mut(strict.face),
                 None => None,
             },
             None => None,
         }
     }
 
+    /// Store a tile in a cell, returning the tile it replaced on the same face.
+    /// Keeps the tile counts of the group instances of both tiles up to date.
+    pub fn insert_tile(
+        &mut self,
+        cell: LayerTargetCell,
+        item: CellItem,
+    ) -> Result<Option<CellItem>, InsertTileError> {
+        let strict = self.get_strict_cell(cell.target.clone());
+        let indexes;
+        match self.get_indexes(strict.id) {
+            Ok(i) => {
+                indexes = i;
+            }
+            Err(rr) => {
+                return Err(InsertTileError::OutOfMapLimits(rr));
+            }
+        }
+        let group_option = item.group_id_option;
+        let grid;
+        match self.layer_mut(cell.target.grid, cell.layer) {
+            Some(g) => {
+                grid = g;
+            }
+            None => {
+                return Err(InsertTileError::UnknownLayer(cell.target.grid, cell.layer));
+            }
+        }
+        let grid_items = grid
+            .entry(indexes.chunk)
+            .or_default()
+            .get_or_insert(indexes.cell);
+        let new = Some(item);
+        let old;
+        match strict.face {
+            StrictCellFace::FrontWall => {
+                old = replace(&mut grid_items.front_wall, new);
+            }
+            StrictCellFace::RightWall => {
+                old = replace(&mut grid_items.right_wall, new);
+            }
+            StrictCellFace::Floor => {
+                old = replace(&mut grid_items.floor, new);
+            }
+            StrictCellFace::Center => {
+                old = replace(&mut grid_items.center, new);
+            }
+        }
+        // Overwritten group tiles leave their instance.
+        match old.as_ref().and_then(|item| item.group_id_option) {
+            Some(instance) => {
+                self.remove_group_tile(instance);
+            }
+            None => {}
+        }
+        match group_option {
+            Some(instance) => {
+                self.add_group_tile(instance);
+            }
+            None => {}
+        }
+        Ok(old)
+    }
+
+    /// Chunk index storing the cell.
+    pub fn get_cell_chunk(&self, cell: &TargetCell) -> Option<usize> {
+        let strict = self.get_strict_cell(cell.clone());
+        match self.get_indexes(strict.id) {
+            Ok(indexes) => Some(indexes.chunk),
+            Err(_) => None,
+        }
+    }
+    /// Chunk indexes in a cube of `radius` chunks around a cell.
+    pub fn get_chunks_in_radius(&self, id: Vec3Int, radius: i16) -> Vec<usize> {
+        let mut chunks = vec![];
+        let chunk_length = CHUNK_CUBIC_LENGTH as i32;
+        for x in -radius..radius + 1 {
+            for y in -radius..radius + 1 {
+                for z in -radius..radius + 1 {
+                    let offset_id = (
+                        i16::try_from(id.x as i32 + x as i32 * chunk_length),
+                        i16::try_from(id.y as i32 + y as i32 * chunk_length),
+                        i16::try_from(id.z as i32 + z as i32 * chunk_length),
+                    );
+                    match offset_id {
+                        (Ok(x), Ok(y), Ok(z)) => match self.get_indexes(Vec3Int { x, y, z }) {
+                            Ok(indexes) => {
+                                if !chunks.contains(&indexes.chunk) {
+                                    chunks.push(indexes.chunk);
+                                }
+                            }
+                            Err(_) => {}
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+        chunks
+    }
+    /// All cells of all layers in a chunk of a grid.
+    pub fn get_chunk_snapshot(&self, grid_id: GridId, chunk: usize) -> Vec<NewCell> {
+        let mut cells = vec![];
+        for layer in self.layer_properties.keys() {
+            let grid;
+            match self.layer(grid_id, *layer) {
+                Some(g) => {
+                    grid = g;
+                }
+                None => {
+                    continue;
+                }
+            }
+            match grid.get(&chunk) {
+                Some(chunk_data) => {
+                    for (cell_i, cell) in chunk_data.cells.iter() {
+                        let id;
+                        match self.get_id(CellIndexes {
+                            chunk,
+                            cell: *cell_i,
+                        }) {
+                            Some(i) => {
+                                id = i;
+                            }
+                            None => {
+                                continue;
+                            }
+                        }
+                        for (item, face) in cell.get_items() {
+                            cells.push(NewCell {
+                                cell: LayerTargetCell {
+                                    target: TargetCell {
+                                        id,
+                                        face,
+                                        grid: grid_id,
+                                    },
+                                    layer: *layer,
+                                },
+                                orientation: item.orientation,
+                                tile_type: item.tile_type,
+                                metadata: item.metadata.clone(),
+                            });
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        cells
+    }
+    /// All cells of a grid.
+    pub fn get_grid_snapshot(&self, grid: GridId) -> Vec<NewCell> {
+        let mut cells = vec![];
+        for chunk in self.get_grid_chunks(grid) {
+            cells.extend(self.get_chunk_snapshot(grid, chunk));
+        }
+        cells
+    }
+    /// Chunk indexes with cells in any layer of a grid.
+    pub fn get_grid_chunks(&self, grid: GridId) -> Vec<usize> {
+        let mut chunks = vec![];
+        for layer in self.layer_properties.keys() {
+            match self.layer(grid, *layer) {
+                Some(layer) => {
+                    for chunk in layer.keys() {
+                        if !chunks.contains(chunk) {
+                            chunks.push(*chunk);
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        chunks
+    }
+    /// Chunks of a layer of a grid.
+    pub fn layer(&self, grid: GridId, layer: LayerId) -> Option<&BTreeMap<usize, GridmapChunk>> {
+        if grid == GridId::MAIN {
+            self.layers.get(&layer)
+        } else {
+            self.grids.get(&grid).and_then(|sub| sub.layers.get(&layer))
+        }
+    }
+    /// Chunks of a layer of a grid, created on first use. [None] for unknown grids and layers.
+    pub fn layer_mut(
+        &mut self,
+        grid: GridId,
+        layer: LayerId,
+    ) -> Option<&mut BTreeMap<usize, GridmapChunk>> {
+        if !self.layer_properties.contains_key(&layer) {
+            return None;
+        }
+        if grid == GridId::MAIN {
+            Some(self.layers.entry(layer).or_default())
+        } else {
+            self.grids
+                .get_mut(&grid)
+                .map(|sub| sub.layers.entry(layer).or_default())
+        }
+    }
+    /// Register a layer and get its id. [None] when its name is taken or there are no ids left.
+    pub fn register_layer(&mut self, properties: LayerProperties) -> Option<LayerId> {
+        if self.layer_id(&properties.name).is_some() {
+            return None;
+        }
+        let id;
+        match u8::try_from(self.layer_properties.len()) {
+            Ok(i) => {
+                id = LayerId(i);
+            }
+            Err(_) => {
+                return None;
+            }
+        }
+        self.layer_properties.insert(id, properties);
+        Some(id)
+    }
+    /// Id of a layer by its name.
+    pub fn layer_id(&self, name: &str) -> Option<LayerId> {
+        self.layer_properties
+            .iter()
+            .find(|(_, properties)| properties.name == name)
+            .map(|(id, _)| *id)
+    }
+    /// Whether tiles of a layer are part of the chunk colliders.
+    pub fn layer_collides(&self, layer: LayerId) -> bool {
+        match self.layer_properties.get(&layer) {
+            Some(properties) => properties.collision,
+            None => false,
+        }
+    }
+    /// Whether tiles of a layer can block the field of view.
+    pub fn layer_blocks_fov(&self, layer: LayerId) -> bool {
+        match self.layer_properties.get(&layer) {
+            Some(properties) => properties.fov,
+            None => false,
+        }
+    }
+    /// Collider entities of the chunks of a grid.
+    pub fn chunk_colliders_mut(&mut self, grid: GridId) -> Option<&mut HashMap<usize, Entity>> {
+        if grid == GridId::MAIN {
+            Some(&mut self.colliders)
+        } else {
+            self.grids.get_mut(&grid).map(|sub| &mut sub.colliders)
+        }
+    }
+    pub fn has_grid(&self, grid: GridId) -> bool {
+        grid == GridId::MAIN || self.grids.contains_key(&grid)
+    }
+    /// World transform of a grid. The main grid doesn't move.
+    pub fn grid_transform(&self, grid: GridId) -> Transform {
+        match self.grids.get(&grid) {
+            Some(sub) => sub.transform,
+            None => Transform::IDENTITY,
+        }
+    }
+    /// Id of the cell of a grid that contains a world position.
+    pub fn world_to_grid_cell_id(&self, grid: GridId, position: Vec3) -> Vec3Int {
+        let local = self
+            .grid_transform(grid)
+            .compute_affine()
+            .inverse()
+            .transform_point3(position);
+        world_to_cell_id(local)
+    }
+    /// Register a placed group and get the instance id its tiles share.
+    pub fn new_group_instance(&mut self, group: GroupTypeId, orientation: u8) -> u32 {
+        self.group_instance_incremental += 1;
+        let instance = self.group_instance_incremental;
+        self.group_instances.insert(
+            instance,
+            GroupInstance {
+                group,
+                orientation,
+                tiles: 0,
+            },
+        );
+        instance
+    }
+    /// Count a tile that got added with a group instance id.
+    pub fn add_group_tile(&mut self, instance: u32) {
+        match self.group_instances.get_mut(&instance) {
+            Some(group_instance) => {
+                group_instance.tiles += 1;
+                self.emptied_group_instances.remove(&instance);
+            }
+            None => {}
+        }
+    }
+    /// Uncount a removed group tile. Instances without tiles get forgotten by [Gridmap::forget_emptied_group_instances].
+    pub fn remove_group_tile(&mut self, instance: u32) {
+        match self.group_instances.get_mut(&instance) {
+            Some(group_instance) => {
+                group_instance.tiles = group_instance.tiles.saturating_sub(1);
+                if group_instance.tiles == 0 {
+                    self.emptied_group_instances.insert(instance);
+                }
+            }
+            None => {}
+        }
+    }
+    /// Remove the instances that are still without tiles.
+    pub fn forget_emptied_group_instances(&mut self) {
+        let emptied: Vec<u32> = self.emptied_group_instances.drain().collect();
+        for instance in emptied {
+            if self
+                .group_instances
+                .get(&instance)
+                .map_or(false, |group_instance| group_instance.tiles == 0)
+            {
+                self.group_instances.remove(&instance);
+            }
+        }
+    }
+    /// The tiles of a group placed at `origin` of `grid`, rotated by one of the [OrthogonalBases].
+    /// Walls keep the face of their group member, other tiles sit on their default face.
+    pub fn group_cells(
+        &self,
+        group: GroupTypeId,
+        grid: GridId,
+        origin: Vec3Int,
+        orientation: u8,
+    ) -> Vec<NewCell> {
+        let bases = OrthogonalBases::default().bases;
+        let rotation = bases[orientation as usize % bases.len()];
+        let mut cells = vec![];
+        let members;
+        match self.groups.get(&group) {
+            Some(m) => {
+                members = m;
+            }
+            None => {
+                warn!("Couldnt find group {:?}.", group);
+                return cells;
+            }
+        }
+        for (offset, member) in members.iter() {
+            let properties;
+            match self.tile_properties.get(&member.tile_type) {
+                Some(p) => {
+                    properties = p;
+                }
+                None => {
+                    warn!("no tile properties found");
+                    continue;
+                }
+            }
+            let face;
+            match properties.cell_type {
+                CellType::Wall | CellType::WallDetail => {
+                    face = member.face.clone();
+                }
+                CellType::Floor | CellType::Center => {
+                    face = properties.cell_type.default_face();
+                }
+            }
+            let (face, member_orientation) = rotate_placement(&face, member.orientation, rotation);
+            cells.push(NewCell {
+                cell: LayerTargetCell {
+                    target: TargetCell {
+                        id: origin + rotate_offset(*offset, rotation),
+                        face,
+                        grid,
+                    },
+                    layer: properties.layer,
+                },
+                orientation: member_orientation,
+                tile_type: member.tile_type,
+                metadata: TileMetadata::default(),
+            });
+        }
+        cells
+    }
+    /// Register a grid with its own rigid body. Tiles can get added to it right after.
+    pub fn insert_grid(
+        &mut self,
+        commands: &mut Commands,
+        grid: GridId,
+        motion: GridMotion,
+        rigid_body: RigidBody,
+    ) {
+        if grid == GridId::MAIN || self.grids.contains_key(&grid) {
+            warn!("Grid {:?} already exists.", grid);
+            return;
+        }
+        let entity = spawn_grid_body(commands, grid, &motion, rigid_body);
+        self.grid_incremental = self.grid_incremental.max(grid.0);
+        self.grids.insert(
+            grid,
+            SubGrid {
+                layers: BTreeMap::default(),
+                colliders: HashMap::default(),
+                entity,
+                transform: motion.transform(),
+                linear_velocity: motion.linear_velocity,
+                angular_velocity: motion.angular_velocity,
+            },
+        );
+    }
+    /// Create a new dynamic grid.
+    pub fn spawn_grid(&mut self, commands: &mut Commands, motion: GridMotion) -> GridId {
+        let grid = GridId(self.grid_incremental + 1);
+        self.insert_grid(commands, grid, motion, RigidBody::Dynamic);
+        grid
+    }
+    /// Whether any layer has cells in the chunk.
+    pub fn has_chunk(&self, chunk: usize) -> bool {
+        self.layers.values().any(|layer| layer.contains_key(&chunk))
+    }
+    /// Transform of a tile in the world.
+    pub fn get_cell_world_transform(&self, cell: TargetCell, orientation: u8) -> Transform {
+        self.grid_transform(cell.grid)
+            .mul_transform(self.get_cell_transform(cell, orientation))
+    }
+    /// The main grid cell a cell of any grid currently overlaps, for FOV and other main grid checks.
+    pub fn get_world_cell_id(&self, cell: &TargetCell) -> Vec3Int {
+        if cell.grid == GridId::MAIN {
+            return cell.id;
+        }
+        let center = cell_id_to_world(cell.id) + Vec3::splat(0.5);
+        world_to_cell_id(self.grid_transform(cell.grid).transform_point(center))
+    }
+    /// Transform of a tile relative to its grid.
     pub fn get_cell_transform(&self, cell: TargetCell, orientation: u8) -> Transform {
         let strict = self.get_strict_cell(cell);
 
@@ -716,66 +1299,94 @@ impl Gridmap {
         transform.rotation *= OrthogonalBases::default().bases[orientation as usize];
         transform
     }
-    pub fn get_chunk_collider_data(&self, chunk_id: usize) -> Vec<(Vec3, Quat, Collider)> {
-        match self.main_grid.get(chunk_id) {
-            Some(chunk_data_option) => match chunk_data_option {
+    /// Collider of a tile with its world position and rotation.
+    pub fn get_tile_collider_data(
+        &self,
+        cell: TargetCell,
+        tile_type: &CellTypeId,
+        orientation: u8,
+    ) -> Option<(Vec3, Quat, Collider)> {
+        match self.tile_properties.get(tile_type) {
+            Some(properties) => {
+                let mut world_position = self.get_cell_transform(cell, orientation);
+                let relative_collider_position = OrthogonalBases::default().bases
+                    [orientation as usize]
+                    .mul_vec3(properties.collider_position.translation);
+
+                world_position.translation += relative_collider_position;
+                world_position.rotation *= properties.collider_position.rotation;
+                Some((
+                    world_position.translation,
+                    world_position.rotation,
+                    properties.collider.clone(),
+                ))
+            }
+            None => {
+                warn!("No tile_properties found for cell type.");
+                None
+            }
+        }
+    }
+    /// Colliders of the tiles of all colliding layers in a chunk.
+    pub fn get_chunk_collider_data(
+        &self,
+        grid: GridId,
+        chunk_id: usize,
+    ) -> Vec<(Vec3, Quat, Collider)> {
+        let mut data = vec![];
+        for (layer, properties) in self.layer_properties.iter() {
+            if !properties.collision {
+                continue;
+            }
+            match self
+                .layer(grid, *layer)
+                .and_then(|layer| layer.get(&chunk_id))
+            {
                 Some(chunk_data) => {
-                    let mut data = vec![];
-                    let mut cell_id = 0;
-                    for cell_option in chunk_data.cells.iter() {
-                        match cell_option {
-                            Some(cell_data) => {
-                                for (cell_item, cell_face) in cell_data.get_items() {
-                                    match self.tile_properties.get(&cell_item.tile_type) {
-                                        Some(properties) => {
-                                            let mut world_position = self.get_cell_transform(
-                                                TargetCell {
-                                                    id: self
-                                                        .get_id(CellIndexes {
-                                                            chunk: chunk_id,
-                                                            cell: cell_id,
-                                                        })
-                                                        .unwrap(),
-                                                    face: cell_face,
-                                                },
-                                                cell_item.orientation,
-                                            );
-                                            let relative_collider_position =
-                                                OrthogonalBases::default().bases
-                                                    [cell_item.orientation as usize]
-                                                    .mul_vec3(
-                                                        properties.collider_position.translation,
-                                                    );
-
-                                            world_position.translation +=
-                                                relative_collider_position;
-                                            world_position.rotation *=
-                                                properties.collider_position.rotation;
-                                            data.push((
-                                                world_position.translation,
-                                                world_position.rotation,
-                                                properties.collider.clone(),
-                                            ));
-                                        }
-                                        None => {
-                                            warn!("No tile_properties found for cell type.");
-                                        }
-                                    }
+                    for (cell_id, cell_data) in chunk_data.cells.iter() {
+                        let id = self
+                            .get_id(CellIndexes {
+                                chunk: chunk_id,
+                                cell: *cell_id,
+                            })
+                            .unwrap();
+                        for (cell_item, cell_face) in cell_data.get_items() {
+                            match self.get_tile_collider_data(
+                                TargetCell {
+                                    id,
+                                    face: cell_face,
+                                    grid,
+                                },
+                                &cell_item.tile_type,
+                                cell_item.orientation,
+                            ) {
+                                Some(collider_data) => {
+                                    data.push(collider_data);
                                 }
+                                None => {}
                             }
-                            None => {}
                         }
-                        cell_id += 1;
                     }
-                    data
                 }
-                None => vec![],
-            },
-            None => {
-                warn!("Chunk doesnt exist.");
-                vec![]
+                None => {}
+            }
+        }
+        data
+    }
+    /// The cell stored under a cell id.
+    pub fn get_grid_cell(&self, grid: GridId, id: Vec3Int, layer: LayerId) -> Option<&GridCell> {
+        let indexes;
+        match self.get_indexes(id) {
+            Ok(i) => {
+                indexes = i;
+            }
+            Err(_) => {
+                return None;
             }
         }
+        self.layer(grid, layer)
+            .and_then(|layer| layer.get(&indexes.chunk))
+            .and_then(|chunk| chunk.get(indexes.cell))
     }
     pub fn batch_updates(
         &self,
@@ -827,7 +1438,7 @@ impl Gridmap {
 
                     match self.get_cell(LayerTargetCell {
                         target: id.target.clone(),
-                        is_detail: id.is_detail,
+                        layer: id.layer,
                     }) {
                         Some(_) => {
                             removetile.send(RemoveTile {
@@ -844,9 +1455,11 @@ impl Gridmap {
                         orientation: new_cell.orientation,
                         face: id.target.face.clone(),
                         group_instance_id_option: None,
+                        metadata: new_cell.metadata.clone(),
                         entity: commands.spawn(()).id(),
                         default_map_spawn: false,
-                        is_detail: id.is_detail,
+                        layer: id.layer,
+                        grid: id.target.grid,
                         stamp: *tick,
                     });
                 }
@@ -855,7 +1468,7 @@ impl Gridmap {
 
                     match self.get_cell(LayerTargetCell {
                         target: id.target.clone(),
-                        is_detail: id.is_detail,
+                        layer: id.layer,
                     }) {
                         Some(_) => {
                             removetile.send(RemoveTile {
@@ -947,12 +1560,24 @@ pub struct AddTile {
     pub orientation: u8,
     pub face: CellFace,
     pub group_instance_id_option: Option<u32>,
+    pub metadata: TileMetadata,
     pub entity: Entity,
     pub default_map_spawn: bool,
-    pub is_detail: bool,
+    pub layer: LayerId,
+    pub grid: GridId,
     pub stamp: u32,
 }
 
+impl AddTile {
+    pub fn target(&self) -> TargetCell {
+        TargetCell {
+            id: self.id,
+            face: self.face.clone(),
+            grid: self.grid,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FullCell {
     pub face: CellFace,
@@ -967,142 +1592,113 @@ pub struct AddGroup {
     pub id: Vec3Int,
     /// Group id.
     pub group_id: GroupTypeId,
-    /// Rotation.
+    /// Index of [OrthogonalBases] the group gets rotated by.
     pub orientation: u8,
-    pub face: CellFace,
     pub default_map_spawn: bool,
+    pub grid: GridId,
     pub stamp: u32,
 }
 
-use bevy::prelude::{EventReader, ResMut, TransformBundle};
+use bevy::prelude::{EventReader, ResMut};
 use entity::health::{HealthContainer, HealthFlag, StructureHealth};
 
 use crate::{
+    blueprint::{rotate_offset, rotate_placement},
+    colliders::ChunkColliderRebuilds,
+    damage::{CellDamage, TileDurability},
+    format::{encode_gridmap, GridmapEncoding, GridmapFormatError},
+    grids::{spawn_grid_body, GridMotion, SubGrid},
     init::{CellDataExport, GroupItem, ItemExport},
+    layers::{LayerId, LayerProperties, STRUCTURE_LAYER},
     net::{GridmapServerMessage, NewCell},
+    pathfinding::Ramp,
+    streaming::StreamedChunks,
 };
 pub(crate) fn remove_tile(
     mut events: EventReader<RemoveTile>,
     mut gridmap: ResMut<Gridmap>,
     mut despawn: EventWriter<DespawnEntity>,
-    mut gridmap_collider_query: Query<(&GridmapCollider, &mut Collider)>,
+    mut rebuilds: ResMut<ChunkColliderRebuilds>,
+    stamp: Res<TickRateStamp>,
 ) {
-    let mut changed_chunks = vec![];
     for event in events.read() {
-        let chunk_id = gridmap.get_indexes(event.cell.target.id).chunk;
-        if !changed_chunks.contains(&chunk_id) {
-            changed_chunks.push(chunk_id);
-        }
         let strict_cell = gridmap.get_strict_cell(event.cell.target.clone());
-        let indexes = gridmap.get_indexes(strict_cell.id);
+        let indexes;
+        match gridmap.get_indexes(strict_cell.id) {
+            Ok(i) => {
+                indexes = i;
+            }
+            Err(rr) => {
+                warn!("Couldn't remove tile: {}", rr);
+                continue;
+            }
+        }
         let grid;
-        if !event.cell.is_detail {
-            grid = &mut gridmap.main_grid;
-        } else {
-            grid = &mut gridmap.details_grid;
-        }
-        match grid.get_mut(indexes.chunk) {
-            Some(grid_chunk_option) => {
-                let mut clear_chunk = false;
-                match grid_chunk_option {
-                    Some(grid_chunk) => {
-                        match grid_chunk.cells.get_mut(indexes.cell) {
-                            Some(cell_option) => {
-                                let mut cell_empty = false;
-                                match cell_option {
-                                    Some(cell) => {
-                                        let mut old_cell_entity = None;
-                                        match strict_cell.face {
-                                            StrictCellFace::FrontWall => {
-                                                match &cell.front_wall {
-                                                    Some(wall) => {
-                                                        old_cell_entity = wall.entity;
-                                                    }
-                                                    None => {}
-                                                }
-
-                                                cell.front_wall = None;
-                                            }
-                                            StrictCellFace::RightWall => {
-                                                match &cell.right_wall {
-                                                    Some(wall) => {
-                                                        old_cell_entity = wall.entity;
-                                                    }
-                                                    None => {}
-                                                }
-
-                                                cell.right_wall = None;
-                                            }
-                                            StrictCellFace::Floor => {
-                                                match &cell.floor {
-                                                    Some(wall) => {
-                                                        old_cell_entity = wall.entity;
-                                                    }
-                                                    None => {}
-                                                }
-
-                                                cell.floor = None;
-                                            }
-                                            StrictCellFace::Center => {
-                                                match &cell.center {
-                                                    Some(wall) => {
-                                                        old_cell_entity = wall.entity;
-                                                    }
-                                                    None => {}
-                                                }
-
-                                                cell.center = None;
-                                            }
-                                        }
-
-                                        match old_cell_entity {
-                                            Some(ent) => {
-                                                despawn.send(DespawnEntity { entity: ent });
-                                            }
-                                            None => {}
-                                        }
-                                        cell_empty = cell.is_empty();
-                                    }
-                                    None => {}
-                                }
-                                if cell_empty {
-                                    *cell_option = None;
-                                }
+        match gridmap.layer_mut(event.cell.target.grid, event.cell.layer) {
+            Some(g) => {
+                grid = g;
+            }
+            None => {
+                warn!(
+                    "Couldn't remove tile of unknown grid {:?} or layer {:?}.",
+                    event.cell.target.grid, event.cell.layer
+                );
+                continue;
+            }
+        }
+        let mut clear_chunk = false;
+        let mut removed_group = None;
+        match grid.get_mut(&indexes.chunk) {
+            Some(grid_chunk) => {
+                let mut cell_empty = false;
+                match grid_chunk.get_mut(indexes.cell) {
+                    Some(cell) => {
+                        let old_cell_item;
+                        match strict_cell.face {
+                            StrictCellFace::FrontWall => {
+                                old_cell_item = cell.front_wall.take();
+                            }
+                            StrictCellFace::RightWall => {
+                                old_cell_item = cell.right_wall.take();
+                            }
+                            StrictCellFace::Floor => {
+                                old_cell_item = cell.floor.take();
+                            }
+                            StrictCellFace::Center => {
+                                old_cell_item = cell.center.take();
                             }
-                            None => {}
                         }
 
-                        clear_chunk = grid_chunk.is_empty();
+                        removed_group =
+                            old_cell_item.as_ref().and_then(|item| item.group_id_option);
+                        match old_cell_item.and_then(|item| item.entity) {
+                            Some(ent) => {
+                                despawn.send(DespawnEntity { entity: ent });
+                            }
+                            None => {}
+                        }
+                        cell_empty = cell.is_empty();
                     }
                     None => {}
                 }
-
-                if clear_chunk {
-                    *grid_chunk_option = None;
+                if cell_empty {
+                    grid_chunk.remove(indexes.cell);
                 }
+                clear_chunk = grid_chunk.is_empty();
             }
             None => {}
         }
-    }
-    for chunk_id in changed_chunks {
-        let chunk_colliders = gridmap.get_chunk_collider_data(chunk_id);
-
-        match gridmap.colliders.get(&chunk_id) {
-            Some(chunk_collider_entity) => {
-                match gridmap_collider_query.get_mut(*chunk_collider_entity) {
-                    Ok((gridmap_collider_component, mut collider)) => {
-                        if gridmap_collider_component.chunk_id == chunk_id {
-                            *collider = Collider::compound(chunk_colliders);
-                        }
-                    }
-                    Err(_) => {
-                        warn!("No collider chunk found.");
-                    }
-                }
-            }
-            None => {
-                warn!("Trying to remove a non-existing chunk.");
+        if clear_chunk {
+            grid.remove(&indexes.chunk);
+        }
+        match removed_group {
+            Some(instance) => {
+                gridmap.remove_group_tile(instance);
             }
+            None => {}
+        }
+        if gridmap.layer_collides(event.cell.layer) {
+            rebuilds.queue(event.cell.target.grid, indexes.chunk, stamp.tick);
         }
     }
 }
@@ -1111,87 +1707,44 @@ pub(crate) fn add_tile_net(
     mut events: EventReader<AddTile>,
     connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
     mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
-    mut gridmap: ResMut<Gridmap>,
+    gridmap: Res<Gridmap>,
+    streamed: Res<StreamedChunks>,
 ) {
     for event in events.read() {
-        let mut received = vec![];
-
-        let target = TargetCell {
-            id: event.id,
-            face: event.face.clone(),
-        };
-
-        if !event.default_map_spawn {
-            for connected_player in connected_players.iter() {
-                if !connected_player.connected {
-                    continue;
-                }
-                net.send(OutgoingReliableServerMessage {
-                    handle: connected_player.handle,
-                    message: GridmapServerMessage::AddCell(NewCell {
-                        cell: LayerTargetCell {
-                            target: target.clone(),
-                            is_detail: event.is_detail,
-                        },
-                        orientation: event.orientation,
-                        tile_type: event.tile_type,
-                    }),
-                });
-                received.push(connected_player.handle);
+        if event.default_map_spawn {
+            continue;
+        }
+        let target = event.target();
+        let chunk;
+        match gridmap.get_cell_chunk(&target) {
+            Some(c) => {
+                chunk = c;
             }
-            let tick_data;
-            match gridmap.updates.get_mut(&event.stamp) {
-                Some(u) => {
-                    tick_data = u;
-                }
-                None => {
-                    gridmap.updates.insert(event.stamp, HashMap::new());
-                    tick_data = gridmap.updates.get_mut(&event.stamp).unwrap();
-                }
+            None => {
+                continue;
             }
-            tick_data.insert(
-                LayerTargetCell {
-                    target: target.clone(),
-                    is_detail: event.is_detail,
-                },
-                AddedUpdate {
-                    cell: GridmapUpdate::Added(NewCell {
-                        cell: LayerTargetCell {
-                            target,
-                            is_detail: event.is_detail,
-                        },
-                        orientation: event.orientation,
-                        tile_type: event.tile_type,
-                    }),
-                    players_received: received,
-                },
-            );
         }
-    }
 
-    for connected_player in connected_players.iter() {
-        if !connected_player.connected {
-            return;
-        }
-        for (_, subs) in gridmap.updates.iter_mut() {
-            for (_target_cell, update) in subs.iter_mut() {
-                match &update.cell {
-                    GridmapUpdate::Added(add) => {
-                        if !update.players_received.contains(&connected_player.handle) {
-                            update.players_received.push(connected_player.handle);
-                            net.send(OutgoingReliableServerMessage {
-                                handle: connected_player.handle,
-                                message: GridmapServerMessage::AddCell(NewCell {
-                                    cell: add.cell.clone(),
-                                    orientation: add.orientation,
-                                    tile_type: add.tile_type,
-                                }),
-                            });
-                        }
-                    }
-                    _ => (),
-                }
+        for connected_player in connected_players.iter() {
+            // Only the main grid gets streamed by chunk, other grids are sent whole.
+            if !connected_player.connected
+                || (target.grid == GridId::MAIN
+                    && !streamed.has_chunk(&connected_player.handle, chunk))
+            {
+                continue;
             }
+            net.send(OutgoingReliableServerMessage {
+                handle: connected_player.handle,
+                message: GridmapServerMessage::AddCell(NewCell {
+                    cell: LayerTargetCell {
+                        target: target.clone(),
+                        layer: event.layer,
+                    },
+                    orientation: event.orientation,
+                    tile_type: event.tile_type,
+                    metadata: event.metadata.clone(),
+                }),
+            });
         }
     }
 }
@@ -1200,60 +1753,30 @@ pub(crate) fn remove_tile_net(
     mut events: EventReader<RemoveTile>,
     connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
     mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
-    mut gridmap: ResMut<Gridmap>,
+    gridmap: Res<Gridmap>,
+    streamed: Res<StreamedChunks>,
 ) {
     for event in events.read() {
-        let mut received = vec![];
-
+        let chunk;
+        match gridmap.get_cell_chunk(&event.cell.target) {
+            Some(c) => {
+                chunk = c;
+            }
+            None => {
+                continue;
+            }
+        }
         for connected_player in connected_players.iter() {
-            if !connected_player.connected {
+            if !connected_player.connected
+                || (event.cell.target.grid == GridId::MAIN
+                    && !streamed.has_chunk(&connected_player.handle, chunk))
+            {
                 continue;
             }
             net.send(OutgoingReliableServerMessage {
                 handle: connected_player.handle,
                 message: GridmapServerMessage::RemoveCell(event.cell.clone()),
             });
-            received.push(connected_player.handle);
-        }
-        let subs;
-        match gridmap.updates.get_mut(&event.stamp) {
-            Some(u) => {
-                subs = u;
-            }
-            None => {
-                gridmap.updates.insert(event.stamp, HashMap::new());
-                subs = gridmap.updates.get_mut(&event.stamp).unwrap();
-            }
-        }
-
-        subs.insert(
-            event.cell.clone(),
-            AddedUpdate {
-                cell: GridmapUpdate::Removed,
-                players_received: received,
-            },
-        );
-    }
-
-    for connected_player in connected_players.iter() {
-        if !connected_player.connected {
-            return;
-        }
-        for (_, subs) in gridmap.updates.iter_mut() {
-            for (target_cell, update) in subs.iter_mut() {
-                match update.cell {
-                    GridmapUpdate::Removed => {
-                        if !update.players_received.contains(&connected_player.handle) {
-                            update.players_received.push(connected_player.handle);
-                            net.send(OutgoingReliableServerMessage {
-                                handle: connected_player.handle,
-                                message: GridmapServerMessage::RemoveCell(target_cell.clone()),
-                            });
-                        }
-                    }
-                    _ => (),
-                }
-            }
         }
     }
 }
@@ -1272,9 +1795,11 @@ pub(crate) fn add_cell_client(
                     orientation: new.orientation,
                     face: new.cell.target.face.clone(),
                     group_instance_id_option: None,
+                    metadata: new.metadata.clone(),
                     entity: commands.spawn(()).id(),
-                    is_detail: new.cell.is_detail,
+                    layer: new.cell.layer,
                     default_map_spawn: false,
+                    grid: new.cell.target.grid,
                     stamp: message.stamp,
                 });
             }
@@ -1300,57 +1825,24 @@ pub(crate) fn remove_cell_client(
     }
 }
 
+/// Queue the colliders of chunks with new tiles for a rebuild, see [ChunkColliderRebuilds].
 pub(crate) fn add_tile_collision(
     mut events: EventReader<AddTile>,
-    mut commands: Commands,
-    mut gridmap_data: ResMut<Gridmap>,
-    mut gridmap_collider_query: Query<(&GridmapCollider, &mut Collider)>,
+    gridmap_data: Res<Gridmap>,
+    mut rebuilds: ResMut<ChunkColliderRebuilds>,
+    stamp: Res<TickRateStamp>,
 ) {
-    let mut changed_chunks = vec![];
     for event in events.read() {
-        let chunk_id = gridmap_data.get_indexes(event.id).chunk;
-        if !changed_chunks.contains(&chunk_id) {
-            changed_chunks.push(chunk_id);
+        if !gridmap_data.has_grid(event.grid) || !gridmap_data.layer_collides(event.layer) {
+            continue;
         }
-    }
-    for chunk_id in changed_chunks {
-        let mut friction_component = Friction::new(STANDARD_BODY_FRICTION);
-        friction_component.combine_rule = CoefficientCombine::Min;
-        let masks = get_bit_masks(ColliderGroup::Standard);
-
-        let chunk_colliders = gridmap_data.get_chunk_collider_data(chunk_id);
-
-        match gridmap_data.colliders.get(&chunk_id) {
-            Some(chunk_collider_entity) => {
-                match gridmap_collider_query.get_mut(*chunk_collider_entity) {
-                    Ok((gridmap_collider_component, mut collider)) => {
-                        if gridmap_collider_component.chunk_id == chunk_id {
-                            *collider = Collider::compound(chunk_colliders);
-                        }
-                    }
-                    Err(_) => {
-                        warn!("No collider chunk found.");
-                    }
-                }
-            }
-            None => {
-                let new_chunk_id = commands
-                    .spawn((
-                        Collider::compound(chunk_colliders),
-                        GridmapCollider { chunk_id: chunk_id },
-                        friction_component,
-                        RigidBody::Static,
-                        CollisionLayers::from_bits(masks.0, masks.1),
-                        TransformBundle::default(),
-                    ))
-                    .id();
-                gridmap_data.colliders.insert(chunk_id, new_chunk_id);
+        let strict = gridmap_data.get_strict_cell(event.target());
+        match gridmap_data.get_indexes(strict.id) {
+            Ok(indexes) => {
+                rebuilds.queue(event.grid, indexes.chunk, stamp.tick);
             }
+            Err(_) => {}
         }
-
-        /*if is_server() || matches!(*app_mode, AppMode::Correction) {
-            entity_builder.insert(TransformBundle::from(world_position));
-        }*/
     }
 }
 
@@ -1359,13 +1851,7 @@ pub(crate) fn remove_tile_client_updates(
     mut gridmap: ResMut<Gridmap>,
 ) {
     for remove_event in events.read() {
-        let target = LayerTargetCell {
-            target: TargetCell {
-                id: remove_event.cell.target.id,
-                face: remove_event.cell.target.face.clone(),
-            },
-            is_detail: remove_event.cell.is_detail,
-        };
+        let target = remove_event.cell.clone();
         let mut_ref;
         match gridmap.updates.get_mut(&remove_event.stamp) {
             Some(r) => {
@@ -1380,7 +1866,6 @@ pub(crate) fn remove_tile_client_updates(
             target.clone(),
             AddedUpdate {
                 cell: GridmapUpdate::Removed,
-                players_received: vec![],
             },
         );
     }
@@ -1391,11 +1876,8 @@ pub(crate) fn add_tile_client_updates(
 ) {
     for add_tile_event in events.read() {
         let target = LayerTargetCell {
-            target: TargetCell {
-                id: add_tile_event.id,
-                face: add_tile_event.face.clone(),
-            },
-            is_detail: add_tile_event.is_detail,
+            target: add_tile_event.target(),
+            layer: add_tile_event.layer,
         };
         let mut_ref;
         match gridmap.updates.get_mut(&add_tile_event.stamp) {
@@ -1414,102 +1896,69 @@ pub(crate) fn add_tile_client_updates(
                     cell: target,
                     orientation: add_tile_event.orientation,
                     tile_type: add_tile_event.tile_type,
+                    metadata: add_tile_event.metadata.clone(),
                 }),
-                players_received: vec![],
             },
         );
     }
 }
 
+/// Forget group instances whose last tile got removed, see [GroupInstance::tiles].
+pub(crate) fn forget_group_instances(mut gridmap: ResMut<Gridmap>) {
+    if !gridmap.emptied_group_instances.is_empty() {
+        gridmap.forget_emptied_group_instances();
+    }
+}
+
 pub(crate) fn add_tile(
     mut events: EventReader<AddTile>,
     mut gridmap: ResMut<Gridmap>,
     mut commands: Commands,
 ) {
     for add_tile_event in events.read() {
+        let mut health_flags = HashMap::new();
+
+        health_flags.insert(0, HealthFlag::ArmourPlated);
+
+        let new = CellItem {
+            tile_type: add_tile_event.tile_type,
+            entity: Some(add_tile_event.entity),
+            health: Health {
+                health_flags: health_flags.clone(),
+                health_container: HealthContainer::Structure(StructureHealth::default()),
+                ..Default::default()
+            },
+            orientation: add_tile_event.orientation.clone(),
+            group_id_option: add_tile_event.group_instance_id_option,
+            metadata: add_tile_event.metadata.clone(),
+        };
+        match gridmap.insert_tile(
+            LayerTargetCell {
+                target: add_tile_event.target(),
+                layer: add_tile_event.layer,
+            },
+            new,
+        ) {
+            Ok(_) => {}
+            Err(rr) => {
+                warn!("Couldn't add tile: {}", rr);
+                commands.entity(add_tile_event.entity).despawn();
+                continue;
+            }
+        }
+
         commands.entity(add_tile_event.entity).insert((
             Tile,
             Cell {
                 id: add_tile_event.id,
             },
         ));
-
-        let strict = gridmap.get_strict_cell(TargetCell {
-            id: add_tile_event.id,
-            face: add_tile_event.face.clone(),
-        });
-
-        let indexes = gridmap.get_indexes(strict.id);
-        let grid;
-        if !add_tile_event.is_detail {
-            grid = &mut gridmap.main_grid;
-        } else {
-            grid = &mut gridmap.details_grid;
-        }
-        match grid.get_mut(indexes.chunk) {
-            Some(chunk_option) => {
-                match chunk_option {
-                    Some(_) => {}
-                    None => {
-                        *chunk_option = Some(GridmapChunk::default());
-                    }
-                }
-                match chunk_option {
-                    Some(chunk) => {
-                        let mut y = chunk.cells.get_mut(indexes.cell);
-                        let x = y.as_mut().unwrap();
-
-                        match x {
-                            Some(_) => {}
-                            None => {
-                                **x = Some(GridCell::default());
-                            }
-                        }
-
-                        let grid_items = x.as_mut().unwrap();
-
-                        let mut health_flags = HashMap::new();
-
-                        health_flags.insert(0, HealthFlag::ArmourPlated);
-
-                        let new = Some(CellItem {
-                            tile_type: add_tile_event.tile_type,
-                            entity: Some(add_tile_event.entity),
-                            health: Health {
-                                health_flags: health_flags.clone(),
-                                health_container: HealthContainer::Structure(
-                                    StructureHealth::default(),
-                                ),
-                                ..Default::default()
-                            },
-                            orientation: add_tile_event.orientation.clone(),
-                            group_id_option: add_tile_event.group_instance_id_option,
-                        });
-
-                        match strict.face {
-                            StrictCellFace::FrontWall => {
-                                grid_items.front_wall = new;
-                            }
-                            StrictCellFace::RightWall => {
-                                grid_items.right_wall = new;
-                            }
-                            StrictCellFace::Floor => {
-                                grid_items.floor = new;
-                            }
-                            StrictCellFace::Center => {
-                                grid_items.center = new;
-                            }
-                        }
-                    }
-                    None => {
-                        warn!("No chunk option");
-                        continue;
-                    }
-                }
-            }
-            None => {
-                warn!("set_cell couldn't find chunk.");
+        match gridmap.grids.get(&add_tile_event.grid) {
+            // Tiles of other grids move along with their grid.
+            Some(sub) => {
+                commands.entity(sub.entity).add_child(add_tile_event.entity);
             }
+            None => {}
         }
     }
 }
@@ -1638,64 +2087,32 @@ pub(crate) fn spawn_group(
     mut commands: Commands,
 ) {
     for add_group_event in events.read() {
-        match gridmap_main.groups.get(&add_group_event.group_id) {
-            Some(tiles) => {
-                let mut i = 0;
-
-                for (local_id, tile_type) in tiles.iter() {
-                    let int = &Vec3Int { x: 0, y: 0, z: 0 };
-
-                    let mut new_id = local_id.clone();
-
-                    if local_id != int {
-                        let mut point = Transform::from_translation(Vec3::new(
-                            local_id.x as f32,
-                            local_id.y as f32,
-                            local_id.z as f32,
-                        ));
-
-                        point.rotate(
-                            OrthogonalBases::default().bases[add_group_event.orientation as usize],
-                        );
-
-                        new_id = Vec3Int {
-                            x: point.translation.x as i16,
-                            y: point.translation.y as i16,
-                            z: point.translation.z as i16,
-                        }
-                    }
-                    let is_detail;
-
-                    match gridmap_main.tile_properties.get(&tile_type.tile_type) {
-                        Some(properties) => {
-                            is_detail = properties.is_detail;
-                        }
-                        None => {
-                            warn!("no tile properties found");
-                            continue;
-                        }
-                    }
-
-                    set_tile.send(AddTile {
-                        id: add_group_event.id + new_id,
-                        tile_type: tile_type.tile_type,
-                        orientation: add_group_event.orientation,
-                        face: add_group_event.face.clone(),
-                        group_instance_id_option: Some(
-                            gridmap_main.group_instance_incremental + i + 1,
-                        ),
-                        entity: commands.spawn(()).id(),
-                        default_map_spawn: add_group_event.default_map_spawn,
-                        is_detail: is_detail,
-                        stamp: add_group_event.stamp,
-                    });
-                    i += 1;
-                }
-                gridmap_main.group_instance_incremental += i;
-            }
-            None => {
-                warn!("Couldnt find to be spawned group.");
-            }
+        if !gridmap_main.groups.contains_key(&add_group_event.group_id) {
+            warn!("Couldnt find to be spawned group.");
+            continue;
+        }
+        let cells = gridmap_main.group_cells(
+            add_group_event.group_id,
+            add_group_event.grid,
+            add_group_event.id,
+            add_group_event.orientation,
+        );
+        let instance =
+            gridmap_main.new_group_instance(add_group_event.group_id, add_group_event.orientation);
+        for new in cells {
+            set_tile.send(AddTile {
+                id: new.cell.target.id,
+                tile_type: new.tile_type,
+                orientation: new.orientation,
+                face: new.cell.target.face,
+                group_instance_id_option: Some(instance),
+                metadata: new.metadata,
+                entity: commands.spawn(()).id(),
+                default_map_spawn: add_group_event.default_map_spawn,
+                layer: new.cell.layer,
+                grid: add_group_event.grid,
+                stamp: add_group_event.stamp,
+            });
         }
     }
 }
@@ -1706,4 +2123,172 @@ pub struct TargetCellWithOrientationWType {
     pub face: CellFace,
     pub orientation: u8,
     pub tile_type: CellTypeId,
+    pub grid: GridId,
+}
+
+#[cfg(test)]
+impl Gridmap {
+    /// Put a tile of a type straight into the structure layer of a grid, for tests without the gridmap systems.
+    pub(crate) fn set_test_tile(
+        &mut self,
+        grid: GridId,
+        id: Vec3Int,
+        face: CellFace,
+        tile_type: CellTypeId,
+    ) -> &mut CellItem {
+        let cell = LayerTargetCell {
+            target: TargetCell { id, face, grid },
+            layer: LayerId::STRUCTURE,
+        };
+        self.insert_tile(
+            cell.clone(),
+            CellItem {
+                tile_type,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        self.get_cell_mut(cell).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gridmap_with_length(length: i16) -> Gridmap {
+        Gridmap {
+            map_length_limit: MapLimits::new(length),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_indexes_round_trip() {
+        let gridmap = gridmap_with_length(32);
+        for id in [
+            Vec3Int { x: 0, y: 0, z: 0 },
+            Vec3Int { x: -1, y: 5, z: 31 },
+            Vec3Int {
+                x: -512,
+                y: -512,
+                z: -512,
+            },
+            Vec3Int {
+                x: 511,
+                y: 511,
+                z: 511,
+            },
+        ] {
+            let indexes = gridmap.get_indexes(id).unwrap();
+            assert_eq!(gridmap.get_id(indexes), Some(id));
+        }
+    }
+
+    #[test]
+    fn test_out_of_limits_rejected() {
+        let gridmap = gridmap_with_length(32);
+        assert!(gridmap.get_indexes(Vec3Int { x: 512, y: 0, z: 0 }).is_err());
+        assert!(gridmap
+            .get_indexes(Vec3Int {
+                x: 0,
+                y: -513,
+                z: 0
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_unbounded_limits() {
+        let mut gridmap = Gridmap::default();
+        gridmap.map_length_limit = MapLimits::unbounded();
+        for id in [
+            Vec3Int {
+                x: i16::MIN,
+                y: i16::MIN,
+                z: i16::MIN,
+            },
+            Vec3Int {
+                x: i16::MAX,
+                y: i16::MAX,
+                z: i16::MAX,
+            },
+        ] {
+            let indexes = gridmap.get_indexes(id).unwrap();
+            assert_eq!(gridmap.get_id(indexes), Some(id));
+        }
+    }
+
+    #[test]
+    fn test_group_cells_rotated() {
+        let mut gridmap = Gridmap::default();
+        let tile_type = CellTypeId(1);
+        gridmap.tile_properties.insert(
+            tile_type,
+            TileProperties {
+                cell_type: CellType::Center,
+                ..Default::default()
+            },
+        );
+        let mut members = HashMap::default();
+        members.insert(
+            Vec3Int { x: 1, y: 0, z: 0 },
+            FullCell {
+                face: CellFace::Center,
+                orientation: 0,
+                tile_type,
+                entity_option: None,
+            },
+        );
+        gridmap.groups.insert(GroupTypeId(0), members);
+        let orientation =
+            Quat::from_axis_angle(Vec3::Y, std::f32::consts::PI / 2.).get_orthogonal_index();
+        let cells = gridmap.group_cells(
+            GroupTypeId(0),
+            GridId::MAIN,
+            Vec3Int { x: 4, y: 0, z: 4 },
+            orientation,
+        );
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].cell.target.id, Vec3Int { x: 4, y: 0, z: 3 });
+        assert_eq!(cells[0].orientation, orientation);
+    }
+
+    #[test]
+    fn test_group_instance_removed_with_last_tile() {
+        let mut gridmap = Gridmap::default();
+        let instance = gridmap.new_group_instance(GroupTypeId(0), 0);
+        gridmap.add_group_tile(instance);
+        gridmap.add_group_tile(instance);
+        gridmap.remove_group_tile(instance);
+        gridmap.forget_emptied_group_instances();
+        assert_eq!(gridmap.group_instances[&instance].tiles, 1);
+        // Moved to another grid within the same update.
+        gridmap.remove_group_tile(instance);
+        gridmap.add_group_tile(instance);
+        gridmap.forget_emptied_group_instances();
+        assert_eq!(gridmap.group_instances[&instance].tiles, 1);
+        gridmap.remove_group_tile(instance);
+        gridmap.forget_emptied_group_instances();
+        assert!(!gridmap.group_instances.contains_key(&instance));
+    }
+
+    #[test]
+    fn test_register_layer() {
+        let mut gridmap = Gridmap::default();
+        assert_eq!(gridmap.layer_id(STRUCTURE_LAYER), Some(LayerId::STRUCTURE));
+        let pipes = gridmap
+            .register_layer(LayerProperties {
+                name: "pipes".to_string(),
+                collision: false,
+                fov: false,
+            })
+            .unwrap();
+        assert_eq!(gridmap.layer_id("pipes"), Some(pipes));
+        assert!(gridmap.layer_mut(GridId::MAIN, pipes).is_some());
+        assert!(gridmap.layer_mut(GridId::MAIN, LayerId(200)).is_none());
+        assert!(gridmap
+            .register_layer(LayerProperties::structure())
+            .is_none());
+    }
 }
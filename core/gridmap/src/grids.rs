@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bevy::hierarchy::DespawnRecursiveExt;
+use bevy::prelude::{
+    Commands, Component, Entity, EventReader, EventWriter, Local, Quat, Query, Res, ResMut,
+    Transform, TransformBundle, Vec3, VisibilityBundle, With, Without,
+};
+use bevy_xpbd_3d::prelude::{AngularVelocity, GravityScale, LinearVelocity, RigidBody};
+use networking::{
+    client::{IncomingReliableServerMessage, IncomingUnreliableServerMessage},
+    server::{ConnectedPlayer, OutgoingReliableServerMessage, OutgoingUnreliableServerMessage},
+};
+use resources::{grid::GridId, player::SoftPlayer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grid::{AddTile, Gridmap, GridmapChunk, RemoveTile},
+    layers::LayerId,
+    net::{GridSnapshot, GridmapServerMessage, GridmapUnreliableServerMessage},
+};
+
+/// Ticks between grid motion updates sent to clients.
+pub const GRID_SYNC_INTERVAL: u8 = 6;
+
+/// Cells and rigid body of a grid other than [GridId::MAIN].
+#[derive(Clone)]
+pub struct SubGrid {
+    /// Occupied chunks by layer and chunk index.
+    pub layers: BTreeMap<LayerId, BTreeMap<usize, GridmapChunk>>,
+    /// Colliders for chunks, children of the grid entity.
+    pub colliders: HashMap<usize, Entity>,
+    /// Rigid body of the grid, the tiles of the grid are its children.
+    pub entity: Entity,
+    pub transform: Transform,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+}
+
+impl SubGrid {
+    pub fn motion(&self) -> GridMotion {
+        GridMotion {
+            translation: self.transform.translation,
+            rotation: self.transform.rotation,
+            linear_velocity: self.linear_velocity,
+            angular_velocity: self.angular_velocity,
+        }
+    }
+}
+
+/// Position and velocity of a grid.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GridMotion {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+}
+
+impl GridMotion {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.translation).with_rotation(self.rotation)
+    }
+}
+
+/// The rigid body of a grid.
+#[derive(Component)]
+pub struct GridBody {
+    pub grid: GridId,
+}
+
+pub(crate) fn spawn_grid_body(
+    commands: &mut Commands,
+    grid: GridId,
+    motion: &GridMotion,
+    rigid_body: RigidBody,
+) -> Entity {
+    commands
+        .spawn((
+            GridBody { grid },
+            rigid_body,
+            GravityScale(0.),
+            LinearVelocity(motion.linear_velocity),
+            AngularVelocity(motion.angular_velocity),
+            TransformBundle::from(motion.transform()),
+            VisibilityBundle::default(),
+        ))
+        .id()
+}
+
+/// Keep the grid transforms of [Gridmap] up to date with their rigid bodies.
+pub(crate) fn update_grid_motion(
+    bodies: Query<(&GridBody, &Transform, &LinearVelocity, &AngularVelocity)>,
+    mut gridmap: ResMut<Gridmap>,
+) {
+    for (body, transform, linear_velocity, angular_velocity) in bodies.iter() {
+        match gridmap.grids.get_mut(&body.grid) {
+            Some(sub) => {
+                sub.transform = *transform;
+                sub.linear_velocity = linear_velocity.0;
+                sub.angular_velocity = angular_velocity.0;
+            }
+            None => {}
+        }
+    }
+}
+
+/// Announce new grids to the players. Their cells follow as [GridmapServerMessage::AddCell].
+pub(crate) fn send_new_grids(
+    gridmap: Res<Gridmap>,
+    mut announced: Local<HashSet<GridId>>,
+    connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
+    mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    announced.retain(|grid| gridmap.grids.contains_key(grid));
+    for (grid, sub) in gridmap.grids.iter() {
+        if !announced.insert(*grid) {
+            continue;
+        }
+        for connected_player in connected_players.iter() {
+            if !connected_player.connected {
+                continue;
+            }
+            net.send(OutgoingReliableServerMessage {
+                handle: connected_player.handle,
+                message: GridmapServerMessage::SpawnGrid(GridSnapshot {
+                    grid: *grid,
+                    motion: sub.motion(),
+                    cells: vec![],
+                }),
+            });
+        }
+    }
+}
+
+/// Remove grids that lost their last tile.
+pub(crate) fn despawn_empty_grids(
+    mut events: EventReader<RemoveTile>,
+    mut gridmap: ResMut<Gridmap>,
+    mut commands: Commands,
+    connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
+    mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    for event in events.read() {
+        let grid = event.cell.target.grid;
+        if grid == GridId::MAIN || !gridmap.get_grid_chunks(grid).is_empty() {
+            continue;
+        }
+        match gridmap.grids.remove(&grid) {
+            Some(sub) => {
+                commands.entity(sub.entity).despawn_recursive();
+                for connected_player in connected_players.iter() {
+                    if !connected_player.connected {
+                        continue;
+                    }
+                    net.send(OutgoingReliableServerMessage {
+                        handle: connected_player.handle,
+                        message: GridmapServerMessage::DespawnGrid(grid),
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Send the motion of the grids at a low rate, clients move them on their own in between.
+pub(crate) fn send_grid_motion(
+    gridmap: Res<Gridmap>,
+    connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
+    mut net: EventWriter<OutgoingUnreliableServerMessage<GridmapUnreliableServerMessage>>,
+    mut local: Local<u8>,
+) {
+    *local += 1;
+    if *local < GRID_SYNC_INTERVAL {
+        return;
+    }
+    *local = 0;
+    if gridmap.grids.is_empty() {
+        return;
+    }
+    let motions: Vec<(GridId, GridMotion)> = gridmap
+        .grids
+        .iter()
+        .map(|(grid, sub)| (*grid, sub.motion()))
+        .collect();
+    for connected_player in connected_players.iter() {
+        if !connected_player.connected {
+            continue;
+        }
+        net.send(OutgoingUnreliableServerMessage {
+            handle: connected_player.handle,
+            message: GridmapUnreliableServerMessage::GridMotions(motions.clone()),
+        });
+    }
+}
+
+pub(crate) fn receive_grids(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut gridmap: ResMut<Gridmap>,
+    mut commands: Commands,
+    mut add_tile: EventWriter<AddTile>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::SpawnGrid(snapshot) => {
+                if gridmap.grids.contains_key(&snapshot.grid) {
+                    continue;
+                }
+                // The server simulates the grid, the client only moves it along.
+                gridmap.insert_grid(
+                    &mut commands,
+                    snapshot.grid,
+                    snapshot.motion.clone(),
+                    RigidBody::Kinematic,
+                );
+                for new in snapshot.cells.iter() {
+                    add_tile.send(AddTile {
+                        id: new.cell.target.id,
+                        tile_type: new.tile_type,
+                        orientation: new.orientation,
+                        face: new.cell.target.face.clone(),
+                        group_instance_id_option: None,
+                        metadata: new.metadata.clone(),
+                        entity: commands.spawn(()).id(),
+                        default_map_spawn: false,
+                        layer: new.cell.layer,
+                        grid: snapshot.grid,
+                        stamp: message.stamp,
+                    });
+                }
+            }
+            GridmapServerMessage::DespawnGrid(grid) => match gridmap.grids.remove(grid) {
+                Some(sub) => {
+                    commands.entity(sub.entity).despawn_recursive();
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn receive_grid_motion(
+    mut net: EventReader<IncomingUnreliableServerMessage<GridmapUnreliableServerMessage>>,
+    gridmap: Res<Gridmap>,
+    mut bodies: Query<(&mut Transform, &mut LinearVelocity, &mut AngularVelocity), With<GridBody>>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapUnreliableServerMessage::GridMotions(motions) => {
+                for (grid, motion) in motions.iter() {
+                    let entity;
+                    match gridmap.grids.get(grid) {
+                        Some(sub) => {
+                            entity = sub.entity;
+                        }
+                        None => {
+                            continue;
+                        }
+                    }
+                    match bodies.get_mut(entity) {
+                        Ok((mut transform, mut linear_velocity, mut angular_velocity)) => {
+                            *transform = motion.transform();
+                            linear_velocity.0 = motion.linear_velocity;
+                            angular_velocity.0 = motion.angular_velocity;
+                        }
+                        Err(_) => {}
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,18 @@
-use std::{fs, path::Path};
-
+use bevy::log::error;
 use bevy::log::info;
 use bevy::log::warn;
-use bevy::prelude::{Commands, EventWriter, Res, ResMut, Resource};
-use resources::grid::CellFace;
+use bevy::prelude::{Commands, Entity, EventWriter, Res, ResMut, Resource, SystemSet};
+use resources::grid::{CellFace, GridId, TargetCell};
+use resources::maps::{read_map_file, MapError, SelectedMap};
 use resources::math::Vec3Int;
 
+use crate::damage::{CellDamage, DamageTile};
+use crate::format::{decode_gridmap, GridmapEncoding, GRIDMAP_FORMAT_VERSION};
 use crate::grid::{
-    AddTile, CellTypeId, CellTypeName, Gridmap, GroupTypeId, GroupTypeName, TileGroup,
-    TileProperties,
+    AddTile, CellTypeId, CellTypeName, Gridmap, GroupInstance, GroupTypeId, GroupTypeName,
+    LayerTargetCell, MapLimits, TileGroup, TileMetadata, TileProperties,
 };
+use crate::validate::validate_cells;
 
 /// Initiate map resource meta-data.
 
@@ -18,6 +21,13 @@ pub struct InitTileProperties {
     pub properties: Vec<TileProperties>,
 }
 
+/// Systems that add tile types to [InitTileProperties].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub enum InitTileSet {
+    /// Tile types defined in Rust, RON definitions are loaded after them.
+    BuiltIn,
+}
+
 #[derive(Default, Resource)]
 pub struct InitTileGroups {
     pub groups: Vec<TileGroup>,
@@ -66,85 +76,201 @@ pub(crate) fn init_tile_groups(mut gridmap: ResMut<Gridmap>, init_group: Res<Ini
     info!("Loaded {} gridmap group types.", groups_ordered.len());
 }
 
-/// Build the gridmaps in their own resources from ron.
+/// Map issues logged when loading, the validateMap command lists all of them.
+const MAX_LOGGED_MAP_ISSUES: usize = 16;
+
+/// Build the gridmaps in their own resources from the selected map.
 
 pub(crate) fn load_ron_gridmap(
-    gridmap: Res<Gridmap>,
+    mut gridmap: ResMut<Gridmap>,
     mut set_cell: EventWriter<AddTile>,
+    mut damage_tile: EventWriter<DamageTile>,
     mut commands: Commands,
+    selected_map: Option<Res<SelectedMap>>,
 ) {
-    // Load map json data into real static bodies.
-    let main_ron = Path::new("data")
-        .join("maps")
-        .join("bullseye")
-        .join("main.bin");
-    let current_map_main_raw_ron = fs::read(main_ron)
-        .expect("startup_build_map() Error reading map main.ron file from drive.");
-
-    if current_map_main_raw_ron.len() == 0 {
-        warn!("Empty main.ron map file.");
+    let map;
+    match selected_map {
+        Some(m) => {
+            map = m;
+        }
+        None => {
+            error!("No map selected, starting with an empty gridmap.");
+            return;
+        }
+    }
+    let cells_path = map.cells_path();
+    let current_map_main_raw;
+    match read_map_file(&cells_path) {
+        Ok(raw) => {
+            current_map_main_raw = raw;
+        }
+        Err(rr) => {
+            error!("{}", rr);
+            return;
+        }
+    }
+
+    if current_map_main_raw.len() == 0 {
+        warn!("Empty map file {:?}.", cells_path);
         return;
     }
 
-    let current_map_main_data: Vec<CellDataExport> =
-        bincode::deserialize(&current_map_main_raw_ron)
-            .expect("startup_build_map() Error parsing map main.ron String.");
+    let decoded;
+    match decode_gridmap(
+        &current_map_main_raw,
+        GridmapEncoding::from_path(&cells_path),
+    ) {
+        Ok(d) => {
+            decoded = d;
+        }
+        Err(rr) => {
+            error!("{}", MapError::Parse(cells_path, rr.to_string()));
+            return;
+        }
+    }
+    if decoded.source_version < GRIDMAP_FORMAT_VERSION {
+        info!(
+            "Migrated map file {:?} from format version {} to {}.",
+            cells_path, decoded.source_version, GRIDMAP_FORMAT_VERSION
+        );
+    }
+    match map.manifest.map_length {
+        Some(length) => {
+            gridmap.map_length_limit = MapLimits::new(length);
+        }
+        None => {
+            gridmap.map_length_limit = MapLimits::new(decoded.map_limits.length);
+        }
+    }
+    let current_map_main_data = decoded.cells;
+
+    let issues = validate_cells(
+        &gridmap,
+        &current_map_main_data,
+        gridmap.map_length_limit.clone(),
+    );
+    if !issues.is_empty() {
+        warn!(
+            "Map {:?} has {} issues, run validateMap for the full list.",
+            cells_path,
+            issues.len()
+        );
+        for issue in issues.iter().take(MAX_LOGGED_MAP_ISSUES) {
+            warn!("{}", issue);
+        }
+    }
 
+    let mut skipped = 0;
     for cell_data in current_map_main_data.iter() {
-        match &cell_data.item {
-            ItemExport::Cell(item) => {
-                let cell_item_id;
-
-                match gridmap.name_id_map.get(item) {
-                    Some(x) => {
-                        cell_item_id = *x;
-                    }
-                    None => {
-                        warn!("Couldnt find item {:?}", item);
-                        break;
-                    }
-                };
-
-                set_cell.send(AddTile {
-                    id: cell_data.id,
-                    face: cell_data.face.clone(),
-                    orientation: cell_data.orientation.clone(),
-                    tile_type: cell_item_id,
-                    group_instance_id_option: None,
-                    entity: commands.spawn(()).id(),
-                    default_map_spawn: true,
-                    is_detail: cell_data.is_detail,
-                    stamp: 0,
-                });
+        let layer;
+        match map_cell_tile(&mut gridmap, cell_data) {
+            Some(mut add_tile) => {
+                layer = add_tile.layer;
+                add_tile.entity = commands.spawn(()).id();
+                set_cell.send(add_tile);
             }
-            ItemExport::Group(item) => {
-                let id;
-                match gridmap.name_id_map.get(&item.cell) {
-                    Some(n) => {
-                        id = n;
-                    }
-                    None => {
-                        warn!("couildnt find name");
-                        continue;
-                    }
-                }
-
-                set_cell.send(AddTile {
-                    id: cell_data.id,
-                    face: cell_data.face.clone(),
-                    orientation: cell_data.orientation.clone(),
-                    tile_type: *id,
-                    group_instance_id_option: Some(item.group_id),
-                    entity: commands.spawn(()).id(),
-                    default_map_spawn: true,
-                    is_detail: cell_data.is_detail,
-                    stamp: 0,
+            None => {
+                skipped += 1;
+                continue;
+            }
+        }
+        match &cell_data.damage {
+            Some(damage) => {
+                damage_tile.send(DamageTile {
+                    cell: LayerTargetCell {
+                        target: TargetCell {
+                            id: cell_data.id,
+                            face: cell_data.face.clone(),
+                            grid: GridId::MAIN,
+                        },
+                        layer,
+                    },
+                    damage: damage.clone(),
                 });
             }
+            None => {}
         }
     }
 
-    info!("Spawned {} map cells.", current_map_main_data.len());
+    info!(
+        "Spawned {} map cells.",
+        current_map_main_data.len() - skipped
+    );
+    if skipped > 0 {
+        warn!(
+            "Skipped {} map cells with unknown tiles or layers.",
+            skipped
+        );
+    }
+}
+
+/// The tile a map file cell spawns on the main grid, [None] for unknown tiles or layers.
+/// Registers the group instance of group tiles. The entity is left for the caller to spawn.
+pub(crate) fn map_cell_tile(gridmap: &mut Gridmap, cell_data: &CellDataExport) -> Option<AddTile> {
+    let layer;
+    match gridmap.layer_id(&cell_data.layer) {
+        Some(l) => {
+            layer = l;
+        }
+        None => {
+            return None;
+        }
+    }
+    let tile_type;
+    let group_instance_id_option;
+    match &cell_data.item {
+        ItemExport::Cell(item) => {
+            match gridmap.name_id_map.get(item) {
+                Some(x) => {
+                    tile_type = *x;
+                }
+                None => {
+                    return None;
+                }
+            };
+            group_instance_id_option = None;
+        }
+        ItemExport::Group(item) => {
+            match gridmap.name_id_map.get(&item.cell) {
+                Some(n) => {
+                    tile_type = *n;
+                }
+                None => {
+                    return None;
+                }
+            }
+            match gridmap.group_id_map.get(&item.name) {
+                Some(group) => {
+                    let group = *group;
+                    gridmap
+                        .group_instances
+                        .entry(item.group_id)
+                        .or_insert(GroupInstance {
+                            group,
+                            orientation: item.orientation,
+                            tiles: 0,
+                        });
+                }
+                None => {}
+            }
+            gridmap.group_instance_incremental =
+                gridmap.group_instance_incremental.max(item.group_id);
+            group_instance_id_option = Some(item.group_id);
+        }
+    }
+    Some(AddTile {
+        id: cell_data.id,
+        face: cell_data.face.clone(),
+        orientation: cell_data.orientation,
+        tile_type,
+        group_instance_id_option,
+        metadata: cell_data.metadata.clone().unwrap_or_default(),
+        entity: Entity::PLACEHOLDER,
+        default_map_spawn: true,
+        layer,
+        grid: GridId::MAIN,
+        stamp: 0,
+    })
 }
 
 use serde::{Deserialize, Serialize};
@@ -167,7 +293,14 @@ pub struct CellDataExport {
     /// Cell rotation.
     pub orientation: u8,
     pub face: CellFace,
-    pub is_detail: bool,
+    /// Name of the layer of the tile.
+    pub layer: String,
+    /// Damage the tile has taken.
+    #[serde(default)]
+    pub damage: Option<CellDamage>,
+    /// Per instance data, [None] when empty.
+    #[serde(default)]
+    pub metadata: Option<TileMetadata>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -180,4 +313,57 @@ pub struct GroupItem {
     pub name: GroupTypeName,
     pub group_id: u32,
     pub cell: CellTypeName,
+    /// Index of [crate::grid::OrthogonalBases] the group got placed with. Added in version 3.
+    #[serde(default)]
+    pub orientation: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::encode_gridmap;
+    use crate::grid::CellType;
+    use crate::layers::LayerId;
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut gridmap = Gridmap::default();
+        let tile_type = CellTypeId(0);
+        let name = CellTypeName("generic_floor".to_string());
+        gridmap.tile_properties.insert(
+            tile_type,
+            TileProperties {
+                name_id: name.clone(),
+                cell_type: CellType::Floor,
+                ..Default::default()
+            },
+        );
+        gridmap.name_id_map.insert(name.clone(), tile_type);
+        gridmap.id_name_map.insert(tile_type, name);
+        let metadata = TileMetadata {
+            tint: Some([200, 40, 40]),
+            label: Some("Medbay".to_string()),
+            variant: 2,
+        };
+        let id = Vec3Int { x: 3, y: 0, z: -2 };
+        gridmap
+            .set_test_tile(GridId::MAIN, id, CellFace::Floor, tile_type)
+            .metadata = metadata.clone();
+
+        for encoding in [GridmapEncoding::Text, GridmapEncoding::Binary] {
+            let raw = encode_gridmap(
+                &gridmap.export_cells(),
+                gridmap.map_length_limit.clone(),
+                encoding,
+            )
+            .unwrap();
+            let decoded = decode_gridmap(&raw, encoding).unwrap();
+            assert_eq!(decoded.cells.len(), 1);
+            let add_tile = map_cell_tile(&mut gridmap, &decoded.cells[0]).unwrap();
+            assert_eq!(add_tile.id, id);
+            assert_eq!(add_tile.tile_type, tile_type);
+            assert_eq!(add_tile.layer, LayerId::STRUCTURE);
+            assert_eq!(add_tile.metadata, metadata);
+        }
+    }
 }
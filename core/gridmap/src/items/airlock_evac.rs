@@ -8,6 +8,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -69,6 +70,8 @@ pub(crate) fn init_airlock_evac(
         cell_type: CellType::Wall,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
+        durability: Some(TileDurability::default()),
+        door: true,
         ..Default::default()
     });
 }
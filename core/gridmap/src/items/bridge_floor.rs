@@ -7,6 +7,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -113,6 +114,7 @@ pub(crate) fn init_filled_bridge_floor(
         cell_type: CellType::Floor,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -147,6 +149,7 @@ pub(crate) fn init_half_bridge_floor(
         cell_type: CellType::Floor,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -181,6 +184,7 @@ pub(crate) fn init_corner_bridge_floor(
         cell_type: CellType::Floor,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -214,6 +218,7 @@ pub(crate) fn init_corner2_bridge_floor(
         cell_type: CellType::Floor,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -6,6 +6,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileGroup, TileProperties},
     init::{InitTileGroups, InitTileProperties},
 };
@@ -75,6 +76,7 @@ pub(crate) fn init_bridge_wall(
         cell_type: CellType::Wall,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
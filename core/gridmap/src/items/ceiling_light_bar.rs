@@ -15,6 +15,7 @@ use resources::{
 use crate::{
     grid::{CellType, CellTypeName, TileLight, TileProperties},
     init::InitTileProperties,
+    power::LIGHT_POWER_USAGE,
 };
 
 use super::generic_assets::GenericMeshes;
@@ -89,6 +90,7 @@ pub(crate) fn init_ceiling_light_bar(
             light: default_point_light(),
             local_offset: Vec3::new(0., -1.25, -0.),
         }),
+        power_usage: LIGHT_POWER_USAGE,
         ..Default::default()
     });
 }
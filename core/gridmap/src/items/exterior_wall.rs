@@ -4,6 +4,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -62,6 +63,12 @@ pub(crate) fn init_exterior_wall(
         cell_type: CellType::Wall,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
+        durability: Some(TileDurability {
+            damaged: 100.,
+            heavily_damaged: 200.,
+            destroyed: 300.,
+            ..Default::default()
+        }),
         ..Default::default()
     });
 }
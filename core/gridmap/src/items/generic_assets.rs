@@ -21,8 +21,6 @@ pub struct GenericMeshes {
     pub floor_reinforced: Handle<GltfMesh>,
     pub half_diagonal_template_low_reinforced: Handle<GltfMesh>,
     pub half_diagonal_template_high_reinforced: Handle<GltfMesh>,
-    pub large_window_3x3: Handle<GltfMesh>,
-    pub small_window_3x3: Handle<GltfMesh>,
     pub wall_lights: Handle<GltfMesh>,
     pub airlock: Handle<GltfMesh>,
     pub horizontal_light_strip: Handle<GltfMesh>,
@@ -48,8 +46,6 @@ pub(crate) fn init_generic_meshes(
     res.half_diagonal_template_high =
         assets.load("gridmap/half_diagonal_template/half_diagonal_template_high.glb#Mesh0");
     res.half_ceiling = assets.load("gridmap/half_ceiling/half_ceiling.glb#Mesh0");
-    res.large_window_3x3 = assets.load("gridmap/large_windows/3x3/window.glb#Mesh0");
-    res.small_window_3x3 = assets.load("gridmap/small_windows/3x3/window.glb#Mesh0");
     res.wall_low_curbed = assets.load("gridmap/wall_low_curbed/wall.glb#Mesh0");
     res.wall_high_curbed = assets.load("gridmap/wall_high_curbed/wall.glb#Mesh0");
     res.exterior_wall = assets.load("gridmap/wall_exterior/wall.glb#Mesh0");
@@ -9,6 +9,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -48,6 +49,7 @@ pub(crate) fn init_generic_diagonal_ceiling(
             rotation: rot,
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -9,8 +9,10 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
+    pathfinding::Ramp,
 };
 
 use super::generic_assets::GenericMeshes;
@@ -49,6 +51,8 @@ pub(crate) fn init_generic_diagonal_floor(
             rotation: rot,
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
+        ramp: Some(Ramp::Full),
         ..Default::default()
     });
 }
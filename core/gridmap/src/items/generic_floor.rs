@@ -7,6 +7,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -69,6 +70,7 @@ pub(crate) fn init_generic_floor(
         cell_type: CellType::Floor,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
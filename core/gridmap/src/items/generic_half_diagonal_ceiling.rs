@@ -15,6 +15,7 @@ use resources::{
 };
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, FullCell, Gridmap, GroupTypeName, TileGroup, TileProperties},
     init::{InitTileGroups, InitTileProperties},
 };
@@ -102,6 +103,7 @@ pub(crate) fn init_generic_half_diagonal_ceiling_low(
             translation: Vec3::new(-0.05, -0.272, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -144,6 +146,7 @@ pub(crate) fn init_generic_half_diagonal_ceiling_high(
             translation: Vec3::new(-0.05, 0.23, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -15,8 +15,10 @@ use resources::{
 };
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, FullCell, Gridmap, GroupTypeName, TileGroup, TileProperties},
     init::{InitTileGroups, InitTileProperties},
+    pathfinding::Ramp,
 };
 
 use super::generic_assets::GenericMeshes;
@@ -102,6 +104,8 @@ pub(crate) fn init_generic_half_diagonal_floor_low(
             translation: Vec3::new(-0.05, -0.272, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
+        ramp: Some(Ramp::Low),
         ..Default::default()
     });
 }
@@ -144,6 +148,8 @@ pub(crate) fn init_generic_half_diagonal_floor_high(
             translation: Vec3::new(-0.05, 0.23, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
+        ramp: Some(Ramp::High),
         ..Default::default()
     });
 }
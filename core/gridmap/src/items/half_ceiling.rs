@@ -12,6 +12,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -84,6 +85,7 @@ pub(crate) fn init_half_ceiling(
             translation: Vec3::new(-0.05, -0.272, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
@@ -9,6 +9,7 @@ use resources::modes::{is_server, AppMode};
 use crate::{
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
+    layers::LayerId,
 };
 
 use super::{generic_assets::GenericMeshes, wall_flat::WallMaterials};
@@ -70,7 +71,7 @@ pub(crate) fn init_light_strip_horizontal(
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
         x_rotations: vec![0, 16, 10, 22],
-        is_detail: true,
+        layer: LayerId::DETAILS,
         ..Default::default()
     });
 }
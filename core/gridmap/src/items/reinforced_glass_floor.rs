@@ -7,6 +7,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -70,8 +71,11 @@ pub(crate) fn init_reinforced_glass_floor(
         constructable: true,
         mesh_option,
         cell_type: CellType::Floor,
+        non_fov_blocker: true,
         material_option,
         collider: Collider::cuboid(1., 0.2, 1.),
+        durability: Some(TileDurability::default()),
+        window: true,
         ..Default::default()
     });
 }
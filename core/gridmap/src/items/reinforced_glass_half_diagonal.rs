@@ -16,6 +16,7 @@ use resources::{
 };
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, FullCell, Gridmap, GroupTypeName, TileGroup, TileProperties},
     init::{InitTileGroups, InitTileProperties},
 };
@@ -110,6 +111,8 @@ pub(crate) fn init_reinforced_glass_half_diagonal_ceiling_low(
             translation: Vec3::new(-0.05, -0.272, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
+        window: true,
         ..Default::default()
     });
 }
@@ -152,6 +155,8 @@ pub(crate) fn init_reinforced_glass_half_diagonal_ceiling_high(
             translation: Vec3::new(-0.05, 0.23, 0.),
             ..Default::default()
         },
+        durability: Some(TileDurability::default()),
+        window: true,
         ..Default::default()
     });
 }
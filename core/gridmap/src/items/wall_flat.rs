@@ -6,16 +6,11 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
-    grid::{CellType, CellTypeName, TileGroup, TileProperties},
-    init::{InitTileGroups, InitTileProperties},
+    damage::TileDurability,
+    grid::{CellType, CellTypeName, TileProperties},
+    init::InitTileProperties,
 };
 
-use std::collections::HashMap;
-
-use resources::{grid::CellFace, math::Vec3Int};
-
-use crate::grid::{FullCell, Gridmap, GroupTypeName};
-
 use super::generic_assets::GenericMeshes;
 
 #[derive(Default, Resource)]
@@ -86,41 +81,7 @@ pub(crate) fn init_flat_wall(
         cell_type: CellType::Wall,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
+        durability: Some(TileDurability::default()),
         ..Default::default()
     });
 }
-pub(crate) fn init_generic_wall_group(
-    gridmap_data: Res<Gridmap>,
-    mut groups: ResMut<InitTileGroups>,
-) {
-    let mut wall_group = HashMap::new();
-    wall_group.insert(
-        Vec3Int { x: 0, y: 0, z: 0 },
-        FullCell {
-            face: CellFace::default(),
-            orientation: 0,
-            tile_type: *gridmap_data
-                .name_id_map
-                .get(&CellTypeName("wall_flat".to_string()))
-                .unwrap(),
-            entity_option: None,
-        },
-    );
-    wall_group.insert(
-        Vec3Int { x: 0, y: 1, z: 0 },
-        FullCell {
-            face: CellFace::default(),
-            orientation: 0,
-            tile_type: *gridmap_data
-                .name_id_map
-                .get(&CellTypeName("wall_flat".to_string()))
-                .unwrap(),
-            entity_option: None,
-        },
-    );
-
-    groups.groups.push(TileGroup {
-        name_id: GroupTypeName("wall_flat_group".to_string()),
-        map: wall_group,
-    });
-}
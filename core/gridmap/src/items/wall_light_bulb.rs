@@ -13,6 +13,8 @@ use resources::{
 use crate::{
     grid::{CellType, CellTypeName, TileLight, TileProperties},
     init::InitTileProperties,
+    layers::LayerId,
+    power::LIGHT_POWER_USAGE,
 };
 
 use super::{ceiling_light_bar::LightMaterials, generic_assets::GenericMeshes};
@@ -71,12 +73,13 @@ pub(crate) fn init_wall_light_bulb(
         cell_type: CellType::WallDetail,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
-        is_detail: true,
+        layer: LayerId::DETAILS,
         x_rotations: vec![0, 16, 10, 22],
         is_light: Some(TileLight {
             light: default_point_light(),
             local_offset: Vec3::new(0., 0., 0.5),
         }),
+        power_usage: LIGHT_POWER_USAGE,
         ..Default::default()
     });
 }
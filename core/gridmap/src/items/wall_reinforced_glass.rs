@@ -4,6 +4,7 @@ use entity::examine::RichName;
 use resources::modes::{is_server, AppMode};
 
 use crate::{
+    damage::TileDurability,
     grid::{CellType, CellTypeName, TileProperties},
     init::InitTileProperties,
 };
@@ -65,8 +66,16 @@ pub(crate) fn init_wall_reinforced_glass(
         constructable: true,
         mesh_option,
         cell_type: CellType::Wall,
+        non_fov_blocker: true,
+        window: true,
         material_option,
         collider: Collider::cuboid(1., 1., 0.2),
+        durability: Some(TileDurability {
+            damaged: 40.,
+            heavily_damaged: 80.,
+            destroyed: 120.,
+            ..Default::default()
+        }),
         ..Default::default()
     });
 }
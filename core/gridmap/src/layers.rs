@@ -0,0 +1,73 @@
+use bevy::log::{info, warn};
+use bevy::prelude::{Res, ResMut, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Gridmap;
+
+/// Name of the [LayerId::STRUCTURE] layer.
+pub const STRUCTURE_LAYER: &str = "structure";
+/// Name of the [LayerId::DETAILS] layer.
+pub const DETAILS_LAYER: &str = "details";
+
+/// Id of a gridmap layer. Every layer can hold a tile on each face of a cell.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default,
+)]
+pub struct LayerId(pub u8);
+
+impl LayerId {
+    /// Main ship construction parts, like walls, floors and tiles that block other walls.
+    pub const STRUCTURE: LayerId = LayerId(0);
+    /// Details on top of the structure, like posters, small lights and cables.
+    pub const DETAILS: LayerId = LayerId(1);
+}
+
+/// How the tiles of a layer take part in the world.
+#[derive(Clone, Debug)]
+pub struct LayerProperties {
+    /// Name tile definitions and map files refer to the layer with.
+    pub name: String,
+    /// Tiles of the layer are part of the chunk colliders.
+    pub collision: bool,
+    /// Tiles of the layer can block the field of view.
+    pub fov: bool,
+}
+
+impl LayerProperties {
+    pub fn structure() -> Self {
+        Self {
+            name: STRUCTURE_LAYER.to_string(),
+            collision: true,
+            fov: true,
+        }
+    }
+    pub fn details() -> Self {
+        Self {
+            name: DETAILS_LAYER.to_string(),
+            collision: false,
+            fov: false,
+        }
+    }
+}
+
+/// Layers registered by plugins, like pipes or decals. Fill this while building the app.
+#[derive(Default, Resource)]
+pub struct InitGridmapLayers {
+    pub layers: Vec<LayerProperties>,
+}
+
+/// Give registered layers their ids after the built-in ones. Sorted by name so server and clients agree on them.
+pub(crate) fn init_gridmap_layers(mut gridmap: ResMut<Gridmap>, init: Res<InitGridmapLayers>) {
+    let mut layers = init.layers.clone();
+    layers.sort_by(|a, b| a.name.cmp(&b.name));
+    for properties in layers {
+        let name = properties.name.clone();
+        match gridmap.register_layer(properties) {
+            Some(_) => {}
+            None => {
+                warn!("Couldn't register gridmap layer {}.", name);
+            }
+        }
+    }
+    info!("Loaded {} gridmap layers.", gridmap.layer_properties.len());
+}
@@ -4,28 +4,60 @@
 //! "Entity tiles" are when entities are statically placed on the gridmap and take up gridmap space.
 //! Tiles/cells can be examined and are included with FOV calculations.
 //! Sensing abilities are integrated with examinig the gridmap so players can examine additional information about individual cells.
-//! The gridmap is split up into multiple layers that can both exist peacefully on the same tiles. One is a "main" layer which includes the main ship construction parts, like walls and floors and tile entities that block other wall constructions. Whereas the "details1" layer is for added details on each gridmap cell, like posters, small lights, repeated visual effects and indicators etc.
+//! The gridmap is split up into multiple layers that can all exist peacefully on the same tiles. The "structure" layer includes the main ship construction parts, like walls and floors and tile entities that block other wall constructions. The "details" layer is for added details on each gridmap cell, like posters, small lights, repeated visual effects and indicators etc. Plugins can register more layers.
 //! Not all tiles are their own traditional entity with their own components, in fact most tiles that have no expected special behaviour have their data stored in a resource rather than as an individual entity with an ID for performance reasons.
 
+/// Per cell gas simulation.
+pub mod atmospherics;
+/// Save regions of the gridmap as blueprints and paste them elsewhere.
+pub mod blueprint;
+/// Chunk colliders with their tiles merged into as few boxes as possible.
+pub mod colliders;
 /// Configuration to send to newly connected clients.
 pub mod connections;
 pub mod construction;
+/// Tile damage states and destruction.
+pub mod damage;
+/// Tile types and groups defined in RON files.
+pub mod definitions;
+/// Download the map of the server and cache it by hash.
+pub mod download;
 /// Manage gridmap exmination.
 pub mod examine;
+/// Versioned gridmap file format with text and compressed binary variants.
+pub mod format;
 /// Manage gridmap FOV.
 pub mod fov;
+/// Generate random ship layouts as gridmap exports.
+pub mod generator;
 /// Get a spawn position for an item that is free.
 pub mod get_spawn_position;
 /// Client-side graphics handling, such as loading and spawning meshes and textures for gridmap items.
 pub mod graphics;
 /// Core gridmap resources.
 pub mod grid;
+/// Grids other than the main grid that move as their own rigid body, like shuttles.
+pub mod grids;
 /// Initialize gridmap meta data.
 mod init;
 pub mod items;
+/// Named gridmap layers and their collision and FOV behaviour.
+pub mod layers;
+/// Render top-down images of the map per y level.
+pub mod map_image;
 pub mod net;
+/// Find paths over walkable floor cells.
+pub mod pathfinding;
 /// The Bevy plugin of this crate.
 pub mod plugin;
+/// Ship power network over cable tiles.
+pub mod power;
+/// Sections of the gridmap that broke off and drift as their own rigid body.
+pub mod sections;
 /// Manage sensing authorization for gridmap examining.
 mod sensing_ability;
+/// Stream gridmap chunks to clients near their pawn.
+pub mod streaming;
 pub mod sync;
+/// Validate map files against the registered tiles and groups.
+pub mod validate;
@@ -0,0 +1,438 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::Vec3;
+use entity::init::RawEntityRon;
+use resources::{
+    grid::CellFace,
+    maps::{read_map_file, MapError, SelectedMap},
+    math::{world_to_cell_id, Vec3Int},
+};
+
+use crate::{
+    format::{decode_gridmap, GridmapEncoding},
+    grid::{CellIndexes, CellItem, CellType, Gridmap, LayerTargetCell, MapLimits, TileProperties},
+    init::map_cell_tile,
+};
+
+/// Width and height of a cell in pixels.
+pub const MAP_IMAGE_CELL_PIXELS: u32 = 8;
+/// Largest width or height of a map image. Cells get fewer pixels on maps that would exceed it.
+pub const MAX_MAP_IMAGE_SIZE: u32 = 4096;
+
+const BACKGROUND_COLOR: [u8; 4] = [16, 18, 24, 255];
+const MARKER_COLOR: [u8; 4] = [230, 60, 200, 255];
+
+/// How a tile is drawn on map images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MapImageTile {
+    Floor,
+    /// Center tiles that aren't lights, such as half ceilings.
+    Fixture,
+    Wall,
+    Window,
+    Door,
+    Light,
+}
+
+impl MapImageTile {
+    pub fn color(&self) -> [u8; 4] {
+        match self {
+            MapImageTile::Floor => [92, 98, 110, 255],
+            MapImageTile::Fixture => [130, 120, 96, 255],
+            MapImageTile::Wall => [220, 224, 232, 255],
+            MapImageTile::Window => [90, 190, 240, 255],
+            MapImageTile::Door => [240, 150, 40, 255],
+            MapImageTile::Light => [255, 236, 120, 255],
+        }
+    }
+    pub fn from_properties(properties: &TileProperties) -> Self {
+        if properties.is_light.is_some() {
+            MapImageTile::Light
+        } else if properties.door {
+            MapImageTile::Door
+        } else if properties.window {
+            MapImageTile::Window
+        } else {
+            match properties.cell_type {
+                CellType::Floor => MapImageTile::Floor,
+                CellType::Wall | CellType::WallDetail => MapImageTile::Wall,
+                CellType::Center => MapImageTile::Fixture,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MapImageError {
+    /// Width and height in cells of a map that doesn't fit in [MAX_MAP_IMAGE_SIZE].
+    TooLarge(u32, u32),
+    Encode(String),
+    Write(PathBuf, String),
+}
+
+impl fmt::Display for MapImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapImageError::TooLarge(width, height) => write!(
+                f,
+                "Map of {}x{} cells doesn't fit in a {} pixel map image.",
+                width, height, MAX_MAP_IMAGE_SIZE
+            ),
+            MapImageError::Encode(rr) => write!(f, "Couldn't encode map image: {}", rr),
+            MapImageError::Write(path, rr) => {
+                write!(f, "Couldn't write map image {:?}: {}", path, rr)
+            }
+        }
+    }
+}
+
+/// Top-down RGBA image of a single y level.
+pub struct MapLevelImage {
+    pub y: i16,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl MapLevelImage {
+    fn new(y: i16, width: u32, height: u32) -> Self {
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for _ in 0..width * height {
+            pixels.extend_from_slice(&BACKGROUND_COLOR);
+        }
+        Self {
+            y,
+            width,
+            height,
+            pixels,
+        }
+    }
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                let i = ((py * self.width + px) * 4) as usize;
+                self.pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+    pub fn encode_png(&self) -> Result<Vec<u8>, MapImageError> {
+        let mut data = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder
+                .write_header()
+                .map_err(|rr| MapImageError::Encode(rr.to_string()))?;
+            writer
+                .write_image_data(&self.pixels)
+                .map_err(|rr| MapImageError::Encode(rr.to_string()))?;
+        }
+        Ok(data)
+    }
+}
+
+/// Positions of the entities of a map, to mark on its images.
+pub fn read_entity_markers(map: &SelectedMap) -> Result<Vec<Vec3>, MapError> {
+    let path = map.entities_path();
+    let raw = read_map_file(&path)?;
+    if raw.is_empty() {
+        return Ok(vec![]);
+    }
+    let entities: Vec<RawEntityRon> = ron::from_str(&String::from_utf8_lossy(&raw))
+        .map_err(|rr| MapError::Parse(path.clone(), rr.to_string()))?;
+    Ok(entities.iter().map(|e| e.translation).collect())
+}
+
+/// Read the cells of a map straight into a gridmap, for tools that run without the gridmap systems.
+/// Returns the amount of tiles loaded, unknown tiles are left out.
+pub fn load_map_tiles(gridmap: &mut Gridmap, map: &SelectedMap) -> Result<usize, MapError> {
+    let path = map.cells_path();
+    let raw = read_map_file(&path)?;
+    if raw.is_empty() {
+        return Ok(0);
+    }
+    let decoded = decode_gridmap(&raw, GridmapEncoding::from_path(&path))
+        .map_err(|rr| MapError::Parse(path.clone(), rr.to_string()))?;
+    gridmap.map_length_limit =
+        MapLimits::new(map.manifest.map_length.unwrap_or(decoded.map_limits.length));
+    let mut loaded = 0;
+    for cell_data in decoded.cells.iter() {
+        let add_tile;
+        match map_cell_tile(gridmap, cell_data) {
+            Some(a) => {
+                add_tile = a;
+            }
+            None => {
+                continue;
+            }
+        }
+        match gridmap.insert_tile(
+            LayerTargetCell {
+                target: add_tile.target(),
+                layer: add_tile.layer,
+            },
+            CellItem {
+                tile_type: add_tile.tile_type,
+                orientation: add_tile.orientation,
+                group_id_option: add_tile.group_instance_id_option,
+                metadata: add_tile.metadata,
+                ..Default::default()
+            },
+        ) {
+            Ok(_) => {}
+            Err(_) => {
+                continue;
+            }
+        }
+        loaded += 1;
+    }
+    Ok(loaded)
+}
+
+/// Pixels per cell for a map of this many cells along its longest side, [None] if even one pixel per cell is too large.
+pub fn map_image_cell_pixels(cells: u32) -> Option<u32> {
+    if cells > MAX_MAP_IMAGE_SIZE {
+        return None;
+    }
+    Some((MAX_MAP_IMAGE_SIZE / cells.max(1)).min(MAP_IMAGE_CELL_PIXELS))
+}
+
+/// Render an image per y level that has cells. All images share the same bounds so they line up.
+/// Entity markers are drawn on the highest level at or below them.
+pub fn render_map_images(
+    gridmap: &Gridmap,
+    markers: &[Vec3],
+) -> Result<Vec<MapLevelImage>, MapImageError> {
+    let mut levels: BTreeMap<i16, Vec<(Vec3Int, CellFace, MapImageTile)>> = BTreeMap::new();
+    for grid in gridmap.layers.values() {
+        for (chunk_i, chunk) in grid.iter() {
+            for (cell_i, cell) in chunk.cells.iter() {
+                let id;
+                match gridmap.get_id(CellIndexes {
+                    chunk: *chunk_i,
+                    cell: *cell_i,
+                }) {
+                    Some(i) => {
+                        id = i;
+                    }
+                    None => {
+                        continue;
+                    }
+                }
+                for (item, face) in cell.get_items() {
+                    match gridmap.tile_properties.get(&item.tile_type) {
+                        Some(properties) => {
+                            levels.entry(id.y).or_default().push((
+                                id,
+                                face,
+                                MapImageTile::from_properties(properties),
+                            ));
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+    if levels.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (mut min_x, mut min_z, mut max_x, mut max_z) = (i16::MAX, i16::MAX, i16::MIN, i16::MIN);
+    for tiles in levels.values() {
+        for (id, _, _) in tiles.iter() {
+            min_x = min_x.min(id.x);
+            min_z = min_z.min(id.z);
+            max_x = max_x.max(id.x);
+            max_z = max_z.max(id.z);
+        }
+    }
+    let cells_x = (max_x as i32 - min_x as i32 + 1) as u32;
+    let cells_z = (max_z as i32 - min_z as i32 + 1) as u32;
+    let pixels;
+    match map_image_cell_pixels(cells_x.max(cells_z)) {
+        Some(p) => {
+            pixels = p;
+        }
+        None => {
+            return Err(MapImageError::TooLarge(cells_x, cells_z));
+        }
+    }
+    // Sizes keep at least a pixel when cells get small.
+    let wall_pixels = (pixels / 4).max(1);
+    let (fixture_offset, fixture_pixels) = (pixels / 8, (pixels * 3 / 4).max(1));
+    let (dot_offset, dot_pixels) = (pixels / 4, (pixels / 2).max(1));
+    let width = cells_x * pixels;
+    let height = cells_z * pixels;
+    let origin = |x: i16, z: i16| {
+        (
+            (x as i32 - min_x as i32) as u32 * pixels,
+            (z as i32 - min_z as i32) as u32 * pixels,
+        )
+    };
+
+    let level_ys: BTreeSet<i16> = levels.keys().cloned().collect();
+    let mut images = vec![];
+    for (y, mut tiles) in levels {
+        let mut image = MapLevelImage::new(y, width, height);
+        // Floors first, lights last.
+        tiles.sort_by_key(|(_, face, tile)| (*face != CellFace::Floor, *tile));
+        for (id, face, tile) in tiles {
+            let (px, pz) = origin(id.x, id.z);
+            let color = tile.color();
+            match (tile, face) {
+                (MapImageTile::Light, _) => {
+                    image.fill(
+                        px + dot_offset,
+                        pz + dot_offset,
+                        dot_pixels,
+                        dot_pixels,
+                        color,
+                    );
+                }
+                (_, CellFace::Floor) | (_, CellFace::Ceiling) => {
+                    image.fill(px, pz, pixels, pixels, color);
+                }
+                (_, CellFace::Center) => {
+                    image.fill(
+                        px + fixture_offset,
+                        pz + fixture_offset,
+                        fixture_pixels,
+                        fixture_pixels,
+                        color,
+                    );
+                }
+                (_, CellFace::FrontWall) => {
+                    image.fill(px, pz + pixels - wall_pixels, pixels, wall_pixels, color);
+                }
+                (_, CellFace::BackWall) => {
+                    image.fill(px, pz, pixels, wall_pixels, color);
+                }
+                (_, CellFace::RightWall) => {
+                    image.fill(px + pixels - wall_pixels, pz, wall_pixels, pixels, color);
+                }
+                (_, CellFace::LeftWall) => {
+                    image.fill(px, pz, wall_pixels, pixels, color);
+                }
+            }
+        }
+        for marker in markers.iter() {
+            let cell = world_to_cell_id(*marker);
+            let marker_level = level_ys
+                .range(..=cell.y)
+                .next_back()
+                .or(level_ys.iter().next())
+                .cloned();
+            if marker_level != Some(y)
+                || cell.x < min_x
+                || cell.x > max_x
+                || cell.z < min_z
+                || cell.z > max_z
+            {
+                continue;
+            }
+            let (px, pz) = origin(cell.x, cell.z);
+            image.fill(
+                px + dot_offset,
+                pz + dot_offset,
+                dot_pixels,
+                dot_pixels,
+                MARKER_COLOR,
+            );
+        }
+        images.push(image);
+    }
+    Ok(images)
+}
+
+/// Write the images of all y levels as `level_<y>.png` into a directory.
+pub fn export_map_images(
+    gridmap: &Gridmap,
+    markers: &[Vec3],
+    directory: &Path,
+) -> Result<Vec<PathBuf>, MapImageError> {
+    fs::create_dir_all(directory)
+        .map_err(|rr| MapImageError::Write(directory.to_path_buf(), rr.to_string()))?;
+    let mut paths = vec![];
+    for image in render_map_images(gridmap, markers)? {
+        let path = directory.join(format!("level_{}.png", image.y));
+        let data = image.encode_png()?;
+        fs::write(&path, data).map_err(|rr| MapImageError::Write(path.clone(), rr.to_string()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Directory the images of a map get exported to.
+pub fn map_images_directory(map: &SelectedMap) -> PathBuf {
+    map.directory.join("images")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellTypeId, CellTypeName};
+    use resources::grid::GridId;
+
+    fn pixel(image: &MapLevelImage, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * image.width + x) * 4) as usize;
+        image.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_render_level_pixels() {
+        let mut gridmap = Gridmap::default();
+        let tiles = [
+            ("floor", CellType::Floor, false),
+            ("wall", CellType::Wall, false),
+            ("window", CellType::Wall, true),
+        ];
+        for (i, (name, cell_type, window)) in tiles.into_iter().enumerate() {
+            gridmap.tile_properties.insert(
+                CellTypeId(i as u16),
+                TileProperties {
+                    name_id: CellTypeName(name.to_string()),
+                    cell_type,
+                    window,
+                    ..Default::default()
+                },
+            );
+        }
+        let left = Vec3Int { x: 0, y: 0, z: 0 };
+        let right = Vec3Int { x: 1, y: 0, z: 0 };
+        gridmap.set_test_tile(GridId::MAIN, left, CellFace::Floor, CellTypeId(0));
+        gridmap.set_test_tile(GridId::MAIN, right, CellFace::Floor, CellTypeId(0));
+        gridmap.set_test_tile(GridId::MAIN, left, CellFace::RightWall, CellTypeId(1));
+        gridmap.set_test_tile(GridId::MAIN, right, CellFace::FrontWall, CellTypeId(2));
+
+        let images = render_map_images(&gridmap, &[Vec3::new(0., 0., 0.)]).unwrap();
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        let cell = MAP_IMAGE_CELL_PIXELS;
+        assert_eq!((image.width, image.height), (2 * cell, cell));
+
+        let floor = MapImageTile::Floor.color();
+        assert_eq!(pixel(image, 0, 0), floor);
+        assert_eq!(pixel(image, cell + 1, 1), floor);
+        assert_eq!(pixel(image, cell - 1, 1), MapImageTile::Wall.color());
+        assert_eq!(
+            pixel(image, cell + 1, cell - 1),
+            MapImageTile::Window.color()
+        );
+        assert_eq!(pixel(image, cell / 2, cell / 2), MARKER_COLOR);
+    }
+
+    #[test]
+    fn test_cell_pixels_fit_max_size() {
+        assert_eq!(map_image_cell_pixels(10), Some(MAP_IMAGE_CELL_PIXELS));
+        let pixels = map_image_cell_pixels(1000).unwrap();
+        assert!(pixels * 1000 <= MAX_MAP_IMAGE_SIZE);
+        assert_eq!(map_image_cell_pixels(MAX_MAP_IMAGE_SIZE), Some(1));
+        assert_eq!(map_image_cell_pixels(MAX_MAP_IMAGE_SIZE + 1), None);
+    }
+}
@@ -1,34 +1,66 @@
 use bevy::prelude::Vec3;
+use resources::grid::{GridId, TargetCell};
 use resources::math::Vec3Int;
 use serde::Deserialize;
 use serde::Serialize;
 use typename::TypeName;
 
+use crate::blueprint::Blueprint;
+use crate::damage::TileDamageState;
 use crate::grid::CellIds;
 use crate::grid::CellTypeId;
 use crate::grid::CellTypeName;
 use crate::grid::LayerTargetCell;
+use crate::grid::MapLimits;
 use crate::grid::TargetCellWithOrientationWType;
+use crate::grid::TileMetadata;
+use crate::grids::GridMotion;
 
 /// Gets serialized and sent over the net, this is the client message.
 #[derive(Serialize, Deserialize, Debug, Clone, TypeName)]
 
 pub enum GridmapClientMessage {
-    ExamineMap(i16, i16, i16),
+    ExamineMap(TargetCell),
     ConstructCells(ConstructCell),
     DeconstructCells(DeconstructCell),
+    /// The client doesn't have the map with the configured hash cached.
+    RequestMapDownload,
+    /// Undo the last construction operation of the player.
+    UndoConstruction,
+    /// Redo the last undone construction operation of the player.
+    RedoConstruction,
+    /// Mark a corner of the region to save as blueprint.
+    SelectBlueprintCorner(SelectBlueprintCorner),
+    PasteBlueprint(PasteBlueprint),
+}
+/// A corner of the region to save as blueprint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelectBlueprintCorner {
+    pub grid: GridId,
+    pub id: Vec3Int,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasteBlueprint {
+    pub name: String,
+    pub grid: GridId,
+    pub origin: Vec3Int,
+    /// Index of [crate::grid::OrthogonalBases].
+    pub orientation: u8,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConstructCell {
     pub cells: Vec<TargetCellWithOrientationWType>,
     // For when using groups to pass an absolute coordinate.
     pub group_option: Option<Vec3Int>,
+    /// Index of [crate::grid::OrthogonalBases] the group gets placed with.
+    pub group_orientation: u8,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NewCell {
     pub cell: LayerTargetCell,
     pub orientation: u8,
     pub tile_type: CellTypeId,
+    pub metadata: TileMetadata,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DeconstructCell {
@@ -43,7 +75,53 @@ pub enum GridmapServerMessage {
     AddCell(NewCell),
     FireProjectile(ProjectileData),
     ConfigOrderedCellsMain(Vec<CellTypeName>),
+    ConfigMapLimits(MapLimits),
     GhostCellType(CellIds),
+    /// Blueprint selected with the construction tool, for the ghost preview.
+    GhostBlueprint(Blueprint),
+    /// Full contents of a chunk that came in range of the client's pawn.
+    LoadChunk(ChunkSnapshot),
+    /// A chunk that went out of range of the client's pawn.
+    UnloadChunk(usize),
+    /// Content hash of the map the server is running.
+    ConfigMapHash(String),
+    /// Part of a map download requested with [GridmapClientMessage::RequestMapDownload].
+    MapDownloadPart(MapDownloadPart),
+    /// Tiles that gained or lost power, such as lights that switch on or off.
+    TilesPowered(Vec<(LayerTargetCell, bool)>),
+    /// Tiles that changed damaged state.
+    TileDamageStates(Vec<(LayerTargetCell, TileDamageState)>),
+    /// A grid other than the main grid with its cells. Cells added later arrive as [GridmapServerMessage::AddCell].
+    SpawnGrid(GridSnapshot),
+    /// A grid that lost all its cells.
+    DespawnGrid(GridId),
+}
+
+/// Gets serialized and sent over the net unreliably, this is the server message.
+#[derive(Serialize, Deserialize, Debug, Clone, TypeName)]
+pub enum GridmapUnreliableServerMessage {
+    /// Current motion of all grids other than the main grid.
+    GridMotions(Vec<(GridId, GridMotion)>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GridSnapshot {
+    pub grid: GridId,
+    pub motion: GridMotion,
+    pub cells: Vec<NewCell>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapDownloadPart {
+    pub index: u32,
+    pub total: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChunkSnapshot {
+    pub chunk: usize,
+    pub cells: Vec<NewCell>,
 }
 
 /// Contains information about the projectile and its visual graphics.
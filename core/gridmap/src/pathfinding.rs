@@ -0,0 +1,440 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use bevy::prelude::{EventReader, Res, ResMut, Resource, Vec3};
+use resources::{
+    grid::{CellFace, GridId, TargetCell},
+    math::Vec3Int,
+};
+use serde::Deserialize;
+
+use crate::grid::{AddTile, CellItem, Gridmap, LayerTargetCell, OrthogonalBases, RemoveTile};
+use crate::layers::LayerId;
+
+/// Cost of moving to a horizontally adjacent cell.
+pub const STEP_COST: u32 = 10;
+/// Cost of moving along a diagonal floor.
+pub const RAMP_STEP_COST: u32 = 14;
+/// Uphill direction of diagonal floors without rotation.
+const RAMP_UPHILL: Vec3 = Vec3::NEG_X;
+
+const HORIZONTAL: [(Vec3Int, CellFace); 4] = [
+    (Vec3Int { x: 0, y: 0, z: 1 }, CellFace::FrontWall),
+    (Vec3Int { x: 0, y: 0, z: -1 }, CellFace::BackWall),
+    (Vec3Int { x: 1, y: 0, z: 0 }, CellFace::RightWall),
+    (Vec3Int { x: -1, y: 0, z: 0 }, CellFace::LeftWall),
+];
+const UP: Vec3Int = Vec3Int { x: 0, y: 1, z: 0 };
+
+/// How far a diagonal floor rises within its cell. Tiles with a ramp are walked along their slope.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ramp {
+    /// Rises a full level.
+    Full,
+    /// Lower half of a two cell diagonal floor.
+    Low,
+    /// Upper half of a two cell diagonal floor.
+    High,
+}
+
+fn get_item(gridmap: &Gridmap, id: Vec3Int, face: CellFace) -> Option<CellItem> {
+    gridmap.get_cell(LayerTargetCell {
+        target: TargetCell {
+            id,
+            face,
+            grid: GridId::MAIN,
+        },
+        layer: LayerId::STRUCTURE,
+    })
+}
+
+/// The diagonal floor in the cell, if any, with its horizontal uphill direction.
+fn get_ramp(gridmap: &Gridmap, id: Vec3Int) -> Option<(Ramp, Vec3Int)> {
+    let item = get_item(gridmap, id, CellFace::Center)?;
+    let ramp = gridmap.tile_properties.get(&item.tile_type)?.ramp?;
+    let rotation = OrthogonalBases::default().bases[item.orientation as usize % 24];
+    // The surface runs from the lower end up to the uphill end.
+    let surface = rotation.mul_vec3(RAMP_UPHILL + Vec3::Y).round();
+    let uphill;
+    if surface.y > 0. {
+        uphill = surface;
+    } else if surface.y < 0. {
+        uphill = -surface;
+    } else {
+        return None;
+    }
+    Some((
+        ramp,
+        Vec3Int {
+            x: uphill.x as i16,
+            y: 0,
+            z: uphill.z as i16,
+        },
+    ))
+}
+
+/// Whether a tile on a wall face blocks movement through it.
+fn wall_blocks(gridmap: &Gridmap, id: Vec3Int, face: CellFace) -> bool {
+    match get_item(gridmap, id, face) {
+        Some(item) => match gridmap.tile_properties.get(&item.tile_type) {
+            Some(properties) => properties.combat_obstacle,
+            None => true,
+        },
+        None => false,
+    }
+}
+
+/// Cells with a floor you can stand on, without an obstacle in their center.
+fn is_floor_walkable(gridmap: &Gridmap, id: Vec3Int) -> bool {
+    if get_item(gridmap, id, CellFace::Floor).is_none() {
+        return false;
+    }
+    match get_item(gridmap, id, CellFace::Center) {
+        Some(item) => match gridmap.tile_properties.get(&item.tile_type) {
+            Some(properties) => !properties.combat_obstacle,
+            None => false,
+        },
+        None => true,
+    }
+}
+
+fn is_walkable(gridmap: &Gridmap, id: Vec3Int) -> bool {
+    get_ramp(gridmap, id).is_some() || is_floor_walkable(gridmap, id)
+}
+
+/// Cells reachable in one step from a cell with their costs, [None] if the cell is not walkable.
+pub fn walkable_neighbours(gridmap: &Gridmap, id: Vec3Int) -> Option<Vec<(Vec3Int, u32)>> {
+    let mut neighbours = vec![];
+    match get_ramp(gridmap, id) {
+        Some((ramp, uphill)) => {
+            let down = id - uphill;
+            let up = match ramp {
+                Ramp::Low => id + uphill,
+                Ramp::Full | Ramp::High => id + uphill + UP,
+            };
+            let down_face = HORIZONTAL
+                .iter()
+                .find(|(d, _)| *d == Vec3Int::default() - uphill);
+            let up_face = HORIZONTAL.iter().find(|(d, _)| *d == uphill);
+            match down_face {
+                Some((_, face)) => {
+                    if !wall_blocks(gridmap, id, face.clone()) && is_walkable(gridmap, down) {
+                        neighbours.push((down, RAMP_STEP_COST));
+                    }
+                }
+                None => {}
+            }
+            match up_face {
+                Some((_, face)) => {
+                    let wall_cell = match ramp {
+                        Ramp::Low => id,
+                        Ramp::Full | Ramp::High => id + UP,
+                    };
+                    if !wall_blocks(gridmap, wall_cell, face.clone()) && is_walkable(gridmap, up) {
+                        neighbours.push((up, RAMP_STEP_COST));
+                    }
+                }
+                None => {}
+            }
+            return Some(neighbours);
+        }
+        None => {}
+    }
+    if !is_floor_walkable(gridmap, id) {
+        return None;
+    }
+    for (direction, face) in HORIZONTAL.iter() {
+        let neighbour = id + *direction;
+        if wall_blocks(gridmap, id, face.clone()) {
+            continue;
+        }
+        if is_floor_walkable(gridmap, neighbour) {
+            neighbours.push((neighbour, STEP_COST));
+            continue;
+        }
+        // Diagonal floors are entered from their ends only.
+        for ramp_cell in [neighbour, neighbour - UP] {
+            match get_ramp(gridmap, ramp_cell) {
+                Some((ramp, uphill)) => {
+                    let bottom = ramp_cell - uphill;
+                    let top = match ramp {
+                        Ramp::Low => ramp_cell + uphill,
+                        Ramp::Full | Ramp::High => ramp_cell + uphill + UP,
+                    };
+                    if bottom == id || top == id {
+                        neighbours.push((ramp_cell, RAMP_STEP_COST));
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+    Some(neighbours)
+}
+
+/// Lower bound of the cost between two cells, the heuristic of [NavGrid::find_path].
+/// Every step moves one cell horizontally and at most one level, changing levels costs [RAMP_STEP_COST].
+pub fn estimate_cost(from: Vec3Int, to: Vec3Int) -> u32 {
+    let horizontal = (from.x - to.x).unsigned_abs() as u32 + (from.z - to.z).unsigned_abs() as u32;
+    let vertical = (from.y - to.y).unsigned_abs() as u32;
+    horizontal.max(vertical) * STEP_COST + vertical * (RAMP_STEP_COST - STEP_COST)
+}
+
+/// [Vec3Int] isn't ordered, the open sets of the searches store cells as tuples.
+fn heap_key(id: Vec3Int) -> (i16, i16, i16) {
+    (id.x, id.y, id.z)
+}
+fn from_heap_key(key: (i16, i16, i16)) -> Vec3Int {
+    Vec3Int {
+        x: key.0,
+        y: key.1,
+        z: key.2,
+    }
+}
+
+/// Walkable cells of the main gridmap layer and how they connect. Kept up to date with tile changes on the server.
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    pub nodes: HashMap<Vec3Int, Vec<(Vec3Int, u32)>>,
+}
+
+impl NavGrid {
+    /// Recompute the nodes around a changed cell, including the levels above and below for diagonal floors.
+    pub fn update_around(&mut self, gridmap: &Gridmap, changed: &HashSet<Vec3Int>) {
+        let mut dirty = HashSet::new();
+        for id in changed.iter() {
+            for x in -2..=2 {
+                for y in -1..=1 {
+                    for z in -2..=2 {
+                        dirty.insert(*id + Vec3Int { x, y, z });
+                    }
+                }
+            }
+        }
+        for id in dirty {
+            match walkable_neighbours(gridmap, id) {
+                Some(neighbours) => {
+                    self.nodes.insert(id, neighbours);
+                }
+                None => {
+                    self.nodes.remove(&id);
+                }
+            }
+        }
+    }
+    pub fn is_walkable(&self, id: &Vec3Int) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// Shortest path with A*, including both ends.
+    pub fn find_path(&self, from: Vec3Int, to: Vec3Int) -> Option<Vec<Vec3Int>> {
+        if !self.is_walkable(&from) || !self.is_walkable(&to) {
+            return None;
+        }
+        let heuristic = |id: Vec3Int| estimate_cost(id, to);
+        let mut open = BinaryHeap::new();
+        let mut costs: HashMap<Vec3Int, u32> = HashMap::new();
+        let mut came_from: HashMap<Vec3Int, Vec3Int> = HashMap::new();
+        costs.insert(from, 0);
+        open.push(Reverse((heuristic(from), heap_key(from))));
+        while let Some(Reverse((_, key))) = open.pop() {
+            let current = from_heap_key(key);
+            if current == to {
+                let mut path = vec![current];
+                let mut step = current;
+                while let Some(previous) = came_from.get(&step) {
+                    path.push(*previous);
+                    step = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let cost = costs[&current];
+            for (neighbour, step_cost) in self.nodes.get(&current).into_iter().flatten() {
+                let new_cost = cost + step_cost;
+                if costs.get(neighbour).map_or(true, |c| new_cost < *c) {
+                    costs.insert(*neighbour, new_cost);
+                    came_from.insert(*neighbour, current);
+                    open.push(Reverse((
+                        new_cost + heuristic(*neighbour),
+                        heap_key(*neighbour),
+                    )));
+                }
+            }
+        }
+        None
+    }
+
+    /// Costs towards a target from every cell within `max_cost`, for many agents heading to the same place.
+    pub fn flow_field(&self, target: Vec3Int, max_cost: u32) -> FlowField {
+        let mut field = FlowField::default();
+        if !self.is_walkable(&target) {
+            return field;
+        }
+        let mut open = BinaryHeap::new();
+        field.cells.insert(target, (0, target));
+        open.push(Reverse((0, heap_key(target))));
+        while let Some(Reverse((cost, key))) = open.pop() {
+            let current = from_heap_key(key);
+            if field.cells[&current].0 < cost {
+                continue;
+            }
+            // Edges are symmetric except for blocked diagonal floor ends, walk them backwards.
+            for (neighbour, step_cost) in self.nodes.get(&current).into_iter().flatten() {
+                let new_cost = cost + step_cost;
+                if new_cost > max_cost {
+                    continue;
+                }
+                let reaches_current = self
+                    .nodes
+                    .get(neighbour)
+                    .map_or(false, |n| n.iter().any(|(id, _)| *id == current));
+                if !reaches_current {
+                    continue;
+                }
+                if field
+                    .cells
+                    .get(neighbour)
+                    .map_or(true, |(c, _)| new_cost < *c)
+                {
+                    field.cells.insert(*neighbour, (new_cost, current));
+                    open.push(Reverse((new_cost, heap_key(*neighbour))));
+                }
+            }
+        }
+        field
+    }
+}
+
+/// Result of [NavGrid::flow_field].
+#[derive(Default, Clone, Debug)]
+pub struct FlowField {
+    /// Cost to the target and the next cell towards it.
+    pub cells: HashMap<Vec3Int, (u32, Vec3Int)>,
+}
+
+impl FlowField {
+    /// The next cell to move to from a cell.
+    pub fn next(&self, from: &Vec3Int) -> Option<Vec3Int> {
+        self.cells.get(from).map(|(_, next)| *next)
+    }
+    /// Follow the field from a cell to the target.
+    pub fn path_from(&self, from: Vec3Int) -> Option<Vec<Vec3Int>> {
+        let mut path = vec![from];
+        let mut current = from;
+        loop {
+            let (cost, next) = *self.cells.get(&current)?;
+            if cost == 0 {
+                return Some(path);
+            }
+            path.push(next);
+            current = next;
+        }
+    }
+}
+
+/// Update the navigation grid with the tiles that got added or removed this tick.
+pub(crate) fn update_nav_grid(
+    mut add_events: EventReader<AddTile>,
+    mut remove_events: EventReader<RemoveTile>,
+    gridmap: Res<Gridmap>,
+    mut nav: ResMut<NavGrid>,
+) {
+    let mut changed = HashSet::new();
+    for event in add_events.read() {
+        if gridmap.layer_collides(event.layer) && event.grid == GridId::MAIN {
+            changed.insert(event.id);
+        }
+    }
+    for event in remove_events.read() {
+        if gridmap.layer_collides(event.cell.layer) && event.cell.target.grid == GridId::MAIN {
+            changed.insert(event.cell.target.id);
+        }
+    }
+    if !changed.is_empty() {
+        nav.update_around(&gridmap, &changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellTypeId, CellTypeName, TileProperties};
+
+    #[test]
+    fn test_path_around_wall() {
+        let mut gridmap = Gridmap::default();
+        for (i, name) in ["floor", "wall"].iter().enumerate() {
+            gridmap
+                .name_id_map
+                .insert(CellTypeName(name.to_string()), CellTypeId(i as u16));
+            gridmap
+                .id_name_map
+                .insert(CellTypeId(i as u16), CellTypeName(name.to_string()));
+            gridmap.tile_properties.insert(
+                CellTypeId(i as u16),
+                TileProperties {
+                    name_id: CellTypeName(name.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut changed = HashSet::new();
+        for x in 0..3 {
+            for z in 0..3 {
+                let id = Vec3Int { x, y: 0, z };
+                gridmap.set_test_tile(GridId::MAIN, id, CellFace::Floor, CellTypeId(0));
+                changed.insert(id);
+            }
+        }
+        // Wall between the left and middle column, except at the far end.
+        for z in 0..2 {
+            set(
+                &mut gridmap,
+                Vec3Int { x: 0, y: 0, z },
+                CellFace::RightWall,
+                1,
+            );
+        }
+        let mut nav = NavGrid::default();
+        nav.update_around(&gridmap, &changed);
+
+        let from = Vec3Int { x: 0, y: 0, z: 0 };
+        let to = Vec3Int { x: 1, y: 0, z: 0 };
+        let path = nav.find_path(from, to).unwrap();
+        assert_eq!(path.len(), 6);
+        let field = nav.flow_field(to, 1000);
+        assert_eq!(field.path_from(from).unwrap(), path);
+    }
+
+    #[test]
+    fn test_path_up_ramp() {
+        let mut gridmap = Gridmap::default();
+        for (i, ramp) in [None, Some(Ramp::Full)].into_iter().enumerate() {
+            gridmap.tile_properties.insert(
+                CellTypeId(i as u16),
+                TileProperties {
+                    ramp,
+                    ..Default::default()
+                },
+            );
+        }
+        // Without rotation the ramp rises towards negative x.
+        let bottom = Vec3Int { x: 2, y: 0, z: 0 };
+        let ramp = Vec3Int { x: 1, y: 0, z: 0 };
+        let top = Vec3Int { x: 0, y: 1, z: 0 };
+        gridmap.set_test_tile(GridId::MAIN, bottom, CellFace::Floor, CellTypeId(0));
+        gridmap.set_test_tile(GridId::MAIN, ramp, CellFace::Center, CellTypeId(1));
+        gridmap.set_test_tile(GridId::MAIN, top, CellFace::Floor, CellTypeId(0));
+        let mut nav = NavGrid::default();
+        nav.update_around(&gridmap, &HashSet::from([bottom, ramp, top]));
+
+        assert_eq!(nav.find_path(bottom, top).unwrap(), vec![bottom, ramp, top]);
+        let field = nav.flow_field(top, 1000);
+        let cost = field.cells[&bottom].0;
+        assert_eq!(cost, 2 * RAMP_STEP_COST);
+        assert!(estimate_cost(bottom, top) <= cost);
+    }
+}
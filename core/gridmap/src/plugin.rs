@@ -1,29 +1,48 @@
 use std::time::Duration;
 
 use crate::{
+    atmospherics::{
+        simulate_atmospherics, update_atmospherics_map, Atmospherics, ATMOSPHERICS_TICK,
+    },
+    colliders::{rebuild_chunk_colliders, ChunkColliderRebuilds},
     connections::configure,
     construction::{
         apply_ghost_rotation, change_ghost_tile_request, client_mouse_click_input,
-        input_yplane_position, insert_plane_resource, load_plane_asset, move_ylevel_plane,
-        register_input, select_cell_in_front_camera, set_yplane_position, show_ylevel_plane,
-        update_ghost_cell, ConstructionCellSelectionChanged, ConstructionSelection,
-        GridmapConstructionState, NewGhostBuffer, SetYPlanePosition, YPlaneSet,
+        follow_construction_grid, input_blueprint_corner, input_construction_grid,
+        input_construction_history, input_placement_mode, input_yplane_position,
+        insert_plane_resource, load_plane_asset, move_ylevel_plane, register_input,
+        select_cell_in_front_camera, set_yplane_position, show_ylevel_plane, update_ghost_cell,
+        ConstructionCellSelectionChanged, ConstructionSelection, GridmapConstructionState,
+        NewGhostBuffer, SetYPlanePosition, YPlaneSet,
+    },
+    damage::{
+        damage_tiles, receive_tile_damage_states, DamageTile, TileDamageStates, TileDestroyed,
+    },
+    definitions::{load_group_definitions, load_tile_definitions},
+    download::{
+        forget_disconnected_downloads, invalidate_served_map, map_download_ui,
+        receive_map_download, receive_map_hash, send_map_download_parts, start_map_downloads,
+        MapBaseline, MapDownloadProgress, MapDownloads, ServedMap,
     },
     examine::{
         examine_grid, examine_map, examine_map_abilities, examine_map_health, finalize_examine_map,
         finalize_grid_examine_input, incoming_messages, set_action_header_name,
         GridmapExamineMessages, InputExamineMap,
     },
-    fov::ProjectileFOV,
-    graphics::{set_cell_graphics, CellGraphicsBuffer},
+    graphics::{set_cell_graphics, CellGraphicsBuffer, MaterialVariants},
     grid::{
         add_cell_client, add_tile, add_tile_client_updates, add_tile_collision, add_tile_net,
-        export_debug_map, remove_cell_client, remove_tile, remove_tile_client_updates,
-        remove_tile_net, spawn_group, AddGroup, AddTile, EditTileSet, Gridmap, RemoveTile,
+        export_debug_map, forget_group_instances, remove_cell_client, remove_tile,
+        remove_tile_client_updates, remove_tile_net, spawn_group, AddGroup, AddTile, EditTileSet,
+        Gridmap, RemoveTile,
+    },
+    grids::{
+        despawn_empty_grids, receive_grid_motion, receive_grids, send_grid_motion, send_new_grids,
+        update_grid_motion,
     },
     init::{
         init_tile_groups, init_tile_properties, load_ron_gridmap, InitTileGroups,
-        InitTileProperties,
+        InitTileProperties, InitTileSet,
     },
     items::{
         airlock_evac::{init_airlock_evac, init_airlock_evac_material, AirlockMaterials},
@@ -63,9 +82,6 @@ use crate::{
             GenericHalfDiagonalFloorMaterial,
         },
         half_ceiling::{init_half_ceiling, init_half_ceiling_material, HalfCeilingMaterial},
-        large_window_3x3::{
-            init_large_window_3x3, init_large_window_3x3_material, LargeWindowMaterials,
-        },
         light_strip_horizontal::{
             init_light_strip_horizontal, init_light_strip_horizontal_material,
         },
@@ -81,19 +97,28 @@ use crate::{
             init_reinforced_glass_half_diagonal_ceiling_material,
             HalfDiagonalReinforcedGlassMaterial,
         },
-        small_window_3x3::{init_small_window_3x3, init_small_window_3x3_material},
         star_lights::{init_star_lights, init_star_lights_material},
         vents_access::{init_vents_access, init_vents_access_material},
         wall_clean::{init_clean_wall, init_wall_clean_material},
-        wall_flat::{
-            init_flat_wall, init_flat_wall_material, init_generic_wall_group, WallMaterials,
-        },
+        wall_flat::{init_flat_wall, init_flat_wall_material, WallMaterials},
         wall_high_curbed::{init_wall_high_curb, init_wall_high_curb_material},
         wall_light_bulb::{init_wall_light_bulb, init_wall_light_bulb_material},
         wall_low_curbed::{init_wall_low_curb, init_wall_low_curb_material},
         wall_reinforced_glass::{init_wall_reinforced_glass, init_wall_reinforced_glass_material},
     },
-    net::{GridmapClientMessage, GridmapServerMessage},
+    layers::{init_gridmap_layers, InitGridmapLayers},
+    net::{GridmapClientMessage, GridmapServerMessage, GridmapUnreliableServerMessage},
+    pathfinding::{update_nav_grid, NavGrid},
+    power::{
+        balance_power, examine_entity_power, receive_tiles_powered, update_power_network,
+        PowerNetwork, UnpoweredTiles, POWER_TICK,
+    },
+    sections::{
+        carry_grid_passengers, detach_sections, queue_connectivity_checks, ConnectivityChecks,
+    },
+    streaming::{
+        load_chunks_client, set_map_limits_client, stream_chunks, LoadedChunks, StreamedChunks,
+    },
     sync::correction_gridmap_sync,
 };
 use bevy::prelude::IntoSystemSetConfigs;
@@ -101,16 +126,18 @@ use bevy::{
     prelude::{resource_exists, App, IntoSystemConfigs, Plugin, Startup},
     time::common_conditions::on_timer,
 };
-use entity::despawn::DespawnEntitySet;
-use networking::messaging::{register_reliable_message, MessageSender, MessagingSet};
+use entity::{despawn::DespawnEntitySet, examine::examine_entity};
+use networking::messaging::{
+    register_reliable_message, register_unreliable_message, MessageSender, MessagingSet,
+};
 use player::{connections::process_response, plugin::ConfigurationLabel};
 use resources::{
     modes::{is_correction_mode, is_server_mode},
-    ordering::{ActionsSet, BuildingSet, PreUpdate, StartupSet, Update},
+    ordering::{ActionsSet, BuildingSet, PreUpdate, SensingSet, StartupSet, Update},
 };
 
 use super::{
-    fov::{senser_update_fov, DoryenMap},
+    fov::{projectile_fov, senser_update_fov, update_fov_map, FovMap, ProjectileFOV},
     sensing_ability::gridmap_sensing_ability,
 };
 
@@ -122,7 +149,9 @@ impl Plugin for GridmapPlugin {
             app.add_systems(
                 Update,
                 (
-                    senser_update_fov,
+                    senser_update_fov
+                        .after(update_fov_map)
+                        .before(SensingSet::VisibleChecker),
                     gridmap_sensing_ability,
                     examine_map.after(ActionsSet::Action),
                     set_action_header_name
@@ -138,13 +167,62 @@ impl Plugin for GridmapPlugin {
                         .after(ConfigurationLabel::SpawnEntity),
                     add_tile_net.after(EditTileSet::Add),
                     remove_tile_net.after(EditTileSet::Remove),
+                    stream_chunks
+                        .after(add_tile_net)
+                        .after(remove_tile_net)
+                        .run_if(on_timer(Duration::from_secs_f32(1. / 4.))),
+                    invalidate_served_map,
+                    send_map_download_parts.after(start_map_downloads),
+                    forget_disconnected_downloads,
+                    send_new_grids.after(EditTileSet::Add).before(add_tile_net),
+                    despawn_empty_grids
+                        .after(remove_tile)
+                        .after(DespawnEntitySet),
+                    send_grid_motion.after(update_grid_motion),
+                ),
+            )
+            .init_resource::<StreamedChunks>()
+            .init_resource::<ServedMap>()
+            .init_resource::<MapDownloads>()
+            .init_resource::<NavGrid>()
+            .init_resource::<FovMap>()
+            .init_resource::<Atmospherics>()
+            .add_systems(
+                Update,
+                (
+                    update_nav_grid.after(add_tile).after(remove_tile),
+                    update_atmospherics_map.after(add_tile).after(remove_tile),
+                    simulate_atmospherics
+                        .after(update_atmospherics_map)
+                        .run_if(on_timer(Duration::from_secs_f32(ATMOSPHERICS_TICK))),
+                    update_fov_map.after(add_tile).after(remove_tile),
+                    projectile_fov.after(senser_update_fov),
+                    update_power_network.after(add_tile).after(remove_tile),
+                    balance_power
+                        .after(update_power_network)
+                        .run_if(on_timer(Duration::from_secs_f32(POWER_TICK))),
+                    examine_entity_power
+                        .after(ActionsSet::Action)
+                        .after(examine_entity),
+                    damage_tiles.after(add_tile),
+                    queue_connectivity_checks.after(remove_tile),
+                    detach_sections
+                        .after(remove_tile)
+                        .after(queue_connectivity_checks),
+                    carry_grid_passengers
+                        .after(update_grid_motion)
+                        .after(add_tile),
                 ),
             )
+            .init_resource::<ConnectivityChecks>()
+            .init_resource::<PowerNetwork>()
+            .add_event::<TileDestroyed>()
             .add_event::<ProjectileFOV>()
             .add_systems(
                 PreUpdate,
                 (
                     incoming_messages,
+                    start_map_downloads,
                     finalize_grid_examine_input,
                     finalize_examine_map,
                 )
@@ -155,14 +233,46 @@ impl Plugin for GridmapPlugin {
         }
         if !is_server_mode(app) {
             app.init_resource::<NewGhostBuffer>()
+                .init_resource::<LoadedChunks>()
+                .init_resource::<MapBaseline>()
+                .init_resource::<MapDownloadProgress>()
                 .init_resource::<CellGraphicsBuffer>()
+                .init_resource::<UnpoweredTiles>()
+                .init_resource::<TileDamageStates>()
+                .init_resource::<MaterialVariants>()
+                .add_systems(
+                    Update,
+                    (
+                        receive_grids.before(EditTileSet::Add),
+                        receive_grid_motion.before(update_grid_motion),
+                    ),
+                )
                 .add_systems(Startup, export_debug_map)
                 .add_systems(
                     Update,
                     (
                         add_cell_client.before(EditTileSet::Add),
                         remove_cell_client.in_set(EditTileSet::Remove),
+                        load_chunks_client.in_set(EditTileSet::Remove),
+                        set_map_limits_client.before(EditTileSet::Remove),
+                        (
+                            receive_map_hash
+                                .after(set_map_limits_client)
+                                .after(load_chunks_client)
+                                .before(EditTileSet::Add),
+                            receive_map_download
+                                .after(set_map_limits_client)
+                                .after(load_chunks_client)
+                                .before(EditTileSet::Add),
+                            map_download_ui
+                                .after(receive_map_hash)
+                                .after(receive_map_download),
+                        ),
                         set_cell_graphics.after(EditTileSet::Add),
+                        receive_tiles_powered.after(EditTileSet::Add),
+                        receive_tile_damage_states
+                            .after(add_tile)
+                            .before(set_cell_graphics),
                         set_yplane_position
                             .run_if(resource_exists::<GridmapConstructionState>)
                             .after(YPlaneSet::Input)
@@ -190,9 +300,25 @@ impl Plugin for GridmapPlugin {
                             .after(ConstructionSelection::Changed)
                             .run_if(resource_exists::<GridmapConstructionState>)
                             .before(update_ghost_cell),
-                        (client_mouse_click_input
-                            .after(update_ghost_cell)
-                            .run_if(resource_exists::<GridmapConstructionState>),),
+                        (
+                            client_mouse_click_input
+                                .after(update_ghost_cell)
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                            input_construction_history
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                            input_blueprint_corner
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                            input_placement_mode
+                                .in_set(ConstructionSelection::Changed)
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                            input_construction_grid
+                                .in_set(ConstructionSelection::Changed)
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                            follow_construction_grid
+                                .after(update_grid_motion)
+                                .after(update_ghost_cell)
+                                .run_if(resource_exists::<GridmapConstructionState>),
+                        ),
                         remove_tile_client_updates.after(EditTileSet::Remove),
                         add_tile_client_updates.after(EditTileSet::Add),
                     ),
@@ -236,10 +362,8 @@ impl Plugin for GridmapPlugin {
                                 .before(init_reinforced_glass_floor),
                             init_half_ceiling_material.before(init_half_ceiling),
                             init_wall_reinforced_glass_material.before(init_wall_reinforced_glass),
-                            init_large_window_3x3_material.before(init_large_window_3x3),
                         ),
                         (
-                            init_small_window_3x3_material.before(init_small_window_3x3),
                             init_wall_evac_clean_material.before(init_wall_evac_clean),
                             init_evac_wall_lights_material.before(init_evec_wall_lights),
                             init_airlock_evac_material.before(init_airlock_evac),
@@ -256,13 +380,59 @@ impl Plugin for GridmapPlugin {
                 );
         }
 
+        if is_server_mode(app) {
+            // The correction app re-simulates against the same static map as the server.
+            app.add_systems(
+                Startup,
+                load_ron_gridmap
+                    .before(EditTileSet::Add)
+                    .in_set(StartupSet::BuildGridmap)
+                    .after(StartupSet::InitDefaultGridmapData),
+            )
+            .add_event::<DamageTile>();
+        }
         if is_server_mode(app) && is_correction_mode(app) {
             app.add_systems(Update, correction_gridmap_sync.before(EditTileSet::Remove));
         }
 
+        app.add_plugins(GridmapTilesPlugin)
+            .configure_sets(Update, (EditTileSet::Remove, EditTileSet::Add).chain())
+            .add_systems(
+                Update,
+                (
+                    remove_tile
+                        .after(EditTileSet::Remove)
+                        .before(add_tile)
+                        .before(DespawnEntitySet),
+                    add_tile_collision.after(add_tile).after(EditTileSet::Add),
+                    rebuild_chunk_colliders
+                        .after(add_tile_collision)
+                        .after(remove_tile),
+                    add_tile.after(EditTileSet::Add),
+                    forget_group_instances.after(add_tile).after(remove_tile),
+                    spawn_group.before(EditTileSet::Add),
+                    update_grid_motion,
+                ),
+            )
+            .add_event::<AddTile>()
+            .add_event::<AddGroup>()
+            .add_event::<RemoveTile>()
+            .init_resource::<ChunkColliderRebuilds>();
+
+        register_reliable_message::<GridmapClientMessage>(app, MessageSender::Client, true);
+        register_reliable_message::<GridmapServerMessage>(app, MessageSender::Server, true);
+        register_unreliable_message::<GridmapUnreliableServerMessage>(app, MessageSender::Server);
+    }
+}
+
+/// Tile, group and layer definitions of the gridmap, without the systems that run the map.
+/// Tools add this on its own to get a [Gridmap] with all tile properties.
+pub struct GridmapTilesPlugin;
+
+impl Plugin for GridmapTilesPlugin {
+    fn build(&self, app: &mut App) {
         app.init_resource::<GenericMaterials>()
             .init_resource::<HalfCeilingMaterial>()
-            .init_resource::<LargeWindowMaterials>()
             .init_resource::<GenericMeshes>()
             .init_resource::<AirlockMaterials>()
             .init_resource::<RadarMaterials>()
@@ -276,14 +446,6 @@ impl Plugin for GridmapPlugin {
             .init_resource::<BridgeWallMaterial>()
             .init_resource::<BridgeHalfDiagonalCeilingMaterial>()
             .init_resource::<LightMaterials>()
-            .configure_sets(Update, (EditTileSet::Remove, EditTileSet::Add).chain())
-            .add_systems(
-                Startup,
-                load_ron_gridmap
-                    .before(EditTileSet::Add)
-                    .in_set(StartupSet::BuildGridmap)
-                    .after(StartupSet::InitDefaultGridmapData),
-            )
             .add_systems(
                 Startup,
                 (
@@ -295,10 +457,10 @@ impl Plugin for GridmapPlugin {
                         init_tile_groups.after(init_tile_properties),
                         init_generic_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
-                        init_generic_wall_group
-                            .after(init_tile_properties)
                             .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
+                        load_group_definitions
+                            .after(init_tile_properties)
                             .before(init_tile_groups),
                         init_bridge_wall_group
                             .after(init_tile_properties)
@@ -322,136 +484,153 @@ impl Plugin for GridmapPlugin {
                             .before(init_tile_groups),
                         init_flat_wall
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_clean_wall
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_bridge_wall
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_reinforced_glass_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_generic_diagonal_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_generic_diagonal_ceiling
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_generic_half_diagonal_ceiling_low
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_bridge_half_diagonal_ceiling_low
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_reinforced_glass_half_diagonal_ceiling_low
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_generic_half_diagonal_floor_low
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_star_lights
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                     ),
                     (
                         init_generic_half_diagonal_ceiling_high
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_bridge_half_diagonal_ceiling_high
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_reinforced_glass_half_diagonal_ceiling_high
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_generic_half_diagonal_floor_high
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_filled_bridge_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_half_bridge_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_corner_bridge_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_corner2_bridge_floor
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_exterior_wall
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_wall_low_curb
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_wall_high_curb
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_half_ceiling
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_wall_reinforced_glass
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
-                        init_large_window_3x3
-                            .before(init_tile_properties)
-                            .after(init_generic_meshes),
-                        init_small_window_3x3
-                            .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
+                        load_tile_definitions
+                            .after(InitTileSet::BuiltIn)
+                            .before(init_tile_properties),
                         init_wall_evac_clean
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_evec_wall_lights
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_airlock_evac
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_floor_evac
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_light_strip_horizontal
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                     ),
                     (
+                        init_gridmap_layers
+                            .before(load_tile_definitions)
+                            .before(init_tile_properties)
+                            .after(StartupSet::MiscResources),
                         init_radar
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_ceiling_light_bar
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_wall_light_bulb
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                         init_vents_access
                             .before(init_tile_properties)
-                            .after(init_generic_meshes),
+                            .after(init_generic_meshes)
+                            .in_set(InitTileSet::BuiltIn),
                     ),
                 ),
             )
             .init_resource::<Gridmap>()
-            .init_resource::<DoryenMap>()
-            .add_systems(
-                Update,
-                (
-                    remove_tile
-                        .after(EditTileSet::Remove)
-                        .before(DespawnEntitySet),
-                    add_tile_collision.after(add_tile).after(EditTileSet::Add),
-                    add_tile.after(EditTileSet::Add),
-                    spawn_group.before(EditTileSet::Add),
-                ),
-            )
-            .add_event::<AddTile>()
-            .add_event::<AddGroup>()
-            .add_event::<RemoveTile>()
             .init_resource::<InitTileProperties>()
-            .init_resource::<InitTileGroups>();
-
-        register_reliable_message::<GridmapClientMessage>(app, MessageSender::Client, true);
-        register_reliable_message::<GridmapServerMessage>(app, MessageSender::Server, true);
+            .init_resource::<InitTileGroups>()
+            .init_resource::<InitGridmapLayers>();
     }
 }
@@ -0,0 +1,563 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::{
+    hierarchy::Children,
+    pbr::PointLight,
+    prelude::{
+        Component, Entity, EventReader, EventWriter, Query, Res, ResMut, Resource, Transform,
+        Visibility, With, Without,
+    },
+};
+use entity::{
+    examine::ExamineEntityMessages,
+    senser::{Senser, SensingAbility},
+};
+use networking::{
+    client::IncomingReliableServerMessage,
+    server::{ConnectedPlayer, OutgoingReliableServerMessage},
+};
+use resources::{
+    grid::{CellFace, GridId, TargetCell},
+    math::{world_to_cell_id, Vec3Int},
+    player::SoftPlayer,
+};
+use text_api::core::{ENGINEERING_TEXT_COLOR, FURTHER_ITALIC_FONT};
+
+use crate::{
+    grid::{AddTile, Gridmap, LayerTargetCell, RemoveTile},
+    layers::LayerId,
+    net::GridmapServerMessage,
+};
+
+/// Power in kW a light tile draws.
+pub const LIGHT_POWER_USAGE: f32 = 0.1;
+/// Seconds between balancing the supply and demand of power networks.
+pub const POWER_TICK: f32 = 1.;
+
+/// Role of an entity in the power network of its cell.
+#[derive(Clone, Debug)]
+pub enum PowerDeviceKind {
+    /// Supplies a steady output in kW.
+    Producer { output: f32 },
+    /// Holds up to `capacity` kJ and charges or discharges with at most `rate` kW.
+    Storage {
+        charge: f32,
+        capacity: f32,
+        rate: f32,
+    },
+    /// Draws `usage` kW.
+    Consumer { usage: f32 },
+}
+
+/// An entity connected to the power network through a cable in its cell.
+#[derive(Component, Clone, Debug)]
+pub struct PowerDevice {
+    pub kind: PowerDeviceKind,
+    /// Network of the cable in the cell of the device.
+    pub network: Option<u32>,
+    /// Whether the network of the device has enough power.
+    pub powered: bool,
+}
+
+impl PowerDevice {
+    pub fn producer(output: f32) -> Self {
+        Self::new(PowerDeviceKind::Producer { output })
+    }
+    pub fn storage(capacity: f32, rate: f32) -> Self {
+        Self::new(PowerDeviceKind::Storage {
+            charge: capacity,
+            capacity,
+            rate,
+        })
+    }
+    pub fn consumer(usage: f32) -> Self {
+        Self::new(PowerDeviceKind::Consumer { usage })
+    }
+    fn new(kind: PowerDeviceKind) -> Self {
+        Self {
+            kind,
+            network: None,
+            powered: true,
+        }
+    }
+}
+
+/// Whether a door like device opens by itself when someone walks up to it.
+/// Unpowered devices have to be opened by hand, entities without a [PowerDevice] always open.
+pub fn opens_automatically(power_devices: &Query<&PowerDevice>, entity: Entity) -> bool {
+    match power_devices.get(entity) {
+        Ok(power_device) => power_device.powered,
+        Err(_) => true,
+    }
+}
+
+/// Supply and demand of a power network during the last tick.
+#[derive(Clone, Default, Debug)]
+pub struct PowerNetworkState {
+    /// Output of producers in kW.
+    pub supply: f32,
+    /// Usage of consumer devices and tiles in kW.
+    pub demand: f32,
+    /// Energy in storage in kJ.
+    pub stored: f32,
+    pub capacity: f32,
+    pub powered: bool,
+}
+
+/// The power networks formed by cable tiles of the details layer, connected to the cables in the six neighbouring cells.
+/// A map without any cables has no power network and everything on it counts as powered.
+#[derive(Resource, Default)]
+pub struct PowerNetwork {
+    /// Cells with a cable.
+    pub cables: HashSet<Vec3Int>,
+    /// Network of every cable cell.
+    pub networks: HashMap<Vec3Int, u32>,
+    pub states: HashMap<u32, PowerNetworkState>,
+    /// Tiles that draw power with their usage in kW.
+    pub tile_consumers: HashMap<LayerTargetCell, f32>,
+    /// Tile consumers that are currently without power.
+    pub unpowered_tiles: HashSet<LayerTargetCell>,
+}
+
+impl PowerNetwork {
+    pub fn network_at(&self, id: &Vec3Int) -> Option<u32> {
+        self.networks.get(id).copied()
+    }
+    /// Whether something in the cell gets power.
+    pub fn is_powered(&self, id: &Vec3Int) -> bool {
+        if self.cables.is_empty() {
+            return true;
+        }
+        match self.network_at(id) {
+            Some(network) => match self.states.get(&network) {
+                Some(state) => state.powered,
+                None => false,
+            },
+            None => false,
+        }
+    }
+    /// Flood fill the cables into connected networks.
+    fn rebuild(&mut self) {
+        self.networks.clear();
+        let mut next_network = 0;
+        for cable in self.cables.iter() {
+            if self.networks.contains_key(cable) {
+                continue;
+            }
+            let mut queue = VecDeque::from([*cable]);
+            self.networks.insert(*cable, next_network);
+            while let Some(id) = queue.pop_front() {
+                for offset in NEIGHBOURS {
+                    let neighbour = id + offset;
+                    if self.cables.contains(&neighbour) && !self.networks.contains_key(&neighbour) {
+                        self.networks.insert(neighbour, next_network);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            next_network += 1;
+        }
+    }
+}
+
+const NEIGHBOURS: [Vec3Int; 6] = [
+    Vec3Int { x: 1, y: 0, z: 0 },
+    Vec3Int { x: -1, y: 0, z: 0 },
+    Vec3Int { x: 0, y: 1, z: 0 },
+    Vec3Int { x: 0, y: -1, z: 0 },
+    Vec3Int { x: 0, y: 0, z: 1 },
+    Vec3Int { x: 0, y: 0, z: -1 },
+];
+
+fn has_cable(gridmap: &Gridmap, id: Vec3Int) -> bool {
+    match gridmap.get_cell(LayerTargetCell {
+        target: TargetCell {
+            id,
+            face: CellFace::Center,
+            grid: GridId::MAIN,
+        },
+        layer: LayerId::DETAILS,
+    }) {
+        Some(item) => match gridmap.tile_properties.get(&item.tile_type) {
+            Some(properties) => properties.power_cable,
+            None => false,
+        },
+        None => false,
+    }
+}
+
+/// Track cables and tiles that draw power as they get constructed and deconstructed.
+pub(crate) fn update_power_network(
+    mut add_events: EventReader<AddTile>,
+    mut remove_events: EventReader<RemoveTile>,
+    gridmap: Res<Gridmap>,
+    mut power: ResMut<PowerNetwork>,
+) {
+    let mut changed_cells = HashSet::new();
+    let mut removed = false;
+    for event in remove_events.read() {
+        removed = true;
+        if event.cell.layer == LayerId::DETAILS && event.cell.target.grid == GridId::MAIN {
+            changed_cells.insert(event.cell.target.id);
+        }
+    }
+    if removed {
+        power
+            .tile_consumers
+            .retain(|cell, _| gridmap.get_cell(cell.clone()).is_some());
+    }
+    for event in add_events.read() {
+        // Only the main grid is wired up to the power network.
+        if event.grid != GridId::MAIN {
+            continue;
+        }
+        if event.layer == LayerId::DETAILS {
+            changed_cells.insert(event.id);
+        }
+        match gridmap.tile_properties.get(&event.tile_type) {
+            Some(properties) => {
+                if properties.power_usage > 0. {
+                    power.tile_consumers.insert(
+                        LayerTargetCell {
+                            target: event.target(),
+                            layer: event.layer,
+                        },
+                        properties.power_usage,
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+
+    let mut cables_changed = false;
+    for id in changed_cells {
+        if has_cable(&gridmap, id) {
+            cables_changed |= power.cables.insert(id);
+        } else {
+            cables_changed |= power.cables.remove(&id);
+        }
+    }
+    if cables_changed {
+        power.rebuild();
+    }
+}
+
+/// Balance supply and demand of every power network, charging or draining storage, and tell clients which tiles lost or regained power.
+pub(crate) fn balance_power(
+    mut power: ResMut<PowerNetwork>,
+    mut devices: Query<(&mut PowerDevice, &Transform)>,
+    connected_players: Query<&ConnectedPlayer, Without<SoftPlayer>>,
+    mut server: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    let mut states: HashMap<u32, PowerNetworkState> = HashMap::new();
+    // Energy in kJ storage can deliver this tick.
+    let mut deliverable: HashMap<u32, f32> = HashMap::new();
+    for (mut device, transform) in devices.iter_mut() {
+        let network = power.network_at(&world_to_cell_id(transform.translation));
+        if device.network != network {
+            device.network = network;
+        }
+        match network {
+            Some(n) => {
+                let state = states.entry(n).or_default();
+                match device.kind {
+                    PowerDeviceKind::Producer { output } => {
+                        state.supply += output;
+                    }
+                    PowerDeviceKind::Storage {
+                        charge,
+                        capacity,
+                        rate,
+                    } => {
+                        state.stored += charge;
+                        state.capacity += capacity;
+                        *deliverable.entry(n).or_default() += charge.min(rate * POWER_TICK);
+                    }
+                    PowerDeviceKind::Consumer { usage } => {
+                        state.demand += usage;
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+    for (cell, usage) in power.tile_consumers.iter() {
+        match power.network_at(&cell.target.id) {
+            Some(n) => {
+                states.entry(n).or_default().demand += usage;
+            }
+            None => {}
+        }
+    }
+
+    // Energy in kJ storage has to absorb (positive) or deliver (negative) this tick.
+    let mut storage_budget: HashMap<u32, f32> = HashMap::new();
+    for (network, state) in states.iter_mut() {
+        let balance = (state.supply - state.demand) * POWER_TICK;
+        state.powered =
+            balance >= 0. || deliverable.get(network).copied().unwrap_or(0.) >= -balance;
+        if state.powered {
+            storage_budget.insert(*network, balance);
+        }
+    }
+
+    let no_cables = power.cables.is_empty();
+    for (mut device, _) in devices.iter_mut() {
+        let powered;
+        match device.network {
+            Some(n) => {
+                powered = states.get(&n).map(|s| s.powered).unwrap_or(false);
+                match storage_budget.get_mut(&n) {
+                    Some(budget) => {
+                        if *budget != 0. && matches!(device.kind, PowerDeviceKind::Storage { .. }) {
+                            match &mut device.kind {
+                                PowerDeviceKind::Storage {
+                                    charge,
+                                    capacity,
+                                    rate,
+                                } => {
+                                    let max_step = *rate * POWER_TICK;
+                                    let step;
+                                    if *budget > 0. {
+                                        step = budget.min(max_step).min(*capacity - *charge);
+                                    } else {
+                                        step = -(-*budget).min(max_step).min(*charge);
+                                    }
+                                    *charge += step;
+                                    *budget -= step;
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+            None => {
+                powered = no_cables;
+            }
+        }
+        if device.powered != powered {
+            device.powered = powered;
+        }
+    }
+    power.states = states;
+
+    let mut unpowered = HashSet::new();
+    if !no_cables {
+        for cell in power.tile_consumers.keys() {
+            if !power.is_powered(&cell.target.id) {
+                unpowered.insert(cell.clone());
+            }
+        }
+    }
+    let mut changes = vec![];
+    for cell in unpowered.difference(&power.unpowered_tiles) {
+        changes.push((cell.clone(), false));
+    }
+    for cell in power.unpowered_tiles.difference(&unpowered) {
+        if power.tile_consumers.contains_key(cell) {
+            changes.push((cell.clone(), true));
+        }
+    }
+    power.unpowered_tiles = unpowered;
+    if changes.is_empty() {
+        return;
+    }
+    for connected_player in connected_players.iter() {
+        if !connected_player.connected {
+            continue;
+        }
+        server.send(OutgoingReliableServerMessage {
+            handle: connected_player.handle,
+            message: GridmapServerMessage::TilesPowered(changes.clone()),
+        });
+    }
+}
+
+/// Tiles the client knows to be without power.
+#[derive(Resource, Default)]
+pub struct UnpoweredTiles {
+    pub cells: HashSet<LayerTargetCell>,
+}
+
+/// Switch the lights of tiles on and off as they gain and lose power.
+pub(crate) fn receive_tiles_powered(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut unpowered: ResMut<UnpoweredTiles>,
+    gridmap: Res<Gridmap>,
+    children: Query<&Children>,
+    mut lights: Query<&mut Visibility, With<PointLight>>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::TilesPowered(changes) => {
+                for (cell, powered) in changes {
+                    if *powered {
+                        unpowered.cells.remove(cell);
+                    } else {
+                        unpowered.cells.insert(cell.clone());
+                    }
+                    let entity;
+                    match gridmap.get_cell(cell.clone()).and_then(|item| item.entity) {
+                        Some(e) => {
+                            entity = e;
+                        }
+                        None => {
+                            continue;
+                        }
+                    }
+                    match children.get(entity) {
+                        Ok(c) => {
+                            for child in c.iter() {
+                                match lights.get_mut(*child) {
+                                    Ok(mut visibility) => {
+                                        *visibility = light_visibility(*powered);
+                                    }
+                                    Err(_) => {}
+                                }
+                            }
+                        }
+                        Err(_) => {}
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(crate) fn light_visibility(powered: bool) -> Visibility {
+    if powered {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+fn power_state_text(state_option: Option<&PowerNetworkState>) -> String {
+    match state_option {
+        Some(state) => {
+            let status;
+            if state.powered {
+                status = "powered";
+            } else {
+                status = "unpowered";
+            }
+            format!(
+                "Power network is {}: {:.1} kW supply, {:.1} kW demand, {:.0}/{:.0} kJ stored.",
+                status, state.supply, state.demand, state.stored, state.capacity
+            )
+        }
+        None => "Not connected to a power network.".to_string(),
+    }
+}
+
+/// Engineer readout of the power network of a cell for gridmap examining.
+pub(crate) fn examine_power_text(power: &PowerNetwork, id: &Vec3Int) -> String {
+    if power.cables.is_empty() {
+        return "".to_string();
+    }
+    let state_option = power
+        .network_at(id)
+        .and_then(|network| power.states.get(&network));
+    "[font=".to_string()
+        + FURTHER_ITALIC_FONT
+        + "][color="
+        + ENGINEERING_TEXT_COLOR
+        + "]"
+        + &power_state_text(state_option)
+        + "[/color][/font]\n"
+}
+
+/// Show the power state of examined power devices to sensers with [SensingAbility::ShipEngineerKnowledge].
+pub(crate) fn examine_entity_power(
+    mut examine_entity_events: ResMut<ExamineEntityMessages>,
+    sensers: Query<&Senser>,
+    devices: Query<&PowerDevice>,
+    power: Res<PowerNetwork>,
+) {
+    for examine_event in examine_entity_events.messages.iter_mut() {
+        match sensers.get(examine_event.entity) {
+            Ok(senser) => {
+                if !senser
+                    .sensing_abilities
+                    .contains(&SensingAbility::ShipEngineerKnowledge)
+                {
+                    continue;
+                }
+            }
+            Err(_rr) => {
+                continue;
+            }
+        }
+        let device;
+        match devices.get(examine_event.examine_entity) {
+            Ok(d) => {
+                device = d;
+            }
+            Err(_rr) => {
+                continue;
+            }
+        }
+        let mut text;
+        match device.kind {
+            PowerDeviceKind::Producer { output } => {
+                text = format!("It produces {:.1} kW. ", output);
+            }
+            PowerDeviceKind::Storage {
+                charge, capacity, ..
+            } => {
+                text = format!("It stores {:.0}/{:.0} kJ. ", charge, capacity);
+            }
+            PowerDeviceKind::Consumer { usage } => {
+                if device.powered {
+                    text = format!("It draws {:.1} kW. ", usage);
+                } else {
+                    text = "It is unpowered. ".to_string();
+                }
+            }
+        }
+        if !power.cables.is_empty() {
+            text = text
+                + &power_state_text(
+                    device
+                        .network
+                        .and_then(|network| power.states.get(&network)),
+                );
+        }
+        examine_event.message = examine_event.message.clone()
+            + "\n[font="
+            + FURTHER_ITALIC_FONT
+            + "][color="
+            + ENGINEERING_TEXT_COLOR
+            + "]"
+            + &text
+            + "[/color][/font]";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cables_split_into_networks() {
+        let mut power = PowerNetwork::default();
+        assert!(power.is_powered(&Vec3Int { x: 0, y: 0, z: 0 }));
+        for x in [0, 1, 2, 4, 5] {
+            power.cables.insert(Vec3Int { x, y: 0, z: 0 });
+        }
+        power.cables.insert(Vec3Int { x: 5, y: 1, z: 0 });
+        power.rebuild();
+        let first = power.network_at(&Vec3Int { x: 0, y: 0, z: 0 });
+        let second = power.network_at(&Vec3Int { x: 5, y: 1, z: 0 });
+        assert!(first.is_some() && second.is_some());
+        assert_eq!(first, power.network_at(&Vec3Int { x: 2, y: 0, z: 0 }));
+        assert_eq!(second, power.network_at(&Vec3Int { x: 4, y: 0, z: 0 }));
+        assert_ne!(first, second);
+        assert_eq!(power.network_at(&Vec3Int { x: 3, y: 0, z: 0 }), None);
+        assert!(!power.is_powered(&Vec3Int { x: 3, y: 0, z: 0 }));
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::{
+    math::Vec3,
+    prelude::{
+        Commands, Component, Entity, EventReader, EventWriter, Query, Res, ResMut, Resource,
+        Transform, Without,
+    },
+};
+use bevy_xpbd_3d::prelude::RigidBody;
+use networking::stamp::TickRateStamp;
+use resources::{
+    grid::{GridId, TargetCell},
+    math::{cell_id_to_world, world_to_cell_id, Vec3Int},
+};
+
+use crate::{
+    grid::{AddTile, Gridmap, LayerTargetCell, RemoveTile},
+    grids::{GridBody, GridMotion},
+    layers::LayerId,
+};
+
+/// Islands with more cells than this are considered part of the main structure of their grid.
+pub const MAX_SECTION_CELLS: usize = 4096;
+/// Speed in m/s with which detached sections drift away from where they broke off.
+pub const SECTION_DRIFT_SPEED: f32 = 0.2;
+
+const NEIGHBOURS: [Vec3Int; 6] = [
+    Vec3Int { x: 1, y: 0, z: 0 },
+    Vec3Int { x: -1, y: 0, z: 0 },
+    Vec3Int { x: 0, y: 1, z: 0 },
+    Vec3Int { x: 0, y: -1, z: 0 },
+    Vec3Int { x: 0, y: 0, z: 1 },
+    Vec3Int { x: 0, y: 0, z: -1 },
+];
+
+fn offset(id: Vec3Int, by: Vec3Int) -> Vec3Int {
+    Vec3Int {
+        x: id.x + by.x,
+        y: id.y + by.y,
+        z: id.z + by.z,
+    }
+}
+
+fn is_structure(gridmap: &Gridmap, grid: GridId, id: Vec3Int) -> bool {
+    match gridmap.get_grid_cell(grid, id, LayerId::STRUCTURE) {
+        Some(cell) => !cell.is_empty(),
+        None => false,
+    }
+}
+
+/// Structure layer cells of a grid connected to `start` through their sides.
+/// Stops once more than `limit` cells are found, the second value tells whether the search completed.
+pub fn connected_cells(
+    gridmap: &Gridmap,
+    grid: GridId,
+    start: Vec3Int,
+    limit: usize,
+) -> (HashSet<Vec3Int>, bool) {
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(start);
+    queue.push_back(start);
+    while let Some(id) = queue.pop_front() {
+        for neighbour in NEIGHBOURS.iter().map(|by| offset(id, *by)) {
+            if visited.contains(&neighbour) || !is_structure(gridmap, grid, neighbour) {
+                continue;
+            }
+            visited.insert(neighbour);
+            if visited.len() > limit {
+                return (visited, false);
+            }
+            queue.push_back(neighbour);
+        }
+    }
+    (visited, true)
+}
+
+/// Islands of cells around removed cells of a grid that are no longer attached to its main structure.
+/// When no island is larger than `limit` the largest one is considered the main structure.
+pub fn find_detached_sections(
+    gridmap: &Gridmap,
+    grid: GridId,
+    removed: &Vec<Vec3Int>,
+    limit: usize,
+) -> Vec<HashSet<Vec3Int>> {
+    let mut seeds = vec![];
+    for id in removed.iter() {
+        for seed in NEIGHBOURS
+            .iter()
+            .map(|by| offset(*id, *by))
+            .chain([*id].into_iter())
+        {
+            if is_structure(gridmap, grid, seed) && !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+        }
+    }
+
+    let mut islands: Vec<HashSet<Vec3Int>> = vec![];
+    let mut attached: HashSet<Vec3Int> = HashSet::new();
+    let mut has_main_structure = false;
+    for seed in seeds {
+        if attached.contains(&seed) || islands.iter().any(|island| island.contains(&seed)) {
+            continue;
+        }
+        let (cells, complete) = connected_cells(gridmap, grid, seed, limit);
+        if complete {
+            islands.push(cells);
+        } else {
+            has_main_structure = true;
+            attached.extend(cells);
+        }
+    }
+
+    if !has_main_structure {
+        match islands
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, island)| island.len())
+            .map(|(i, _)| i)
+        {
+            Some(largest) => {
+                islands.remove(largest);
+            }
+            None => {}
+        }
+    }
+    islands
+}
+
+/// A rigid body standing on a grid other than the one it was on that moves along with it.
+#[derive(Component)]
+pub struct GridPassenger {
+    pub grid: GridId,
+    /// Transform of the grid as of the last carry of its passengers.
+    pub last: Transform,
+}
+
+/// Whether a body at a world position stands in or right on top of one of `cells` of a grid.
+fn stands_on(gridmap: &Gridmap, grid: GridId, position: Vec3, cells: &HashSet<Vec3Int>) -> bool {
+    let id = gridmap.world_to_grid_cell_id(grid, position);
+    cells.contains(&id) || cells.contains(&offset(id, Vec3Int { x: 0, y: -1, z: 0 }))
+}
+
+/// Cells of removed tiles per grid to check for sections that lost their connection to the rest of the grid.
+#[derive(Resource, Default)]
+pub struct ConnectivityChecks {
+    pub removed: HashMap<GridId, HashSet<Vec3Int>>,
+}
+
+pub(crate) fn queue_connectivity_checks(
+    mut events: EventReader<RemoveTile>,
+    gridmap: Res<Gridmap>,
+    mut checks: ResMut<ConnectivityChecks>,
+) {
+    for event in events.read() {
+        if event.cell.layer != LayerId::STRUCTURE {
+            continue;
+        }
+        checks
+            .removed
+            .entry(event.cell.target.grid)
+            .or_default()
+            .insert(gridmap.get_strict_cell(event.cell.target.clone()).id);
+    }
+}
+
+/// Move islands of cells that got cut off from the main structure of their grid onto a new grid.
+/// The new grid starts with the velocity of the grid it broke off from, plus a small drift away from the cut.
+/// Dynamic bodies standing on an island become passengers of its new grid.
+pub(crate) fn detach_sections(
+    mut checks: ResMut<ConnectivityChecks>,
+    bodies: Query<(Entity, &Transform, &RigidBody), Without<GridBody>>,
+    mut gridmap: ResMut<Gridmap>,
+    mut commands: Commands,
+    mut add_tile: EventWriter<AddTile>,
+    mut remove_tile: EventWriter<RemoveTile>,
+    stamp: Res<TickRateStamp>,
+) {
+    if checks.removed.is_empty() {
+        return;
+    }
+    let removed_per_grid: Vec<(GridId, Vec<Vec3Int>)> = checks
+        .removed
+        .drain()
+        .map(|(grid, removed)| (grid, removed.into_iter().collect()))
+        .collect();
+
+    for (grid, removed) in removed_per_grid {
+        if !gridmap.has_grid(grid) || removed.is_empty() {
+            continue;
+        }
+        let mut broke_off = Vec3::ZERO;
+        for id in removed.iter() {
+            broke_off += cell_id_to_world(*id);
+        }
+        broke_off /= removed.len() as f32;
+        let grid_transform = gridmap.grid_transform(grid);
+        let grid_velocity;
+        match gridmap.grids.get(&grid) {
+            Some(sub) => {
+                grid_velocity = sub.linear_velocity;
+            }
+            None => {
+                grid_velocity = Vec3::ZERO;
+            }
+        }
+
+        for island in find_detached_sections(&gridmap, grid, &removed, MAX_SECTION_CELLS) {
+            let mut center = Vec3::ZERO;
+            for id in island.iter() {
+                center += cell_id_to_world(*id);
+            }
+            center /= island.len() as f32;
+            let origin = world_to_cell_id(center + Vec3::splat(0.5));
+            let origin_local = cell_id_to_world(origin);
+
+            let drift = grid_transform.rotation
+                * (origin_local - broke_off).normalize_or_zero()
+                * SECTION_DRIFT_SPEED;
+            let motion = GridMotion {
+                translation: grid_transform.transform_point(origin_local),
+                rotation: grid_transform.rotation,
+                linear_velocity: grid_velocity + drift,
+                angular_velocity: Vec3::ZERO,
+            };
+            let new_grid = gridmap.spawn_grid(&mut commands, motion.clone());
+
+            for (entity, transform, rigid_body) in bodies.iter() {
+                if rigid_body.is_dynamic()
+                    && stands_on(&gridmap, grid, transform.translation, &island)
+                {
+                    commands.entity(entity).insert(GridPassenger {
+                        grid: new_grid,
+                        last: motion.transform(),
+                    });
+                }
+            }
+
+            for id in island.iter() {
+                let relative = Vec3Int {
+                    x: id.x - origin.x,
+                    y: id.y - origin.y,
+                    z: id.z - origin.z,
+                };
+                let layers: Vec<LayerId> = gridmap.layer_properties.keys().cloned().collect();
+                for layer in layers {
+                    match gridmap.get_grid_cell(grid, *id, layer) {
+                        Some(grid_cell) => {
+                            for (item, face) in grid_cell.get_items() {
+                                add_tile.send(AddTile {
+                                    id: relative,
+                                    tile_type: item.tile_type,
+                                    orientation: item.orientation,
+                                    face: face.clone(),
+                                    group_instance_id_option: item.group_id_option,
+                                    metadata: item.metadata.clone(),
+                                    entity: commands.spawn(()).id(),
+                                    default_map_spawn: false,
+                                    layer,
+                                    grid: new_grid,
+                                    stamp: stamp.tick,
+                                });
+                                remove_tile.send(RemoveTile {
+                                    cell: LayerTargetCell {
+                                        target: TargetCell {
+                                            id: *id,
+                                            face,
+                                            grid,
+                                        },
+                                        layer,
+                                    },
+                                    stamp: stamp.tick,
+                                });
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Move passengers along with the grid they stand on, until they step off it.
+pub(crate) fn carry_grid_passengers(
+    mut passengers: Query<(Entity, &mut Transform, &mut GridPassenger)>,
+    gridmap: Res<Gridmap>,
+    mut commands: Commands,
+) {
+    for (entity, mut transform, mut passenger) in passengers.iter_mut() {
+        let grid_transform;
+        match gridmap.grids.get(&passenger.grid) {
+            Some(sub) => {
+                grid_transform = sub.transform;
+            }
+            None => {
+                commands.entity(entity).remove::<GridPassenger>();
+                continue;
+            }
+        }
+        let rotation = grid_transform.rotation * passenger.last.rotation.inverse();
+        transform.translation = grid_transform.translation
+            + rotation * (transform.translation - passenger.last.translation);
+        transform.rotation = rotation * transform.rotation;
+        passenger.last = grid_transform;
+
+        // The cells of a new grid arrive a little after the grid itself.
+        if gridmap.get_grid_chunks(passenger.grid).is_empty() {
+            continue;
+        }
+        let id = gridmap.world_to_grid_cell_id(passenger.grid, transform.translation);
+        if !is_structure(&gridmap, passenger.grid, id)
+            && !is_structure(
+                &gridmap,
+                passenger.grid,
+                offset(id, Vec3Int { x: 0, y: -1, z: 0 }),
+            )
+        {
+            commands.entity(entity).remove::<GridPassenger>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CellTypeId;
+    use crate::grids::SubGrid;
+    use resources::grid::CellFace;
+
+    #[test]
+    fn test_cut_floor_detaches_smaller_side() {
+        let mut gridmap = Gridmap::default();
+        for x in -5..=2 {
+            gridmap.set_test_tile(
+                GridId::MAIN,
+                Vec3Int { x, y: 0, z: 0 },
+                CellFace::Floor,
+                CellTypeId(0),
+            );
+        }
+        // Floor at x 3 was removed, leaving x 4 and 5 on their own.
+        for x in 4..=5 {
+            gridmap.set_test_tile(
+                GridId::MAIN,
+                Vec3Int { x, y: 0, z: 0 },
+                CellFace::Floor,
+                CellTypeId(0),
+            );
+        }
+        let sections = find_detached_sections(
+            &gridmap,
+            GridId::MAIN,
+            &vec![Vec3Int { x: 3, y: 0, z: 0 }],
+            64,
+        );
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].len(), 2);
+        assert!(sections[0].contains(&Vec3Int { x: 5, y: 0, z: 0 }));
+
+        // The main structure is found by the limit instead of by size.
+        let sections = find_detached_sections(
+            &gridmap,
+            GridId::MAIN,
+            &vec![Vec3Int { x: 3, y: 0, z: 0 }],
+            4,
+        );
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].len(), 2);
+    }
+
+    #[test]
+    fn test_sections_detach_per_grid() {
+        let mut gridmap = Gridmap::default();
+        let grid = GridId(1);
+        gridmap.grids.insert(
+            grid,
+            SubGrid {
+                layers: Default::default(),
+                colliders: Default::default(),
+                entity: Entity::PLACEHOLDER,
+                transform: Transform::IDENTITY,
+                linear_velocity: Vec3::ZERO,
+                angular_velocity: Vec3::ZERO,
+            },
+        );
+        for x in [0, 1, 2, 4] {
+            gridmap.set_test_tile(
+                grid,
+                Vec3Int { x, y: 0, z: 0 },
+                CellFace::Floor,
+                CellTypeId(0),
+            );
+        }
+        // The same cells on the main grid are untouched by the cut in the other grid.
+        for x in 0..=4 {
+            gridmap.set_test_tile(
+                GridId::MAIN,
+                Vec3Int { x, y: 0, z: 0 },
+                CellFace::Floor,
+                CellTypeId(0),
+            );
+        }
+        let removed = vec![Vec3Int { x: 3, y: 0, z: 0 }];
+        let sections = find_detached_sections(&gridmap, grid, &removed, 64);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].len(), 1);
+        assert!(sections[0].contains(&Vec3Int { x: 4, y: 0, z: 0 }));
+        assert!(find_detached_sections(&gridmap, GridId::MAIN, &removed, 64).is_empty());
+    }
+
+    #[test]
+    fn test_passenger_stands_on_island() {
+        let gridmap = Gridmap::default();
+        let island: HashSet<Vec3Int> = [Vec3Int { x: 4, y: 0, z: 0 }].into_iter().collect();
+        let on_floor = cell_id_to_world(Vec3Int { x: 4, y: 0, z: 0 }) + Vec3::new(0.5, 0.9, 0.5);
+        let above = cell_id_to_world(Vec3Int { x: 4, y: 1, z: 0 }) + Vec3::new(0.5, 0.5, 0.5);
+        let next_to = cell_id_to_world(Vec3Int { x: 5, y: 0, z: 0 }) + Vec3::new(0.5, 0.9, 0.5);
+        assert!(stands_on(&gridmap, GridId::MAIN, on_floor, &island));
+        assert!(stands_on(&gridmap, GridId::MAIN, above, &island));
+        assert!(!stands_on(&gridmap, GridId::MAIN, next_to, &island));
+    }
+}
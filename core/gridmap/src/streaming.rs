@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::{
+    Commands, EventReader, EventWriter, Query, Res, ResMut, Resource, Transform, Without,
+};
+use bevy_renet::renet::ClientId;
+use networking::{
+    client::IncomingReliableServerMessage,
+    server::{ConnectedPlayer, OutgoingReliableServerMessage},
+};
+use resources::{grid::GridId, math::world_to_cell_id, player::SoftPlayer};
+
+use crate::{
+    damage::chunk_damage_states,
+    download::{spawn_baseline_cells, MapBaseline},
+    grid::{AddTile, Gridmap, RemoveTile},
+    net::{ChunkSnapshot, GridmapServerMessage},
+};
+
+/// Radius in chunks around a pawn in which chunks get streamed to its client.
+pub const STREAM_LOAD_RADIUS: i16 = 2;
+/// Radius in chunks around a pawn outside of which streamed chunks get unloaded again.
+/// Larger than [STREAM_LOAD_RADIUS] so chunks on the border don't get resent every time the pawn moves back and forth.
+pub const STREAM_UNLOAD_RADIUS: i16 = 3;
+
+/// The chunks each client is subscribed to. Cell updates only get sent to clients that have the chunk.
+#[derive(Resource, Default)]
+pub struct StreamedChunks {
+    pub clients: HashMap<ClientId, HashSet<usize>>,
+}
+
+impl StreamedChunks {
+    pub fn has_chunk(&self, handle: &ClientId, chunk: usize) -> bool {
+        match self.clients.get(handle) {
+            Some(chunks) => chunks.contains(&chunk),
+            None => false,
+        }
+    }
+}
+
+/// Client-side, the chunks currently streamed from the server.
+#[derive(Resource, Default)]
+pub struct LoadedChunks {
+    pub chunks: HashSet<usize>,
+}
+
+/// Stream chunks to clients based on the position of their pawn.
+pub(crate) fn stream_chunks(
+    connected_players: Query<(&ConnectedPlayer, &Transform), Without<SoftPlayer>>,
+    gridmap: Res<Gridmap>,
+    mut streamed: ResMut<StreamedChunks>,
+    mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
+) {
+    let mut active = HashSet::new();
+    for (connected_player, transform) in connected_players.iter() {
+        if !connected_player.connected {
+            continue;
+        }
+        active.insert(connected_player.handle);
+
+        let cell_id = world_to_cell_id(transform.translation);
+        let keep: HashSet<usize> = gridmap
+            .get_chunks_in_radius(cell_id, STREAM_UNLOAD_RADIUS)
+            .into_iter()
+            .collect();
+        let load = gridmap.get_chunks_in_radius(cell_id, STREAM_LOAD_RADIUS);
+
+        let client_chunks = streamed.clients.entry(connected_player.handle).or_default();
+
+        let unload: Vec<usize> = client_chunks
+            .iter()
+            .filter(|chunk| !keep.contains(chunk))
+            .cloned()
+            .collect();
+        for chunk in unload {
+            client_chunks.remove(&chunk);
+            net.send(OutgoingReliableServerMessage {
+                handle: connected_player.handle,
+                message: GridmapServerMessage::UnloadChunk(chunk),
+            });
+        }
+
+        for chunk in load {
+            if client_chunks.contains(&chunk) {
+                continue;
+            }
+            // Empty chunks get subscribed too so newly constructed cells in them reach the client.
+            client_chunks.insert(chunk);
+            if gridmap.has_chunk(chunk) {
+                net.send(OutgoingReliableServerMessage {
+                    handle: connected_player.handle,
+                    message: GridmapServerMessage::LoadChunk(ChunkSnapshot {
+                        chunk,
+                        cells: gridmap.get_chunk_snapshot(GridId::MAIN, chunk),
+                    }),
+                });
+                let damage_states = chunk_damage_states(&gridmap, chunk);
+                if !damage_states.is_empty() {
+                    net.send(OutgoingReliableServerMessage {
+                        handle: connected_player.handle,
+                        message: GridmapServerMessage::TileDamageStates(damage_states),
+                    });
+                }
+            }
+        }
+    }
+    streamed.clients.retain(|handle, _| active.contains(handle));
+}
+
+/// Replace the content of chunks with the streamed chunk snapshots.
+/// Unloaded chunks fall back to the map as it was downloaded.
+pub(crate) fn load_chunks_client(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    gridmap: Res<Gridmap>,
+    mut add_tile: EventWriter<AddTile>,
+    mut remove_tile: EventWriter<RemoveTile>,
+    mut commands: Commands,
+    mut loaded: ResMut<LoadedChunks>,
+    baseline: Res<MapBaseline>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::LoadChunk(snapshot) => {
+                loaded.chunks.insert(snapshot.chunk);
+                for old in gridmap.get_chunk_snapshot(GridId::MAIN, snapshot.chunk) {
+                    remove_tile.send(RemoveTile {
+                        cell: old.cell,
+                        stamp: message.stamp,
+                    });
+                }
+                for new in snapshot.cells.iter() {
+                    add_tile.send(AddTile {
+                        id: new.cell.target.id,
+                        tile_type: new.tile_type,
+                        orientation: new.orientation,
+                        face: new.cell.target.face.clone(),
+                        group_instance_id_option: None,
+                        metadata: new.metadata.clone(),
+                        entity: commands.spawn(()).id(),
+                        default_map_spawn: false,
+                        layer: new.cell.layer,
+                        grid: new.cell.target.grid,
+                        stamp: message.stamp,
+                    });
+                }
+            }
+            GridmapServerMessage::UnloadChunk(chunk) => {
+                loaded.chunks.remove(chunk);
+                for old in gridmap.get_chunk_snapshot(GridId::MAIN, *chunk) {
+                    remove_tile.send(RemoveTile {
+                        cell: old.cell,
+                        stamp: message.stamp,
+                    });
+                }
+                match baseline.chunks.get(chunk) {
+                    Some(cells) => {
+                        spawn_baseline_cells(cells, &mut add_tile, &mut commands, message.stamp);
+                    }
+                    None => {}
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Chunk indexes depend on the map limits, so the client uses the limits of the server.
+pub(crate) fn set_map_limits_client(
+    mut net: EventReader<IncomingReliableServerMessage<GridmapServerMessage>>,
+    mut gridmap: ResMut<Gridmap>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapServerMessage::ConfigMapLimits(limits) => {
+                gridmap.map_length_limit = limits.clone();
+            }
+            _ => (),
+        }
+    }
+}
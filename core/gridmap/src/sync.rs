@@ -42,9 +42,11 @@ pub(crate) fn correction_gridmap_sync(
                                 orientation: new_cell.orientation,
                                 face: target.target.face.clone(),
                                 group_instance_id_option: None,
+                                metadata: new_cell.metadata.clone(),
                                 entity: commands.spawn(()).id(),
                                 default_map_spawn: false,
-                                is_detail: target.is_detail,
+                                layer: target.layer,
+                                grid: target.target.grid,
                                 stamp: stamp.tick,
                             });
                         }
@@ -0,0 +1,321 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use resources::{
+    grid::{CellFace, GridId, TargetCell},
+    maps::{read_map_file, MapError, SelectedMap},
+    math::Vec3Int,
+};
+
+use crate::{
+    format::decode_gridmap,
+    format::GridmapEncoding,
+    grid::{CellTypeName, Gridmap, GroupTypeName, MapLimits, StrictCellFace, TileProperties},
+    init::{CellDataExport, ItemExport},
+};
+
+/// A problem found in a map file.
+#[derive(Debug, Clone)]
+pub enum MapIssue {
+    UnknownTile {
+        id: Vec3Int,
+        tile: CellTypeName,
+    },
+    UnknownGroup {
+        id: Vec3Int,
+        group: GroupTypeName,
+    },
+    /// Orientation isn't one of the `x_rotations` or `y_rotations` of the tile.
+    InvalidOrientation {
+        id: Vec3Int,
+        tile: CellTypeName,
+        orientation: u8,
+    },
+    /// A placed group instance lacks tiles of its [crate::grid::TileGroup].
+    MissingGroupMembers {
+        group: GroupTypeName,
+        group_id: u32,
+        missing: Vec<CellTypeName>,
+    },
+    /// More than one tile on the same face of a cell.
+    Overlap {
+        id: Vec3Int,
+        face: CellFace,
+        layer: String,
+    },
+    UnknownLayer {
+        id: Vec3Int,
+        layer: String,
+    },
+    OutOfLimits {
+        id: Vec3Int,
+        length: i16,
+    },
+}
+
+impl fmt::Display for MapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapIssue::UnknownTile { id, tile } => {
+                write!(f, "Unknown tile {} at {:?}.", tile.0, id)
+            }
+            MapIssue::UnknownGroup { id, group } => {
+                write!(f, "Unknown group {} at {:?}.", group.0, id)
+            }
+            MapIssue::InvalidOrientation {
+                id,
+                tile,
+                orientation,
+            } => write!(
+                f,
+                "Orientation {} of tile {} at {:?} is not allowed.",
+                orientation, tile.0, id
+            ),
+            MapIssue::MissingGroupMembers {
+                group,
+                group_id,
+                missing,
+            } => write!(
+                f,
+                "Group {} instance {} is missing {} tiles: {}.",
+                group.0,
+                group_id,
+                missing.len(),
+                missing
+                    .iter()
+                    .map(|t| t.0.clone())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            MapIssue::Overlap { id, face, layer } => write!(
+                f,
+                "Overlapping tiles on {:?} of {:?} (layer: {}).",
+                face, id, layer
+            ),
+            MapIssue::UnknownLayer { id, layer } => {
+                write!(f, "Unknown layer {} at {:?}.", layer, id)
+            }
+            MapIssue::OutOfLimits { id, length } => write!(
+                f,
+                "Cell {:?} is outside of the map limits of {} chunks.",
+                id, length
+            ),
+        }
+    }
+}
+
+/// Whether the tile may be placed with this index of [crate::grid::OrthogonalBases].
+/// Tiles without `x_rotations` rotate freely, as do vertically rotating tiles without `y_rotations`.
+pub fn orientation_allowed(properties: &TileProperties, orientation: u8) -> bool {
+    if orientation >= 24 {
+        return false;
+    }
+    if properties.x_rotations.is_empty()
+        || (properties.vertical_rotation && properties.y_rotations.is_empty())
+    {
+        return true;
+    }
+    properties.x_rotations.contains(&orientation) || properties.y_rotations.contains(&orientation)
+}
+
+fn strict_face_index(face: &StrictCellFace) -> u8 {
+    match face {
+        StrictCellFace::FrontWall => 0,
+        StrictCellFace::RightWall => 1,
+        StrictCellFace::Floor => 2,
+        StrictCellFace::Center => 3,
+    }
+}
+
+/// Check map cells against the registered tile properties and groups of the gridmap.
+pub fn validate_cells(
+    gridmap: &Gridmap,
+    cells: &Vec<CellDataExport>,
+    limits: MapLimits,
+) -> Vec<MapIssue> {
+    let mut issues = vec![];
+    let mut occupied: HashSet<(Vec3Int, u8, String)> = HashSet::new();
+    // Tiles placed per group instance.
+    let mut instances: HashMap<(GroupTypeName, u32), Vec<CellTypeName>> = HashMap::new();
+
+    for cell in cells.iter() {
+        if !limits.contains(cell.id) {
+            issues.push(MapIssue::OutOfLimits {
+                id: cell.id,
+                length: limits.length,
+            });
+        }
+
+        let tile;
+        match &cell.item {
+            ItemExport::Cell(name) => {
+                tile = name.clone();
+            }
+            ItemExport::Group(item) => {
+                tile = item.cell.clone();
+                if gridmap.group_id_map.contains_key(&item.name) {
+                    instances
+                        .entry((item.name.clone(), item.group_id))
+                        .or_default()
+                        .push(item.cell.clone());
+                } else {
+                    issues.push(MapIssue::UnknownGroup {
+                        id: cell.id,
+                        group: item.name.clone(),
+                    });
+                }
+            }
+        }
+
+        match gridmap.name_id_map.get(&tile) {
+            Some(tile_id) => match gridmap.tile_properties.get(tile_id) {
+                Some(properties) => {
+                    if !orientation_allowed(properties, cell.orientation) {
+                        issues.push(MapIssue::InvalidOrientation {
+                            id: cell.id,
+                            tile: tile.clone(),
+                            orientation: cell.orientation,
+                        });
+                    }
+                }
+                None => {}
+            },
+            None => {
+                issues.push(MapIssue::UnknownTile {
+                    id: cell.id,
+                    tile: tile.clone(),
+                });
+            }
+        }
+
+        let strict = gridmap.get_strict_cell(TargetCell {
+            id: cell.id,
+            face: cell.face.clone(),
+            grid: GridId::MAIN,
+        });
+        if gridmap.layer_id(&cell.layer).is_none() {
+            issues.push(MapIssue::UnknownLayer {
+                id: cell.id,
+                layer: cell.layer.clone(),
+            });
+        }
+        let key = (
+            strict.id,
+            strict_face_index(&strict.face),
+            cell.layer.clone(),
+        );
+        if !occupied.insert(key) {
+            issues.push(MapIssue::Overlap {
+                id: cell.id,
+                face: cell.face.clone(),
+                layer: cell.layer.clone(),
+            });
+        }
+    }
+
+    let mut instance_keys: Vec<&(GroupTypeName, u32)> = instances.keys().collect();
+    instance_keys.sort_by(|a, b| (&a.0 .0, a.1).cmp(&(&b.0 .0, b.1)));
+    for key in instance_keys {
+        let group_type = gridmap.group_id_map.get(&key.0).unwrap();
+        let mut placed = instances.get(key).unwrap().clone();
+        let mut missing = vec![];
+        match gridmap.groups.get(group_type) {
+            Some(group) => {
+                for member in group.values() {
+                    let name;
+                    match gridmap.id_name_map.get(&member.tile_type) {
+                        Some(n) => {
+                            name = n;
+                        }
+                        None => {
+                            continue;
+                        }
+                    }
+                    match placed.iter().position(|p| p == name) {
+                        Some(i) => {
+                            placed.swap_remove(i);
+                        }
+                        None => {
+                            missing.push(name.clone());
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+        if !missing.is_empty() {
+            missing.sort_by(|a, b| a.0.cmp(&b.0));
+            issues.push(MapIssue::MissingGroupMembers {
+                group: key.0.clone(),
+                group_id: key.1,
+                missing,
+            });
+        }
+    }
+
+    issues
+}
+
+/// Read the cells file of a map and validate it.
+pub fn validate_map_file(gridmap: &Gridmap, map: &SelectedMap) -> Result<Vec<MapIssue>, MapError> {
+    let cells_path = map.cells_path();
+    let raw = read_map_file(&cells_path)?;
+    let decoded = decode_gridmap(&raw, GridmapEncoding::from_path(&cells_path))
+        .map_err(|rr| MapError::Parse(cells_path.clone(), rr.to_string()))?;
+    let limits;
+    match map.manifest.map_length {
+        Some(length) => {
+            limits = MapLimits::new(length);
+        }
+        None => {
+            limits = decoded.map_limits;
+        }
+    }
+    Ok(validate_cells(gridmap, &decoded.cells, limits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CellTypeId;
+    use crate::layers::STRUCTURE_LAYER;
+
+    #[test]
+    fn test_validate_cells() {
+        let mut gridmap = Gridmap::default();
+        let wall = CellTypeName("wall".to_string());
+        gridmap.name_id_map.insert(wall.clone(), CellTypeId(0));
+        gridmap.tile_properties.insert(
+            CellTypeId(0),
+            TileProperties {
+                name_id: wall.clone(),
+                x_rotations: vec![0, 16],
+                ..Default::default()
+            },
+        );
+        let cell = |id: Vec3Int, face: CellFace, tile: &str, orientation: u8| CellDataExport {
+            id,
+            item: ItemExport::Cell(CellTypeName(tile.to_string())),
+            orientation,
+            face,
+            layer: STRUCTURE_LAYER.to_string(),
+            damage: None,
+            metadata: None,
+        };
+        let origin = Vec3Int { x: 0, y: 0, z: 0 };
+        let cells = vec![
+            cell(origin, CellFace::FrontWall, "wall", 0),
+            // Same face as the front wall of the origin.
+            cell(Vec3Int { x: 0, y: 0, z: 1 }, CellFace::BackWall, "wall", 16),
+            cell(origin, CellFace::Floor, "wall", 3),
+            cell(origin, CellFace::Center, "missing", 0),
+        ];
+        let issues = validate_cells(&gridmap, &cells, MapLimits::new(4));
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(issues[0], MapIssue::Overlap { .. }));
+        assert!(matches!(issues[1], MapIssue::InvalidOrientation { .. }));
+        assert!(matches!(issues[2], MapIssue::UnknownTile { .. }));
+    }
+}
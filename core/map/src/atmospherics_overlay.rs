@@ -0,0 +1,161 @@
+use bevy::prelude::{Entity, EventWriter, Query, Res, ResMut, Transform};
+use gridmap::atmospherics::{Atmospherics, GasMixture};
+use networking::server::{ConnectedPlayer, OutgoingReliableServerMessage};
+use resources::math::{world_to_cell_id, Vec3Int, CELL_SIZE};
+
+use crate::{
+    map::{
+        get_overlay_tile_item, get_overlay_tile_priority, overlay_cache_index, Map, MapHolders,
+        OverlayTile,
+    },
+    net::MapServerMessage,
+};
+
+/// Most cells from the camera an atmospherics overlay covers.
+pub const MAX_OVERLAY_RANGE: usize = 48;
+/// Overlay item of cells without an overlay tile.
+pub const EMPTY_OVERLAY_ITEM: i16 = -1;
+
+/// Pick the first tile whose inclusive range contains the value, red when none do.
+fn tile_in_ranges(value: f32, ranges: [(f32, f32); 3]) -> OverlayTile {
+    let tiles = [OverlayTile::Green, OverlayTile::Yellow, OverlayTile::Orange];
+    for (tile, (min, max)) in tiles.into_iter().zip(ranges) {
+        if value >= min && value <= max {
+            return tile;
+        }
+    }
+    OverlayTile::Red
+}
+
+fn worst(tiles: Vec<OverlayTile>) -> OverlayTile {
+    tiles
+        .into_iter()
+        .max_by_key(get_overlay_tile_priority)
+        .unwrap_or(OverlayTile::Red)
+}
+
+/// The overlay tile of a gas mixture for an atmospherics display mode.
+pub fn atmospherics_overlay_tile(display_mode: &str, mixture: &GasMixture) -> Option<OverlayTile> {
+    let temperature = tile_in_ranges(
+        mixture.temperature,
+        [(283., 303.), (273., 313.), (253., 333.)],
+    );
+    let pressure = tile_in_ranges(mixture.pressure(), [(90., 110.), (70., 130.), (40., 160.)]);
+    match display_mode {
+        "atmospherics_temperature" => Some(temperature),
+        "atmospherics_pressure" => Some(pressure),
+        "atmospherics_liveable" => {
+            let oxygen = tile_in_ranges(
+                mixture.partial_pressure(mixture.oxygen),
+                [(19., 50.), (16., 60.), (12., 80.)],
+            );
+            let carbon_dioxide = tile_in_ranges(
+                mixture.partial_pressure(mixture.carbon_dioxide),
+                [(0., 0.5), (0., 1.), (0., 3.)],
+            );
+            Some(worst(vec![temperature, pressure, oxygen, carbon_dioxide]))
+        }
+        _ => None,
+    }
+}
+
+/// Readout of a cell for the hover text of atmospherics overlays.
+fn hover_text(mixture_option: Option<&GasMixture>) -> String {
+    match mixture_option {
+        Some(mixture) => format!(
+            "Pressure: {:.1} kPa\nTemperature: {:.1} K\nOxygen: {:.1} kPa\nNitrogen: {:.1} kPa\nCarbon dioxide: {:.1} kPa",
+            mixture.pressure(),
+            mixture.temperature,
+            mixture.partial_pressure(mixture.oxygen),
+            mixture.partial_pressure(mixture.nitrogen),
+            mixture.partial_pressure(mixture.carbon_dioxide),
+        ),
+        None => "Space.".to_string(),
+    }
+}
+
+/// Send the atmospherics overlay tiles that changed around the map camera, at the y level of the map holder.
+pub(crate) fn atmospherics_map_overlay(
+    map_holders: Query<(Entity, &Map, &ConnectedPlayer, &Transform)>,
+    mut holders: ResMut<MapHolders>,
+    atmospherics: Res<Atmospherics>,
+    mut server: EventWriter<OutgoingReliableServerMessage<MapServerMessage>>,
+) {
+    for (entity, map_component, connected_player_component, transform) in map_holders.iter() {
+        let display_mode;
+        match &map_component.display_mode {
+            Some(mode) => {
+                if !mode.starts_with("atmospherics_") {
+                    continue;
+                }
+                display_mode = mode;
+            }
+            None => {
+                continue;
+            }
+        }
+        if !connected_player_component.connected {
+            continue;
+        }
+        let holder = holders.holders.entry(entity).or_default();
+        if holder.reset_cache {
+            for cached in holder.cache.iter_mut() {
+                cached.tile_color = None;
+            }
+            holder.reset_cache = false;
+        }
+
+        let y = world_to_cell_id(transform.translation).y;
+        let range = map_component.view_range.min(MAX_OVERLAY_RANGE) as i16;
+        let center_x = (map_component.camera_position.x / CELL_SIZE).floor() as i16;
+        let center_z = (map_component.camera_position.y / CELL_SIZE).floor() as i16;
+
+        let mut updates = vec![];
+        for x in center_x.saturating_sub(range)..=center_x.saturating_add(range) {
+            for z in center_z.saturating_sub(range)..=center_z.saturating_add(range) {
+                let index;
+                match overlay_cache_index(x, z) {
+                    Some(i) => {
+                        index = i;
+                    }
+                    None => {
+                        continue;
+                    }
+                }
+                let tile = atmospherics
+                    .get(&Vec3Int { x, y, z })
+                    .and_then(|mixture| atmospherics_overlay_tile(display_mode, mixture));
+                if holder.cache[index].tile_color != tile {
+                    updates.push((
+                        x,
+                        z,
+                        tile.as_ref()
+                            .map(get_overlay_tile_item)
+                            .unwrap_or(EMPTY_OVERLAY_ITEM),
+                    ));
+                    holder.cache[index].tile_color = tile;
+                }
+            }
+        }
+        if !updates.is_empty() {
+            server.send(OutgoingReliableServerMessage {
+                handle: connected_player_component.handle,
+                message: MapServerMessage::MapOverlayUpdate(updates),
+            });
+        }
+
+        match map_component.passed_mouse_cell {
+            Some((x, z)) => {
+                let text = hover_text(atmospherics.get(&Vec3Int { x, y, z }));
+                if text != holder.hovering_data {
+                    holder.hovering_data = text.clone();
+                    server.send(OutgoingReliableServerMessage {
+                        handle: connected_player_component.handle,
+                        message: MapServerMessage::MapOverlayHoverData(text),
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -1,5 +1,7 @@
 //! The 2D mini-map that can display various data with overlays.
 
+/// Send atmospherics overlay data.
+mod atmospherics_overlay;
 /// Manage overlay changes.
 mod change_overlay;
 /// Manage connection events.
@@ -45,6 +45,17 @@ pub struct AtmosphericsCache {
     pub tile_color: Option<OverlayTile>,
 }
 
+/// Index of a cell in [MapHolderData::cache], [None] outside of the world.
+pub fn overlay_cache_index(x: i16, z: i16) -> Option<usize> {
+    let half = WORLD_WIDTH_CELLS as i32 / 2;
+    let x = x as i32 + half;
+    let z = z as i32 + half;
+    if x < 0 || z < 0 || x >= WORLD_WIDTH_CELLS as i32 || z >= WORLD_WIDTH_CELLS as i32 {
+        return None;
+    }
+    Some(x as usize + z as usize * WORLD_WIDTH_CELLS)
+}
+
 pub const GREEN_MAP_TILE_ENTRANCE: i16 = 3;
 pub const GREEN_MAP_TILE_COUNTER: i16 = 4;
 
@@ -1,4 +1,7 @@
+use std::time::Duration;
+
 use bevy::prelude::{App, IntoSystemConfigs, Plugin};
+use bevy::time::common_conditions::on_timer;
 use networking::messaging::{
     register_reliable_message, register_unreliable_message, MessageSender, MessagingSet,
 };
@@ -18,6 +21,7 @@ use crate::{
 };
 
 use super::{
+    atmospherics_overlay::atmospherics_map_overlay,
     change_overlay::change_map_overlay,
     map_input::{map_input, request_map_overlay},
 };
@@ -33,6 +37,9 @@ impl Plugin for MapPlugin {
                         request_map_overlay,
                         change_map_overlay.in_set(MapSet::ChangeMode),
                         map_input.in_set(MapSet::ChangeMode),
+                        atmospherics_map_overlay
+                            .after(MapSet::ChangeMode)
+                            .run_if(on_timer(Duration::from_secs_f32(1. / 2.))),
                         configure
                             .in_set(ConfigurationLabel::Main)
                             .after(ConfigurationLabel::SpawnEntity)
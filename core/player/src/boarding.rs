@@ -1,10 +1,10 @@
 use bevy::{
-    prelude::{Commands, Entity, EventReader, ResMut, Resource},
+    prelude::{Commands, Entity, EventReader, Local, Res, ResMut, Resource},
     time::Timer,
 };
 use bevy_renet::renet::ClientId;
 use networking::client::IncomingReliableServerMessage;
-use resources::{pawn::HUMANOID_HEIGHT, player::SoftPlayer};
+use resources::{maps::SelectedMap, pawn::HUMANOID_HEIGHT, player::SoftPlayer};
 use ui::cursor::GrabCursor;
 
 /// Component with boarding data.
@@ -67,17 +67,31 @@ pub fn done_boarding(
     mut commands: Commands,
     mut spawning: EventWriter<SpawningPlayer>,
     mut asana_boarding_announcements: ResMut<BoardingAnnouncements>,
+    selected_map: Option<Res<SelectedMap>>,
+    mut spawn_point_i: Local<usize>,
 ) {
     for boarding_player in boarding_player_event.read() {
         let player_character_name = boarding_player.player_character_name.clone();
         let player_handle = boarding_player.player_handle;
         let entity_id = boarding_player.entity;
 
-        //let mut assigned_spawn_transform = spawn_points.list[spawn_points.i].transform;
         let mut assigned_spawn_transform = Transform::IDENTITY;
-        assigned_spawn_transform.translation.y = HUMANOID_HEIGHT - 0.5 - 0.5 + 0.2;
         assigned_spawn_transform.translation.x += 3.;
         assigned_spawn_transform.translation.z += 7.;
+        match &selected_map {
+            Some(map) => {
+                let spawn_points = &map.manifest.spawn_points;
+                if !spawn_points.is_empty() {
+                    let spawn_point = &spawn_points[*spawn_point_i % spawn_points.len()];
+                    assigned_spawn_transform.translation = spawn_point.translation;
+                    assigned_spawn_transform.rotation = spawn_point.rotation;
+                    *spawn_point_i += 1;
+                }
+            }
+            None => {}
+        }
+        // Spawn points are on floor level.
+        assigned_spawn_transform.translation.y += HUMANOID_HEIGHT - 0.5 - 0.5 + 0.2;
 
         commands
             .entity(entity_id)
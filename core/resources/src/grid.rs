@@ -15,10 +15,22 @@ pub enum CellFace {
     Center,
 }
 
+/// Identifies a gridmap. Every grid has its own cells, transform and velocity.
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone, Copy, Debug,
+)]
+pub struct GridId(pub u32);
+
+impl GridId {
+    /// The static grid the map gets loaded into.
+    pub const MAIN: GridId = GridId(0);
+}
+
 #[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
 pub struct TargetCell {
     pub id: Vec3Int,
     pub face: CellFace,
+    pub grid: GridId,
 }
 #[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
 pub struct TargetCellWithOrientation {
@@ -33,4 +45,5 @@ pub struct Tile;
 #[derive(Component)]
 pub struct GridmapCollider {
     pub chunk_id: usize,
+    pub grid: GridId,
 }
@@ -11,6 +11,8 @@ pub mod grid;
 pub mod hud;
 pub mod input;
 pub mod light;
+/// Map selection and map manifests.
+pub mod maps;
 pub mod math;
 pub mod modes;
 pub mod pawn;
@@ -0,0 +1,190 @@
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::log::{error, info, warn};
+use bevy::prelude::{Commands, Quat, Resource, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Map that gets loaded when neither the command line nor the server settings select one.
+pub const DEFAULT_MAP: &str = "bullseye";
+/// Name of the manifest file inside a map directory.
+pub const MAP_MANIFEST_FILE: &str = "manifest.ron";
+
+/// A position players can be spawned at.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapSpawnPoint {
+    pub translation: Vec3,
+    #[serde(default)]
+    pub rotation: Quat,
+}
+
+fn default_entities_file() -> String {
+    "entities.ron".to_string()
+}
+fn default_cells_file() -> String {
+    "main.bin".to_string()
+}
+
+/// Describes a map directory. Stored as `manifest.ron` in `data/maps/<map>`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub spawn_points: Vec<MapSpawnPoint>,
+    /// Entities file relative to the map directory.
+    #[serde(default = "default_entities_file")]
+    pub entities: String,
+    /// Gridmap cells file relative to the map directory.
+    #[serde(default = "default_cells_file")]
+    pub cells: String,
+    /// Full length of the map cube in chunks. Overrides the limits stored in the cells file.
+    #[serde(default)]
+    pub map_length: Option<i16>,
+}
+
+impl MapManifest {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: "".to_string(),
+            spawn_points: vec![],
+            entities: default_entities_file(),
+            cells: default_cells_file(),
+            map_length: None,
+        }
+    }
+}
+
+/// The map this instance runs, with its manifest.
+#[derive(Resource, Clone, Debug)]
+pub struct SelectedMap {
+    pub manifest: MapManifest,
+    pub directory: PathBuf,
+}
+
+impl SelectedMap {
+    pub fn cells_path(&self) -> PathBuf {
+        self.directory.join(&self.manifest.cells)
+    }
+    pub fn entities_path(&self) -> PathBuf {
+        self.directory.join(&self.manifest.entities)
+    }
+}
+
+/// Errors that can occur while reading map files from the drive.
+#[derive(Debug)]
+pub enum MapError {
+    NotFound(PathBuf),
+    Read(PathBuf, String),
+    Parse(PathBuf, String),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::NotFound(path) => write!(f, "Map file {:?} does not exist.", path),
+            MapError::Read(path, rr) => write!(f, "Couldn't read map file {:?}: {}", path, rr),
+            MapError::Parse(path, rr) => write!(f, "Couldn't parse map file {:?}: {}", path, rr),
+        }
+    }
+}
+
+/// Read a map file from the drive.
+pub fn read_map_file(path: &Path) -> Result<Vec<u8>, MapError> {
+    if !path.exists() {
+        return Err(MapError::NotFound(path.to_path_buf()));
+    }
+    fs::read(path).map_err(|rr| MapError::Read(path.to_path_buf(), rr.to_string()))
+}
+
+/// Server settings stored in `data/settings/server.ron`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerSettings {
+    pub map: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            map: DEFAULT_MAP.to_string(),
+        }
+    }
+}
+
+/// Get the name of the map to load. The `map <name>` command line argument takes priority over `data/settings/server.ron`.
+pub fn get_map_name() -> String {
+    let args: Vec<String> = env::args().collect();
+
+    match args.iter().position(|a| a == "map") {
+        Some(i) => match args.get(i + 1) {
+            Some(name) => {
+                return name.to_string();
+            }
+            None => {
+                warn!("Missing map name after \"map\" argument.");
+            }
+        },
+        None => {}
+    }
+
+    let path = Path::new("data").join("settings").join("server.ron");
+    if path.exists() {
+        match fs::read_to_string(path.clone()) {
+            Ok(settings_ron) => match ron::from_str::<ServerSettings>(&settings_ron) {
+                Ok(settings) => {
+                    return settings.map;
+                }
+                Err(rr) => {
+                    warn!("Couldn't parse {:?}: {}", path, rr);
+                }
+            },
+            Err(rr) => {
+                warn!("Couldn't read {:?}: {}", path, rr);
+            }
+        }
+    }
+    DEFAULT_MAP.to_string()
+}
+
+/// Load the manifest of a map in `data/maps`. Maps without a manifest use the default file names.
+pub fn load_map_manifest(name: &str) -> Result<SelectedMap, MapError> {
+    let directory = Path::new("data").join("maps").join(name);
+    if !directory.exists() {
+        return Err(MapError::NotFound(directory));
+    }
+    let manifest_path = directory.join(MAP_MANIFEST_FILE);
+    let manifest;
+    if manifest_path.exists() {
+        let raw = read_map_file(&manifest_path)?;
+        let manifest_ron = String::from_utf8_lossy(&raw);
+        manifest = ron::from_str::<MapManifest>(&manifest_ron)
+            .map_err(|rr| MapError::Parse(manifest_path.clone(), rr.to_string()))?;
+    } else {
+        warn!(
+            "No {} found for map {}, using defaults.",
+            MAP_MANIFEST_FILE, name
+        );
+        manifest = MapManifest::new(name);
+    }
+    Ok(SelectedMap {
+        manifest,
+        directory,
+    })
+}
+
+pub(crate) fn init_selected_map(mut commands: Commands) {
+    let name = get_map_name();
+    match load_map_manifest(&name) {
+        Ok(selected) => {
+            info!("Selected map \"{}\".", selected.manifest.name);
+            commands.insert_resource(selected);
+        }
+        Err(rr) => {
+            error!("Couldn't select map \"{}\": {}", name, rr);
+        }
+    }
+}
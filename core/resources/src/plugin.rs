@@ -8,8 +8,9 @@ use bevy::{
 use crate::{
     correction::StartCorrection,
     input::{buffer_input, clear_buffer, InputBuffer, KeyBinds},
+    maps::init_selected_map,
     modes::is_server_mode,
-    ordering::{BuildingSet, PostUpdate, PreUpdate},
+    ordering::{BuildingSet, PostUpdate, PreUpdate, StartupSet},
     set_icon::set_window_icon,
     ui::MainMenuState,
 };
@@ -28,16 +29,17 @@ impl Plugin for ResourcesPlugin {
                 .add_event::<StartCorrection>()
                 .add_systems(Startup, set_window_icon);
         }
-        app.configure_sets(
-            PreUpdate,
-            (
-                BuildingSet::RawTriggerBuild,
-                BuildingSet::TriggerBuild,
-                SpawnItemSet::SpawnHeldItem,
-                BuildingSet::NormalBuild,
-            )
-                .chain(),
-        );
+        app.add_systems(Startup, init_selected_map.in_set(StartupSet::MiscResources))
+            .configure_sets(
+                PreUpdate,
+                (
+                    BuildingSet::RawTriggerBuild,
+                    BuildingSet::TriggerBuild,
+                    SpawnItemSet::SpawnHeldItem,
+                    BuildingSet::NormalBuild,
+                )
+                    .chain(),
+            );
     }
 }
 
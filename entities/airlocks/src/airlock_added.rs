@@ -1,11 +1,11 @@
 use std::collections::BTreeMap;
 
-use bevy::prelude::{Added, Entity, Query, ResMut, Transform};
+use bevy::prelude::{Added, Changed, Entity, Query, RemovedComponents, ResMut, Transform};
 use entity::{
     entity_data::{DefaultMapEntity, EntityData},
     examine::{Examinable, RichName},
 };
-use gridmap::grid::Gridmap;
+use gridmap::{atmospherics::Atmospherics, grid::Gridmap};
 use map::{map::GREEN_MAP_TILE_ENTRANCE, map_input::MapData};
 use resources::math::{world_to_cell_id, Vec2Int};
 use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR};
@@ -14,7 +14,7 @@ use crate::spawn::{
     BRIDGE_AIRLOCK_ENTITY_NAME, GOVERNMENT_AIRLOCK_ENTITY_NAME, VACUUM_AIRLOCK_ENTITY_NAME,
 };
 
-use super::resources::Airlock;
+use super::resources::{Airlock, AirlockStatus};
 
 /// On new air lock spawn.
 
@@ -146,3 +146,26 @@ pub(crate) fn airlock_default_map_added(
         );*/
     }
 }
+
+/// Seal the cells of closed air locks for atmospherics, until they open or are removed.
+
+pub(crate) fn airlock_seal_atmospherics(
+    airlocks: Query<(Entity, &Airlock, &Transform), Changed<Airlock>>,
+    mut removed: RemovedComponents<Airlock>,
+    mut atmospherics: ResMut<Atmospherics>,
+) {
+    for entity in removed.read() {
+        atmospherics.remove_entity_seal(entity);
+    }
+    for (entity, airlock_component, rigid_body_position_component) in airlocks.iter() {
+        let cell_id = world_to_cell_id(rigid_body_position_component.translation);
+        match airlock_component.status {
+            AirlockStatus::Open => {
+                atmospherics.set_entity_sealed(entity, cell_id, false);
+            }
+            AirlockStatus::Closed => {
+                atmospherics.set_entity_sealed(entity, cell_id, true);
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ use bevy::{
 
 use bevy_renet::renet::ClientId;
 use entity::{entity_data::EntityGroup, examine::Examinable};
+use gridmap::power::{opens_automatically, PowerDevice};
 use pawn::pawn::{Pawn, ShipAuthorization};
 use resources::math::{world_to_cell_id, Vec2Int};
 use sfx::builder::sfx_builder;
@@ -43,6 +44,7 @@ pub(crate) fn airlock_events(
     mut airlock_lock_close_event: EventReader<AirlockLockClosed>,
     mut unlock_events: EventReader<AirlockUnlock>,
     mut server: EventWriter<OutgoingReliableServerMessage<NetworkingChatServerMessage>>,
+    power_devices: Query<&PowerDevice>,
 ) {
     let mut close_requests = vec![];
     let mut open_requests = vec![];
@@ -278,6 +280,10 @@ pub(crate) fn airlock_events(
             pawn_entity = collision_event.collider1_entity;
         }
 
+        if !opens_automatically(&power_devices, airlock_entity) {
+            continue;
+        }
+
         open_requests.push(AirLockOpenRequest {
             opener_option: Some(pawn_entity),
             opened: airlock_entity,
@@ -18,7 +18,7 @@ use crate::{
 };
 
 use super::{
-    airlock_added::{airlock_added, airlock_default_map_added},
+    airlock_added::{airlock_added, airlock_default_map_added, airlock_seal_atmospherics},
     airlock_events::airlock_events,
     airlock_tick_timers::airlock_tick_timers,
     spawn::{build_airlocks, AirlockType},
@@ -43,6 +43,7 @@ impl Plugin for AirLocksPlugin {
                         airlock_tick_timers,
                         airlock_default_map_added,
                         airlock_events,
+                        airlock_seal_atmospherics.after(airlock_events),
                         health_combat_hit_result_sfx::<Airlock>
                             .after(CombatSet::FinalizeApplyDamage),
                         toggle_open_action_prequisite_check
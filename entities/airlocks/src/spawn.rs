@@ -17,6 +17,7 @@ use entity::{
     health::Health,
     spawn::{BaseEntityBuilder, BaseEntityBundle, EntityBuildData, NoData, SpawnEntity},
 };
+use gridmap::power::PowerDevice;
 use pawn::pawn::ShipAuthorizationEnum;
 use physics::spawn::{RigidBodyBuilder, RigidBodyBundle};
 use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR};
@@ -92,6 +93,8 @@ impl BaseEntityBuilder<NoData> for AirlockType {
 }
 
 pub const DEFAULT_AIRLOCK_Y: f32 = 1.;
+/// Power in kW an air lock draws.
+pub const AIRLOCK_POWER_USAGE: f32 = 0.5;
 
 impl RigidBodyBuilder<NoData> for AirlockType {
     fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> RigidBodyBundle {
@@ -130,10 +133,13 @@ pub fn build_airlocks<T: Send + Sync + 'static>(
     for spawn_event in airlock_spawns.read() {
         commands
             .entity(spawn_event.spawn_data.entity.unwrap())
-            .insert(Airlock {
-                access_permissions: vec![ShipAuthorizationEnum::Security],
-                ..Default::default()
-            });
+            .insert((
+                Airlock {
+                    access_permissions: vec![ShipAuthorizationEnum::Security],
+                    ..Default::default()
+                },
+                PowerDevice::consumer(AIRLOCK_POWER_USAGE),
+            ));
     }
 }
 use resources::core::SF_CONTENT_PREFIX;
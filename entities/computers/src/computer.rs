@@ -1,12 +1,13 @@
 use std::collections::BTreeMap;
 
 use crate::spawn::ComputerType;
-use bevy::prelude::{Added, Component, Query};
+use bevy::prelude::{Added, Changed, Component, Query, With};
 use entity::{
     entity_data::EntityData,
     examine::{Examinable, RichName},
 };
-use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR};
+use gridmap::power::PowerDevice;
+use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR, WARNING_COLOR};
 
 /// On a computer spawn.
 
@@ -38,6 +39,30 @@ pub(crate) fn computer_added(
     }
 }
 
+/// Show whether a computer is operational as it gains or loses power.
+
+pub(crate) fn computer_power(
+    mut computers: Query<(&PowerDevice, &mut Examinable), (With<Computer>, Changed<PowerDevice>)>,
+) {
+    for (power_device, mut examinable_component) in computers.iter_mut() {
+        let text;
+        if power_device.powered {
+            text = "[font=".to_string()
+                + FURTHER_ITALIC_FONT
+                + "][color="
+                + HEALTHY_COLOR
+                + "]It is fully operational.[/color][/font]";
+        } else {
+            text = "[font=".to_string()
+                + FURTHER_ITALIC_FONT
+                + "][color="
+                + WARNING_COLOR
+                + "]Its screen is dark, it has no power.[/color][/font]";
+        }
+        examinable_component.assigned_texts.insert(1, text);
+    }
+}
+
 /// The computer component.
 #[derive(Component)]
 
@@ -9,7 +9,7 @@ use resources::ordering::{BuildingSet, CombatSet, PreUpdate, Update};
 use crate::computer::Computer;
 
 use super::{
-    computer::computer_added,
+    computer::{computer_added, computer_power},
     spawn::{build_computers, ComputerType},
 };
 
@@ -23,6 +23,7 @@ impl Plugin for ComputersPlugin {
                 (
                     health_combat_hit_result_sfx::<Computer>.after(CombatSet::FinalizeApplyDamage),
                     computer_added,
+                    computer_power.after(computer_added),
                 ),
             );
         }
@@ -87,9 +87,13 @@ pub fn build_computers<T: Send + Sync + 'static>(
     for spawn_event in spawn_events.read() {
         commands
             .entity(spawn_event.spawn_data.entity.unwrap())
-            .insert(Computer);
+            .insert((Computer, PowerDevice::consumer(COMPUTER_POWER_USAGE)));
     }
 }
+use gridmap::power::PowerDevice;
 use resources::core::SF_CONTENT_PREFIX;
 
+/// Power in kW a computer draws.
+pub const COMPUTER_POWER_USAGE: f32 = 0.2;
+
 use super::computer::Computer;
@@ -1,6 +1,7 @@
 use actions::core::{Action, ActionData, ActionRequests, BuildingActions};
 use bevy::log::warn;
 use bevy::prelude::{EventReader, EventWriter, Query, Res, ResMut, With};
+use gridmap::blueprint::{list_blueprints, Blueprints, BLUEPRINT_OPTION_PREFIX};
 use gridmap::grid::LayerTargetCell;
 use gridmap::{
     grid::{CellIds, CellTypeName, Gridmap, GroupTypeName},
@@ -126,6 +127,9 @@ pub fn send_constructable_items(
                 for (name, _) in gridmap.group_id_map.iter() {
                     names.push(name.to_string());
                 }
+                for name in list_blueprints() {
+                    names.push(format!("{}{}", BLUEPRINT_OPTION_PREFIX, name));
+                }
                 net.send(OutgoingReliableServerMessage {
                     handle: handle,
                     message: UiServerMessage::TextTreeSelection(TextTreeSelection {
@@ -160,7 +164,7 @@ pub fn construct_action_prequisite_check(
                         continue;
                     }
                 }
-                let is_detail;
+                let layer;
                 match building.action_taker_item {
                     Some(item_entity) => match tools.get(item_entity) {
                         Ok(t) => match &t.construction_option {
@@ -168,7 +172,7 @@ pub fn construct_action_prequisite_check(
                                 CellIds::CellType(cell_type_id) => {
                                     match gridmap.tile_properties.get(cell_type_id) {
                                         Some(properties) => {
-                                            is_detail = properties.is_detail;
+                                            layer = properties.layer;
                                         }
                                         None => {
                                             warn!("Couldnt find tileproperties");
@@ -177,7 +181,7 @@ pub fn construct_action_prequisite_check(
                                         }
                                     }
                                 }
-                                CellIds::GroupType(_) => {
+                                CellIds::GroupType(_) | CellIds::Blueprint(_) => {
                                     action.do_not_approve();
                                     continue;
                                 }
@@ -200,7 +204,7 @@ pub fn construct_action_prequisite_check(
 
                 let cell_option = gridmap.get_cell(LayerTargetCell {
                     target: cell_id,
-                    is_detail,
+                    layer,
                 });
 
                 match building.target_cell_option.is_some() && cell_option.is_none() {
@@ -234,17 +238,15 @@ pub(crate) fn deconstruct_action_prequisite_check(
                     }
                 }
 
-                let cell_option = gridmap_main.get_cell(LayerTargetCell {
-                    target: cell_id.clone(),
-                    is_detail: false,
-                });
-                let cell_option_detail = gridmap_main.get_cell(LayerTargetCell {
-                    target: cell_id,
-                    is_detail: true,
+                let occupied = gridmap_main.layer_properties.keys().any(|layer| {
+                    gridmap_main
+                        .get_cell(LayerTargetCell {
+                            target: cell_id.clone(),
+                            layer: *layer,
+                        })
+                        .is_some()
                 });
-                match building.target_cell_option.is_some()
-                    && (cell_option.is_some() || cell_option_detail.is_some())
-                {
+                match building.target_cell_option.is_some() && occupied {
                     true => {
                         action.approve();
                     }
@@ -329,9 +331,35 @@ pub(crate) fn construction_tool_select_construction_option(
     mut query: Query<&mut ConstructionTool>,
     mut net: EventWriter<OutgoingReliableServerMessage<GridmapServerMessage>>,
     gridmap: Res<Gridmap>,
+    mut blueprints: ResMut<Blueprints>,
 ) {
     for event in input_events.read() {
         if event.id == CONSTRUCTION_OPTIONS_TEXT_LIST_ID {
+            match event.entry.strip_prefix(BLUEPRINT_OPTION_PREFIX) {
+                Some(name) => {
+                    match query.get_mut(event.entity) {
+                        Ok(mut c) => match blueprints.get_or_load(name) {
+                            Ok(blueprint) => {
+                                c.construction_option = Some(CellIds::Blueprint(name.to_string()));
+                                net.send(OutgoingReliableServerMessage {
+                                    handle: event.handle,
+                                    message: GridmapServerMessage::GhostBlueprint(
+                                        blueprint.clone(),
+                                    ),
+                                });
+                            }
+                            Err(rr) => {
+                                warn!("{}", rr);
+                            }
+                        },
+                        Err(_) => {
+                            warn!("Couldnt find construction tool {:?}.", event.entity);
+                        }
+                    }
+                    continue;
+                }
+                None => {}
+            }
             match query.get_mut(event.entity) {
                 Ok(mut c) => match gridmap.name_id_map.get(&CellTypeName(event.entry.clone())) {
                     Some(type_id) => {
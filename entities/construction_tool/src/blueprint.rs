@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use bevy::log::info;
+use bevy::prelude::{Entity, EventReader, EventWriter, Query, Res, ResMut, Resource};
+use bevy_renet::renet::ClientId;
+use console_commands::{
+    commands::{AllConsoleCommands, ConsoleCommand, InputConsoleCommand},
+    net::{ConsoleCommandsServerMessage, ConsoleLine},
+};
+use gridmap::{
+    blueprint::{Blueprint, Blueprints},
+    grid::Gridmap,
+    net::GridmapClientMessage,
+};
+use hud::communication::build::CONSOLE_FONT_COLOR;
+use inventory::server::inventory::Inventory;
+use networking::server::{
+    ConsoleArgVariant, HandleToEntity, IncomingReliableClientMessage, OutgoingReliableServerMessage,
+};
+use resources::{grid::GridId, math::Vec3Int};
+use ui::{
+    fonts::{Fonts, SOURCECODE_REGULAR_FONT},
+    text::{NetTextSection, COMMUNICATION_FONT_SIZE},
+};
+
+use crate::{construction_tool::ConstructionTool, map_construction::get_active_construction_tool};
+
+/// The two corners of the region a player selected to save as blueprint.
+#[derive(Default)]
+pub struct BlueprintSelection {
+    /// Grid both corners are on.
+    pub grid: GridId,
+    pub corners: [Option<Vec3Int>; 2],
+    /// Corner that gets set next.
+    pub next: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct BlueprintSelections {
+    pub players: HashMap<Entity, BlueprintSelection>,
+}
+
+pub(crate) fn console_reply(
+    net: &mut EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: &Fonts,
+    handle: ClientId,
+    text: String,
+) {
+    net.send(OutgoingReliableServerMessage {
+        handle,
+        message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+            sections: vec![NetTextSection {
+                text,
+                font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                font_size: COMMUNICATION_FONT_SIZE,
+                color: CONSOLE_FONT_COLOR,
+            }],
+        }),
+    });
+}
+
+pub(crate) fn select_blueprint_corner(
+    mut net: EventReader<IncomingReliableClientMessage<GridmapClientMessage>>,
+    inventory_query: Query<&Inventory>,
+    handle_to_entity: Res<HandleToEntity>,
+    construction_tool_query: Query<&ConstructionTool>,
+    mut selections: ResMut<BlueprintSelections>,
+    mut console: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for message in net.read() {
+        match &message.message {
+            GridmapClientMessage::SelectBlueprintCorner(select) => {
+                let player_entity;
+                match get_active_construction_tool(
+                    &message.handle,
+                    &handle_to_entity,
+                    &inventory_query,
+                    &construction_tool_query,
+                ) {
+                    Some((entity, _)) => {
+                        player_entity = entity;
+                    }
+                    None => {
+                        continue;
+                    }
+                }
+                let selection = selections.players.entry(player_entity).or_default();
+                if selection.grid != select.grid {
+                    *selection = BlueprintSelection {
+                        grid: select.grid,
+                        ..Default::default()
+                    };
+                }
+                let corner = selection.next;
+                let id = select.id;
+                selection.corners[corner] = Some(id);
+                selection.next = (corner + 1) % 2;
+                console_reply(
+                    &mut console,
+                    &fonts,
+                    message.handle,
+                    format!(
+                        "Blueprint corner {} set to ({}, {}, {}).",
+                        corner + 1,
+                        id.x,
+                        id.y,
+                        id.z
+                    ),
+                );
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(crate) fn add_blueprint_commands(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "saveBlueprint".to_string(),
+        description: "Saves the region between the selected blueprint corners as blueprint. Pass true to overwrite an existing blueprint.".to_string(),
+        args: vec![
+            ("name".to_string(), ConsoleArgVariant::String),
+            ("overwrite".to_string(), ConsoleArgVariant::Bool),
+        ],
+    });
+}
+
+pub(crate) fn save_blueprint_command(
+    mut queue: EventReader<InputConsoleCommand>,
+    inventory_query: Query<&Inventory>,
+    handle_to_entity: Res<HandleToEntity>,
+    construction_tool_query: Query<&ConstructionTool>,
+    selections: Res<BlueprintSelections>,
+    gridmap: Res<Gridmap>,
+    mut blueprints: ResMut<Blueprints>,
+    mut console: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for command in queue.read() {
+        if command.input.command != "saveBlueprint" {
+            continue;
+        }
+        let handle;
+        match command.handle_option {
+            Some(h) => {
+                handle = h;
+            }
+            None => {
+                continue;
+            }
+        }
+        let player_entity;
+        match get_active_construction_tool(
+            &handle,
+            &handle_to_entity,
+            &inventory_query,
+            &construction_tool_query,
+        ) {
+            Some((entity, _)) => {
+                player_entity = entity;
+            }
+            None => {
+                console_reply(
+                    &mut console,
+                    &fonts,
+                    handle,
+                    "Hold a construction tool to save blueprints.".to_string(),
+                );
+                continue;
+            }
+        }
+        let name;
+        match command.input.args.get(0) {
+            Some(n) => {
+                name = n.clone();
+            }
+            None => {
+                console_reply(
+                    &mut console,
+                    &fonts,
+                    handle,
+                    "Missing blueprint name.".to_string(),
+                );
+                continue;
+            }
+        }
+        let overwrite = match command.input.args.get(1) {
+            Some(overwrite) => overwrite == "true",
+            None => false,
+        };
+        let (grid, corners) = match selections.players.get(&player_entity) {
+            Some(selection) => (selection.grid, (selection.corners[0], selection.corners[1])),
+            None => (GridId::MAIN, (None, None)),
+        };
+        let text;
+        match corners {
+            (Some(a), Some(b)) => match Blueprint::capture(&name, &gridmap, grid, a, b) {
+                Ok(blueprint) => match blueprint.save(overwrite) {
+                    Ok(path) => {
+                        text = format!(
+                            "Saved blueprint {} with {} cells and {} groups to {:?}.",
+                            name,
+                            blueprint.cells.len(),
+                            blueprint.groups.len(),
+                            path
+                        );
+                        info!("{}", text);
+                        blueprints.map.insert(name, blueprint);
+                    }
+                    Err(rr) => {
+                        text = rr.to_string();
+                    }
+                },
+                Err(rr) => {
+                    text = rr.to_string();
+                }
+            },
+            _ => {
+                text = "Select two blueprint corners first.".to_string();
+            }
+        }
+        console_reply(&mut console, &fonts, handle, text);
+    }
+}
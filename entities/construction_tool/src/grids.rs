@@ -0,0 +1,206 @@
+use bevy::log::info;
+use bevy::prelude::{Commands, EventReader, EventWriter, Query, Res, ResMut, Transform};
+use bevy_xpbd_3d::prelude::LinearVelocity;
+use console_commands::{
+    commands::{AllConsoleCommands, ConsoleCommand, InputConsoleCommand},
+    net::ConsoleCommandsServerMessage,
+};
+use gridmap::{
+    grid::{AddTile, CellIds, Gridmap, TileMetadata},
+    grids::GridMotion,
+};
+use inventory::server::inventory::Inventory;
+use networking::{
+    server::{ConsoleArgVariant, HandleToEntity, OutgoingReliableServerMessage},
+    stamp::TickRateStamp,
+};
+use resources::{grid::GridId, math::Vec3Int};
+use ui::fonts::Fonts;
+
+use crate::{
+    blueprint::console_reply, construction_tool::ConstructionTool,
+    map_construction::get_active_construction_tool,
+};
+
+/// Distance in front of the player new grids get spawned at.
+const NEW_GRID_DISTANCE: f32 = 3.;
+
+pub(crate) fn add_grid_commands(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "newGrid".to_string(),
+        description: "Starts a new grid in front of you with the selected tile.".to_string(),
+        args: vec![],
+    });
+    commands.list.push(ConsoleCommand {
+        base: "setGridVelocity".to_string(),
+        description: "Sets the linear velocity of a grid.".to_string(),
+        args: vec![
+            ("grid".to_string(), ConsoleArgVariant::Int),
+            ("x".to_string(), ConsoleArgVariant::Float),
+            ("y".to_string(), ConsoleArgVariant::Float),
+            ("z".to_string(), ConsoleArgVariant::Float),
+        ],
+    });
+}
+
+/// Perform "newGrid" command.
+pub(crate) fn new_grid_command(
+    mut queue: EventReader<InputConsoleCommand>,
+    inventory_query: Query<&Inventory>,
+    handle_to_entity: Res<HandleToEntity>,
+    construction_tool_query: Query<&ConstructionTool>,
+    transforms: Query<&Transform>,
+    mut gridmap: ResMut<Gridmap>,
+    mut commands: Commands,
+    mut add_tile: EventWriter<AddTile>,
+    stamp: Res<TickRateStamp>,
+    mut console: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for command in queue.read() {
+        if command.input.command != "newGrid" {
+            continue;
+        }
+        let handle;
+        match command.handle_option {
+            Some(h) => {
+                handle = h;
+            }
+            None => {
+                continue;
+            }
+        }
+        let player_entity;
+        let tile_type;
+        match get_active_construction_tool(
+            &handle,
+            &handle_to_entity,
+            &inventory_query,
+            &construction_tool_query,
+        ) {
+            Some((entity, component)) => match &component.construction_option {
+                Some(CellIds::CellType(id)) => {
+                    player_entity = entity;
+                    tile_type = *id;
+                }
+                _ => {
+                    console_reply(
+                        &mut console,
+                        &fonts,
+                        handle,
+                        "Select a single tile to start a grid with.".to_string(),
+                    );
+                    continue;
+                }
+            },
+            None => {
+                console_reply(
+                    &mut console,
+                    &fonts,
+                    handle,
+                    "Hold a construction tool to start a grid.".to_string(),
+                );
+                continue;
+            }
+        }
+        let face;
+        let layer;
+        match gridmap.tile_properties.get(&tile_type) {
+            Some(properties) => {
+                face = properties.cell_type.default_face();
+                layer = properties.layer;
+            }
+            None => {
+                continue;
+            }
+        }
+        let translation;
+        match transforms.get(player_entity) {
+            Ok(transform) => {
+                translation = transform.translation + transform.forward() * NEW_GRID_DISTANCE;
+            }
+            Err(_) => {
+                continue;
+            }
+        }
+        let grid = gridmap.spawn_grid(
+            &mut commands,
+            GridMotion {
+                translation,
+                ..Default::default()
+            },
+        );
+        add_tile.send(AddTile {
+            id: Vec3Int { x: 0, y: 0, z: 0 },
+            tile_type,
+            orientation: 0,
+            face,
+            group_instance_id_option: None,
+            metadata: TileMetadata::default(),
+            entity: commands.spawn(()).id(),
+            default_map_spawn: false,
+            layer,
+            grid,
+            stamp: stamp.tick,
+        });
+        let text = format!("Spawned grid {}.", grid.0);
+        info!("{}", text);
+        console_reply(&mut console, &fonts, handle, text);
+    }
+}
+
+/// Perform "setGridVelocity" command.
+pub(crate) fn set_grid_velocity_command(
+    mut queue: EventReader<InputConsoleCommand>,
+    gridmap: Res<Gridmap>,
+    mut velocities: Query<&mut LinearVelocity>,
+    mut console: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for command in queue.read() {
+        if command.input.command != "setGridVelocity" {
+            continue;
+        }
+        let handle;
+        match command.handle_option {
+            Some(h) => {
+                handle = h;
+            }
+            None => {
+                continue;
+            }
+        }
+        let args: Vec<f32> = command
+            .input
+            .args
+            .iter()
+            .filter_map(|arg| arg.parse::<f32>().ok())
+            .collect();
+        if command.input.args.len() != 4 || args.len() != 4 {
+            console_reply(
+                &mut console,
+                &fonts,
+                handle,
+                "Usage: setGridVelocity <grid> <x> <y> <z>".to_string(),
+            );
+            continue;
+        }
+        let grid = GridId(args[0] as u32);
+        let text;
+        match gridmap.grids.get(&grid) {
+            Some(sub) => match velocities.get_mut(sub.entity) {
+                Ok(mut velocity) => {
+                    velocity.0 = [args[1], args[2], args[3]].into();
+                    text = format!("Set velocity of grid {} to {:?}.", grid.0, velocity.0);
+                }
+                Err(_) => {
+                    text = format!("Grid {} has no rigid body.", grid.0);
+                }
+            },
+            None => {
+                text = format!("Grid {} doesnt exist or cant move.", grid.0);
+            }
+        }
+        console_reply(&mut console, &fonts, handle, text);
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use bevy::log::warn;
+use bevy::prelude::{
+    Commands, Entity, Event, EventReader, EventWriter, Query, Res, ResMut, Resource,
+};
+use bevy_renet::renet::ClientId;
+use console_commands::{
+    commands::{AllConsoleCommands, ConsoleCommand, InputConsoleCommand},
+    net::{ConsoleCommandsServerMessage, ConsoleLine},
+};
+use gridmap::{
+    grid::{AddTile, CellItem, CellTypeId, Gridmap, LayerTargetCell, RemoveTile, TileMetadata},
+    net::GridmapClientMessage,
+};
+use hud::communication::build::CONSOLE_FONT_COLOR;
+use inventory::server::inventory::Inventory;
+use networking::{
+    server::{HandleToEntity, IncomingReliableClientMessage, OutgoingReliableServerMessage},
+    stamp::TickRateStamp,
+};
+use ui::{
+    fonts::{Fonts, SOURCECODE_REGULAR_FONT},
+    text::{NetTextSection, COMMUNICATION_FONT_SIZE},
+};
+
+use crate::{construction_tool::ConstructionTool, map_construction::get_active_construction_tool};
+
+/// Amount of construction operations remembered per player.
+pub const MAX_CONSTRUCTION_HISTORY: usize = 64;
+
+/// Content of a cell face as far as construction is concerned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstructionCellContent {
+    pub tile_type: CellTypeId,
+    pub orientation: u8,
+    pub group_id_option: Option<u32>,
+    pub metadata: TileMetadata,
+}
+
+impl ConstructionCellContent {
+    pub fn from_item(item: &CellItem) -> Self {
+        Self {
+            tile_type: item.tile_type,
+            orientation: item.orientation,
+            group_id_option: item.group_id_option,
+            metadata: item.metadata.clone(),
+        }
+    }
+}
+
+/// A single cell changed by a construction operation.
+#[derive(Clone, Debug)]
+pub struct ConstructionChange {
+    pub cell: LayerTargetCell,
+    pub before: Option<ConstructionCellContent>,
+    pub after: Option<ConstructionCellContent>,
+}
+
+/// All cells changed by one construct or deconstruct input, ie a full group placement.
+#[derive(Clone, Debug, Default)]
+pub struct ConstructionOperation {
+    pub changes: Vec<ConstructionChange>,
+}
+
+#[derive(Default)]
+pub struct PlayerConstructionHistory {
+    pub undo: Vec<ConstructionOperation>,
+    pub redo: Vec<ConstructionOperation>,
+}
+
+/// Server-side construction history of each player entity.
+#[derive(Resource, Default)]
+pub struct ConstructionHistory {
+    pub players: HashMap<Entity, PlayerConstructionHistory>,
+}
+
+impl ConstructionHistory {
+    /// Remember a new operation. Clears the redo history of the player.
+    pub fn record(&mut self, player: Entity, operation: ConstructionOperation) {
+        if operation.changes.is_empty() {
+            return;
+        }
+        let history = self.players.entry(player).or_default();
+        history.redo.clear();
+        history.undo.push(operation);
+        if history.undo.len() > MAX_CONSTRUCTION_HISTORY {
+            history.undo.remove(0);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstructionHistoryAction {
+    Undo,
+    Redo,
+}
+
+/// Player requested input event.
+#[derive(Event)]
+pub struct InputConstructionHistory {
+    /// Connection handle that fired this input.
+    pub handle: ClientId,
+    pub action: ConstructionHistoryAction,
+}
+
+pub(crate) fn construction_history_net(
+    mut net: EventReader<IncomingReliableClientMessage<GridmapClientMessage>>,
+    mut events: EventWriter<InputConstructionHistory>,
+) {
+    for message in net.read() {
+        let action;
+        match &message.message {
+            GridmapClientMessage::UndoConstruction => {
+                action = ConstructionHistoryAction::Undo;
+            }
+            GridmapClientMessage::RedoConstruction => {
+                action = ConstructionHistoryAction::Redo;
+            }
+            _ => {
+                continue;
+            }
+        }
+        events.send(InputConstructionHistory {
+            handle: message.handle,
+            action,
+        });
+    }
+}
+
+pub(crate) fn add_construction_history_commands(mut commands: ResMut<AllConsoleCommands>) {
+    commands.list.push(ConsoleCommand {
+        base: "undoConstruction".to_string(),
+        description: "Undoes your last map construction. Requires holding a construction tool."
+            .to_string(),
+        args: vec![],
+    });
+    commands.list.push(ConsoleCommand {
+        base: "redoConstruction".to_string(),
+        description:
+            "Redoes your last undone map construction. Requires holding a construction tool."
+                .to_string(),
+        args: vec![],
+    });
+}
+
+pub(crate) fn construction_history_commands(
+    mut queue: EventReader<InputConsoleCommand>,
+    mut events: EventWriter<InputConstructionHistory>,
+) {
+    for command in queue.read() {
+        let action;
+        if command.input.command == "undoConstruction" {
+            action = ConstructionHistoryAction::Undo;
+        } else if command.input.command == "redoConstruction" {
+            action = ConstructionHistoryAction::Redo;
+        } else {
+            continue;
+        }
+        match command.handle_option {
+            Some(handle) => {
+                events.send(InputConstructionHistory { handle, action });
+            }
+            None => {}
+        }
+    }
+}
+
+/// Undo or redo construction operations. Only players holding a construction tool may do so, same as for constructing.
+pub(crate) fn apply_construction_history(
+    mut events: EventReader<InputConstructionHistory>,
+    mut history: ResMut<ConstructionHistory>,
+    inventory_query: Query<&Inventory>,
+    handle_to_entity: Res<HandleToEntity>,
+    construction_tool_query: Query<&ConstructionTool>,
+    mut add_events: EventWriter<AddTile>,
+    mut remove_events: EventWriter<RemoveTile>,
+    mut commands: Commands,
+    gridmap: Res<Gridmap>,
+    stamp: Res<TickRateStamp>,
+    mut net: EventWriter<OutgoingReliableServerMessage<ConsoleCommandsServerMessage>>,
+    fonts: Res<Fonts>,
+) {
+    for event in events.read() {
+        let player_entity;
+        match get_active_construction_tool(
+            &event.handle,
+            &handle_to_entity,
+            &inventory_query,
+            &construction_tool_query,
+        ) {
+            Some((entity, _)) => {
+                player_entity = entity;
+            }
+            None => {
+                continue;
+            }
+        }
+
+        let player_history = history.players.entry(player_entity).or_default();
+        let operation_option = match event.action {
+            ConstructionHistoryAction::Undo => player_history.undo.pop(),
+            ConstructionHistoryAction::Redo => player_history.redo.pop(),
+        };
+        let operation;
+        match operation_option {
+            Some(o) => {
+                operation = o;
+            }
+            None => {
+                let text = match event.action {
+                    ConstructionHistoryAction::Undo => "Nothing to undo.",
+                    ConstructionHistoryAction::Redo => "Nothing to redo.",
+                };
+                net.send(OutgoingReliableServerMessage {
+                    handle: event.handle,
+                    message: ConsoleCommandsServerMessage::ConsoleWriteLine(ConsoleLine {
+                        sections: vec![NetTextSection {
+                            text: text.to_string(),
+                            font: *fonts.inv_map.get(SOURCECODE_REGULAR_FONT).unwrap(),
+                            font_size: COMMUNICATION_FONT_SIZE,
+                            color: CONSOLE_FONT_COLOR,
+                        }],
+                    }),
+                });
+                continue;
+            }
+        }
+
+        let changes: Vec<&ConstructionChange> = match event.action {
+            ConstructionHistoryAction::Undo => operation.changes.iter().rev().collect(),
+            ConstructionHistoryAction::Redo => operation.changes.iter().collect(),
+        };
+        for change in changes {
+            let (expected, target) = match event.action {
+                ConstructionHistoryAction::Undo => (&change.after, &change.before),
+                ConstructionHistoryAction::Redo => (&change.before, &change.after),
+            };
+            let current = gridmap
+                .get_cell(change.cell.clone())
+                .map(|item| ConstructionCellContent::from_item(&item));
+            // Cells that got changed since by something else are left alone.
+            if current != *expected {
+                warn!(
+                    "Skipped {:?} of cell {:?}, it changed since.",
+                    event.action, change.cell.target.id
+                );
+                continue;
+            }
+            if current.is_some() {
+                remove_events.send(RemoveTile {
+                    cell: change.cell.clone(),
+                    stamp: stamp.tick,
+                });
+            }
+            match target {
+                Some(content) => {
+                    add_events.send(AddTile {
+                        id: change.cell.target.id,
+                        tile_type: content.tile_type,
+                        orientation: content.orientation,
+                        face: change.cell.target.face.clone(),
+                        group_instance_id_option: content.group_id_option,
+                        metadata: content.metadata.clone(),
+                        entity: commands.spawn(()).id(),
+                        default_map_spawn: false,
+                        layer: change.cell.layer,
+                        grid: change.cell.target.grid,
+                        stamp: stamp.tick,
+                    });
+                }
+                None => {}
+            }
+        }
+
+        match event.action {
+            ConstructionHistoryAction::Undo => player_history.redo.push(operation),
+            ConstructionHistoryAction::Redo => player_history.undo.push(operation),
+        }
+    }
+}
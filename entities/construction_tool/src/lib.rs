@@ -3,8 +3,14 @@
 
 /// Manage construction tool actions.
 mod action;
+/// Select regions of the map and save them as blueprints.
+pub mod blueprint;
 /// Manage consturction tools.
 pub mod construction_tool;
+/// Console commands to start and move grids.
+mod grids;
+/// Per player undo and redo history of map construction.
+pub mod history;
 pub mod map_construction;
 /// The Bevy plugin of this crate.
 pub mod plugin;
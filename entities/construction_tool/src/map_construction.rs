@@ -1,17 +1,25 @@
+use std::collections::HashSet;
+
 use bevy::log::warn;
-use bevy::prelude::{Commands, EventReader, EventWriter, Query, Res};
+use bevy::prelude::{Commands, Entity, EventReader, EventWriter, Query, Res, ResMut};
+use bevy_renet::renet::ClientId;
 use entity::spawn::ServerEntityClientEntity;
-use gridmap::grid::Gridmap;
+use gridmap::blueprint::{Blueprints, PlacedGroup};
+use gridmap::grid::{CellIds, Gridmap, LayerTargetCell, OrthogonalBases, TileMetadata};
 use gridmap::{
-    construction::{GridmapConstructionState, ShowYLevelPlane},
+    construction::{GridmapConstructionState, ShowYLevelPlane, MAX_PLACEMENT_CELLS},
     grid::{AddTile, RemoveTile},
-    net::GridmapClientMessage,
+    net::{GridmapClientMessage, NewCell},
 };
 use inventory::server::inventory::Inventory;
 use networking::server::{HandleToEntity, IncomingReliableClientMessage};
 use networking::stamp::TickRateStamp;
+use resources::grid::TargetCell;
 
 use crate::construction_tool::ConstructionTool;
+use crate::history::{
+    ConstructionCellContent, ConstructionChange, ConstructionHistory, ConstructionOperation,
+};
 
 pub(crate) fn construction_tool_enable_select_cell_in_front_camera(
     inventory: Res<Inventory>,
@@ -49,6 +57,46 @@ pub(crate) fn construction_tool_enable_select_cell_in_front_camera(
     }
 }
 
+/// The player entity and its construction tool, if the player is holding a construction tool.
+pub fn get_active_construction_tool<'a>(
+    handle: &ClientId,
+    handle_to_entity: &HandleToEntity,
+    inventory_query: &Query<&Inventory>,
+    construction_tool_query: &'a Query<&ConstructionTool>,
+) -> Option<(Entity, &'a ConstructionTool)> {
+    let client_entity;
+    match handle_to_entity.map.get(handle) {
+        Some(entity) => {
+            client_entity = *entity;
+        }
+        None => {
+            warn!("Couldnt get entity from map.");
+            return None;
+        }
+    }
+
+    let active_item_entity;
+
+    match inventory_query.get(client_entity) {
+        Ok(inventory) => match inventory.active_item {
+            Some(e) => {
+                active_item_entity = e;
+            }
+            None => {
+                return None;
+            }
+        },
+        Err(_) => {
+            return None;
+        }
+    }
+
+    match construction_tool_query.get(active_item_entity) {
+        Ok(component) => Some((client_entity, component)),
+        Err(_) => None,
+    }
+}
+
 pub(crate) fn mouse_click_input(
     mut net: EventReader<IncomingReliableClientMessage<GridmapClientMessage>>,
     inventory_query: Query<&Inventory>,
@@ -57,50 +105,52 @@ pub(crate) fn mouse_click_input(
     mut add_events: EventWriter<AddTile>,
     mut remove_events: EventWriter<RemoveTile>,
     mut commands: Commands,
-    gridmap: Res<Gridmap>,
+    mut gridmap: ResMut<Gridmap>,
     stamp: Res<TickRateStamp>,
+    mut history: ResMut<ConstructionHistory>,
+    mut blueprints: ResMut<Blueprints>,
 ) {
     for message in net.read() {
-        let client_entity;
-        match handle_to_entity.map.get(&message.handle) {
-            Some(entity) => {
-                client_entity = *entity;
-            }
-            None => {
-                warn!("Couldnt get entity from map.");
-                return;
-            }
-        }
-
-        let active_item_entity;
-
-        match inventory_query.get(client_entity) {
-            Ok(inventory) => match inventory.active_item {
-                Some(e) => {
-                    active_item_entity = e;
-                }
-                None => {
-                    return;
-                }
-            },
-            Err(_) => {
-                return;
+        match &message.message {
+            GridmapClientMessage::ConstructCells(_)
+            | GridmapClientMessage::DeconstructCells(_)
+            | GridmapClientMessage::PasteBlueprint(_) => {}
+            _ => {
+                continue;
             }
         }
-
+        let client_entity;
         let construction_tool_component;
-
-        match construction_tool_query.get(active_item_entity) {
-            Ok(component) => {
+        match get_active_construction_tool(
+            &message.handle,
+            &handle_to_entity,
+            &inventory_query,
+            &construction_tool_query,
+        ) {
+            Some((entity, component)) => {
+                client_entity = entity;
                 construction_tool_component = component;
             }
-            Err(_) => {
-                return;
+            None => {
+                continue;
             }
         }
 
+        let mut operation = ConstructionOperation::default();
+        let mut new_cells = vec![];
+        // Groups placed by this operation, each becomes its own group instance.
+        let mut group_placements: Vec<PlacedGroup> = vec![];
+
         match &message.message {
             GridmapClientMessage::ConstructCells(construct) => {
+                if construct.cells.len() > MAX_PLACEMENT_CELLS {
+                    warn!(
+                        "Rejected construction of {} cells, the maximum is {}.",
+                        construct.cells.len(),
+                        MAX_PLACEMENT_CELLS
+                    );
+                    continue;
+                }
                 let type_id;
 
                 match construction_tool_component.construction_option.clone() {
@@ -108,66 +158,127 @@ pub(crate) fn mouse_click_input(
                         type_id = i;
                     }
                     None => {
-                        return;
+                        continue;
                     }
                 }
 
-                match type_id {
-                    gridmap::grid::CellIds::CellType(id) => {
-                        for cell in construct.cells.iter() {
-                            let is_detail;
-                            match gridmap.tile_properties.get(&id) {
-                                Some(properties) => {
-                                    is_detail = properties.is_detail;
-                                }
-                                None => {
-                                    warn!("Couldnt find tile properties.");
-                                    continue;
-                                }
+                match (&type_id, construct.group_option) {
+                    (CellIds::GroupType(group), Some(origin)) => {
+                        if construct.group_orientation as usize
+                            >= OrthogonalBases::default().bases.len()
+                        {
+                            warn!("Rejected group with invalid orientation.");
+                            continue;
+                        }
+                        let grid;
+                        match construct.cells.first() {
+                            Some(cell) => {
+                                grid = cell.grid;
+                            }
+                            None => {
+                                continue;
                             }
-
-                            add_events.send(AddTile {
-                                id: cell.id,
-                                tile_type: id,
-                                orientation: cell.orientation,
-                                face: cell.face.clone(),
-                                group_instance_id_option: None,
-                                entity: commands.spawn(()).id(),
-                                default_map_spawn: false,
-                                is_detail: is_detail,
-                                stamp: stamp.tick,
-                            });
                         }
+                        group_placements.push(PlacedGroup {
+                            group: *group,
+                            orientation: construct.group_orientation,
+                            cells: gridmap.group_cells(
+                                *group,
+                                grid,
+                                origin,
+                                construct.group_orientation,
+                            ),
+                        });
                     }
-                    gridmap::grid::CellIds::GroupType(_id) => {
+                    _ => {
                         for cell in construct.cells.iter() {
-                            let is_detail;
-                            match gridmap.tile_properties.get(&cell.tile_type) {
+                            let tile_type;
+                            match &type_id {
+                                CellIds::CellType(id) => {
+                                    tile_type = *id;
+                                }
+                                CellIds::GroupType(_) => {
+                                    warn!("Rejected construction: group without origin.");
+                                    break;
+                                }
+                                CellIds::Blueprint(_) => {
+                                    warn!("Rejected construction: blueprints get pasted.");
+                                    break;
+                                }
+                            }
+                            let layer;
+                            match gridmap.tile_properties.get(&tile_type) {
                                 Some(properties) => {
-                                    is_detail = properties.is_detail;
+                                    layer = properties.layer;
                                 }
                                 None => {
                                     warn!("Couldnt find tile properties.");
                                     continue;
                                 }
                             }
-                            add_events.send(AddTile {
-                                id: cell.id,
-                                tile_type: cell.tile_type,
+                            new_cells.push(NewCell {
+                                cell: LayerTargetCell {
+                                    target: TargetCell {
+                                        id: cell.id,
+                                        face: cell.face.clone(),
+                                        grid: cell.grid,
+                                    },
+                                    layer,
+                                },
                                 orientation: cell.orientation,
-                                face: cell.face.clone(),
-                                group_instance_id_option: None,
-                                entity: commands.spawn(()).id(),
-                                default_map_spawn: false,
-                                is_detail: is_detail,
-                                stamp: stamp.tick,
+                                tile_type,
+                                metadata: TileMetadata::default(),
                             });
                         }
                     }
                 }
             }
+            GridmapClientMessage::PasteBlueprint(paste) => {
+                match &construction_tool_component.construction_option {
+                    Some(CellIds::Blueprint(name)) => {
+                        if *name != paste.name {
+                            warn!("Rejected blueprint {}, {} is selected.", paste.name, name);
+                            continue;
+                        }
+                    }
+                    _ => {
+                        warn!("Rejected blueprint {}, no blueprint selected.", paste.name);
+                        continue;
+                    }
+                }
+                if paste.orientation as usize >= OrthogonalBases::default().bases.len() {
+                    warn!("Rejected blueprint with invalid orientation.");
+                    continue;
+                }
+                match blueprints.get_or_load(&paste.name) {
+                    Ok(blueprint) => {
+                        let placement = blueprint.placed_cells(
+                            &gridmap,
+                            paste.grid,
+                            paste.origin,
+                            paste.orientation,
+                        );
+                        new_cells = placement.cells;
+                        group_placements = placement.groups;
+                    }
+                    Err(rr) => {
+                        warn!("{}", rr);
+                        continue;
+                    }
+                }
+            }
             GridmapClientMessage::DeconstructCells(deconstruct) => {
                 for cell in deconstruct.cells.iter() {
+                    match gridmap.get_cell(cell.clone()) {
+                        Some(item) => {
+                            operation.changes.push(ConstructionChange {
+                                cell: cell.clone(),
+                                before: Some(ConstructionCellContent::from_item(&item)),
+                                after: None,
+                            });
+                        }
+                        None => {}
+                    }
                     remove_events.send(RemoveTile {
                         cell: cell.clone(),
                         stamp: stamp.tick,
@@ -176,5 +287,79 @@ pub(crate) fn mouse_click_input(
             }
             _ => (),
         }
+
+        // Batches get constructed entirely or not at all.
+        let mut valid = true;
+        let mut targets = HashSet::new();
+        for new in new_cells
+            .iter()
+            .chain(group_placements.iter().flat_map(|group| group.cells.iter()))
+        {
+            if !gridmap.has_grid(new.cell.target.grid) {
+                warn!(
+                    "Rejected construction: grid {} doesnt exist.",
+                    new.cell.target.grid.0
+                );
+                valid = false;
+                break;
+            }
+            match gridmap.get_indexes(new.cell.target.id) {
+                Ok(_) => {}
+                Err(rr) => {
+                    warn!("Rejected construction: {}", rr);
+                    valid = false;
+                    break;
+                }
+            }
+            if !targets.insert(new.cell.clone()) {
+                warn!(
+                    "Rejected construction: cell {:?} is targeted twice.",
+                    new.cell.target.id
+                );
+                valid = false;
+                break;
+            }
+        }
+        if !valid {
+            continue;
+        }
+        let mut placements = vec![(None, new_cells)];
+        for group in group_placements {
+            let instance = gridmap.new_group_instance(group.group, group.orientation);
+            placements.push((Some(instance), group.cells));
+        }
+
+        for (group_instance_id_option, new) in placements
+            .into_iter()
+            .flat_map(|(instance, cells)| cells.into_iter().map(move |new| (instance, new)))
+        {
+            operation.changes.push(ConstructionChange {
+                before: gridmap
+                    .get_cell(new.cell.clone())
+                    .map(|item| ConstructionCellContent::from_item(&item)),
+                after: Some(ConstructionCellContent {
+                    tile_type: new.tile_type,
+                    orientation: new.orientation,
+                    group_id_option: group_instance_id_option,
+                    metadata: new.metadata.clone(),
+                }),
+                cell: new.cell.clone(),
+            });
+
+            add_events.send(AddTile {
+                id: new.cell.target.id,
+                tile_type: new.tile_type,
+                orientation: new.orientation,
+                face: new.cell.target.face,
+                group_instance_id_option,
+                metadata: new.metadata,
+                entity: commands.spawn(()).id(),
+                default_map_spawn: false,
+                layer: new.cell.layer,
+                grid: new.cell.target.grid,
+                stamp: stamp.tick,
+            });
+        }
+        history.record(client_entity, operation);
     }
 }
@@ -2,14 +2,16 @@ use basic_console_commands::register::{
     register_basic_console_commands_for_inventory_item_type,
     register_basic_console_commands_for_type,
 };
-use bevy::prelude::{resource_exists, App, IntoSystemConfigs, Plugin, PostUpdate};
+use bevy::prelude::{resource_exists, App, IntoSystemConfigs, Plugin, PostUpdate, Startup};
 use combat::melee_queries::melee_attack_handler;
 use combat::sfx::{attack_sfx, health_combat_hit_result_sfx};
+use console_commands::commands::ConsoleCommandsSet;
 use entity::base_mesh::link_base_mesh;
 use entity::entity_types::register_entity_type;
 use entity::loading::load_entity;
 use entity::spawn::build_base_entities;
 
+use gridmap::blueprint::Blueprints;
 use gridmap::construction::{GridmapConstructionState, ShowYLevelPlane, YPlaneSet};
 use gridmap::grid::EditTileSet;
 use hud::inventory::items::update_inventory_hud_add_item_to_slot;
@@ -29,7 +31,15 @@ use crate::action::{
     construction_tool_inventory_prequisite_check, construction_tool_select_construction_option,
     deconstruct_action_prequisite_check, send_constructable_items,
 };
+use crate::blueprint::{
+    add_blueprint_commands, save_blueprint_command, select_blueprint_corner, BlueprintSelections,
+};
 use crate::construction_tool::ConstructionTool;
+use crate::grids::{add_grid_commands, new_grid_command, set_grid_velocity_command};
+use crate::history::{
+    add_construction_history_commands, apply_construction_history, construction_history_commands,
+    construction_history_net, ConstructionHistory, InputConstructionHistory,
+};
 use crate::map_construction::{
     construction_tool_enable_select_cell_in_front_camera, mouse_click_input,
 };
@@ -47,6 +57,10 @@ impl Plugin for ConstructionToolAdminPlugin {
             app.add_event::<InputConstruct>()
                 .add_event::<InputDeconstruct>()
                 .add_event::<InputConstructionOptions>()
+                .add_event::<InputConstructionHistory>()
+                .init_resource::<ConstructionHistory>()
+                .init_resource::<Blueprints>()
+                .init_resource::<BlueprintSelections>()
                 .add_systems(
                     Update,
                     (
@@ -80,6 +94,26 @@ impl Plugin for ConstructionToolAdminPlugin {
                         mouse_click_input
                             .before(EditTileSet::Add)
                             .in_set(EditTileSet::Remove),
+                        (
+                            construction_history_net.before(apply_construction_history),
+                            construction_history_commands
+                                .after(ConsoleCommandsSet::Input)
+                                .before(apply_construction_history),
+                            apply_construction_history
+                                .after(mouse_click_input)
+                                .before(EditTileSet::Add)
+                                .in_set(EditTileSet::Remove),
+                        ),
+                        (
+                            select_blueprint_corner,
+                            save_blueprint_command.after(ConsoleCommandsSet::Input),
+                        ),
+                        (
+                            new_grid_command
+                                .after(ConsoleCommandsSet::Input)
+                                .before(EditTileSet::Add),
+                            set_grid_velocity_command.after(ConsoleCommandsSet::Input),
+                        ),
                     ),
                 );
         } else {
@@ -113,6 +147,14 @@ impl Plugin for ConstructionToolAdminPlugin {
         register_entity_type::<ConstructionToolType>(app);
         register_basic_console_commands_for_type::<ConstructionToolType>(app);
         register_basic_console_commands_for_inventory_item_type::<ConstructionToolType>(app);
+        app.add_systems(
+            Startup,
+            (
+                add_construction_history_commands,
+                add_blueprint_commands,
+                add_grid_commands,
+            ),
+        );
         app.add_systems(
             PreUpdate,
             (
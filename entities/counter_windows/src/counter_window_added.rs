@@ -1,17 +1,17 @@
 use std::collections::BTreeMap;
 
-use bevy::prelude::{Added, Entity, Query, ResMut, Transform};
+use bevy::prelude::{Added, Changed, Entity, Query, RemovedComponents, ResMut, Transform};
 use entity::{
     entity_data::{DefaultMapEntity, EntityData},
     examine::{Examinable, RichName},
 };
-use gridmap::grid::Gridmap;
+use gridmap::{atmospherics::Atmospherics, grid::Gridmap};
 use map::{map::GREEN_MAP_TILE_COUNTER, map_input::MapData};
 use resources::math::{world_to_cell_id, Vec2Int};
 use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR};
 
 use super::{
-    counter_window_events::CounterWindow,
+    counter_window_events::{CounterWindow, CounterWindowStatus},
     spawn::{BRIDGE_COUNTER_WINDOW_ENTITY_NAME, SECURITY_COUNTER_WINDOW_ENTITY_NAME},
 };
 
@@ -93,3 +93,27 @@ pub(crate) fn counter_window_default_map_added(
         }
     }
 }
+
+/// Seal the cells of closed counter windows for atmospherics, until they open or are removed.
+
+pub(crate) fn counter_window_seal_atmospherics(
+    counter_windows: Query<(Entity, &CounterWindow, &Transform), Changed<CounterWindow>>,
+    mut removed: RemovedComponents<CounterWindow>,
+    mut atmospherics: ResMut<Atmospherics>,
+) {
+    for entity in removed.read() {
+        atmospherics.remove_entity_seal(entity);
+    }
+    for (entity, counter_window_component, rigid_body_position_component) in counter_windows.iter()
+    {
+        let cell_id = world_to_cell_id(rigid_body_position_component.translation);
+        match counter_window_component.status {
+            CounterWindowStatus::Open => {
+                atmospherics.set_entity_sealed(entity, cell_id, false);
+            }
+            CounterWindowStatus::Closed => {
+                atmospherics.set_entity_sealed(entity, cell_id, true);
+            }
+        }
+    }
+}
@@ -10,6 +10,7 @@ use bevy::{
 use bevy_renet::renet::ClientId;
 use bevy_xpbd_3d::prelude::{Collider, CollisionLayers};
 use entity::{entity_data::EntityGroup, examine::Examinable};
+use gridmap::power::{opens_automatically, PowerDevice};
 use networking::server::NetworkingChatServerMessage;
 use pawn::pawn::{Pawn, ShipAuthorization, ShipAuthorizationEnum};
 use physics::physics::{get_bit_masks, ColliderGroup};
@@ -50,6 +51,7 @@ pub(crate) fn counter_window_events(
     mut counter_window_lock_close_events: EventReader<CounterWindowLockClosed>,
     mut unlock_events: EventReader<CounterWindowUnlock>,
     mut server: EventWriter<OutgoingReliableServerMessage<NetworkingChatServerMessage>>,
+    power_devices: Query<&PowerDevice>,
 ) {
     let mut close_requests = vec![];
     let mut open_requests = vec![];
@@ -341,6 +343,10 @@ pub(crate) fn counter_window_events(
             }
         }
 
+        if !opens_automatically(&power_devices, counter_window_entity) {
+            continue;
+        }
+
         open_requests.push(CounterWindowOpenRequest {
             opener_option: Some(pawn_entity),
             opened: counter_window_entity,
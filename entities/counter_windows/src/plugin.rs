@@ -15,7 +15,7 @@ use crate::{
 };
 
 use super::{
-    counter_window_added::counter_window_default_map_added,
+    counter_window_added::{counter_window_default_map_added, counter_window_seal_atmospherics},
     counter_window_events::{
         counter_window_events, CounterWindowLockClosed, CounterWindowLockOpen,
         CounterWindowSensorCollision, CounterWindowUnlock, InputCounterWindowToggleOpen,
@@ -36,6 +36,7 @@ impl Plugin for CounterWindowsPlugin {
                         counter_window_tick_timers,
                         counter_window_default_map_added,
                         counter_window_events,
+                        counter_window_seal_atmospherics.after(counter_window_events),
                         health_combat_hit_result_sfx::<CounterWindow>
                             .after(CombatSet::FinalizeApplyDamage),
                         toggle_open_action_prequisite_check
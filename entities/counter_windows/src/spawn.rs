@@ -6,6 +6,7 @@ use bevy::{
     math::Vec3,
     prelude::{Commands, EventReader, GlobalTransform, Transform},
 };
+use gridmap::power::PowerDevice;
 
 use bevy_xpbd_3d::prelude::{
     CoefficientCombine, Collider, CollisionLayers, Friction, RigidBody, Sensor,
@@ -102,6 +103,8 @@ impl RigidBodyBuilder<NoData> for CounterWindowType {
 }
 
 pub const COUNTER_WINDOW_COLLISION_Y: f32 = 0.5;
+/// Power in kW a counter window draws.
+pub const COUNTER_WINDOW_POWER_USAGE: f32 = 0.3;
 
 #[derive(Clone, Identity)]
 pub struct CounterWindowType {
@@ -127,10 +130,13 @@ pub fn build_counter_windows<T: Send + Sync + 'static>(
     for spawn_event in spawn_events.read() {
         commands
             .entity(spawn_event.spawn_data.entity.unwrap())
-            .insert(CounterWindow {
-                access_permissions: vec![ShipAuthorizationEnum::Security],
-                ..Default::default()
-            });
+            .insert((
+                CounterWindow {
+                    access_permissions: vec![ShipAuthorizationEnum::Security],
+                    ..Default::default()
+                },
+                PowerDevice::consumer(COUNTER_WINDOW_POWER_USAGE),
+            ));
 
         let rigid_body = RigidBody::Static;
 
@@ -0,0 +1,6 @@
+//! Debris left behind by destroyed tiles.
+
+/// The Bevy plugin of this crate.
+pub mod plugin;
+/// Debris spawner.
+pub mod spawn;
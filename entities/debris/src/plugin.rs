@@ -0,0 +1,24 @@
+use bevy::prelude::{App, IntoSystemConfigs, Plugin};
+use entity::entity_types::register_entity_type;
+use entity::spawn::build_base_entities;
+use gridmap::damage::register_tile_debris;
+use physics::spawn::build_rigid_bodies;
+use resources::ordering::{BuildingSet, PreUpdate};
+
+use super::spawn::DebrisType;
+
+pub struct DebrisPlugin;
+
+impl Plugin for DebrisPlugin {
+    fn build(&self, app: &mut App) {
+        register_entity_type::<DebrisType>(app);
+        register_tile_debris::<DebrisType>(app);
+        app.add_systems(
+            PreUpdate,
+            (
+                (build_rigid_bodies::<DebrisType>).in_set(BuildingSet::NormalBuild),
+                (build_base_entities::<DebrisType>).in_set(BuildingSet::NormalBuild),
+            ),
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::Transform;
+use bevy_xpbd_3d::prelude::{CoefficientCombine, Collider, Friction};
+use entity::{
+    entity_macros::Identity,
+    entity_types::EntityType,
+    examine::{Examinable, RichName},
+    spawn::{BaseEntityBuilder, BaseEntityBundle, EntityBuildData, NoData},
+};
+use gridmap::damage::debris_entity_name;
+use physics::{
+    rigid_body::STANDARD_BODY_FRICTION,
+    spawn::{RigidBodyBuilder, RigidBodyBundle},
+};
+
+impl BaseEntityBuilder<NoData> for DebrisType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> BaseEntityBundle {
+        let mut examine_map = BTreeMap::new();
+        examine_map.insert(0, "Scrap left behind by a destroyed tile.".to_string());
+        BaseEntityBundle {
+            default_transform: Transform::IDENTITY,
+            examinable: Examinable {
+                assigned_texts: examine_map,
+                name: RichName {
+                    name: "debris".to_string(),
+                    n: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            entity_type: Box::new(DebrisType::new()),
+            ..Default::default()
+        }
+    }
+}
+
+impl RigidBodyBuilder<NoData> for DebrisType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> RigidBodyBundle {
+        let mut friction = Friction::new(STANDARD_BODY_FRICTION);
+        friction.combine_rule = CoefficientCombine::Multiply;
+
+        RigidBodyBundle {
+            collider: Collider::cuboid(0.3, 0.1, 0.3),
+            collider_transform: Transform::IDENTITY,
+            collider_friction: friction,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Identity)]
+pub struct DebrisType {
+    pub identifier: String,
+}
+impl Default for DebrisType {
+    fn default() -> Self {
+        Self {
+            identifier: debris_entity_name(),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Reactors, solar panels and batteries that power the ship.
+
+/// The Bevy plugin of this crate.
+pub mod plugin;
+/// Manage power source events.
+pub mod power_source;
+/// Power source spawners.
+pub mod spawn;
@@ -0,0 +1,41 @@
+use bevy::prelude::{App, IntoSystemConfigs, Plugin};
+use entity::entity_types::register_entity_type;
+use entity::spawn::build_base_entities;
+use physics::spawn::build_rigid_bodies;
+use resources::modes::is_server_mode;
+use resources::ordering::{BuildingSet, PreUpdate, Update};
+
+use super::{
+    power_source::power_source_examine,
+    spawn::{
+        build_batteries, build_reactors, build_solar_panels, BatteryType, ReactorType,
+        SolarPanelType,
+    },
+};
+
+pub struct PowerSourcesPlugin;
+
+impl Plugin for PowerSourcesPlugin {
+    fn build(&self, app: &mut App) {
+        if is_server_mode(app) {
+            app.add_systems(Update, power_source_examine);
+        }
+        register_entity_type::<ReactorType>(app);
+        register_entity_type::<SolarPanelType>(app);
+        register_entity_type::<BatteryType>(app);
+        app.add_systems(
+            PreUpdate,
+            (
+                (build_rigid_bodies::<ReactorType>).in_set(BuildingSet::NormalBuild),
+                build_reactors::<ReactorType>.in_set(BuildingSet::NormalBuild),
+                (build_base_entities::<ReactorType>).in_set(BuildingSet::NormalBuild),
+                (build_rigid_bodies::<SolarPanelType>).in_set(BuildingSet::NormalBuild),
+                build_solar_panels::<SolarPanelType>.in_set(BuildingSet::NormalBuild),
+                (build_base_entities::<SolarPanelType>).in_set(BuildingSet::NormalBuild),
+                (build_rigid_bodies::<BatteryType>).in_set(BuildingSet::NormalBuild),
+                build_batteries::<BatteryType>.in_set(BuildingSet::NormalBuild),
+                (build_base_entities::<BatteryType>).in_set(BuildingSet::NormalBuild),
+            ),
+        );
+    }
+}
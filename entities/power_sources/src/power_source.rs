@@ -0,0 +1,43 @@
+use bevy::prelude::{Changed, Component, Query, With};
+use entity::examine::Examinable;
+use gridmap::power::{PowerDevice, PowerDeviceKind};
+use text_api::core::{FURTHER_ITALIC_FONT, HEALTHY_COLOR, WARNING_COLOR};
+
+/// The power source component.
+#[derive(Component)]
+
+pub struct PowerSource;
+
+/// Show whether a power source feeds a power network.
+
+pub(crate) fn power_source_examine(
+    mut power_sources: Query<
+        (&PowerDevice, &mut Examinable),
+        (With<PowerSource>, Changed<PowerDevice>),
+    >,
+) {
+    for (power_device, mut examinable_component) in power_sources.iter_mut() {
+        let status;
+        match (&power_device.kind, power_device.network) {
+            (PowerDeviceKind::Storage { charge, .. }, Some(_)) => {
+                if *charge > 0. {
+                    status = "[color=".to_string() + HEALTHY_COLOR + "]It is charged.[/color]";
+                } else {
+                    status = "[color=".to_string() + WARNING_COLOR + "]It is drained.[/color]";
+                }
+            }
+            (_, Some(_)) => {
+                status = "[color=".to_string() + HEALTHY_COLOR + "]It is connected.[/color]";
+            }
+            (_, None) => {
+                status = "[color=".to_string()
+                    + WARNING_COLOR
+                    + "]It isn't connected to a power cable.[/color]";
+            }
+        }
+        examinable_component.assigned_texts.insert(
+            1,
+            "[font=".to_string() + FURTHER_ITALIC_FONT + "]" + &status + "[/font]",
+        );
+    }
+}
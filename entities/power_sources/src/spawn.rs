@@ -0,0 +1,193 @@
+use bevy::{
+    math::Vec3,
+    prelude::{Commands, EventReader, Transform},
+};
+use bevy_xpbd_3d::prelude::{CoefficientCombine, Collider, Friction};
+use entity::{
+    entity_macros::Identity,
+    entity_types::EntityType,
+    examine::{Examinable, RichName},
+    health::Health,
+    spawn::{BaseEntityBuilder, BaseEntityBundle, EntityBuildData, NoData, SpawnEntity},
+};
+use gridmap::power::PowerDevice;
+use physics::{
+    rigid_body::STANDARD_BODY_FRICTION,
+    spawn::{RigidBodyBuilder, RigidBodyBundle},
+};
+use resources::core::SF_CONTENT_PREFIX;
+use std::collections::BTreeMap;
+
+use super::power_source::PowerSource;
+
+/// Output in kW of a reactor.
+pub const REACTOR_OUTPUT: f32 = 25.;
+/// Output in kW of a solar panel.
+pub const SOLAR_PANEL_OUTPUT: f32 = 2.;
+/// Energy in kJ a battery holds.
+pub const BATTERY_CAPACITY: f32 = 6000.;
+/// Most power in kW a battery charges or discharges with.
+pub const BATTERY_RATE: f32 = 10.;
+
+fn power_source_bundle(
+    entity_type: Box<dyn EntityType>,
+    name: &str,
+    description: &str,
+) -> BaseEntityBundle {
+    let mut examine_map = BTreeMap::new();
+    examine_map.insert(0, description.to_string());
+
+    BaseEntityBundle {
+        default_transform: Transform::IDENTITY,
+        examinable: Examinable {
+            assigned_texts: examine_map,
+            name: RichName {
+                name: name.to_string(),
+                n: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        entity_type,
+        health: Health {
+            is_combat_obstacle: true,
+            is_reach_obstacle: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn power_source_rigid_body(collider: Collider) -> RigidBodyBundle {
+    let mut friction = Friction::new(STANDARD_BODY_FRICTION);
+    friction.combine_rule = CoefficientCombine::Min;
+
+    RigidBodyBundle {
+        collider,
+        collider_transform: Transform::from_translation(Vec3::new(0., 0., 0.)),
+        collider_friction: friction,
+        rigidbody_dynamic: false,
+        collision_events: true,
+        ..Default::default()
+    }
+}
+
+impl BaseEntityBuilder<NoData> for ReactorType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> BaseEntityBundle {
+        power_source_bundle(
+            Box::new(ReactorType::new()),
+            "reactor",
+            "A compact fusion reactor that powers the ship.",
+        )
+    }
+}
+
+impl RigidBodyBuilder<NoData> for ReactorType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> RigidBodyBundle {
+        power_source_rigid_body(Collider::cuboid(1., 1., 1.))
+    }
+}
+
+impl BaseEntityBuilder<NoData> for SolarPanelType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> BaseEntityBundle {
+        power_source_bundle(
+            Box::new(SolarPanelType::new()),
+            "solar panel",
+            "A solar panel that turns starlight into power.",
+        )
+    }
+}
+
+impl RigidBodyBuilder<NoData> for SolarPanelType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> RigidBodyBundle {
+        power_source_rigid_body(Collider::cuboid(1., 0.1, 1.))
+    }
+}
+
+impl BaseEntityBuilder<NoData> for BatteryType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> BaseEntityBundle {
+        power_source_bundle(
+            Box::new(BatteryType::new()),
+            "battery",
+            "A battery bank that stores surplus power for when supply runs short.",
+        )
+    }
+}
+
+impl RigidBodyBuilder<NoData> for BatteryType {
+    fn get_bundle(&self, _spawn_data: &EntityBuildData, _entity_data: NoData) -> RigidBodyBundle {
+        power_source_rigid_body(Collider::cuboid(0.5, 0.7, 0.5))
+    }
+}
+
+#[derive(Clone, Identity)]
+pub struct ReactorType {
+    pub identifier: String,
+}
+impl Default for ReactorType {
+    fn default() -> Self {
+        ReactorType {
+            identifier: SF_CONTENT_PREFIX.to_owned() + "reactor",
+        }
+    }
+}
+
+#[derive(Clone, Identity)]
+pub struct SolarPanelType {
+    pub identifier: String,
+}
+impl Default for SolarPanelType {
+    fn default() -> Self {
+        SolarPanelType {
+            identifier: SF_CONTENT_PREFIX.to_owned() + "solar_panel",
+        }
+    }
+}
+
+#[derive(Clone, Identity)]
+pub struct BatteryType {
+    pub identifier: String,
+}
+impl Default for BatteryType {
+    fn default() -> Self {
+        BatteryType {
+            identifier: SF_CONTENT_PREFIX.to_owned() + "battery",
+        }
+    }
+}
+
+pub fn build_reactors<T: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnEntity<T>>,
+) {
+    for spawn_event in spawn_events.read() {
+        commands
+            .entity(spawn_event.spawn_data.entity.unwrap())
+            .insert((PowerSource, PowerDevice::producer(REACTOR_OUTPUT)));
+    }
+}
+
+pub fn build_solar_panels<T: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnEntity<T>>,
+) {
+    for spawn_event in spawn_events.read() {
+        commands
+            .entity(spawn_event.spawn_data.entity.unwrap())
+            .insert((PowerSource, PowerDevice::producer(SOLAR_PANEL_OUTPUT)));
+    }
+}
+
+pub fn build_batteries<T: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnEntity<T>>,
+) {
+    for spawn_event in spawn_events.read() {
+        commands
+            .entity(spawn_event.spawn_data.entity.unwrap())
+            .insert((
+                PowerSource,
+                PowerDevice::storage(BATTERY_CAPACITY, BATTERY_RATE),
+            ));
+    }
+}
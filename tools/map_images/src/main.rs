@@ -0,0 +1,80 @@
+//! Renders a top-down PNG per y level of a map into its `images` directory.
+//! Only the tile definitions get initialized, the map is read without running the game.
+
+use std::process::ExitCode;
+
+use bevy::{app::App, core::TaskPoolPlugin, log::LogPlugin};
+use gridmap::{
+    grid::Gridmap,
+    map_image::{export_map_images, load_map_tiles, map_images_directory, read_entity_markers},
+    plugin::GridmapTilesPlugin,
+};
+use resources::{
+    maps::{get_map_name, load_map_manifest},
+    modes::AppMode,
+};
+
+fn main() -> ExitCode {
+    let name = get_map_name();
+    let map;
+    match load_map_manifest(&name) {
+        Ok(m) => {
+            map = m;
+        }
+        Err(rr) => {
+            eprintln!("{}", rr);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        LogPlugin::default(),
+        GridmapTilesPlugin,
+    ))
+    .init_resource::<AppMode>();
+    // Runs the startup systems that fill the tile properties.
+    app.update();
+    let mut gridmap;
+    match app.world_mut().remove_resource::<Gridmap>() {
+        Some(g) => {
+            gridmap = g;
+        }
+        None => {
+            eprintln!("Gridmap didn't get initialized.");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match load_map_tiles(&mut gridmap, &map) {
+        Ok(loaded) => {
+            println!("Loaded {} tiles of map {}.", loaded, name);
+        }
+        Err(rr) => {
+            eprintln!("{}", rr);
+            return ExitCode::FAILURE;
+        }
+    }
+    let markers;
+    match read_entity_markers(&map) {
+        Ok(m) => {
+            markers = m;
+        }
+        Err(rr) => {
+            eprintln!("{}", rr);
+            markers = vec![];
+        }
+    }
+    let directory = map_images_directory(&map);
+    match export_map_images(&gridmap, &markers, &directory) {
+        Ok(paths) => {
+            println!("Exported {} map images to {:?}.", paths.len(), directory);
+            ExitCode::SUCCESS
+        }
+        Err(rr) => {
+            eprintln!("{}", rr);
+            ExitCode::FAILURE
+        }
+    }
+}